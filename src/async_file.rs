@@ -0,0 +1,127 @@
+//! Fault path for backends that cannot block, enabled by the `async-backend`
+//! feature.
+
+use alloc::vec;
+use axerrno::LinuxResult;
+use core::future::Future;
+use memory_addr::MemoryAddr;
+
+#[cfg(feature = "log")]
+use crate::trace::{Addr, Label};
+use crate::{
+    AccessFlags, FaultData, FaultResolution, MmapRegion, PageData, RawMutex, VmFile, VmaError,
+    VmaManager, VmaResult, read_progress,
+};
+
+/// File operations awaited by the async fault path
+/// A backend implements this alongside `VmFile`, whose blocking methods the
+/// rest of the crate keeps using
+pub trait AsyncVmFile {
+    /// Read data from the file at the specified offset
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> impl Future<Output = LinuxResult<usize>>;
+
+    /// Get the length of the file
+    fn len(&self) -> impl Future<Output = LinuxResult<u64>>;
+
+    /// Is the file empty?
+    fn is_empty(&self) -> impl Future<Output = LinuxResult<bool>> {
+        async { Ok(self.len().await? == 0) }
+    }
+}
+
+impl<F: VmFile + AsyncVmFile, A: MemoryAddr, R: RawMutex> MmapRegion<F, A, R> {
+    /// Load the page containing `vaddr` like `get_buf`, awaiting the file
+    /// The page is reserved before the first read and recorded as populated
+    /// only once it is complete, so dropping the future leaves it unpopulated
+    /// Like `get_buf`, only the whole-page data is returned
+    pub async fn get_buf_async(&self, vaddr: A) -> VmaResult<PageData, A> {
+        self.check_in_range(vaddr)?;
+        let page_addr = vaddr.align_down(self.align);
+        let guard = self.begin_populate(page_addr)?;
+        if let Some(page) = self.borrow_page(page_addr) {
+            guard.commit();
+            return Ok(PageData::Borrowed(page));
+        }
+
+        let mut buf = vec![0u8; self.align as usize];
+        self.fill_page_async(page_addr, &mut buf).await?;
+        guard.commit();
+        Ok(PageData::Owned(buf))
+    }
+
+    /// Resolve a page fault at `vaddr` like `resolve_fault`, awaiting the file
+    pub(crate) async fn resolve_fault_async(
+        &self,
+        vaddr: A,
+        access: AccessFlags,
+    ) -> VmaResult<FaultResolution<A>, A> {
+        if !self.prot.allows(access) {
+            return Err(VmaError::AccessDenied);
+        }
+        let page_addr = vaddr.align_down(self.align);
+        let data = if self.external_handler {
+            self.defer(page_addr)?;
+            FaultData::Deferred
+        } else if self.is_zero_page(page_addr) {
+            self.populate_zero(page_addr)?;
+            FaultData::Zero
+        } else {
+            self.get_buf_async(vaddr).await?.into()
+        };
+        Ok(self.resolved(vaddr, data))
+    }
+
+    /// Fill `buf` with the data of the page at `page_addr` like `fill_page`,
+    /// awaiting each read
+    async fn fill_page_async(&self, page_addr: A, buf: &mut [u8]) -> VmaResult<(), A> {
+        let Some(source) = self.fill_source(page_addr, buf.len())? else {
+            buf.fill(0);
+            return Ok(());
+        };
+        let file_len = AsyncVmFile::len(source.file).await?;
+        let available = self.available_len(&source, file_len)?;
+        let mut filled = 0;
+        while filled < source.readable {
+            let read = AsyncVmFile::read_at(
+                source.file,
+                &mut buf[filled..source.readable],
+                source.file_offset + filled as u64,
+            )
+            .await;
+            match read_progress(read, filled, source.readable, available)? {
+                Some(read) => filled += read,
+                None => break,
+            }
+        }
+        self.metrics.record_load(filled);
+        vma_trace!(
+            "populate {} vaddr={} offset={:#x} read={filled}",
+            Label(self),
+            Addr(page_addr),
+            source.file_offset,
+        );
+        buf[filled..].fill(0);
+        Ok(())
+    }
+}
+
+impl<F: VmFile + AsyncVmFile, A: MemoryAddr, R: RawMutex> VmaManager<F, A, R> {
+    /// Resolve a page fault at the given virtual address like `handle_fault`,
+    /// awaiting the backing file instead of blocking on it
+    pub async fn handle_fault_async(
+        &self,
+        vaddr: A,
+        access: AccessFlags,
+    ) -> VmaResult<FaultResolution<A>, A> {
+        let region = self
+            .find_region_cached(vaddr)
+            .ok_or(VmaError::Unmapped(vaddr))?;
+        let resolution = region.resolve_fault_async(vaddr, access).await?;
+        if resolution.data != FaultData::Deferred {
+            self.notify(|observer| {
+                observer.on_populate(region.range, resolution.vaddr, resolution.size)
+            });
+        }
+        Ok(resolution)
+    }
+}
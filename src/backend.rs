@@ -0,0 +1,116 @@
+//! Page-table callbacks that let a `VmaManager` apply its mapping changes.
+
+use alloc::vec::Vec;
+use axerrno::LinuxResult;
+use memory_addr::{AddrRange, MemoryAddr, VirtAddr};
+use page_table_multiarch::PageSize;
+
+use crate::{
+    AccessFlags, FaultData, MmapProt, MmapRegion, RawMutex, RemovedRegions, VmFile, VmaError,
+    VmaManager, VmaResult,
+};
+
+/// Page-table operations performed on behalf of a `VmaManager`
+///
+/// The `*_with` variants of `VmaManager` operations call these in the order
+/// the hardware needs: pages are mapped after their contents are loaded, and
+/// the TLB is flushed after pages are unmapped or their protection changes.
+pub trait MapBackend<A: MemoryAddr = VirtAddr> {
+    /// Map the page at `vaddr` with the given contents, size and protection
+    fn map_page(
+        &mut self,
+        vaddr: A,
+        data: FaultData,
+        size: PageSize,
+        prot: MmapProt,
+    ) -> LinuxResult<()>;
+
+    /// Unmap the page at `vaddr` and free its frame
+    fn unmap_page(&mut self, vaddr: A, size: PageSize);
+
+    /// Change the protection of the mapped page at `vaddr`
+    fn protect_page(&mut self, vaddr: A, size: PageSize, prot: MmapProt);
+
+    /// Flush stale TLB entries for the given range
+    fn flush_range(&mut self, range: AddrRange<A>);
+}
+
+impl<F: VmFile, A: MemoryAddr, R: RawMutex> VmaManager<F, A, R> {
+    /// Resolve a page fault and map the page through `backend`
+    /// If mapping fails the page is released again, so that the fault can be
+    /// retried, and the error is returned as Backend; see `handle_fault` for
+    /// the other errors
+    pub fn handle_fault_with(
+        &self,
+        vaddr: A,
+        access: AccessFlags,
+        backend: &mut impl MapBackend<A>,
+    ) -> VmaResult<(), A> {
+        let resolution = self.handle_fault(vaddr, access)?;
+        let page_addr = resolution.vaddr;
+        backend
+            .map_page(page_addr, resolution.data, resolution.size, resolution.prot)
+            .inspect_err(|_| {
+                if let Some(region) = self.find_region(page_addr) {
+                    region.release_range(&AddrRange::from_start_size(page_addr, 1));
+                    self.notify(|observer| {
+                        observer.on_evict(region.range, page_addr, region.align)
+                    });
+                }
+            })
+            .map_err(VmaError::Backend)
+    }
+
+    /// Unmap a range like `munmap`, unmapping its populated pages through
+    /// `backend` and then flushing the range
+    /// Returns the removed regions, whose dirty pages still need writeback
+    pub fn munmap_with(
+        &mut self,
+        start: A,
+        len: usize,
+        backend: &mut impl MapBackend<A>,
+    ) -> VmaResult<Vec<MmapRegion<F, A, R>>, A> {
+        let RemovedRegions { regions, pages, .. } = self.munmap(start, len)?;
+        for &(page, size) in &pages {
+            backend.unmap_page(page, size);
+        }
+        if let (Some(first), Some(last)) = (regions.first(), regions.last()) {
+            backend.flush_range(AddrRange::new(first.range.start, last.range.end));
+        }
+        Ok(regions)
+    }
+
+    /// Remove all regions except pinned ones like `clear`, unmapping their
+    /// populated pages through `backend` and then flushing each region
+    /// Returns the removed regions, whose dirty pages still need writeback
+    pub fn unmap_all(&mut self, backend: &mut impl MapBackend<A>) -> Vec<MmapRegion<F, A, R>> {
+        let RemovedRegions { regions, pages, .. } = self.clear();
+        for &(page, size) in &pages {
+            backend.unmap_page(page, size);
+        }
+        for region in &regions {
+            backend.flush_range(region.range);
+        }
+        regions
+    }
+
+    /// Change protection like `protect`, updating the populated pages of the
+    /// affected ranges through `backend` and then flushing each range
+    pub fn protect_with(
+        &mut self,
+        vaddr_range: AddrRange<A>,
+        prot: MmapProt,
+        backend: &mut impl MapBackend<A>,
+    ) -> VmaResult<Vec<AddrRange<A>>, A> {
+        let affected = self.protect(vaddr_range, prot)?;
+        for range in &affected {
+            for region in self.regions_in(*range) {
+                for (page, size) in region.populated_in(range) {
+                    backend.protect_page(page, size, prot);
+                }
+            }
+            backend.flush_range(*range);
+        }
+        Ok(affected)
+    }
+}
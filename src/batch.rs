@@ -0,0 +1,119 @@
+//! Insertion of a batch of regions in one pass.
+
+use alloc::{vec, vec::Vec};
+use memory_addr::{AddrRange, MemoryAddr};
+
+#[cfg(feature = "log")]
+use crate::trace::Label;
+use crate::{MmapRegion, RawMutex, VmFile, VmaError, VmaManager, VmaResult};
+
+/// Keys of the existing regions spanning regions of a batch only with their
+/// holes, each with the ranges of the batch lying in its holes
+type Spanned<A> = Vec<(A, Vec<AddrRange<A>>)>;
+
+/// Keys of the regions split around a batch, along with their segments
+type HoleSplits<F, A, R> = (Vec<A>, Vec<MmapRegion<F, A, R>>);
+
+impl<F: VmFile, A: MemoryAddr, R: RawMutex> VmaManager<F, A, R> {
+    /// Add several regions at once, as when mapping the initial layout of a
+    /// process
+    /// The batch is sorted and checked against itself and the existing
+    /// regions in a single sweep, and is added entirely or not at all, so
+    /// that any error leaves the manager unchanged. Regions of the batch
+    /// conflict if their ranges overlap, even through holes
+    /// Returns Overlap with the range of the first region in address order
+    /// that overlaps another region of the batch or an existing one, GuardGap
+    /// with the range of the first region violating a guard gap, or any other
+    /// error of `add_region`
+    /// Every region gets a fresh id, as with `add_region`
+    pub fn add_regions(&mut self, mut regions: Vec<MmapRegion<F, A, R>>) -> VmaResult<(), A> {
+        regions.sort_by_key(|region| region.range.start);
+        for region in &mut regions {
+            region.id = None;
+            self.check_insertable(region)?;
+        }
+        for pair in regions.windows(2) {
+            let (prev, next) = (&pair[0], &pair[1]);
+            if prev.range.end > next.range.start {
+                return Err(VmaError::Overlap(next.range));
+            }
+            if next.range.start.sub_addr(prev.range.end) < prev.guard_above.max(next.guard_below) {
+                return Err(VmaError::GuardGap(next.range));
+            }
+        }
+        let spanned = self.sweep(&regions)?;
+        for region in &regions {
+            self.check_guard_gaps(region)?;
+        }
+        let mapped = regions.iter().map(|r| r.mapped_bytes()).sum();
+        let charged = regions.iter().map(|r| r.commit_size()).sum();
+        self.check_total(mapped, 0)?;
+        let _reserved = self.check_commit(charged, 0)?;
+        // Regions spanning the batch only with their holes are split around it
+        let (keys, retained) = self.split_holes(spanned)?;
+        self.check_region_count(self.regions.len() - keys.len() + retained.len() + regions.len())?;
+        self.replace_regions(keys, retained);
+        self.total_bytes += mapped;
+        for mut region in regions {
+            self.assign_id(&mut region);
+            vma_debug!("add {}", Label(&region));
+            let range = region.range;
+            self.store(region);
+            self.notify(|observer| observer.on_add(range));
+        }
+        Ok(())
+    }
+
+    /// Check the sorted, disjoint `regions` against the existing regions in
+    /// one pass over both
+    /// Returns the existing regions spanning some of them only with their
+    /// holes, or Overlap with the range of the first region overlapping an
+    /// existing one
+    fn sweep(&self, regions: &[MmapRegion<F, A, R>]) -> VmaResult<Spanned<A>, A> {
+        let mut existing = self.regions.values().peekable();
+        let mut spanned: Spanned<A> = Vec::new();
+        for region in regions {
+            while existing
+                .next_if(|other| other.range.end <= region.range.start)
+                .is_some()
+            {}
+            for other in existing
+                .clone()
+                .take_while(|other| other.range.start < region.range.end)
+            {
+                if other.maps_any(region.range) {
+                    return Err(VmaError::Overlap(region.range));
+                }
+                match spanned.last_mut() {
+                    Some((key, ranges)) if *key == other.range.end => ranges.push(region.range),
+                    _ => spanned.push((other.range.end, vec![region.range])),
+                }
+            }
+        }
+        Ok(spanned)
+    }
+
+    /// Split each spanned region around the ranges lying in its holes without
+    /// modifying the manager
+    /// Returns the keys of the split regions along with their segments
+    fn split_holes(&self, spanned: Spanned<A>) -> VmaResult<HoleSplits<F, A, R>, A> {
+        let mut keys = Vec::with_capacity(spanned.len());
+        let mut retained = Vec::new();
+        for (key, ranges) in spanned {
+            // Each range lies strictly inside the region, so a segment is
+            // always left after it
+            let mut rest: Option<MmapRegion<F, A, R>> = None;
+            for range in ranges {
+                let (before, _, after) = match &rest {
+                    Some(region) => region.split_at_range(&range)?,
+                    None => self.regions[&key].split_at_range(&range)?,
+                };
+                retained.extend(before);
+                rest = after;
+            }
+            retained.extend(rest);
+            keys.push(key);
+        }
+        Ok((keys, retained))
+    }
+}
@@ -0,0 +1,152 @@
+//! Builder assembling a validated `MmapRegion` in one expression.
+
+use alloc::string::String;
+use memory_addr::{AddrRange, MemoryAddr, VirtAddr};
+use page_table_multiarch::PageSize;
+
+use crate::{
+    MmapFlags, MmapProt, MmapRegion, RegionBacking, VmFile, VmaError, VmaResult, validate_geometry,
+};
+
+/// Builder of a `MmapRegion`, validating the whole configuration in `build`
+/// Regions start anonymous, private, 4 KiB aligned and with full access
+/// permissions, as with `MmapRegion::new_anonymous`
+pub struct MmapRegionBuilder<F: VmFile, A: MemoryAddr = VirtAddr> {
+    range: AddrRange<A>,
+    backing: RegionBacking<F>,
+    align: PageSize,
+    prot: MmapProt,
+    flags: MmapFlags,
+    name: Option<String>,
+    pinned: bool,
+    external_handler: bool,
+    file_limit: Option<u64>,
+    file_backed_len: Option<usize>,
+    guard_below: usize,
+    guard_above: usize,
+}
+
+impl<F: VmFile, A: MemoryAddr> MmapRegionBuilder<F, A> {
+    /// Start building a region covering `range`
+    pub fn new(range: AddrRange<A>) -> Self {
+        Self {
+            range,
+            backing: RegionBacking::Anonymous,
+            align: PageSize::Size4K,
+            prot: MmapProt::all(),
+            flags: MmapFlags::PRIVATE,
+            name: None,
+            pinned: false,
+            external_handler: false,
+            file_limit: None,
+            file_backed_len: None,
+            guard_below: 0,
+            guard_above: 0,
+        }
+    }
+
+    /// Back the region with `file`, starting at `offset`
+    pub fn file(mut self, file: F, offset: i64) -> Self {
+        self.backing = RegionBacking::File { file, offset };
+        self
+    }
+
+    /// Back the region with zero-filled pages
+    pub fn anonymous(mut self) -> Self {
+        self.backing = RegionBacking::Anonymous;
+        self
+    }
+
+    /// Set the page size of the region
+    pub fn align(mut self, align: PageSize) -> Self {
+        self.align = align;
+        self
+    }
+
+    /// Set the access permissions of the region
+    pub fn prot(mut self, prot: MmapProt) -> Self {
+        self.prot = prot;
+        self
+    }
+
+    /// Replace the sharing and inheritance flags of the region
+    pub fn flags(mut self, flags: MmapFlags) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    /// Name the region
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Make the region a stack that grows down on faults below it
+    pub fn grows_down(mut self) -> Self {
+        self.flags |= MmapFlags::GROWSDOWN;
+        self
+    }
+
+    /// Protect the region from being unmapped or moved
+    pub fn pinned(mut self) -> Self {
+        self.pinned = true;
+        self
+    }
+
+    /// Hand the region's faults to an external handler, see
+    /// `MmapRegion::external_handler`
+    pub fn external_handler(mut self) -> Self {
+        self.external_handler = true;
+        self
+    }
+
+    /// End the mapped file contents at `file_limit`, see `MmapRegion::file_limit`
+    pub fn file_limit(mut self, file_limit: u64) -> Self {
+        self.file_limit = Some(file_limit);
+        self
+    }
+
+    /// Back only the first `len` bytes of the region with the file, see
+    /// `MmapRegion::file_backed_len`
+    pub fn file_backed_len(mut self, len: usize) -> Self {
+        self.file_backed_len = Some(len);
+        self
+    }
+
+    /// Keep `below` bytes below and `above` bytes above the region unmapped
+    pub fn guard_gaps(mut self, below: usize, above: usize) -> Self {
+        self.guard_below = below;
+        self.guard_above = above;
+        self
+    }
+
+    /// Build the region
+    /// Returns InvalidArgument for an empty range, flags that are not exactly
+    /// one of SHARED and PRIVATE, or a file-backed grows-down region, and
+    /// Unaligned if the range or file offset is not aligned to the page size
+    pub fn build(self) -> VmaResult<MmapRegion<F, A>, A> {
+        let offset = match &self.backing {
+            RegionBacking::File { offset, .. } => *offset,
+            RegionBacking::Anonymous | RegionBacking::Reserved => 0,
+        };
+        validate_geometry(self.range, offset, self.align)?;
+        if self.flags.contains(MmapFlags::SHARED) == self.flags.contains(MmapFlags::PRIVATE) {
+            return Err(VmaError::InvalidArgument);
+        }
+        if self.flags.contains(MmapFlags::GROWSDOWN) && !self.backing.is_anonymous() {
+            return Err(VmaError::InvalidArgument);
+        }
+
+        let mut region = MmapRegion::with_backing(self.range, self.backing, self.align);
+        region.prot = self.prot;
+        region.flags = self.flags;
+        region.name = self.name;
+        region.pinned = self.pinned;
+        region.external_handler = self.external_handler;
+        region.file_limit = self.file_limit;
+        region.file_backed_len = self.file_backed_len;
+        region.guard_below = self.guard_below;
+        region.guard_above = self.guard_above;
+        Ok(region)
+    }
+}
@@ -0,0 +1,249 @@
+//! Plain descriptors of a manager's layout, for checkpoint and restore.
+
+use alloc::{string::String, vec::Vec};
+use axerrno::LinuxResult;
+use memory_addr::{AddrRange, MemoryAddr};
+use page_table_multiarch::PageSize;
+
+use crate::{
+    AccessHint, EofPolicy, MmapFlags, MmapProt, MmapRegion, PageSet, RawMutex, RegionBacking,
+    VmFile, VmaError, VmaManager, VmaResult,
+};
+
+/// Backing of a region as recorded in a `RegionDescriptor`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BackingDescriptor {
+    /// Backed by a file at the given offset, re-opened on restore
+    File { offset: i64 },
+    /// Zero-filled on demand
+    Anonymous,
+    /// Reserved address space
+    Reserved,
+}
+
+/// Layout of one region without its live file handle, as produced by
+/// `VmaManager::checkpoint`
+/// Addresses are plain numbers and pages are given as indices from the start
+/// of the region, so descriptors can be stored or sent elsewhere
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RegionDescriptor {
+    /// Start address of the region
+    pub start: usize,
+    /// Size of the region in bytes
+    pub size: usize,
+    /// Page size of the region in bytes
+    pub align: usize,
+    /// Backing store of the region
+    pub backing: BackingDescriptor,
+    /// Bits of the region's `MmapProt`
+    pub prot: u32,
+    /// Bits of the region's `MmapFlags`
+    pub flags: u32,
+    /// Name of the region
+    pub name: Option<String>,
+    /// Whether the region is pinned
+    pub pinned: bool,
+    /// Whether the region is locked in memory
+    pub locked: bool,
+    /// Whether the region's faults go to an external handler
+    pub external_handler: bool,
+    /// Handling of pages past the end of the file, as an `EofPolicy`
+    /// discriminant
+    pub eof_policy: u8,
+    /// File offset at which the mapped contents end
+    pub file_limit: Option<u64>,
+    /// Number of bytes from the start of the region backed by the file
+    pub file_backed_len: Option<usize>,
+    /// Bytes below the region that must stay unmapped
+    pub guard_below: usize,
+    /// Bytes above the region that must stay unmapped
+    pub guard_above: usize,
+    /// Readahead window in pages
+    pub readahead: usize,
+    /// Expected access pattern, as an `AccessHint` discriminant
+    pub access_hint: u8,
+    /// Indices of the populated pages, in ascending order
+    pub populated: Vec<usize>,
+    /// Indices of the dirty pages, in ascending order
+    pub dirty: Vec<usize>,
+    /// Indices of the private pages, in ascending order
+    pub private: Vec<usize>,
+    /// Indices of the lazily freed pages, in ascending order
+    pub lazy_free: Vec<usize>,
+    /// Pages with their own file offset, by index, in ascending order
+    pub page_offsets: Vec<(usize, u64)>,
+    /// Holes punched into the region, as byte offsets from its start, in
+    /// ascending order
+    pub holes: Vec<(usize, usize)>,
+}
+
+impl RegionDescriptor {
+    /// Describe the layout of `region`
+    fn of<F: VmFile, A: MemoryAddr, R: RawMutex>(region: &MmapRegion<F, A, R>) -> Self {
+        let index = |page: A| page.sub_addr(region.range.start) / region.align as usize;
+        let indices = |pages: &PageSet<A>| pages.iter().map(index).collect();
+        Self {
+            start: region.range.start.into(),
+            size: region.range.size(),
+            align: region.align as usize,
+            backing: match &region.backing {
+                RegionBacking::File { offset, .. } => BackingDescriptor::File { offset: *offset },
+                RegionBacking::Anonymous => BackingDescriptor::Anonymous,
+                RegionBacking::Reserved => BackingDescriptor::Reserved,
+            },
+            prot: region.prot.bits(),
+            flags: region.flags.bits(),
+            name: region.name.clone(),
+            pinned: region.pinned,
+            locked: region.locked,
+            external_handler: region.external_handler,
+            eof_policy: region.eof_policy as u8,
+            file_limit: region.file_limit,
+            file_backed_len: region.file_backed_len,
+            guard_below: region.guard_below,
+            guard_above: region.guard_above,
+            readahead: region.readahead(),
+            access_hint: region.access_hint() as u8,
+            populated: indices(&region.populated.lock()),
+            dirty: indices(&region.dirty.lock()),
+            private: indices(&region.private.lock()),
+            lazy_free: indices(&region.lazy_free.lock()),
+            page_offsets: region
+                .page_offsets
+                .iter()
+                .map(|(&page, &offset)| (index(page), offset))
+                .collect(),
+            holes: region
+                .holes()
+                .map(|hole| {
+                    let start = hole.start.sub_addr(region.range.start);
+                    (start, start + hole.size())
+                })
+                .collect(),
+        }
+    }
+
+    /// Rebuild the region described, backed by `file` if it is file-backed
+    /// Returns InvalidArgument for an unknown page size, protection, flags,
+    /// access hint or EOF policy, a page index outside the region, or a hole
+    /// that is unaligned or not strictly inside the region
+    fn build<F: VmFile, A: MemoryAddr, R: RawMutex>(
+        &self,
+        file: Option<F>,
+    ) -> VmaResult<MmapRegion<F, A, R>, A> {
+        let align = match self.align {
+            0x1000 => PageSize::Size4K,
+            0x20_0000 => PageSize::Size2M,
+            0x4000_0000 => PageSize::Size1G,
+            _ => return Err(VmaError::InvalidArgument),
+        };
+        let end = self
+            .start
+            .checked_add(self.size)
+            .ok_or(VmaError::InvalidArgument)?;
+        let range = AddrRange::new(A::from(self.start), A::from(end));
+        let backing = match (self.backing, file) {
+            (BackingDescriptor::File { offset }, Some(file)) => {
+                RegionBacking::File { file, offset }
+            }
+            (BackingDescriptor::Anonymous, _) => RegionBacking::Anonymous,
+            (BackingDescriptor::Reserved, _) => RegionBacking::Reserved,
+            (BackingDescriptor::File { .. }, None) => return Err(VmaError::InvalidArgument),
+        };
+        let access_hint = [
+            AccessHint::Normal,
+            AccessHint::Sequential,
+            AccessHint::Random,
+        ]
+        .into_iter()
+        .find(|&hint| hint as u8 == self.access_hint)
+        .ok_or(VmaError::InvalidArgument)?;
+
+        let mut region = MmapRegion::with_backing(range, backing, align);
+        region.prot = MmapProt::from_bits(self.prot).ok_or(VmaError::InvalidArgument)?;
+        region.flags = MmapFlags::from_bits(self.flags)
+            .filter(|flags| flags.is_valid())
+            .ok_or(VmaError::InvalidArgument)?;
+        region.name.clone_from(&self.name);
+        region.pinned = self.pinned;
+        region.locked = self.locked;
+        region.external_handler = self.external_handler;
+        region.eof_policy = EofPolicy::from_u8(self.eof_policy).ok_or(VmaError::InvalidArgument)?;
+        region.file_limit = self.file_limit;
+        region.file_backed_len = self.file_backed_len;
+        region.guard_below = self.guard_below;
+        region.guard_above = self.guard_above;
+        region.set_readahead(self.readahead);
+        region.set_access_hint(access_hint);
+
+        let page = |index: usize| {
+            index
+                .checked_mul(self.align)
+                .filter(|&delta| delta < self.size)
+                .map(|delta| range.start.add(delta))
+                .ok_or(VmaError::InvalidArgument)
+        };
+        for (pages, indices) in [
+            (&*region.populated, &self.populated),
+            (&region.dirty, &self.dirty),
+            (&region.private, &self.private),
+            (&region.lazy_free, &self.lazy_free),
+        ] {
+            let mut pages = pages.lock();
+            for &index in indices {
+                pages.insert(page(index)?);
+            }
+        }
+        for &(index, offset) in &self.page_offsets {
+            region.page_offsets.insert(page(index)?, offset);
+        }
+        for &(start, end) in &self.holes {
+            if start >= end || end >= self.size {
+                return Err(VmaError::InvalidArgument);
+            }
+            let hole = AddrRange::new(range.start.add(start), range.start.add(end));
+            region
+                .punch_hole(hole)
+                .map_err(|_| VmaError::InvalidArgument)?;
+        }
+        region.recount_present();
+        Ok(region)
+    }
+}
+
+impl<F: VmFile, A: MemoryAddr, R: RawMutex> VmaManager<F, A, R> {
+    /// Describe the layout of every region, in address order, without the
+    /// live file handles
+    pub fn checkpoint(&self) -> Vec<RegionDescriptor> {
+        self.iter().map(RegionDescriptor::of).collect()
+    }
+
+    /// Rebuild a manager from the descriptors produced by `checkpoint`
+    /// `resolve` re-opens the backing file of each file-backed region; the
+    /// populated, dirty and private pages are restored exactly, so the caller
+    /// must map their contents again
+    /// Returns Backend if `resolve` fails, InvalidArgument for a malformed
+    /// descriptor, or the same errors as `add_region`
+    pub fn restore(
+        descriptors: &[RegionDescriptor],
+        mut resolve: impl FnMut(&RegionDescriptor) -> LinuxResult<F>,
+    ) -> VmaResult<Self, A> {
+        let mut manager = Self::default();
+        for descriptor in descriptors {
+            let file = match descriptor.backing {
+                BackingDescriptor::File { .. } => Some(resolve(descriptor)?),
+                _ => None,
+            };
+            manager.add_region(descriptor.build(file)?)?;
+        }
+        Ok(manager)
+    }
+
+    /// Check if `other` has the same layout as this manager, comparing their
+    /// checkpoints
+    pub fn same_layout(&self, other: &Self) -> bool {
+        self.checkpoint() == other.checkpoint()
+    }
+}
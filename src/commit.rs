@@ -0,0 +1,130 @@
+//! Commit accounting of private writable mappings.
+
+use alloc::sync::Arc;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// How a `VmaManager` accounts for the memory its mappings may need
+#[derive(Debug, Clone, Default)]
+pub enum CommitPolicy {
+    /// Nothing is charged when mapping, and only resident pages count
+    #[default]
+    Overcommit,
+    /// Private writable mappings without NORESERVE charge their full size
+    /// against the limit when mapped, and fail with CommitLimit once it is
+    /// reached
+    Strict(Arc<CommitLimit>),
+}
+
+impl CommitPolicy {
+    /// Limit charged under the strict policy
+    pub(crate) fn limit(&self) -> Option<&CommitLimit> {
+        match self {
+            Self::Overcommit => None,
+            Self::Strict(limit) => Some(limit),
+        }
+    }
+
+    /// Reserve the growth of the charge by `added` bytes once `removed` ones
+    /// are released, see `CommitLimit::try_charge`
+    /// Returns None if the strict limit cannot take it
+    pub(crate) fn reserve(&self, added: usize, removed: usize) -> Option<CommitReservation> {
+        let Self::Strict(limit) = self else {
+            return Some(CommitReservation::default());
+        };
+        let bytes = added.saturating_sub(removed);
+        limit.try_charge(bytes).then(|| CommitReservation {
+            limit: Some(limit.clone()),
+            bytes,
+        })
+    }
+}
+
+/// Charge taken from a `CommitLimit` ahead of an operation, released when
+/// dropped
+/// The operation charges its bytes itself before the reservation goes, so
+/// other managers sharing the limit may briefly see both, but never a total
+/// above the limit
+#[derive(Default)]
+pub(crate) struct CommitReservation {
+    /// Limit the bytes are charged against, or None under overcommit
+    limit: Option<Arc<CommitLimit>>,
+    /// Number of bytes reserved
+    bytes: usize,
+}
+
+impl Drop for CommitReservation {
+    fn drop(&mut self) {
+        if let Some(limit) = &self.limit {
+            limit.release(self.bytes);
+        }
+    }
+}
+
+/// Commit limit shared by the managers charging against it, as a manager
+/// and the children forked from it
+#[derive(Debug)]
+pub struct CommitLimit {
+    /// Maximum number of bytes that may be charged
+    limit: usize,
+    /// Number of bytes charged by all managers
+    charged: AtomicUsize,
+}
+
+impl CommitLimit {
+    /// Create a limit of `limit` bytes with nothing charged
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            charged: AtomicUsize::new(0),
+        }
+    }
+
+    /// Maximum number of bytes that may be charged
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Number of bytes charged by all managers
+    pub fn charged(&self) -> usize {
+        self.charged.load(Ordering::Relaxed)
+    }
+
+    /// Charge `bytes` if the total charged by all managers stays within the
+    /// limit, in one atomic step so that managers sharing the limit cannot
+    /// exceed it together
+    /// Returns whether the bytes were charged; charging nothing always
+    /// succeeds
+    pub(crate) fn try_charge(&self, bytes: usize) -> bool {
+        if bytes == 0 {
+            return true;
+        }
+        let mut charged = self.charged();
+        loop {
+            let Some(total) = charged
+                .checked_add(bytes)
+                .filter(|&total| total <= self.limit)
+            else {
+                return false;
+            };
+            match self.charged.compare_exchange_weak(
+                charged,
+                total,
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return true,
+                Err(current) => charged = current,
+            }
+        }
+    }
+
+    /// Charge `bytes`, even past the limit
+    pub(crate) fn charge(&self, bytes: usize) {
+        self.charged.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Release `bytes` charged before
+    pub(crate) fn release(&self, bytes: usize) {
+        self.charged.fetch_sub(bytes, Ordering::Relaxed);
+    }
+}
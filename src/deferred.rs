@@ -0,0 +1,113 @@
+//! Faults parked until an external handler supplies the page, as with
+//! userfaultfd.
+
+use alloc::vec::Vec;
+use memory_addr::MemoryAddr;
+
+#[cfg(feature = "log")]
+use crate::trace::{Addr, Label};
+use crate::{
+    FaultData, FaultResolution, MmapRegion, PageState, RawMutex, VmFile, VmaError, VmaManager,
+    VmaResult,
+};
+
+impl<F: VmFile, A: MemoryAddr, R: RawMutex> MmapRegion<F, A, R> {
+    /// Park a fault on the page at `page_addr` until its contents are
+    /// supplied; faulting again on a parked page parks it again
+    /// Returns AlreadyPopulated if the page is already populated and Busy if
+    /// it is being installed or evicted
+    pub(crate) fn defer(&self, page_addr: A) -> VmaResult<(), A> {
+        let populated = self.populated.lock();
+        if populated.contains(page_addr) {
+            return Err(VmaError::AlreadyPopulated);
+        }
+        if self.transitions.lock().contains_key(&page_addr) {
+            return Err(VmaError::Busy);
+        }
+        self.deferred.lock().insert(page_addr);
+        vma_trace!("defer {} vaddr={}", Label(self), Addr(page_addr));
+        Ok(())
+    }
+
+    /// Check if a fault on the page containing `vaddr` is parked
+    pub fn is_deferred(&self, vaddr: A) -> bool {
+        self.deferred.lock().contains(vaddr.align_down(self.align))
+    }
+
+    /// Pages whose faults are parked, in address order
+    pub fn deferred_pages(&self) -> Vec<A> {
+        self.deferred.lock().iter().collect()
+    }
+
+    /// Populate the parked page containing `vaddr` with `data`, which must
+    /// span a whole page
+    /// The page counts as written, since its contents come from the handler
+    /// rather than the file
+    /// Returns InvalidArgument if no fault is parked on the page or `data` is
+    /// not a whole page, AlreadyPopulated if it is already populated, or Busy
+    /// if another caller is installing it
+    pub fn install_deferred(&self, vaddr: A, data: Vec<u8>) -> VmaResult<FaultResolution<A>, A> {
+        let page_addr = vaddr.align_down(self.align);
+        if data.len() != self.align as usize {
+            return Err(VmaError::InvalidArgument);
+        }
+        match self.transition(page_addr, PageState::NotPresent, PageState::Loading) {
+            Ok(()) => {}
+            Err(PageState::Present) => return Err(VmaError::AlreadyPopulated),
+            Err(_) => return Err(VmaError::Busy),
+        }
+        if !self.deferred.lock().remove(page_addr) {
+            self.fail_load(page_addr);
+            return Err(VmaError::InvalidArgument);
+        }
+        self.finish_load(page_addr);
+        self.metrics.record_load(data.len());
+        self.mark_written(page_addr);
+        vma_trace!("install {} vaddr={}", Label(self), Addr(page_addr));
+        Ok(FaultResolution {
+            vaddr: page_addr,
+            data: FaultData::Loaded(data),
+            size: self.align,
+            prot: self.prot,
+        })
+    }
+
+    /// Drop the parked fault on the page containing `vaddr` without
+    /// populating it, so that the next fault there parks anew
+    /// Returns whether a fault was parked on the page
+    pub fn cancel_deferred(&self, vaddr: A) -> bool {
+        self.deferred.lock().remove(vaddr.align_down(self.align))
+    }
+}
+
+impl<F: VmFile, A: MemoryAddr, R: RawMutex> VmaManager<F, A, R> {
+    /// Supply the contents of the page whose fault at `vaddr` was parked for
+    /// an external handler, as a userfaultfd copy does
+    /// Observers see the page populated now rather than at the fault
+    /// Returns Unmapped if the page was unmapped meanwhile, or the same errors
+    /// as `MmapRegion::install_deferred`
+    pub fn resolve_fault(&self, vaddr: A, data: Vec<u8>) -> VmaResult<FaultResolution<A>, A> {
+        let region = self
+            .find_region_cached(vaddr)
+            .ok_or(VmaError::Unmapped(vaddr))?;
+        let resolution = region.install_deferred(vaddr, data)?;
+        self.notify(|observer| {
+            observer.on_populate(region.range, resolution.vaddr, resolution.size)
+        });
+        Ok(resolution)
+    }
+
+    /// Drop the parked fault at `vaddr`, as when the handler gives up on it
+    /// Returns whether a fault was parked there
+    pub fn cancel_fault(&self, vaddr: A) -> bool {
+        self.find_region_cached(vaddr)
+            .is_some_and(|region| region.cancel_deferred(vaddr))
+    }
+
+    /// Addresses of the pages whose faults are parked, in address order
+    pub fn deferred_faults(&self) -> Vec<A> {
+        self.iter()
+            .flat_map(|region| region.deferred_pages())
+            .collect()
+    }
+}
@@ -0,0 +1,162 @@
+//! Error type describing why a VMA operation failed.
+
+use axerrno::LinuxError;
+use core::fmt;
+use memory_addr::{AddrRange, MemoryAddr, VirtAddr};
+
+/// Result type of VMA operations
+pub type VmaResult<T, A = VirtAddr> = Result<T, VmaError<A>>;
+
+/// Cause of a failed VMA operation
+/// Converts into the `LinuxError` a syscall handler should report
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VmaError<A: MemoryAddr = VirtAddr> {
+    /// The page is already populated (EFAULT)
+    AlreadyPopulated,
+    /// Another caller is populating the page (EAGAIN)
+    Busy,
+    /// The file offset lies before the start or past the end of the file (EINVAL)
+    OffsetOutOfFile { offset: i64, file_len: u64 },
+    /// A faulting page lies entirely past the end of its file, where Linux
+    /// raises SIGBUS (EFAULT)
+    BeyondEof { offset: u64, file_len: u64 },
+    /// An address, length or offset is not aligned to the page size (EINVAL)
+    Unaligned,
+    /// The operation needs a file-backed region (EINVAL)
+    Anonymous,
+    /// Any other invalid argument (EINVAL)
+    InvalidArgument,
+    /// The address lies outside the region's range (EFAULT)
+    OutOfRange { vaddr: A, range: AddrRange<A> },
+    /// No region maps the address (EFAULT)
+    Unmapped(A),
+    /// The range contains addresses no region maps (ENOMEM)
+    Hole(AddrRange<A>),
+    /// The range overlaps an existing region (EEXIST)
+    Overlap(AddrRange<A>),
+    /// No free address range is large enough (ENOMEM)
+    NoSpace,
+    /// The region's protection does not allow the access (EACCES)
+    AccessDenied,
+    /// The operation would change a pinned region (EPERM)
+    Pinned,
+    /// An address or file offset cannot be represented (EOVERFLOW)
+    Overflow,
+    /// Locking would exceed the memlock limit of the given number of bytes
+    /// (ENOMEM, or EPERM if the limit is zero and locking is not permitted)
+    LockLimit(usize),
+    /// The operation would raise the region count above the given limit (ENOMEM)
+    RegionLimit(usize),
+    /// The operation would map more than the address space limit of the given
+    /// number of bytes (ENOMEM)
+    AddressSpaceLimit(usize),
+    /// The operation would charge more than the commit limit of the given
+    /// number of bytes (ENOMEM)
+    CommitLimit(usize),
+    /// The range lies in the guard gap of a region, or another region lies in
+    /// the guard gap of the range (ENOMEM)
+    GuardGap(AddrRange<A>),
+    /// The backing file or page table backend failed
+    Backend(LinuxError),
+}
+
+impl<A: MemoryAddr> From<LinuxError> for VmaError<A> {
+    fn from(err: LinuxError) -> Self {
+        Self::Backend(err)
+    }
+}
+
+impl<A: MemoryAddr> From<VmaError<A>> for LinuxError {
+    fn from(err: VmaError<A>) -> Self {
+        match err {
+            VmaError::AlreadyPopulated => LinuxError::EFAULT,
+            VmaError::Busy => LinuxError::EAGAIN,
+            VmaError::OffsetOutOfFile { .. } => LinuxError::EINVAL,
+            VmaError::BeyondEof { .. } => LinuxError::EFAULT,
+            VmaError::Unaligned => LinuxError::EINVAL,
+            VmaError::Anonymous => LinuxError::EINVAL,
+            VmaError::InvalidArgument => LinuxError::EINVAL,
+            VmaError::OutOfRange { .. } => LinuxError::EFAULT,
+            VmaError::Unmapped(_) => LinuxError::EFAULT,
+            VmaError::Hole(_) => LinuxError::ENOMEM,
+            VmaError::Overlap(_) => LinuxError::EEXIST,
+            VmaError::NoSpace => LinuxError::ENOMEM,
+            VmaError::AccessDenied => LinuxError::EACCES,
+            VmaError::Pinned => LinuxError::EPERM,
+            VmaError::Overflow => LinuxError::EOVERFLOW,
+            VmaError::LockLimit(0) => LinuxError::EPERM,
+            VmaError::LockLimit(_) => LinuxError::ENOMEM,
+            VmaError::RegionLimit(_) => LinuxError::ENOMEM,
+            VmaError::AddressSpaceLimit(_) => LinuxError::ENOMEM,
+            VmaError::CommitLimit(_) => LinuxError::ENOMEM,
+            VmaError::GuardGap(_) => LinuxError::ENOMEM,
+            VmaError::Backend(err) => err,
+        }
+    }
+}
+
+impl<A: MemoryAddr> fmt::Display for VmaError<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AlreadyPopulated => write!(f, "page already populated"),
+            Self::Busy => write!(f, "page is being populated"),
+            Self::OffsetOutOfFile { offset, file_len } => {
+                let sign = if *offset < 0 { "-" } else { "" };
+                let offset = offset.unsigned_abs();
+                write!(
+                    f,
+                    "file offset {sign}{offset:#x} outside file of {file_len:#x} bytes"
+                )
+            }
+            Self::BeyondEof { offset, file_len } => write!(
+                f,
+                "page at file offset {offset:#x} beyond end of file of {file_len:#x} bytes"
+            ),
+            Self::Unaligned => write!(f, "unaligned address, length or offset"),
+            Self::Anonymous => write!(f, "region is not file-backed"),
+            Self::InvalidArgument => write!(f, "invalid argument"),
+            Self::OutOfRange { vaddr, range } => write!(
+                f,
+                "address {:#x} outside region {:#x}-{:#x}",
+                addr(*vaddr),
+                addr(range.start),
+                addr(range.end)
+            ),
+            Self::Unmapped(vaddr) => write!(f, "address {:#x} is not mapped", addr(*vaddr)),
+            Self::Hole(range) => write!(
+                f,
+                "range {:#x}-{:#x} contains unmapped addresses",
+                addr(range.start),
+                addr(range.end)
+            ),
+            Self::Overlap(range) => write!(
+                f,
+                "range {:#x}-{:#x} overlaps an existing region",
+                addr(range.start),
+                addr(range.end)
+            ),
+            Self::NoSpace => write!(f, "no free address range"),
+            Self::AccessDenied => write!(f, "access not allowed by region protection"),
+            Self::Pinned => write!(f, "region is pinned"),
+            Self::Overflow => write!(f, "address or file offset overflow"),
+            Self::LockLimit(limit) => write!(f, "memlock limit of {limit:#x} bytes exceeded"),
+            Self::RegionLimit(max) => write!(f, "limit of {max} regions exceeded"),
+            Self::AddressSpaceLimit(max) => {
+                write!(f, "address space limit of {max:#x} bytes exceeded")
+            }
+            Self::CommitLimit(max) => write!(f, "commit limit of {max:#x} bytes exceeded"),
+            Self::GuardGap(range) => write!(
+                f,
+                "range {:#x}-{:#x} violates a guard gap",
+                addr(range.start),
+                addr(range.end)
+            ),
+            Self::Backend(err) => write!(f, "backend error: {err}"),
+        }
+    }
+}
+
+/// Numeric value of an address, for formatting
+fn addr<A: MemoryAddr>(addr: A) -> usize {
+    addr.into()
+}
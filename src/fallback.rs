@@ -0,0 +1,111 @@
+//! Fallback layer of regions shared underneath several managers.
+
+use alloc::{sync::Arc, vec::Vec};
+use core::ops::Bound;
+use memory_addr::{AddrRange, MemoryAddr};
+
+use crate::holes::{merge_range, range_at};
+use crate::{MmapRegion, RawMutex, VmFile, VmaManager, VmaStats};
+
+impl<F: VmFile, A: MemoryAddr, R: RawMutex> VmaManager<F, A, R> {
+    /// Create an empty manager layered over `parent`, as a process over the
+    /// global mappings shared by every process
+    /// Lookups and faults hit a local region first and fall back on `parent`
+    /// at addresses no local region maps. All changes apply to the local
+    /// layer only; unmapping a range the parent maps shadows it locally, so
+    /// the parent's regions no longer show through there
+    pub fn with_fallback(parent: Arc<Self>) -> Self {
+        let mut manager = Self::default();
+        manager.fallback = Some(parent);
+        manager
+    }
+
+    /// Manager consulted at addresses no local region maps, if any
+    pub fn fallback(&self) -> Option<&Arc<Self>> {
+        self.fallback.as_ref()
+    }
+
+    /// Iterate over the local regions together with the fallback regions
+    /// that show through at some address, ordered by start address
+    /// A fallback region is yielded whole even where local regions or shadows
+    /// hide part of it, so it may overlap local regions
+    pub fn iter_with_fallback(&self) -> impl Iterator<Item = &MmapRegion<F, A, R>> {
+        let mut regions: Vec<_> = self.iter().collect();
+        if let Some(fallback) = &self.fallback {
+            regions.extend(
+                fallback
+                    .iter_with_fallback()
+                    .filter(|r| !self.hides(r.range)),
+            );
+        }
+        regions.sort_by_key(|r| r.range.start);
+        regions.into_iter()
+    }
+
+    /// Collect memory accounting like `stats`, also counting the regions of
+    /// `iter_with_fallback`; fallback regions count whole
+    pub fn stats_with_fallback(&self) -> VmaStats {
+        VmaStats::of(self.iter_with_fallback())
+    }
+
+    /// Find the fallback region containing the given virtual address, unless
+    /// a local shadow hides it
+    pub(crate) fn find_fallback(&self, vaddr: A) -> Option<&Arc<MmapRegion<F, A, R>>> {
+        if self.is_shadowed(vaddr) {
+            return None;
+        }
+        self.fallback.as_ref()?.find_region_arc_ref(vaddr)
+    }
+
+    /// Hide the fallback in the given range, merging the shadow with the ones
+    /// it overlaps or touches
+    /// Nothing is recorded if the fallback maps nothing in the range
+    pub(crate) fn shadow(&mut self, vaddr_range: AddrRange<A>) {
+        let underneath = self
+            .fallback
+            .as_ref()
+            .is_some_and(|fallback| fallback.maps_any(vaddr_range));
+        if vaddr_range.is_empty() || !underneath {
+            return;
+        }
+        merge_range(&mut self.shadows, vaddr_range);
+    }
+
+    /// Check if a local shadow hides the fallback at the given address
+    fn is_shadowed(&self, vaddr: A) -> bool {
+        range_at(&self.shadows, vaddr).is_some()
+    }
+
+    /// Check if this manager or its fallback maps any address of the range,
+    /// ignoring shadows
+    fn maps_any(&self, vaddr_range: AddrRange<A>) -> bool {
+        self.overlapping(vaddr_range).next().is_some()
+            || self
+                .fallback
+                .as_ref()
+                .is_some_and(|fallback| fallback.maps_any(vaddr_range))
+    }
+
+    /// Check if local regions and shadows together cover the whole range
+    fn hides(&self, vaddr_range: AddrRange<A>) -> bool {
+        let mut covers: Vec<AddrRange<A>> = self
+            .overlapping(vaddr_range)
+            .map(|r| r.range)
+            .chain(
+                self.shadows
+                    .range((Bound::Excluded(vaddr_range.start), Bound::Unbounded))
+                    .map(|(&end, &start)| AddrRange::new(start, end))
+                    .take_while(|shadow| shadow.start < vaddr_range.end),
+            )
+            .collect();
+        covers.sort_by_key(|cover| cover.start);
+        let mut cursor = vaddr_range.start;
+        for cover in covers {
+            if cover.start > cursor {
+                return false;
+            }
+            cursor = cursor.max(cover.end);
+        }
+        cursor >= vaddr_range.end
+    }
+}
@@ -0,0 +1,112 @@
+//! Program break management on top of an anonymous region.
+
+use alloc::{string::String, vec::Vec};
+use memory_addr::{AddrRange, MemoryAddr, VirtAddr};
+use page_table_multiarch::PageSize;
+
+use crate::{
+    MmapProt, MmapRegion, RawMutex, RegionBacking, VmFile, VmaError, VmaManager, VmaResult,
+    checked_align_up,
+};
+
+/// Name given to the heap region, as shown in maps output
+const HEAP_NAME: &str = "[heap]";
+
+/// Program break of a process, backed by one anonymous region of a manager
+/// The region starts at the heap base and ends at the break rounded up to a
+/// 4 KiB page; no region exists while the break lies at the base
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeapRegion<A: MemoryAddr = VirtAddr> {
+    /// Start of the heap, which must be aligned to 4 KiB
+    pub base: A,
+    /// Maximum distance of the break from the base, in bytes
+    pub max_size: usize,
+    /// Current program break
+    brk: A,
+}
+
+impl<A: MemoryAddr> HeapRegion<A> {
+    /// Create an empty heap starting at `base`, growing at most `max_size` bytes
+    pub fn new(base: A, max_size: usize) -> Self {
+        Self {
+            base,
+            max_size,
+            brk: base,
+        }
+    }
+
+    /// Current program break
+    pub fn brk(&self) -> A {
+        self.brk
+    }
+
+    /// Move the program break to `new_brk`, as brk does
+    /// Growing extends the heap region or creates it readable and writable, so
+    /// that new pages fault in as zero pages; shrinking unmaps the pages past
+    /// the new break
+    /// Returns NoSpace if the break would leave the heap limits or the heap
+    /// would run into another region or its guard gap, or AddressSpaceLimit
+    /// if the manager's address space limit is reached; otherwise returns the
+    /// dropped populated pages so that the caller can unmap and free them
+    pub fn set_brk<F: VmFile, R: RawMutex>(
+        &mut self,
+        manager: &mut VmaManager<F, A, R>,
+        new_brk: A,
+    ) -> VmaResult<Vec<(A, PageSize)>, A> {
+        if new_brk < self.base || new_brk.sub_addr(self.base) > self.max_size {
+            return Err(VmaError::NoSpace);
+        }
+        let old_end = page_end(self.brk)?;
+        let new_end = page_end(new_brk)?;
+
+        let mut dropped = Vec::new();
+        if new_end > old_end {
+            self.grow(manager, old_end, new_end)?;
+        } else if new_end < old_end {
+            dropped = manager.munmap(new_end, old_end.sub_addr(new_end))?.pages;
+        }
+        self.brk = new_brk;
+        Ok(dropped)
+    }
+
+    /// Extend the heap region ending at `old_end`, or map a new one, up to
+    /// `new_end`
+    fn grow<F: VmFile, R: RawMutex>(
+        &self,
+        manager: &mut VmaManager<F, A, R>,
+        old_end: A,
+        new_end: A,
+    ) -> VmaResult<(), A> {
+        let heap = manager
+            .regions
+            .get(&old_end)
+            .filter(|heap| heap.is_anonymous() && heap.range.start >= self.base);
+        let grown = if let Some(heap) = heap {
+            let grown = new_end.sub_addr(old_end);
+            let charged = if heap.is_accountable() { grown } else { 0 };
+            manager.check_total(grown, 0)?;
+            let _reserved = manager.check_commit(charged, 0)?;
+            manager.grow_in_place(old_end, new_end)
+        } else {
+            let range = AddrRange::new(old_end, new_end);
+            let mut heap =
+                MmapRegion::with_backing(range, RegionBacking::Anonymous, PageSize::Size4K);
+            heap.prot = MmapProt::READ | MmapProt::WRITE;
+            heap.name = Some(String::from(HEAP_NAME));
+            match manager.add_region(heap) {
+                Ok(()) => true,
+                Err(VmaError::Overlap(_) | VmaError::GuardGap(_)) => false,
+                Err(err) => return Err(err),
+            }
+        };
+        if !grown {
+            return Err(VmaError::NoSpace);
+        }
+        Ok(())
+    }
+}
+
+/// End of the page holding the last byte below `brk`
+fn page_end<A: MemoryAddr>(brk: A) -> VmaResult<A, A> {
+    checked_align_up(brk, PageSize::Size4K).ok_or(VmaError::NoSpace)
+}
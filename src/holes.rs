@@ -0,0 +1,187 @@
+//! Unmapped holes punched into a region instead of splitting it.
+
+use alloc::{collections::BTreeMap, vec::Vec};
+use core::ops::Bound;
+use memory_addr::{AddrRange, MemoryAddr};
+use page_table_multiarch::PageSize;
+
+#[cfg(feature = "log")]
+use crate::trace::{Label, Span};
+use crate::{
+    MmapRegion, RawMutex, RemovedRegions, VmFile, VmaError, VmaManager, VmaResult, unique_mut,
+};
+
+/// Add `range` to a set of disjoint ranges stored as start addresses keyed by
+/// end address, merging it with the ranges it overlaps or touches
+pub(crate) fn merge_range<A: MemoryAddr>(ranges: &mut BTreeMap<A, A>, range: AddrRange<A>) {
+    let (mut start, mut end) = (range.start, range.end);
+    let touching: Vec<A> = ranges
+        .range(start..)
+        .take_while(|&(_, &range_start)| range_start <= end)
+        .map(|(&range_end, _)| range_end)
+        .collect();
+    for range_end in touching {
+        if let Some(range_start) = ranges.remove(&range_end) {
+            start = start.min(range_start);
+            end = end.max(range_end);
+        }
+    }
+    ranges.insert(end, start);
+}
+
+/// Find the range of a set stored like `merge_range` containing `vaddr`
+pub(crate) fn range_at<A: MemoryAddr>(ranges: &BTreeMap<A, A>, vaddr: A) -> Option<AddrRange<A>> {
+    ranges
+        .range((Bound::Excluded(vaddr), Bound::Unbounded))
+        .next()
+        .filter(|&(_, &start)| start <= vaddr)
+        .map(|(&end, &start)| AddrRange::new(start, end))
+}
+
+impl<F: VmFile, A: MemoryAddr, R: RawMutex> MmapRegion<F, A, R> {
+    /// Iterate over the holes punched into this region, in address order
+    pub fn holes(&self) -> impl Iterator<Item = AddrRange<A>> + '_ {
+        self.holes
+            .iter()
+            .map(|(&end, &start)| AddrRange::new(start, end))
+    }
+
+    /// Check if `vaddr` lies in a hole of this region
+    pub fn in_hole(&self, vaddr: A) -> bool {
+        range_at(&self.holes, vaddr).is_some()
+    }
+
+    /// Unmap `range` from the middle of this region without splitting it,
+    /// dropping the populated pages inside along with their state
+    /// Addresses in the hole are no longer contained in the region, so faults
+    /// there fail with Unmapped
+    /// Returns the dropped pages, InvalidArgument unless the range lies
+    /// strictly inside the region, or Unaligned if it is not aligned to the
+    /// region's page size
+    pub fn punch_hole(&mut self, range: AddrRange<A>) -> VmaResult<Vec<(A, PageSize)>, A> {
+        if range.is_empty() || range.start <= self.range.start || self.range.end <= range.end {
+            return Err(VmaError::InvalidArgument);
+        }
+        if !range.start.is_aligned(self.align) || !range.end.is_aligned(self.align) {
+            return Err(VmaError::Unaligned);
+        }
+        self.insert_hole(range);
+        self.unshare_population();
+        let dropped = self.release_where(|page| range.contains(page));
+        self.page_offsets.retain(|&page, _| !range.contains(page));
+        Ok(dropped.into_iter().map(|page| (page, self.align)).collect())
+    }
+
+    /// Record `range` as a hole, which must lie strictly inside the region
+    pub(crate) fn insert_hole(&mut self, range: AddrRange<A>) {
+        merge_range(&mut self.holes, range);
+    }
+
+    /// Check if any address of `range` lies in a hole of this region
+    pub fn has_hole_in(&self, range: AddrRange<A>) -> bool {
+        self.holes
+            .range((Bound::Excluded(range.start), Bound::Unbounded))
+            .next()
+            .is_some_and(|(_, &start)| start < range.end)
+    }
+
+    /// Number of bytes of this region lying in holes
+    pub(crate) fn hole_bytes(&self) -> usize {
+        self.holes().map(|hole| hole.size()).sum()
+    }
+
+    /// Parts of `range` that this region maps, skipping its holes, in address
+    /// order
+    pub(crate) fn mapped_parts(&self, range: AddrRange<A>) -> Vec<AddrRange<A>> {
+        let start = self.range.start.max(range.start);
+        let end = self.range.end.min(range.end);
+        let mut parts = Vec::new();
+        let mut cursor = start;
+        for hole in self
+            .holes()
+            .filter(|hole| hole.overlaps(AddrRange::new(start, end)))
+        {
+            if hole.start > cursor {
+                parts.push(AddrRange::new(cursor, hole.start));
+            }
+            cursor = cursor.max(hole.end);
+        }
+        if cursor < end {
+            parts.push(AddrRange::new(cursor, end));
+        }
+        parts
+    }
+
+    /// Check if this region maps any address of `range`
+    pub(crate) fn maps_any(&self, range: AddrRange<A>) -> bool {
+        self.overlaps(&range) && (self.holes.is_empty() || !self.mapped_parts(range).is_empty())
+    }
+
+    /// Shrink `range` so that it neither starts nor ends in a hole
+    /// Returns None if the range lies entirely in a hole
+    pub(crate) fn trim_holes(&self, mut range: AddrRange<A>) -> Option<AddrRange<A>> {
+        if let Some(hole) = range_at(&self.holes, range.start) {
+            range.start = hole.end.min(range.end);
+        }
+        if range.is_empty() {
+            return None;
+        }
+        if let Some(hole) = range_at(&self.holes, range.end.sub(1)) {
+            range.end = hole.start;
+        }
+        Some(range)
+    }
+
+    /// Holes of this region lying within `range`
+    pub(crate) fn holes_in(&self, range: AddrRange<A>) -> BTreeMap<A, A> {
+        self.holes
+            .range((Bound::Excluded(range.start), Bound::Unbounded))
+            .take_while(|&(_, &start)| start < range.end)
+            .map(|(&end, &start)| (end, start))
+            .collect()
+    }
+}
+
+impl<F: VmFile, A: MemoryAddr, R: RawMutex> VmaManager<F, A, R> {
+    /// Punch holes into regions instead of splitting them once the manager
+    /// holds `min_regions` regions, or always split if None
+    /// Only unmaps strictly inside one private region punch a hole; other
+    /// unmaps split as before
+    pub fn set_hole_punching(&mut self, min_regions: Option<usize>) {
+        self.hole_threshold = min_regions;
+    }
+
+    /// Unmap `vaddr_range` by punching a hole into the region around it, if
+    /// the hole-punching policy applies
+    /// Returns None if the range must be removed by splitting instead
+    pub(crate) fn try_punch(
+        &mut self,
+        vaddr_range: AddrRange<A>,
+    ) -> Option<RemovedRegions<F, A, R>> {
+        let min_regions = self.hole_threshold?;
+        let region = self.overlapping(vaddr_range).next()?;
+        let interior = region.range.start < vaddr_range.start && vaddr_range.end < region.range.end;
+        let aligned =
+            vaddr_range.start.is_aligned(region.align) && vaddr_range.end.is_aligned(region.align);
+        if self.regions.len() < min_regions || !interior || !aligned || region.is_shared() {
+            return None;
+        }
+        let key = region.range.end;
+        let mut region = self.regions.remove(&key)?;
+        self.lookup_cache.clear();
+        let (mapped, charged) = (region.mapped_bytes(), region.commit_size());
+        let punched = unique_mut(&mut region).punch_hole(vaddr_range);
+        self.total_bytes -= mapped - region.mapped_bytes();
+        self.uncharge(charged - region.commit_size());
+        if punched.is_ok() {
+            vma_debug!("punch {} range={}", Label(&region), Span(vaddr_range));
+        }
+        self.regions.insert(key, region);
+        let pages = punched.ok()?;
+        self.notify(|observer| observer.on_remove(vaddr_range));
+        Some(RemovedRegions {
+            pages,
+            ..RemovedRegions::default()
+        })
+    }
+}
@@ -1,14 +1,96 @@
 //! Virtual Memory Area (VMA) management for file-backed memory mappings.
 //! Provides abstractions for handling memory-mapped files with on-demand loading.
 
-#![no_std]
+#![cfg_attr(not(feature = "std"), no_std)]
 extern crate alloc;
 
-use alloc::{collections::BTreeSet, vec, vec::Vec};
+#[macro_use]
+mod trace;
+#[cfg(feature = "async-backend")]
+mod async_file;
+mod backend;
+mod batch;
+mod builder;
+mod checkpoint;
+mod commit;
+mod deferred;
+mod error;
+mod fallback;
+mod heap;
+mod holes;
+pub mod loader;
+mod maps;
+#[cfg(feature = "mem-backend")]
+mod mem_file;
+mod metrics;
+mod observer;
+mod page_cache;
+mod page_ref;
+#[cfg(feature = "page-runs")]
+#[path = "page_runs.rs"]
+mod page_set;
+#[cfg(not(feature = "page-runs"))]
+mod page_set;
+mod placement;
+mod reclaim;
+mod shared;
+mod smaps;
+mod snapshot;
+mod split_report;
+#[cfg(feature = "std")]
+mod std_file;
+mod view;
+
+#[cfg(feature = "async-backend")]
+pub use async_file::AsyncVmFile;
+pub use backend::MapBackend;
+pub use builder::MmapRegionBuilder;
+pub use checkpoint::{BackingDescriptor, RegionDescriptor};
+pub use commit::{CommitLimit, CommitPolicy};
+pub use error::{VmaError, VmaResult};
+pub use heap::HeapRegion;
+pub use lock_api::RawMutex;
+pub use maps::MapsDisplay;
+#[cfg(feature = "mem-backend")]
+pub use mem_file::{MemFile, SliceFile};
+pub use metrics::VmaMetrics;
+pub use observer::VmaObserver;
+pub use page_cache::{CacheMetrics, CachedFile};
+pub use page_ref::{PageData, PageRef};
+pub use page_set::PageSet;
+pub use placement::PlacementPolicy;
+pub use reclaim::{AddressOrder, ClockPolicy, ReclaimPolicy};
+pub use shared::SharedVmaManager;
+pub use smaps::RegionStats;
+pub use snapshot::{SnapshotVmaManager, VmaSnapshot};
+pub use split_report::{DetailedSplit, SegmentPages, SplitReport};
+#[cfg(feature = "std")]
+pub use std_file::io_error_to_linux;
+pub use view::RegionView;
+
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    string::String,
+    sync::Arc,
+    vec,
+    vec::Vec,
+};
 use axerrno::{LinuxError, LinuxResult};
-use memory_addr::{MemoryAddr, VirtAddr, VirtAddrRange};
+use bitflags::bitflags;
+use commit::CommitReservation;
+use core::{
+    fmt,
+    mem::MaybeUninit,
+    ops::{Bound, ControlFlow},
+    sync::atomic::{AtomicU8, AtomicUsize, Ordering},
+};
+use holes::range_at;
+use lock_api::Mutex;
+use memory_addr::{AddrRange, MemoryAddr, VirtAddr};
+use metrics::MetricCounters;
 use page_table_multiarch::PageSize;
-use spin::Mutex;
+#[cfg(feature = "log")]
+use trace::{Addr, Label, Span, Spans};
 
 /// Trait for file operations required by VMA management
 /// The implementor is responsible for thread safety and sharing semantics
@@ -16,6 +98,28 @@ pub trait VmFile: Send + Sync + Clone {
     /// Read data from the file at the specified offset
     fn read_at(&self, buf: &mut [u8], offset: u64) -> LinuxResult<usize>;
 
+    /// Read data from the file at the specified offset into `buf`, which may
+    /// be uninitialized
+    /// Returns the part of `buf` holding the data read, which must start at
+    /// the start of `buf`. The default zeroes `buf` and reads into it with
+    /// `read_at`; backends that can copy into uninitialized memory override it
+    /// so that faults write every loaded page only once
+    fn read_uninit_at<'a>(
+        &self,
+        buf: &'a mut [MaybeUninit<u8>],
+        offset: u64,
+    ) -> LinuxResult<&'a mut [u8]> {
+        let buf = zeroed(buf);
+        let read = self.read_at(buf, offset)?;
+        buf.get_mut(..read).ok_or(LinuxError::EIO)
+    }
+
+    /// Write data to the file at the specified offset
+    /// Read-only backends can keep the default, which rejects all writes
+    fn write_at(&self, _buf: &[u8], _offset: u64) -> LinuxResult<usize> {
+        Err(LinuxError::EINVAL)
+    }
+
     /// Get the length of the file
     fn len(&self) -> LinuxResult<u64>;
 
@@ -23,200 +127,4271 @@ pub trait VmFile: Send + Sync + Clone {
     fn is_empty(&self) -> LinuxResult<bool> {
         Ok(self.len()? == 0)
     }
+
+    /// Identity of the underlying file, shared by all handles to it
+    /// Files without an identity are never grouped by
+    /// `VmaManager::regions_backed_by` or `VmaManager::invalidate_file`
+    fn file_id(&self) -> Option<u64> {
+        None
+    }
+
+    /// Check if `other` refers to the same underlying file
+    /// Regions are only merged when this returns true; the default compares
+    /// file identities, keeping merging of file-backed regions opt-in per
+    /// backend
+    fn same_file(&self, other: &Self) -> bool {
+        self.file_id().is_some_and(|id| other.file_id() == Some(id))
+    }
+
+    /// Borrow the `len` bytes at `offset` if the backend already holds them
+    /// in memory, so that faults can map them without copying
+    /// Must return None unless all `len` bytes are available; the default
+    /// always does, and pages are then read with `read_at`
+    fn read_page_ref(&self, _offset: u64, _len: usize) -> Option<PageRef> {
+        None
+    }
+}
+
+bitflags! {
+    /// Protection flags of a memory-mapped region
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct MmapProt: u32 {
+        /// Pages may be read
+        const READ = 1 << 0;
+        /// Pages may be written
+        const WRITE = 1 << 1;
+        /// Pages may be executed
+        const EXEC = 1 << 2;
+    }
+}
+
+impl MmapProt {
+    /// Pages may not be accessed
+    pub const NONE: Self = Self::empty();
+
+    /// Check if these permissions allow the given access
+    pub fn allows(self, access: AccessFlags) -> bool {
+        self.contains(Self::from_bits_truncate(access.bits()))
+    }
+}
+
+bitflags! {
+    /// Sharing and inheritance flags of a memory-mapped region
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct MmapFlags: u32 {
+        /// Changes are shared with other mappings of the same backing
+        const SHARED = 1 << 0;
+        /// Changes are private to this mapping (copy-on-write)
+        const PRIVATE = 1 << 1;
+        /// The mapping is not inherited by a forked child
+        const DONTFORK = 1 << 2;
+        /// The anonymous mapping is a stack that grows down on faults below it
+        const GROWSDOWN = 1 << 3;
+        /// The mapping was placed exactly at the requested address
+        const FIXED = 1 << 4;
+        /// No commit charge is reserved for the mapping
+        const NORESERVE = 1 << 5;
+        /// The mapping was requested locked; `VmaManager::lock_range` locks it
+        const LOCKED = 1 << 6;
+    }
+}
+
+impl MmapFlags {
+    /// Flags in the order they are rendered by `Display`, with their letters
+    const LETTERS: [(Self, char); 7] = [
+        (Self::SHARED, 'S'),
+        (Self::PRIVATE, 'P'),
+        (Self::FIXED, 'F'),
+        (Self::NORESERVE, 'N'),
+        (Self::LOCKED, 'L'),
+        (Self::DONTFORK, 'D'),
+        (Self::GROWSDOWN, 'G'),
+    ];
+
+    /// Check that the flags do not request both SHARED and PRIVATE
+    pub fn is_valid(self) -> bool {
+        !self.contains(Self::SHARED | Self::PRIVATE)
+    }
+}
+
+impl fmt::Display for MmapFlags {
+    /// Render one letter per flag, or `-` where it is clear, as in "S---L--"
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (flag, letter) in Self::LETTERS {
+            let c = if self.contains(flag) { letter } else { '-' };
+            fmt::Write::write_char(f, c)?;
+        }
+        Ok(())
+    }
+}
+
+bitflags! {
+    /// Flags controlling how `VmaManager::remap` may relocate a mapping
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct RemapFlags: u32 {
+        /// The mapping may be moved if it cannot grow in place
+        const MAYMOVE = 1 << 0;
+    }
+}
+
+bitflags! {
+    /// Kind of memory access that triggered a page fault
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct AccessFlags: u32 {
+        /// The access was a read
+        const READ = 1 << 0;
+        /// The access was a write
+        const WRITE = 1 << 1;
+        /// The access was an instruction fetch
+        const EXEC = 1 << 2;
+    }
+}
+
+/// Contents of a page resolved by a fault
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FaultData {
+    /// Page data loaded from the backing file
+    Loaded(Vec<u8>),
+    /// Page data borrowed from the backing file, which can be mapped without
+    /// copying
+    Borrowed(PageRef),
+    /// The page should be mapped as a zero-filled page, as in anonymous
+    /// regions and past the file-backed length of a region
+    Zero,
+    /// The fault is parked until an external handler supplies the page with
+    /// `VmaManager::resolve_fault`; map nothing and retry or block the access
+    Deferred,
+}
+
+impl From<PageData> for FaultData {
+    fn from(data: PageData) -> Self {
+        match data {
+            PageData::Owned(data) => Self::Loaded(data),
+            PageData::Borrowed(page) => Self::Borrowed(page),
+        }
+    }
+}
+
+/// Outcome of a successfully handled page fault
+/// This is the page type of the fault path: it carries the page-aligned
+/// address, the data, the size to map it with and, through the variant of
+/// `data`, whether the page comes from the file or is zero-filled
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FaultResolution<A: MemoryAddr = VirtAddr> {
+    /// Page-aligned address of the faulting page
+    pub vaddr: A,
+    /// Contents of the page, a whole page of `size` bytes unless zero-filled
+    /// or deferred, even at the end of a region whose size is not a multiple of it
+    pub data: FaultData,
+    /// Page size to map the page with
+    pub size: PageSize,
+    /// Protection to map the page with
+    pub prot: MmapProt,
+}
+
+/// Private copy of a copy-on-write page
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CowCopy {
+    /// The page is unmodified; map a private page with these contents
+    Data(Vec<u8>),
+    /// The page has been modified; copy the frame currently mapped there
+    CopyFrame,
+}
+
+/// Action a write fault handler takes, as decided by
+/// `MmapRegion::handle_write_fault`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WriteFaultAction {
+    /// The page was not populated and has been loaded; map it writable with
+    /// these contents
+    Load(PageData),
+    /// The page is mapped read-only with contents it must stop sharing; map a
+    /// private writable copy in its place
+    Copy(CowCopy),
+    /// The page can be written in place; make its mapping writable
+    MakeWritable,
+}
+
+/// Population state of a page of a region
+/// Pages move from NotPresent to Loading to Present and, on reclaim, through
+/// Evicting back to NotPresent; Loading and Evicting pages are in flight
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageState {
+    /// The page holds no data
+    NotPresent,
+    /// A caller is loading the page
+    Loading,
+    /// The page is populated
+    Present,
+    /// A caller is evicting the populated page
+    Evicting,
+}
+
+/// Reservation of a page being populated, obtained from `begin_populate`
+/// Dropping the guard without committing aborts the population
+pub struct PopulateGuard<'a, F: VmFile, A: MemoryAddr = VirtAddr, R: RawMutex = DefaultRawMutex> {
+    region: &'a MmapRegion<F, A, R>,
+    page_addr: A,
+    finished: bool,
+}
+
+impl<F: VmFile, A: MemoryAddr, R: RawMutex> PopulateGuard<'_, F, A, R> {
+    /// Page-aligned address of the reserved page
+    pub fn page_addr(&self) -> A {
+        self.page_addr
+    }
+
+    /// Record the reserved page as populated
+    pub fn commit(mut self) {
+        self.region.finish_load(self.page_addr);
+        self.finished = true;
+    }
+
+    /// Release the reserved page without populating it
+    pub fn abort(self) {}
+}
+
+impl<F: VmFile, A: MemoryAddr, R: RawMutex> Drop for PopulateGuard<'_, F, A, R> {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.region.fail_load(self.page_addr);
+        }
+    }
 }
 
-/// Represents a memory-mapped region with file backing
-pub struct MmapRegion<F: VmFile> {
+/// Advice about the expected use of a memory range, as given to madvise
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Advice {
+    /// No special treatment; resets the access hint of overlapping regions
+    Normal,
+    /// The pages will be accessed soon and should be loaded eagerly
+    WillNeed,
+    /// The pages will not be accessed soon and can be released
+    DontNeed,
+    /// The contents of the pages are no longer needed, so reclaim may discard
+    /// them until they are written again; private regions only
+    Free,
+    /// The pages will be accessed in order, so faults read further ahead in
+    /// every region overlapping the range
+    Sequential,
+    /// The pages will be accessed in random order, so readahead is disabled
+    /// for every region overlapping the range
+    Random,
+}
+
+/// Write-xor-execute policy enforced by a `VmaManager`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WxPolicy {
+    /// Regions may be writable and executable at once
+    #[default]
+    Allow,
+    /// No region may be writable and executable at once
+    Deny,
+    /// Like `Deny`, and executable regions may not be made writable nor
+    /// writable regions executable
+    DenyTransitions,
+}
+
+impl WxPolicy {
+    /// Check if a region may be given `prot`, having `old` before if it
+    /// already exists
+    fn permits(self, old: Option<MmapProt>, prot: MmapProt) -> bool {
+        let wx = MmapProt::WRITE | MmapProt::EXEC;
+        let transition = |from: MmapProt, to: MmapProt| {
+            (from.contains(MmapProt::EXEC) && to.contains(MmapProt::WRITE))
+                || (from.contains(MmapProt::WRITE) && to.contains(MmapProt::EXEC))
+        };
+        match self {
+            Self::Allow => true,
+            Self::Deny => !prot.contains(wx),
+            Self::DenyTransitions => {
+                !prot.contains(wx) && old.is_none_or(|old| !transition(old, prot))
+            }
+        }
+    }
+}
+
+/// Expected access pattern of a region, consulted on faults
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AccessHint {
+    /// Faults read ahead by the region's readahead window
+    #[default]
+    Normal,
+    /// Faults read ahead by at least `SEQUENTIAL_READAHEAD_PAGES`, and by
+    /// twice the region's readahead window if that is larger
+    Sequential,
+    /// Faults load only the faulting page
+    Random,
+}
+
+impl AccessHint {
+    fn from_u8(value: u8) -> Self {
+        match value {
+            1 => Self::Sequential,
+            2 => Self::Random,
+            _ => Self::Normal,
+        }
+    }
+}
+
+/// Handling of faults on pages that lie entirely past the end of the file
+/// The part of a partially backed last page past the end of the file is
+/// zero-filled under either policy
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EofPolicy {
+    /// Loading fails with BeyondEof, like the SIGBUS of an access past the end
+    /// of a file mapping
+    #[default]
+    Bus,
+    /// Pages are zero-filled
+    ZeroFill,
+}
+
+impl EofPolicy {
+    fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Self::Bus),
+            1 => Some(Self::ZeroFill),
+            _ => None,
+        }
+    }
+}
+
+/// Minimum number of pages read ahead of a fault in a sequential region
+const SEQUENTIAL_READAHEAD_PAGES: usize = 16;
+
+/// Effect of applying an `Advice` to a memory range
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AdviceOutcome<A: MemoryAddr = VirtAddr> {
+    /// Nothing changed
+    Unchanged,
+    /// Pages were loaded and should be mapped by the caller
+    Loaded(Vec<(A, Vec<u8>)>),
+    /// Pages were dropped and should be unmapped and freed by the caller
+    Released(Vec<A>),
+}
+
+/// Failure part-way through eagerly populating a range
+/// The pages in `loaded` are recorded as populated and must still be mapped
+/// by the caller
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartialPopulate<A: MemoryAddr = VirtAddr> {
+    /// Pages loaded before the failure, in ascending order
+    pub loaded: Vec<(A, Vec<u8>)>,
+    /// Error that stopped the population
+    pub error: VmaError<A>,
+}
+
+/// Result of eagerly populating a range
+pub type PopulateResult<A = VirtAddr> = Result<Vec<(A, Vec<u8>)>, PartialPopulate<A>>;
+
+/// Raw lock guarding the page state of a region unless another is chosen
+/// Hosts can pick any `RawMutex`, such as a sleeping mutex in a preemptible
+/// kernel or a no-op lock on a single core
+pub type DefaultRawMutex = spin::Mutex<()>;
+
+/// Maximum number of pages loaded by a single batched file read
+const POPULATE_BATCH_PAGES: usize = 64;
+
+/// Backing store of a memory-mapped region
+#[derive(Clone)]
+pub enum RegionBacking<F: VmFile> {
+    /// Pages are loaded from a file
+    File {
+        /// File backing this memory region
+        file: F,
+        /// Offset into the file of the first byte of the mapping
+        /// May be unaligned; page data is read from the same relative offset
+        /// A negative offset places the start of the mapping before the start
+        /// of the file, as when a segment's first page begins before its file
+        /// data; pages that then lie entirely before the file fail to load
+        /// with OffsetOutOfFile
+        offset: i64,
+    },
+    /// Pages are zero-filled on demand
+    Anonymous,
+    /// Address space is reserved but never populated, as for a PROT_NONE
+    /// placeholder; see `VmaManager::commit`
+    Reserved,
+}
+
+impl<F: VmFile> RegionBacking<F> {
+    /// Get the backing file, if any
+    pub fn file(&self) -> Option<&F> {
+        match self {
+            Self::File { file, .. } => Some(file),
+            Self::Anonymous | Self::Reserved => None,
+        }
+    }
+
+    /// Is this an anonymous backing?
+    pub fn is_anonymous(&self) -> bool {
+        matches!(self, Self::Anonymous)
+    }
+
+    /// Is this a reservation without any backing?
+    pub fn is_reserved(&self) -> bool {
+        matches!(self, Self::Reserved)
+    }
+}
+
+/// Identifier of a region, assigned in increasing order by the manager that
+/// stores it and never reused by that manager
+/// Splitting a region passes its id on to one segment, see
+/// `MmapRegion::split_at_range`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RegionId(pub u64);
+
+/// Represents a memory-mapped region with file or anonymous backing
+/// Addresses are virtual by default, but any `MemoryAddr` such as a guest
+/// physical address can be used
+/// Page state is guarded by locks built on the raw mutex `R`, a spin lock
+/// unless the host chooses another
+pub struct MmapRegion<F: VmFile, A: MemoryAddr = VirtAddr, R: RawMutex = DefaultRawMutex> {
     /// Virtual address range for this mapping
-    pub range: VirtAddrRange,
-    /// File backing this memory region
-    pub file: F,
-    /// Offset into the file for this mapping
-    pub offset: isize,
+    pub range: AddrRange<A>,
+    /// Backing store of this memory region
+    pub backing: RegionBacking<F>,
     /// Set of populated (loaded) pages in this region
-    pub populated: Mutex<BTreeSet<VirtAddr>>,
+    /// Pages inserted or removed directly through this lock are not counted
+    /// by `is_fully_populated` until the next load or eviction
+    /// Shared, along with the page transitions, by the handles `share` creates
+    pub populated: Arc<Mutex<R, PageSet<A>>>,
+    /// Set of populated pages that have been written since they were loaded
+    /// Always locked after `populated` so that page state stays consistent
+    pub dirty: Mutex<R, PageSet<A>>,
+    /// Set of populated pages shared copy-on-write with a forked region
+    /// Always locked after `dirty`
+    pub cow: Mutex<R, PageSet<A>>,
+    /// Set of populated pages of a private file mapping that were written and
+    /// no longer match the file, so they are never reloaded from it
+    /// Always locked after `cow`
+    pub private: Mutex<R, PageSet<A>>,
+    /// Set of populated pages freed lazily with `Advice::Free`, which reclaim
+    /// takes first and discards without writeback until they are written
+    /// Always locked after `private`
+    pub lazy_free: Mutex<R, PageSet<A>>,
+    /// States of the pages being loaded or evicted; all other pages are
+    /// Present if they are in `populated` and NotPresent otherwise
+    /// Always locked after `populated`; not inherited by clones
+    transitions: Arc<Mutex<R, BTreeMap<A, PageState>>>,
+    /// Set of pages whose faults are parked until `VmaManager::resolve_fault`
+    /// supplies their contents, see `external_handler`
+    /// Always locked after `lazy_free` and `transitions`; not inherited by
+    /// clones
+    pub deferred: Mutex<R, PageSet<A>>,
+    /// Number of pages in the Present state, updated under the populated
+    /// lock, so that faults on a fully resident region skip the locks
+    present: Arc<AtomicUsize>,
     /// Page alignment for this mapping
     pub align: PageSize,
+    /// Access permissions for this mapping
+    pub prot: MmapProt,
+    /// Sharing and inheritance flags for this mapping
+    pub flags: MmapFlags,
+    /// Handling of faults on pages entirely beyond the end of the file
+    pub eof_policy: EofPolicy,
+    /// Optional name identifying this mapping in dumps and maps output
+    pub name: Option<String>,
+    /// Number of pages loaded ahead of a fault by `fault_with_readahead`
+    readahead: AtomicUsize,
+    /// Expected access pattern, stored as an `AccessHint`
+    access_hint: AtomicU8,
+    /// Whether the region is protected from being unmapped or moved, as for
+    /// the vDSO or pages pinned for DMA
+    pub pinned: bool,
+    /// Whether the region is locked in memory by `VmaManager::lock_range`, so
+    /// that its pages are never evicted
+    pub locked: bool,
+    /// Whether faults are handed to an external party instead of loading the
+    /// page, as with userfaultfd: they are parked and report
+    /// `FaultData::Deferred` until `VmaManager::resolve_fault` installs the
+    /// page, and no other path populates the region
+    pub external_handler: bool,
+    /// File offset at which the mapped contents end, as for the file part of
+    /// an ELF segment; bytes at or past it read as zero and are never written
+    pub file_limit: Option<u64>,
+    /// Number of bytes from the start of the region backed by the file, as
+    /// when an ELF segment's memory size exceeds its file size, or None if
+    /// the file backs the whole region; pages past it read as zero without
+    /// touching the file, and the page it ends in reads only up to it
+    pub file_backed_len: Option<usize>,
+    /// Bytes of address space directly below the region that must stay
+    /// unmapped, as for the guard gap of a stack
+    pub guard_below: usize,
+    /// Bytes of address space directly above the region that must stay unmapped
+    pub guard_above: usize,
+    /// File offsets of pages that do not follow the linear mapping, as set by
+    /// `set_page_offset`
+    page_offsets: BTreeMap<A, u64>,
+    /// Unmapped ranges strictly inside the region, as start addresses keyed
+    /// by end address
+    holes: BTreeMap<A, A>,
+    /// Identifier assigned by the manager holding this region
+    id: Option<RegionId>,
+    /// Fault, load and eviction counters of this region
+    metrics: MetricCounters,
 }
 
-impl<F: VmFile> MmapRegion<F> {
+impl<F: VmFile, A: MemoryAddr> MmapRegion<F, A> {
     /// Create a new memory-mapped region
-    pub fn new(range: VirtAddrRange, file: F, offset: isize, align: PageSize) -> Self {
+    /// The region starts as a private mapping with full access permissions
+    /// `offset` need not be aligned to `align`: the data of every page starts
+    /// at `offset + (page_addr - range.start)` in the file, so a page may span
+    /// two file pages
+    pub fn new(range: AddrRange<A>, file: F, offset: i64, align: PageSize) -> Self {
+        Self::with_backing(range, RegionBacking::File { file, offset }, align)
+    }
+
+    /// Create a new memory-mapped region, validating its geometry
+    /// Returns InvalidArgument if the range is empty, or Unaligned if its
+    /// bounds or the file offset are not aligned to `align`; use `new` for
+    /// unaligned offsets
+    pub fn try_new(
+        range: AddrRange<A>,
+        file: F,
+        offset: i64,
+        align: PageSize,
+    ) -> VmaResult<Self, A> {
+        validate_geometry(range, offset, align)?;
+        Ok(Self::new(range, file, offset, align))
+    }
+
+    /// Create a new anonymous memory region whose pages are zero-filled
+    /// The region starts as a private mapping with full access permissions
+    pub fn new_anonymous(range: AddrRange<A>, align: PageSize) -> Self {
+        Self::with_backing(range, RegionBacking::Anonymous, align)
+    }
+
+    /// Create a reservation of address space that is never populated
+    /// The region has no access permissions, so every fault inside it fails
+    /// with AccessDenied
+    pub fn new_reserved(range: AddrRange<A>, align: PageSize) -> Self {
+        let mut region = Self::with_backing(range, RegionBacking::Reserved, align);
+        region.prot = MmapProt::empty();
+        region
+    }
+}
+
+impl<F: VmFile, A: MemoryAddr, R: RawMutex> MmapRegion<F, A, R> {
+    /// Create a region with the given backing, guarding its page state with
+    /// the raw lock `R`
+    /// The region starts as a private mapping with full access permissions
+    pub fn with_backing(range: AddrRange<A>, backing: RegionBacking<F>, align: PageSize) -> Self {
         Self {
             range,
-            file,
-            offset,
-            populated: Mutex::new(BTreeSet::new()),
+            backing,
+            populated: Arc::new(Mutex::new(PageSet::with_page_size(align))),
+            dirty: Mutex::new(PageSet::with_page_size(align)),
+            cow: Mutex::new(PageSet::with_page_size(align)),
+            private: Mutex::new(PageSet::with_page_size(align)),
+            lazy_free: Mutex::new(PageSet::with_page_size(align)),
+            transitions: Arc::new(Mutex::new(BTreeMap::new())),
+            deferred: Mutex::new(PageSet::with_page_size(align)),
+            present: Arc::new(AtomicUsize::new(0)),
             align,
+            prot: MmapProt::all(),
+            flags: MmapFlags::PRIVATE,
+            eof_policy: EofPolicy::Bus,
+            name: None,
+            readahead: AtomicUsize::new(0),
+            access_hint: AtomicU8::new(AccessHint::Normal as u8),
+            pinned: false,
+            locked: false,
+            external_handler: false,
+            file_limit: None,
+            file_backed_len: None,
+            guard_below: 0,
+            guard_above: 0,
+            page_offsets: BTreeMap::new(),
+            holes: BTreeMap::new(),
+            id: None,
+            metrics: MetricCounters::default(),
         }
     }
 
-    /// Check if a virtual address is contained within this region
-    pub fn contains(&self, vaddr: VirtAddr) -> bool {
-        self.range.contains(vaddr)
+    /// Check if a virtual address is contained within this region and not in
+    /// one of its holes
+    pub fn contains(&self, vaddr: A) -> bool {
+        self.range.contains(vaddr) && !self.in_hole(vaddr)
     }
 
     /// Check if this region overlaps with the given range
-    pub fn overlaps(&self, range: &VirtAddrRange) -> bool {
+    pub fn overlaps(&self, range: &AddrRange<A>) -> bool {
         self.range.overlaps(*range)
     }
 
+    /// Identifier of this region within its `VmaManager`, or None if no
+    /// manager has stored it
+    pub fn id(&self) -> Option<RegionId> {
+        self.id
+    }
+
     /// Split this region at the given range, returning up to three segments
-    /// Returns (before_segment, overlap_segment, after_segment)
-    pub fn split_at_range(
-        &self,
-        range: &VirtAddrRange,
-    ) -> (Option<Self>, Option<Self>, Option<Self>) {
+    /// Each page set is locked once and every segment copies only the bitmap
+    /// words covering its own range, so a split costs a single pass over the
+    /// page state however many pages are populated
+    /// The metrics of the segments start at zero
+    /// The before segment keeps the region's id, or the after segment if there
+    /// is no before segment, or the overlap segment if it is the whole region;
+    /// the other segments have no id until a manager stores them, when they get
+    /// fresh ids
+    /// A page in flight could not finish its load or eviction in the segments,
+    /// so the split fails instead of waiting for it
+    /// Segments are trimmed so that they neither start nor end in a hole, and
+    /// a segment lying entirely in a hole is left out
+    /// Returns (before_segment, overlap_segment, after_segment), Unaligned if a
+    /// split point inside the region is not aligned to its page size, Busy if
+    /// a page of the region is loading or being evicted, or Overflow if the
+    /// file offset of a segment cannot be represented
+    pub fn split_at_range(&self, range: &AddrRange<A>) -> VmaResult<SplitSegments<F, A, R>, A> {
         if !self.overlaps(range) {
-            return (None, None, None);
+            return Ok((None, None, None));
+        }
+        let misaligned = |split: A| {
+            self.range.start < split && split < self.range.end && !split.is_aligned(self.align)
+        };
+        if misaligned(range.start) || misaligned(range.end) {
+            return Err(VmaError::Unaligned);
         }
 
         let self_range = &self.range;
         let split_range = range;
-        let populated_pages = self.populated.lock().clone();
+        let populated_pages = self.populated.lock();
+        if !self.transitions.lock().is_empty() {
+            return Err(VmaError::Busy);
+        }
+        let dirty_pages = self.dirty.lock();
+        let cow_pages = self.cow.lock();
+        let private_pages = self.private.lock();
+        let lazy_free_pages = self.lazy_free.lock();
+        let deferred_pages = self.deferred.lock();
 
-        // Helper to create a segment with the given range
-        let create_segment = |segment_range: VirtAddrRange| -> Self {
-            let populated = populated_pages
-                .iter()
-                .filter(|&page| segment_range.contains(*page))
-                .cloned()
-                .collect();
+        // Helper to create a segment with the given range, trimmed of holes
+        let create_segment = |segment_range: AddrRange<A>| -> VmaResult<Option<Self>, A> {
+            let Some(segment_range) = self.trim_holes(segment_range) else {
+                return Ok(None);
+            };
+            let backing = match &self.backing {
+                RegionBacking::File { file, .. } => RegionBacking::File {
+                    file: file.clone(),
+                    offset: self.signed_offset_of(segment_range.start)?,
+                },
+                RegionBacking::Anonymous => RegionBacking::Anonymous,
+                RegionBacking::Reserved => RegionBacking::Reserved,
+            };
 
-            Self {
+            let populated = populated_pages.subset(segment_range);
+            Ok(Some(Self {
                 range: segment_range,
-                file: self.file.clone(),
-                offset: self.offset + (segment_range.start - self_range.start) as isize,
-                populated: Mutex::new(populated),
+                backing,
+                present: Arc::new(AtomicUsize::new(populated.len())),
+                populated: Arc::new(Mutex::new(populated)),
+                dirty: Mutex::new(dirty_pages.subset(segment_range)),
+                cow: Mutex::new(cow_pages.subset(segment_range)),
+                private: Mutex::new(private_pages.subset(segment_range)),
+                lazy_free: Mutex::new(lazy_free_pages.subset(segment_range)),
+                transitions: Arc::new(Mutex::new(BTreeMap::new())),
+                deferred: Mutex::new(deferred_pages.subset(segment_range)),
                 align: self.align,
-            }
+                prot: self.prot,
+                flags: self.flags,
+                eof_policy: self.eof_policy,
+                name: self.name.clone(),
+                readahead: AtomicUsize::new(self.readahead()),
+                access_hint: AtomicU8::new(self.access_hint() as u8),
+                pinned: self.pinned,
+                locked: self.locked,
+                external_handler: self.external_handler,
+                file_limit: self.file_limit,
+                file_backed_len: self.file_backed_len.map(|len| {
+                    len.saturating_sub(segment_range.start.sub_addr(self_range.start))
+                        .min(segment_range.size())
+                }),
+                guard_below: if segment_range.start == self_range.start {
+                    self.guard_below
+                } else {
+                    0
+                },
+                guard_above: if segment_range.end == self_range.end {
+                    self.guard_above
+                } else {
+                    0
+                },
+                page_offsets: self
+                    .page_offsets
+                    .range(segment_range.start..segment_range.end)
+                    .map(|(&page, &offset)| (page, offset))
+                    .collect(),
+                holes: self.holes_in(segment_range),
+                id: None,
+                metrics: MetricCounters::default(),
+            }))
         };
 
         // Create segment before the split range
-        let before = (self_range.start < split_range.start).then(|| {
-            create_segment(VirtAddrRange::from_start_size(
-                self_range.start,
-                split_range.start - self_range.start,
-            ))
-        });
+        let before = (self_range.start < split_range.start)
+            .then(|| {
+                create_segment(AddrRange::from_start_size(
+                    self_range.start,
+                    split_range.start.sub_addr(self_range.start),
+                ))
+            })
+            .transpose()?
+            .flatten();
 
         // Create segment after the split range
-        let after = (split_range.end < self_range.end).then(|| {
-            create_segment(VirtAddrRange::from_start_size(
-                split_range.end,
-                self_range.end - split_range.end,
-            ))
-        });
+        let after = (split_range.end < self_range.end)
+            .then(|| {
+                create_segment(AddrRange::from_start_size(
+                    split_range.end,
+                    self_range.end.sub_addr(split_range.end),
+                ))
+            })
+            .transpose()?
+            .flatten();
 
         // Create overlapping segment
         let overlap_start = self_range.start.max(split_range.start);
         let overlap_end = self_range.end.min(split_range.end);
-        let overlap = (overlap_start < overlap_end).then(|| {
-            create_segment(VirtAddrRange::from_start_size(
-                overlap_start,
-                overlap_end - overlap_start,
-            ))
-        });
+        let overlap = (overlap_start < overlap_end)
+            .then(|| {
+                create_segment(AddrRange::from_start_size(
+                    overlap_start,
+                    overlap_end.sub_addr(overlap_start),
+                ))
+            })
+            .transpose()?
+            .flatten();
 
-        (before, overlap, after)
+        let (mut before, mut overlap, mut after) = (before, overlap, after);
+        if let Some(segment) = before.as_mut().or(after.as_mut()).or(overlap.as_mut()) {
+            segment.id = self.id;
+        }
+        vma_trace!(
+            "split {} at={} before={} overlap={} after={}",
+            Label(self),
+            Span(*split_range),
+            Spans(before.iter().map(|segment| segment.range)),
+            Spans(overlap.iter().map(|segment| segment.range)),
+            Spans(after.iter().map(|segment| segment.range)),
+        );
+        Ok((before, overlap, after))
+    }
+
+    /// Move this region so that it starts at `start`, keeping its file offset
+    /// Populated, dirty, copy-on-write, private, lazily freed and deferred
+    /// pages, per-page file offsets and holes move along with the region,
+    /// which first stops sharing its population state
+    fn rebase(&mut self, start: A) {
+        let old_start = self.range.start;
+        self.unshare_population();
+        for pages in [
+            &*self.populated,
+            &self.dirty,
+            &self.cow,
+            &self.private,
+            &self.lazy_free,
+            &self.deferred,
+        ] {
+            let mut pages = pages.lock();
+            *pages = pages.rebased(old_start, start);
+        }
+        self.page_offsets = core::mem::take(&mut self.page_offsets)
+            .into_iter()
+            .map(|(page, offset)| (start.add(page.sub_addr(old_start)), offset))
+            .collect();
+        let moved = |addr: A| start.add(addr.sub_addr(old_start));
+        self.holes = core::mem::take(&mut self.holes)
+            .into_iter()
+            .map(|(end, hole_start)| (moved(end), moved(hole_start)))
+            .collect();
+        self.range = AddrRange::from_start_size(start, self.range.size());
+    }
+
+    /// Check if `other` directly follows this region and can be merged into it
+    /// Requires adjacent ranges, the same backing with contiguous file offsets,
+    /// identical alignment, protection, flags, name, readahead, access hint,
+    /// pinning, locking, external handling and file limit, and no per-page
+    /// file offsets or file-backed length
+    pub fn can_merge_with(&self, other: &Self) -> bool {
+        let same_backing = match (&self.backing, &other.backing) {
+            (RegionBacking::Anonymous, RegionBacking::Anonymous)
+            | (RegionBacking::Reserved, RegionBacking::Reserved) => true,
+            (
+                RegionBacking::File { file, .. },
+                RegionBacking::File {
+                    file: other_file, ..
+                },
+            ) => {
+                file.same_file(other_file)
+                    && self.signed_offset_of(self.range.end)
+                        == other.signed_offset_of(other.range.start)
+            }
+            _ => false,
+        };
+        self.range.end == other.range.start
+            && same_backing
+            && self.align == other.align
+            && self.prot == other.prot
+            && self.flags == other.flags
+            && self.eof_policy == other.eof_policy
+            && self.name == other.name
+            && self.readahead() == other.readahead()
+            && self.access_hint() == other.access_hint()
+            && self.pinned == other.pinned
+            && self.locked == other.locked
+            && self.external_handler == other.external_handler
+            && self.file_limit == other.file_limit
+            && self.file_backed_len.is_none()
+            && other.file_backed_len.is_none()
+            && self.guard_above == 0
+            && other.guard_below == 0
+            && self.page_offsets.is_empty()
+            && other.page_offsets.is_empty()
+    }
+
+    /// Merge the directly following region `other` into this one, which first
+    /// stops sharing its population state
+    fn absorb(&mut self, mut other: Self) {
+        self.range.end = other.range.end;
+        self.guard_above = other.guard_above;
+        self.unshare_population();
+        self.populated.lock().union_with(&other.populated.lock());
+        self.dirty.get_mut().union_with(other.dirty.get_mut());
+        self.cow.get_mut().union_with(other.cow.get_mut());
+        self.private.get_mut().union_with(other.private.get_mut());
+        self.lazy_free
+            .get_mut()
+            .union_with(other.lazy_free.get_mut());
+        self.deferred.get_mut().union_with(other.deferred.get_mut());
+        self.holes.append(&mut other.holes);
+        self.recount_present();
+        self.metrics.add(other.metrics.get());
+    }
+
+    /// Recount the Present pages after changing the page sets in place
+    fn recount_present(&mut self) {
+        let populated = self.populated.lock();
+        let present = present_in(&populated, &self.transitions.lock());
+        self.present.store(present, Ordering::Release);
+    }
+
+    /// Give this handle its own copy of the population state if it shares it
+    /// with handles created by `share`, before changing it in place
+    /// Pages another handle is loading stay NotPresent in the copy
+    fn unshare_population(&mut self) {
+        if Arc::get_mut(&mut self.populated).is_some() {
+            return;
+        }
+        let populated = self.populated.lock().clone();
+        self.present = Arc::new(AtomicUsize::new(populated.len()));
+        self.populated = Arc::new(Mutex::new(populated));
+        self.transitions = Arc::new(Mutex::new(BTreeMap::new()));
+    }
+
+    /// Create another handle to the same live mapping, sharing the populated
+    /// pages and page transitions, so that a page loaded or evicted through
+    /// one handle is seen by all of them instead of being loaded twice
+    /// Everything else is copied like `clone`, which leaves the copy with its
+    /// own population state; splitting, moving, merging, demoting or punching
+    /// a hole into a handle gives it its own copy first
+    pub fn share(&self) -> Self {
+        let mut handle = self.clone();
+        handle.populated = self.populated.clone();
+        handle.transitions = self.transitions.clone();
+        handle.present = self.present.clone();
+        handle
+    }
+
+    /// Check if this region shares its population state with another handle
+    pub fn shares_population(&self) -> bool {
+        Arc::strong_count(&self.populated) > 1
+    }
+
+    /// Check if every page of the region is present
+    /// Reads a counter without taking any lock, so the answer may be stale
+    /// while another caller is loading or evicting a page
+    pub fn is_fully_populated(&self) -> bool {
+        let start = self.range.start.align_down(self.align);
+        let pages = self.range.end.sub_addr(start).div_ceil(self.align as usize)
+            - self.hole_bytes() / self.align as usize;
+        pages != 0 && self.present.load(Ordering::Acquire) == pages
+    }
+
+    /// Is this region anonymous (not backed by a file)?
+    pub fn is_anonymous(&self) -> bool {
+        self.backing.is_anonymous()
+    }
+
+    /// Is this region a reservation that is never populated?
+    pub fn is_reserved(&self) -> bool {
+        self.backing.is_reserved()
+    }
+
+    /// Translate a page address into the backing file and its offset in it
+    /// Returns the same errors as `file_offset_of`; the offset may still lie
+    /// past the end of the file
+    fn page_file_offset(&self, page_addr: A) -> VmaResult<(&F, u64), A> {
+        let file = self.backing.file().ok_or(VmaError::Anonymous)?;
+        Ok((file, self.file_offset_of(page_addr)?))
+    }
+
+    /// Translate an address within this region into its backing file offset
+    /// Pages given their own file offset by `set_page_offset` use it instead
+    /// of the linear mapping
+    /// Returns OutOfRange if `vaddr` lies outside the region, Anonymous for
+    /// anonymous regions, OffsetOutOfFile if the offset lies before the start
+    /// of the file, and Overflow if it cannot be represented
+    pub fn file_offset_of(&self, vaddr: A) -> VmaResult<u64, A> {
+        if !self.contains(vaddr) {
+            return Err(VmaError::OutOfRange {
+                vaddr,
+                range: self.range,
+            });
+        }
+        let page_addr = vaddr.align_down(self.align);
+        if let Some(&page_offset) = self.page_offsets.get(&page_addr) {
+            return Ok(page_offset + vaddr.sub_addr(page_addr) as u64);
+        }
+        let offset = self.signed_offset_of(vaddr)?;
+        match u64::try_from(offset) {
+            Ok(offset) => Ok(offset),
+            Err(_) => Err(self.offset_out_of_file(offset)),
+        }
+    }
+
+    /// Build the error for a file offset lying outside the backing file
+    /// The file length is reported as zero if it cannot be queried
+    fn offset_out_of_file(&self, offset: i64) -> VmaError<A> {
+        let file_len = self.file_len().ok().flatten().unwrap_or(0);
+        VmaError::OffsetOutOfFile { offset, file_len }
+    }
+
+    /// Translate a backing file offset into the address mapping it
+    /// Returns None for anonymous regions or offsets outside the region
+    pub fn vaddr_of_offset(&self, file_offset: u64) -> Option<A> {
+        let RegionBacking::File { offset, .. } = &self.backing else {
+            return None;
+        };
+        let overridden = self.page_offsets.iter().find(|&(&page, &page_offset)| {
+            (page_offset..page_offset + self.page_size_at(page) as u64).contains(&file_offset)
+        });
+        if let Some((&page, &page_offset)) = overridden {
+            return Some(page.add((file_offset - page_offset) as usize));
+        }
+        let delta = usize::try_from(file_offset as i128 - *offset as i128).ok()?;
+        let vaddr = self.range.start.add(delta);
+        (delta < self.range.size()
+            && !self.in_hole(vaddr)
+            && !self
+                .page_offsets
+                .contains_key(&vaddr.align_down(self.align)))
+        .then_some(vaddr)
     }
 
-    /// Load data from file into a buffer for the given virtual address
-    /// Returns an error if the page is already populated or if file access fails
-    pub fn get_buf(&self, vaddr: VirtAddr) -> LinuxResult<Vec<u8>> {
+    /// Back the page containing `vaddr` with the file contents at
+    /// `file_offset` instead of its linear offset, as remap_file_pages does
+    /// Setting the linear offset again restores the linear mapping of the page
+    /// Returns OutOfRange if `vaddr` lies outside the region, Anonymous for
+    /// anonymous regions, Unaligned if `file_offset` is not aligned to the
+    /// page size, Overflow if it exceeds the largest signed file offset, or
+    /// AlreadyPopulated if the page is populated, since its
+    /// contents would no longer match
+    pub fn set_page_offset(&mut self, vaddr: A, file_offset: u64) -> VmaResult<(), A> {
+        if !self.contains(vaddr) {
+            return Err(VmaError::OutOfRange {
+                vaddr,
+                range: self.range,
+            });
+        }
+        if self.is_anonymous() {
+            return Err(VmaError::Anonymous);
+        }
+        if !file_offset.is_multiple_of(self.align as u64) {
+            return Err(VmaError::Unaligned);
+        }
+        if i64::try_from(file_offset).is_err() {
+            return Err(VmaError::Overflow);
+        }
         let page_addr = vaddr.align_down(self.align);
-        if self.populated.lock().contains(&page_addr) {
-            return Err(LinuxError::EFAULT);
+        if self.is_populated(page_addr) {
+            return Err(VmaError::AlreadyPopulated);
         }
+        let linear = self.signed_offset_of(page_addr)?;
+        if u64::try_from(linear) == Ok(file_offset) {
+            self.page_offsets.remove(&page_addr);
+        } else {
+            self.page_offsets.insert(page_addr, file_offset);
+        }
+        Ok(())
+    }
 
-        let page_offset = page_addr - self.range.start;
-        let file_offset = self.offset + page_offset as isize;
-        if file_offset < 0 || file_offset >= self.file.len()? as isize {
-            return Err(LinuxError::EINVAL);
+    /// Check if the page at `next` directly follows the page at `prev` in the
+    /// backing file, so that both can be loaded with a single read
+    fn follows_in_file(&self, prev: A, next: A) -> bool {
+        if self.page_offsets.is_empty() {
+            return true;
+        }
+        match (self.file_offset_of(prev), self.file_offset_of(next)) {
+            (Ok(prev_offset), Ok(next_offset)) => {
+                prev_offset + self.page_size_at(prev) as u64 == next_offset
+            }
+            _ => false,
         }
+    }
+
+    /// Signed file offset backing `vaddr`, which may lie up to the region end
+    /// This is the single place where the signed mapping offset is applied
+    /// Returns Anonymous for anonymous regions, OutOfRange if `vaddr` lies
+    /// before the region, and Overflow if the offset cannot be represented
+    fn signed_offset_of(&self, vaddr: A) -> VmaResult<i64, A> {
+        let RegionBacking::File { offset, .. } = &self.backing else {
+            return Err(VmaError::Anonymous);
+        };
+        let delta =
+            vaddr
+                .into()
+                .checked_sub(self.range.start.into())
+                .ok_or(VmaError::OutOfRange {
+                    vaddr,
+                    range: self.range,
+                })?;
+        checked_offset_add(*offset, delta)
+    }
 
-        let buf_size = core::cmp::min(self.align as usize, self.range.end - page_addr);
-        let mut buf = vec![0u8; buf_size];
-        self.file.read_at(&mut buf, file_offset as u64)?;
-        self.populated.lock().insert(page_addr);
+    /// Fill `buf` with the contents of the pages starting at `page_addr`
+    /// `buf` may span several consecutive pages, which are read together
+    /// Short reads are continued until the buffer is full or the file reports
+    /// its end, and bytes past the end of the file are zeroed
+    /// Returns Backend(EIO) if the file reports its end before its length,
+    /// so that a page is never populated with data the file did not produce
+    fn fill_page(&self, page_addr: A, buf: &mut [u8]) -> VmaResult<(), A> {
+        let filled = match self.fill_source(page_addr, buf.len())? {
+            Some(source) => self.read_source(page_addr, &source, |filled, offset| {
+                source
+                    .file
+                    .read_at(&mut buf[filled..source.readable], offset)
+            })?,
+            None => 0,
+        };
+        buf[filled..].fill(0);
+        Ok(())
+    }
 
+    /// Load the `len` bytes of the page data at `page_addr` into a new buffer
+    /// like `fill_page`, leaving the buffer uninitialized until it is read
+    /// into so that only the bytes past the data read are zeroed
+    fn load_page(&self, page_addr: A, len: usize) -> VmaResult<Vec<u8>, A> {
+        let mut buf = Vec::with_capacity(len);
+        let spare = &mut buf.spare_capacity_mut()[..len];
+        let filled = match self.fill_source(page_addr, len)? {
+            Some(source) => self.read_source(page_addr, &source, |filled, offset| {
+                let dst = &mut spare[filled..source.readable];
+                let start = dst.as_ptr().cast::<u8>();
+                let data = source.file.read_uninit_at(dst, offset)?;
+                // Only data read into `dst` itself is known to be initialized
+                if data.as_ptr() != start {
+                    return Err(LinuxError::EIO);
+                }
+                Ok(data.len())
+            })?,
+            None => 0,
+        };
+        zeroed(&mut spare[filled..]);
+        // SAFETY: each read initialized the bytes it returned, starting where
+        // the previous one ended, so the first `filled` bytes are initialized,
+        // and the remaining ones up to `len` were just zeroed
+        unsafe { buf.set_len(len) };
         Ok(buf)
     }
-}
 
-impl<F: VmFile> Clone for MmapRegion<F> {
-    fn clone(&self) -> Self {
-        Self {
-            range: self.range,
-            file: self.file.clone(),
-            offset: self.offset,
-            populated: Mutex::new(self.populated.lock().clone()),
-            align: self.align,
+    /// Read the data of `source` for the page at `page_addr` with `read`,
+    /// which reads at a file offset into the buffer past the number of bytes
+    /// filled so far
+    /// Returns the number of bytes filled
+    fn read_source(
+        &self,
+        #[cfg_attr(not(feature = "log"), expect(unused_variables))] page_addr: A,
+        source: &FillSource<'_, F>,
+        mut read: impl FnMut(usize, u64) -> LinuxResult<usize>,
+    ) -> VmaResult<usize, A> {
+        let available = self.available_len(source, source.file.len()?)?;
+        let mut filled = 0;
+        while filled < source.readable {
+            let result = read(filled, source.file_offset + filled as u64);
+            match read_progress(result, filled, source.readable, available)? {
+                Some(read) => filled += read,
+                None => break,
+            }
         }
+        self.metrics.record_load(filled);
+        vma_trace!(
+            "populate {} vaddr={} offset={:#x} read={filled}",
+            Label(self),
+            Addr(page_addr),
+            source.file_offset,
+        );
+        Ok(filled)
     }
-}
 
-/// Manager for Virtual Memory Areas with file backing
-#[derive(Clone)]
-pub struct VmaManager<F: VmFile> {
-    /// Collection of memory-mapped regions
-    regions: Vec<MmapRegion<F>>,
-}
+    /// Locate the file data of the `len` bytes of the page at `page_addr`,
+    /// clipped to the end of the region
+    /// Returns None for anonymous regions and pages past the file-backed
+    /// length, whose pages need no file
+    pub(crate) fn fill_source(
+        &self,
+        page_addr: A,
+        len: usize,
+    ) -> VmaResult<Option<FillSource<'_, F>>, A> {
+        let len = len.min(self.range.end.sub_addr(page_addr));
+        let backed = self.file_backed_bytes(page_addr, len);
+        if self.is_anonymous() || backed == 0 {
+            return Ok(None);
+        }
+        let (file, file_offset) = self.page_file_offset(page_addr)?;
+        let readable = self.readable_len(file_offset, backed);
+        Ok(Some(FillSource {
+            file,
+            file_offset,
+            readable,
+        }))
+    }
 
-impl<F: VmFile> Default for VmaManager<F> {
-    fn default() -> Self {
-        Self::new()
+    /// Number of readable bytes of `source` that lie before the end of a file
+    /// of `file_len` bytes
+    /// Returns BeyondEof if the page lies past the end and the EOF policy is Bus
+    pub(crate) fn available_len(
+        &self,
+        source: &FillSource<'_, F>,
+        file_len: u64,
+    ) -> VmaResult<usize, A> {
+        let (file_offset, readable) = (source.file_offset, source.readable);
+        if readable > 0 && file_offset >= file_len && self.eof_policy == EofPolicy::Bus {
+            return Err(VmaError::BeyondEof {
+                offset: file_offset,
+                file_len,
+            });
+        }
+        Ok(usize::try_from(file_len.saturating_sub(file_offset))
+            .map_or(readable, |n| n.min(readable)))
     }
-}
 
-impl<F: VmFile> VmaManager<F> {
-    /// Create a new VMA manager
-    pub fn new() -> Self {
-        Self {
-            regions: Vec::new(),
+    /// Number of the `len` bytes at `page_addr` that lie within the file-backed
+    /// length of the region
+    fn file_backed_bytes(&self, page_addr: A, len: usize) -> usize {
+        match self.file_backed_len {
+            Some(backed) => backed
+                .saturating_sub(page_addr.into().saturating_sub(self.range.start.into()))
+                .min(len),
+            None => len,
         }
     }
 
-    /// Clear all managed regions
-    pub fn clear(&mut self) {
-        self.regions.clear();
+    /// Number of the `len` bytes at `file_offset` that lie before the file limit
+    fn readable_len(&self, file_offset: u64, len: usize) -> usize {
+        match self.file_limit {
+            Some(limit) => {
+                usize::try_from(limit.saturating_sub(file_offset)).map_or(len, |n| n.min(len))
+            }
+            None => len,
+        }
     }
 
-    /// Add a new memory-mapped region to the manager
-    pub fn add_region(&mut self, region: MmapRegion<F>) -> LinuxResult<()> {
-        self.regions.push(region);
-        Ok(())
+    /// Addresses of the pages of this region whose extent overlaps `range`
+    fn page_addrs(&self, range: &AddrRange<A>) -> impl Iterator<Item = A> + use<F, A, R> {
+        let start = self.range.start.max(range.start).align_down(self.align);
+        let end = self.range.end.min(range.end);
+        let holes = self.holes_in(AddrRange::new(start, end.max(start)));
+        (start.into()..end.into())
+            .step_by(self.align as usize)
+            .map(A::from)
+            .filter(move |&page| range_at(&holes, page).is_none())
     }
 
-    /// Find the region containing the given virtual address
-    pub fn find_region(&self, vaddr: VirtAddr) -> Option<&MmapRegion<F>> {
-        self.regions.iter().find(|r| r.contains(vaddr))
+    /// Size of the page starting at `page_addr`, clipped to the end of the region
+    fn page_size_at(&self, page_addr: A) -> usize {
+        core::cmp::min(self.align as usize, self.range.end.sub_addr(page_addr))
     }
 
-    /// Remove all regions that overlap with the given address range
-    /// Splits overlapping regions and retains non-overlapping parts
-    pub fn remove_overlapped(&mut self, vaddr_range: VirtAddrRange) -> Vec<MmapRegion<F>> {
-        let mut removed = Vec::new();
-        let mut retained = Vec::new();
+    /// Resolve a page fault at `vaddr`, which must lie within this region
+    /// Faults in regions with an external handler are parked instead, see
+    /// `defer`
+    /// Returns AlreadyPopulated for already populated pages and AccessDenied
+    /// if the region's protection does not allow the access
+    pub(crate) fn resolve_fault(
+        &self,
+        vaddr: A,
+        access: AccessFlags,
+    ) -> VmaResult<FaultResolution<A>, A> {
+        if !self.prot.allows(access) {
+            return Err(VmaError::AccessDenied);
+        }
 
-        for region in self.regions.drain(..) {
-            if region.overlaps(&vaddr_range) {
-                let (before, overlap, after) = region.split_at_range(&vaddr_range);
-                if let Some(overlap) = overlap {
-                    removed.push(overlap);
-                }
-                if let Some(before) = before {
-                    retained.push(before);
-                }
-                if let Some(after) = after {
-                    retained.push(after);
-                }
+        let page_addr = vaddr.align_down(self.align);
+        let data = if self.external_handler {
+            self.defer(page_addr)?;
+            FaultData::Deferred
+        } else if self.is_zero_page(page_addr) {
+            self.populate_zero(page_addr)?;
+            FaultData::Zero
+        } else {
+            self.get_buf(vaddr)?.into()
+        };
+        Ok(self.resolved(vaddr, data))
+    }
+
+    /// Check if the page at `page_addr` holds no file data, as in anonymous
+    /// regions and past the file-backed length, so that faults map it as a
+    /// zero-filled page
+    pub(crate) fn is_zero_page(&self, page_addr: A) -> bool {
+        self.is_anonymous() || self.file_backed_bytes(page_addr, self.align as usize) == 0
+    }
+
+    /// Record a resolved fault at `vaddr` whose page was loaded as `data`
+    pub(crate) fn resolved(&self, vaddr: A, data: FaultData) -> FaultResolution<A> {
+        self.metrics.record_fault();
+        FaultResolution {
+            vaddr: vaddr.align_down(self.align),
+            data,
+            size: self.align,
+            prot: self.prot,
+        }
+    }
+
+    /// Reserve the page containing `vaddr` for population
+    /// The page is recorded as populated only once the returned guard is
+    /// committed; dropping the guard without committing releases the page
+    /// A fully populated region is answered without taking any lock
+    /// Returns AlreadyPopulated if the page is already populated, Busy if
+    /// another caller is loading or evicting it, AccessDenied in a
+    /// reservation, and Unmapped in a hole
+    pub fn begin_populate(&self, vaddr: A) -> VmaResult<PopulateGuard<'_, F, A, R>, A> {
+        if self.is_reserved() {
+            return Err(VmaError::AccessDenied);
+        }
+        if self.in_hole(vaddr) {
+            return Err(VmaError::Unmapped(vaddr));
+        }
+        if self.contains(vaddr) && self.is_fully_populated() {
+            return Err(VmaError::AlreadyPopulated);
+        }
+        let page_addr = vaddr.align_down(self.align);
+        if self.external_handler {
+            return Err(if self.is_populated(page_addr) {
+                VmaError::AlreadyPopulated
             } else {
-                retained.push(region);
-            }
+                VmaError::Busy
+            });
         }
-        self.regions = retained;
-        removed
+        match self.transition(page_addr, PageState::NotPresent, PageState::Loading) {
+            Ok(()) => {}
+            Err(PageState::Present) => return Err(VmaError::AlreadyPopulated),
+            Err(_) => return Err(VmaError::Busy),
+        }
+        Ok(PopulateGuard {
+            region: self,
+            page_addr,
+            finished: false,
+        })
+    }
+
+    /// Population state of the page containing `vaddr`
+    pub fn page_state(&self, vaddr: A) -> PageState {
+        let page_addr = vaddr.align_down(self.align);
+        let populated = self.populated.lock();
+        state_in(&populated, &self.transitions.lock(), page_addr)
+    }
+
+    /// Move the page containing `vaddr` from NotPresent to Loading
+    /// Returns whether the transition was legal; reservations never load
+    pub fn try_start_load(&self, vaddr: A) -> bool {
+        let page_addr = vaddr.align_down(self.align);
+        !self.is_reserved()
+            && self
+                .transition(page_addr, PageState::NotPresent, PageState::Loading)
+                .is_ok()
+    }
+
+    /// Move the page containing `vaddr` from Loading to Present
+    /// Returns whether the transition was legal
+    pub fn finish_load(&self, vaddr: A) -> bool {
+        let page_addr = vaddr.align_down(self.align);
+        self.transition(page_addr, PageState::Loading, PageState::Present)
+            .is_ok()
     }
-}
 
+    /// Move the page containing `vaddr` from Loading back to NotPresent
+    /// Returns whether the transition was legal
+    pub fn fail_load(&self, vaddr: A) -> bool {
+        let page_addr = vaddr.align_down(self.align);
+        self.transition(page_addr, PageState::Loading, PageState::NotPresent)
+            .is_ok()
+    }
+
+    /// Move the page containing `vaddr` from Present to Evicting
+    /// While it is evicting, faults on the page fail with Busy and it cannot
+    /// be marked dirty
+    /// Returns whether the transition was legal
+    pub fn try_start_evict(&self, vaddr: A) -> bool {
+        let page_addr = vaddr.align_down(self.align);
+        self.transition(page_addr, PageState::Present, PageState::Evicting)
+            .is_ok()
+    }
+
+    /// Move the page containing `vaddr` from Evicting to NotPresent, dropping
+    /// its copy-on-write state
+    /// Returns whether the transition was legal
+    pub fn finish_evict(&self, vaddr: A) -> bool {
+        let page_addr = vaddr.align_down(self.align);
+        let finished = self
+            .transition(page_addr, PageState::Evicting, PageState::NotPresent)
+            .is_ok();
+        if finished {
+            self.metrics.record_evictions(1);
+            vma_trace!("evict {} vaddr={}", Label(self), Addr(page_addr));
+        }
+        finished
+    }
+
+    /// Move the page containing `vaddr` from Evicting back to Present
+    /// Returns whether the transition was legal
+    pub fn cancel_evict(&self, vaddr: A) -> bool {
+        let page_addr = vaddr.align_down(self.align);
+        self.transition(page_addr, PageState::Evicting, PageState::Present)
+            .is_ok()
+    }
+
+    /// Move the page at `page_addr` from state `from` to state `to`
+    /// Returns the current state if it is not `from`
+    fn transition(&self, page_addr: A, from: PageState, to: PageState) -> Result<(), PageState> {
+        let mut populated = self.populated.lock();
+        let mut transitions = self.transitions.lock();
+        let current = state_in(&populated, &transitions, page_addr);
+        if current != from {
+            return Err(current);
+        }
+        match to {
+            PageState::Loading | PageState::Evicting => {
+                transitions.insert(page_addr, to);
+            }
+            PageState::Present | PageState::NotPresent => {
+                transitions.remove(&page_addr);
+            }
+        }
+        match (from, to) {
+            (PageState::Loading, PageState::Present) => {
+                populated.insert(page_addr);
+            }
+            (PageState::Evicting, PageState::NotPresent) => {
+                populated.remove(page_addr);
+                self.cow.lock().remove(page_addr);
+            }
+            _ => {}
+        }
+        self.present
+            .store(present_in(&populated, &transitions), Ordering::Release);
+        Ok(())
+    }
+
+    /// Record the page at `page_addr` as populated without loading any data
+    /// Returns AlreadyPopulated if the page is already populated
+    fn populate_zero(&self, page_addr: A) -> VmaResult<(), A> {
+        self.begin_populate(page_addr)?.commit();
+        vma_trace!("populate {} vaddr={} zero", Label(self), Addr(page_addr));
+        Ok(())
+    }
+
+    /// Load data from file for the given virtual address
+    /// Whole pages the backend holds in memory are borrowed through
+    /// `VmFile::read_page_ref`; other pages are copied into a fresh buffer
+    /// The data always spans a whole page of the region's alignment, so that
+    /// it can be mapped as such even at the end of a region whose size is not
+    /// a multiple of it
+    /// Anonymous regions and pages past the file-backed length yield a
+    /// zero-filled buffer without touching any file, and the part of a page
+    /// past the end of the region, the file or the file-backed length is
+    /// zero-filled
+    /// Returns OutOfRange if `vaddr` lies outside the region, even within its
+    /// last page, AlreadyPopulated if the page is already populated, Busy if
+    /// another caller is populating it, AccessDenied in a reservation,
+    /// Unmapped in a hole, OffsetOutOfFile if the page lies before the start
+    /// of the file, BeyondEof if it lies past the end of the file and the EOF
+    /// policy is Bus, or Backend if file access fails
+    /// Only the data is returned, as its length always matches the region's
+    /// alignment; `VmaManager::handle_fault` returns the same page as a
+    /// `FaultResolution` with its address, mapping size and protection
+    pub fn get_buf(&self, vaddr: A) -> VmaResult<PageData, A> {
+        self.check_in_range(vaddr)?;
+        let page_addr = vaddr.align_down(self.align);
+        let guard = self.begin_populate(page_addr)?;
+        if let Some(page) = self.borrow_page(page_addr) {
+            guard.commit();
+            vma_trace!(
+                "populate {} vaddr={} borrowed={}",
+                Label(self),
+                Addr(page_addr),
+                page.len(),
+            );
+            return Ok(PageData::Borrowed(page));
+        }
+
+        let buf = self.load_page(page_addr, self.align as usize)?;
+        guard.commit();
+        Ok(PageData::Owned(buf))
+    }
+
+    /// Check that `vaddr` itself lies within the region's range, before it is
+    /// aligned down to a page that may start inside the region
+    /// Returns OutOfRange otherwise
+    pub(crate) fn check_in_range(&self, vaddr: A) -> VmaResult<(), A> {
+        if !self.range.contains(vaddr) {
+            return Err(VmaError::OutOfRange {
+                vaddr,
+                range: self.range,
+            });
+        }
+        Ok(())
+    }
+
+    /// Borrow the whole page at `page_addr` from the backing file, if it
+    /// lies before the end of the region, the file-backed length and the file
+    /// limit and the backend holds it in memory
+    fn borrow_page(&self, page_addr: A) -> Option<PageRef> {
+        let len = self.align as usize;
+        if self.page_size_at(page_addr) < len || self.file_backed_bytes(page_addr, len) < len {
+            return None;
+        }
+        let (file, file_offset) = self.page_file_offset(page_addr).ok()?;
+        if self.readable_len(file_offset, len) < len {
+            return None;
+        }
+        file.read_page_ref(file_offset, len)
+            .filter(|page| page.len() == len)
+    }
+
+    /// Load data for the given virtual address directly into `dst`, like
+    /// `get_buf` a whole page
+    /// Bytes of `dst` past the loaded data are zero-filled
+    /// Returns the size of the loaded page, InvalidArgument if `dst` is
+    /// smaller than the page, or the same errors as `get_buf`
+    pub fn get_buf_into(&self, vaddr: A, dst: &mut [u8]) -> VmaResult<usize, A> {
+        self.check_in_range(vaddr)?;
+        let page_addr = vaddr.align_down(self.align);
+        let page_size = self.align as usize;
+        if dst.len() < page_size {
+            return Err(VmaError::InvalidArgument);
+        }
+        let guard = self.begin_populate(page_addr)?;
+
+        let (page, rest) = dst.split_at_mut(page_size);
+        self.fill_page(page_addr, page)?;
+        rest.fill(0);
+        guard.commit();
+
+        Ok(page_size)
+    }
+
+    /// Eagerly load every unpopulated page whose extent overlaps `range`
+    /// Pages already populated or being populated by another caller are
+    /// skipped, and runs of pages that are consecutive in the file are loaded
+    /// with a single read; reservations load nothing
+    /// Returns the loaded pages in ascending order, or the pages loaded before
+    /// the first failure together with the error
+    pub fn populate_range(&self, range: &AddrRange<A>) -> PopulateResult<A> {
+        let mut loaded = Vec::new();
+        if self.is_reserved() {
+            return Ok(loaded);
+        }
+        // Pages past the end of the file fail on their own in `load_page`
+        let file_len = match self.file_len() {
+            Ok(len) => len.filter(|_| self.eof_policy == EofPolicy::Bus),
+            Err(error) => {
+                return Err(PartialPopulate {
+                    loaded,
+                    error: error.into(),
+                });
+            }
+        };
+
+        let mut pages = self.page_addrs(range).peekable();
+        while let Some(page_addr) = pages.next() {
+            let guard = match self.begin_populate(page_addr) {
+                Ok(guard) => guard,
+                Err(VmaError::AlreadyPopulated | VmaError::Busy) => continue,
+                Err(error) => return Err(PartialPopulate { loaded, error }),
+            };
+            let mut prev = page_addr;
+            let mut guards = vec![guard];
+            while guards.len() < POPULATE_BATCH_PAGES
+                && let Some(&next) = pages.peek()
+                && self.in_file(next, file_len)
+                && self.follows_in_file(prev, next)
+                && let Ok(guard) = self.begin_populate(next)
+            {
+                pages.next();
+                guards.push(guard);
+                prev = next;
+            }
+
+            match self.load_run(guards) {
+                Ok(run) => loaded.extend(run),
+                Err(error) => return Err(PartialPopulate { loaded, error }),
+            }
+        }
+        Ok(loaded)
+    }
+
+    /// Resolve a fault at `vaddr`, also loading following unpopulated pages
+    /// that lie within the region and the file, up to the readahead window
+    /// given by the access hint
+    /// The faulting page and the pages read ahead are loaded with one read;
+    /// readahead stops at the first page that is already populated or does
+    /// not directly follow the previous one in the file
+    /// Returns the loaded pages in ascending order, starting with the faulting
+    /// page, or the same errors as `get_buf`
+    pub fn fault_with_readahead(&self, vaddr: A) -> VmaResult<Vec<(A, Vec<u8>)>, A> {
+        let page_addr = vaddr.align_down(self.align);
+        let mut guards = vec![self.begin_populate(page_addr)?];
+        let readahead = self.readahead_window();
+        if readahead > 0 && !self.is_anonymous() {
+            let file_len = self.file_len()?;
+            let window = AddrRange::new(page_addr, self.range.end);
+            let mut prev = page_addr;
+            for next in self.page_addrs(&window).skip(1).take(readahead) {
+                if !self.in_file(next, file_len) || !self.follows_in_file(prev, next) {
+                    break;
+                }
+                prev = next;
+                let Ok(guard) = self.begin_populate(next) else {
+                    break;
+                };
+                guards.push(guard);
+            }
+        }
+        let loaded = self.load_run(guards)?;
+        self.metrics.record_fault();
+        Ok(loaded)
+    }
+
+    /// Number of pages loaded ahead of a fault by `fault_with_readahead`
+    pub fn readahead(&self) -> usize {
+        self.readahead.load(Ordering::Relaxed)
+    }
+
+    /// Set the number of pages loaded ahead of a fault by `fault_with_readahead`
+    pub fn set_readahead(&self, pages: usize) {
+        self.readahead.store(pages, Ordering::Relaxed);
+    }
+
+    /// Expected access pattern of this region
+    pub fn access_hint(&self) -> AccessHint {
+        AccessHint::from_u8(self.access_hint.load(Ordering::Relaxed))
+    }
+
+    /// Set the expected access pattern of this region
+    pub fn set_access_hint(&self, hint: AccessHint) {
+        self.access_hint.store(hint as u8, Ordering::Relaxed);
+    }
+
+    /// Number of pages a fault actually reads ahead, given the access hint
+    fn readahead_window(&self) -> usize {
+        match self.access_hint() {
+            AccessHint::Normal => self.readahead(),
+            AccessHint::Sequential => self
+                .readahead()
+                .saturating_mul(2)
+                .max(SEQUENTIAL_READAHEAD_PAGES),
+            AccessHint::Random => 0,
+        }
+    }
+
+    /// Length of the backing file, or None for anonymous regions
+    fn file_len(&self) -> LinuxResult<Option<u64>> {
+        self.backing.file().map(VmFile::len).transpose()
+    }
+
+    /// Check if the page at `page_addr` starts before `file_len`
+    /// Always true without a file length limit
+    fn in_file(&self, page_addr: A, file_len: Option<u64>) -> bool {
+        match file_len {
+            Some(len) => self
+                .page_file_offset(page_addr)
+                .is_ok_and(|(_, offset)| offset < len),
+            None => true,
+        }
+    }
+
+    /// Load a run of consecutive reserved pages with a single read
+    /// The guards are committed only if the whole run loads successfully
+    fn load_run(&self, guards: Vec<PopulateGuard<'_, F, A, R>>) -> VmaResult<Vec<(A, Vec<u8>)>, A> {
+        let Some(first) = guards.first() else {
+            return Ok(Vec::new());
+        };
+        let page_size = self.align as usize;
+        let buf = self.load_page(first.page_addr(), guards.len() * page_size)?;
+
+        let mut loaded = Vec::with_capacity(guards.len());
+        for (guard, page) in guards.into_iter().zip(buf.chunks(page_size)) {
+            loaded.push((guard.page_addr(), page.to_vec()));
+            guard.commit();
+        }
+        Ok(loaded)
+    }
+
+    /// Switch the region to the smaller page size `new_align` in place
+    /// Every populated page is replaced by the smaller pages covering it, and
+    /// its dirty, copy-on-write, private and file offset state is carried over
+    /// Returns InvalidArgument if `new_align` is larger than the current page
+    /// size; otherwise returns the previously populated pages and their old
+    /// size, so that the caller can split their page table mappings
+    pub fn demote(&mut self, new_align: PageSize) -> VmaResult<Vec<(A, PageSize)>, A> {
+        let old_align = self.align;
+        if (new_align as usize) > (old_align as usize) {
+            return Err(VmaError::InvalidArgument);
+        }
+        if new_align as usize == old_align as usize {
+            return Ok(Vec::new());
+        }
+
+        let range = self.range;
+        let step = new_align as usize;
+        let subpages = |page: A| {
+            let end = core::cmp::min(page.add(old_align as usize), range.end);
+            (0..end.sub_addr(page).div_ceil(step)).map(move |i| page.add(i * step))
+        };
+        let expand = |pages: &mut PageSet<A>| {
+            let mut expanded = PageSet::with_page_size(new_align);
+            for page in pages.iter() {
+                subpages(page).for_each(|subpage| {
+                    expanded.insert(subpage);
+                });
+            }
+            core::mem::replace(pages, expanded)
+        };
+
+        self.unshare_population();
+        let demoted = expand(&mut self.populated.lock())
+            .iter()
+            .map(|page| (page, old_align))
+            .collect();
+        expand(self.dirty.get_mut());
+        expand(self.cow.get_mut());
+        expand(self.private.get_mut());
+        expand(self.lazy_free.get_mut());
+        expand(self.deferred.get_mut());
+        self.page_offsets = core::mem::take(&mut self.page_offsets)
+            .into_iter()
+            .flat_map(|(page, offset)| {
+                subpages(page).map(move |subpage| (subpage, offset + subpage.sub_addr(page) as u64))
+            })
+            .collect();
+        self.align = new_align;
+        self.recount_present();
+        Ok(demoted)
+    }
+
+    /// Collect the populated pages whose extent overlaps the given range
+    /// Returns the page addresses and sizes in ascending order
+    pub fn populated_in(&self, range: &AddrRange<A>) -> Vec<(A, PageSize)> {
+        let page_size = self.align as usize;
+        self.populated
+            .lock()
+            .iter()
+            .filter(|&page| range.overlaps(AddrRange::from_start_size(page, page_size)))
+            .map(|page| (page, self.align))
+            .collect()
+    }
+
+    /// Iterate over the populated pages in ascending order
+    /// The pages are copied out under the populated lock, which is released
+    /// before the iterator is returned
+    pub fn populated_iter(&self) -> vec::IntoIter<A> {
+        self.populated.lock().iter().collect::<Vec<_>>().into_iter()
+    }
+
+    /// Drop the populated pages whose extent overlaps the given range
+    /// Their dirty, copy-on-write and private state is discarded as well, so
+    /// private pages read the file again on the next fault
+    /// Returns the dropped page addresses in ascending order
+    pub fn release_range(&self, range: &AddrRange<A>) -> Vec<A> {
+        let page_size = self.align as usize;
+        self.release_where(|page| range.overlaps(AddrRange::from_start_size(page, page_size)))
+    }
+
+    /// Drop the populated pages whose file offset lies at or past `file_len`,
+    /// as when the backing file is truncated
+    /// A page straddling the new end of the file keeps its contents; dirty
+    /// pages are dropped without writeback
+    /// Returns the dropped page addresses in ascending order
+    pub fn release_past(&self, file_len: u64) -> Vec<A> {
+        if self.is_anonymous() {
+            return Vec::new();
+        }
+        self.release_where(|page| {
+            self.file_offset_of(page)
+                .is_ok_and(|offset| offset >= file_len)
+        })
+    }
+
+    /// Drop the given populated pages, along with their dirty, copy-on-write
+    /// and private state
+    fn release_pages(&self, pages: impl IntoIterator<Item = A>) {
+        let pages: BTreeSet<A> = pages.into_iter().collect();
+        self.release_where(|page| pages.contains(&page));
+    }
+
+    /// Drop the populated pages selected by `release`, along with their dirty,
+    /// copy-on-write, private and lazy-free state
+    /// Returns the dropped page addresses in ascending order
+    fn release_where(&self, release: impl Fn(A) -> bool) -> Vec<A> {
+        let mut populated = self.populated.lock();
+        let mut dirty = self.dirty.lock();
+        let mut cow = self.cow.lock();
+        let mut private = self.private.lock();
+        let mut lazy_free = self.lazy_free.lock();
+        let mut transitions = self.transitions.lock();
+        let mut deferred = self.deferred.lock();
+        // Parked faults on released pages find them gone when resolved
+        let parked: Vec<A> = deferred.iter().filter(|&page| release(page)).collect();
+        for page in parked {
+            deferred.remove(page);
+        }
+        let released: Vec<A> = populated.iter().filter(|&page| release(page)).collect();
+        for page in &released {
+            populated.remove(*page);
+            dirty.remove(*page);
+            cow.remove(*page);
+            private.remove(*page);
+            lazy_free.remove(*page);
+            // An eviction in flight finds the page gone and fails
+            transitions.remove(page);
+        }
+        self.present
+            .store(present_in(&populated, &transitions), Ordering::Release);
+        released
+    }
+
+    /// Drop the clean page containing `vaddr` so that the next fault reloads it
+    /// The page passes through Evicting, so a racing fault or eviction of the
+    /// same page backs off
+    /// Returns false if the page is not present, is dirty or private, or
+    /// belongs to a locked region or an anonymous region whose contents cannot
+    /// be reloaded
+    pub fn evict(&self, vaddr: A) -> bool {
+        if self.is_anonymous() || self.locked || !self.try_start_evict(vaddr) {
+            return false;
+        }
+        let page_addr = vaddr.align_down(self.align);
+        if self.dirty.lock().contains(page_addr) || self.private.lock().contains(page_addr) {
+            self.cancel_evict(page_addr);
+            return false;
+        }
+        let evicted = self.finish_evict(page_addr);
+        if evicted {
+            self.lazy_free.lock().remove(page_addr);
+        }
+        evicted
+    }
+
+    /// Clean present pages that eviction may drop, in ascending address order
+    /// Private pages and pages in flight are kept, and anonymous and locked
+    /// regions offer none
+    pub(crate) fn clean_candidates(&self) -> Vec<A> {
+        if self.is_anonymous() || self.locked {
+            return Vec::new();
+        }
+        let populated = self.populated.lock();
+        let dirty = self.dirty.lock();
+        let private = self.private.lock();
+        let transitions = self.transitions.lock();
+        populated
+            .iter()
+            .filter(|&page| !dirty.contains(page) && !private.contains(page))
+            .filter(|page| !transitions.contains_key(page))
+            .collect()
+    }
+
+    /// Lazily freed pages that reclaim may drop, in ascending address order
+    /// Pages in flight are kept, and locked regions offer none
+    pub(crate) fn lazy_candidates(&self) -> Vec<A> {
+        if self.locked {
+            return Vec::new();
+        }
+        // Lock in the order of `release_where`, starting with `populated`
+        let _populated = self.populated.lock();
+        let lazy_free = self.lazy_free.lock();
+        let transitions = self.transitions.lock();
+        lazy_free
+            .iter()
+            .filter(|page| !transitions.contains_key(page))
+            .collect()
+    }
+
+    /// Evict those of `pages` that are still clean candidates, see
+    /// `clean_candidates`
+    /// Returns the evicted page addresses
+    pub(crate) fn evict_clean(&self, pages: &[A]) -> Vec<A> {
+        if self.is_anonymous() || self.locked || pages.is_empty() {
+            return Vec::new();
+        }
+        let mut populated = self.populated.lock();
+        let dirty = self.dirty.lock();
+        let mut cow = self.cow.lock();
+        let private = self.private.lock();
+        let mut lazy_free = self.lazy_free.lock();
+        let transitions = self.transitions.lock();
+        let evicted: Vec<A> = pages
+            .iter()
+            .copied()
+            .filter(|&page| populated.contains(page))
+            .filter(|&page| !dirty.contains(page) && !private.contains(page))
+            .filter(|page| !transitions.contains_key(page))
+            .collect();
+        for page in &evicted {
+            populated.remove(*page);
+            cow.remove(*page);
+            lazy_free.remove(*page);
+        }
+        self.present
+            .store(present_in(&populated, &transitions), Ordering::Release);
+        self.metrics.record_evictions(evicted.len());
+        if !evicted.is_empty() {
+            vma_trace!("evict {} pages={} clean", Label(self), evicted.len());
+        }
+        evicted
+    }
+
+    /// Drop those of `pages` that are still lazily freed and not in flight,
+    /// dirty ones included, as their contents are disposable and never
+    /// written back
+    /// Returns the dropped page addresses
+    pub(crate) fn reclaim_lazy(&self, pages: &[A]) -> Vec<A> {
+        if self.locked || pages.is_empty() {
+            return Vec::new();
+        }
+        let mut populated = self.populated.lock();
+        let mut dirty = self.dirty.lock();
+        let mut cow = self.cow.lock();
+        let mut private = self.private.lock();
+        let mut lazy_free = self.lazy_free.lock();
+        let transitions = self.transitions.lock();
+        let reclaimed: Vec<A> = pages
+            .iter()
+            .copied()
+            .filter(|&page| lazy_free.contains(page))
+            .filter(|page| !transitions.contains_key(page))
+            .collect();
+        for page in &reclaimed {
+            populated.remove(*page);
+            dirty.remove(*page);
+            cow.remove(*page);
+            private.remove(*page);
+            lazy_free.remove(*page);
+        }
+        self.present
+            .store(present_in(&populated, &transitions), Ordering::Release);
+        self.metrics.record_evictions(reclaimed.len());
+        if !reclaimed.is_empty() {
+            vma_trace!("evict {} pages={} lazy", Label(self), reclaimed.len());
+        }
+        reclaimed
+    }
+
+    /// Mark the populated pages of this region within `range` as lazily freed
+    fn free_lazily(&self, range: &AddrRange<A>) {
+        let populated = self.populated.lock();
+        let mut lazy_free = self.lazy_free.lock();
+        for page in populated.subset(*range).iter() {
+            lazy_free.insert(page);
+        }
+    }
+
+    /// Check if the page containing `vaddr` is lazily freed
+    pub fn is_lazy_free(&self, vaddr: A) -> bool {
+        self.lazy_free.lock().contains(vaddr.align_down(self.align))
+    }
+
+    /// Cancel the lazy freeing of the page containing `vaddr`, as a write to
+    /// it does, so that reclaim treats it like any other page again
+    /// Called by the write fault path; returns whether the page was lazily
+    /// freed
+    pub fn touch(&self, vaddr: A) -> bool {
+        self.lazy_free.lock().remove(vaddr.align_down(self.align))
+    }
+
+    /// Mark the populated page containing `vaddr` as dirty
+    /// Returns false if the page is not present
+    pub fn mark_dirty(&self, vaddr: A) -> bool {
+        let page_addr = vaddr.align_down(self.align);
+        let populated = self.populated.lock();
+        if state_in(&populated, &self.transitions.lock(), page_addr) != PageState::Present {
+            return false;
+        }
+        self.dirty.lock().insert(page_addr);
+        self.touch(page_addr);
+        true
+    }
+
+    /// Fault, load and eviction counts of this region since it was created
+    /// Always zero without the `metrics` feature
+    pub fn metrics(&self) -> VmaMetrics {
+        self.metrics.get()
+    }
+
+    /// Number of bytes mapped by this region, leaving out its holes
+    pub fn mapped_bytes(&self) -> usize {
+        self.range.size() - self.hole_bytes()
+    }
+
+    /// Number of bytes of this region backed by populated pages
+    pub fn resident_bytes(&self) -> usize {
+        self.page_bytes(&self.populated.lock())
+    }
+
+    /// Number of bytes of this region backed by dirty pages
+    pub fn dirty_bytes(&self) -> usize {
+        self.page_bytes(&self.dirty.lock())
+    }
+
+    /// Total size of the given pages of this region, clipping a partial last page
+    fn page_bytes(&self, pages: &PageSet<A>) -> usize {
+        if pages.is_empty() {
+            return 0;
+        }
+        let last_page = self.range.end.sub(1).align_down(self.align);
+        let clipped = match pages.contains(last_page) {
+            true => self.align as usize - self.page_size_at(last_page),
+            false => 0,
+        };
+        pages.len() * self.align as usize - clipped
+    }
+
+    /// Check if the page containing `vaddr` is populated
+    pub fn is_populated(&self, vaddr: A) -> bool {
+        self.populated.lock().contains(vaddr.align_down(self.align))
+    }
+
+    /// Check if the page containing `vaddr` is dirty
+    pub fn is_dirty(&self, vaddr: A) -> bool {
+        self.dirty.lock().contains(vaddr.align_down(self.align))
+    }
+
+    /// Take all dirty pages, marking them clean
+    /// Returns the dirty page addresses in ascending order
+    pub fn take_dirty_pages(&self) -> Vec<A> {
+        let _populated = self.populated.lock();
+        let mut dirty = self.dirty.lock();
+        let pages = dirty.iter().collect();
+        dirty.clear();
+        pages
+    }
+
+    /// Collect the dirty pages whose extent overlaps the given range
+    /// Returns the page addresses in ascending order
+    pub fn dirty_in(&self, range: &AddrRange<A>) -> Vec<A> {
+        let page_size = self.align as usize;
+        self.dirty
+            .lock()
+            .iter()
+            .filter(|&page| range.overlaps(AddrRange::from_start_size(page, page_size)))
+            .collect()
+    }
+
+    /// Mark the page containing `vaddr` as clean
+    /// Returns whether the page was dirty
+    pub fn clear_dirty(&self, vaddr: A) -> bool {
+        let _populated = self.populated.lock();
+        self.dirty.lock().remove(vaddr.align_down(self.align))
+    }
+
+    /// Is this a shared mapping?
+    pub fn is_shared(&self) -> bool {
+        self.flags.contains(MmapFlags::SHARED)
+    }
+
+    /// Is this a private writable mapping without NORESERVE, which the strict
+    /// commit policy charges for?
+    pub fn is_accountable(&self) -> bool {
+        self.accountable_with(self.prot)
+    }
+
+    /// Would this region be accountable with the protection `prot`?
+    fn accountable_with(&self, prot: MmapProt) -> bool {
+        !self.is_reserved()
+            && !self.is_shared()
+            && prot.contains(MmapProt::WRITE)
+            && !self.flags.contains(MmapFlags::NORESERVE)
+    }
+
+    /// Number of bytes the strict commit policy charges for this region
+    fn commit_size(&self) -> usize {
+        if self.is_accountable() {
+            self.mapped_bytes()
+        } else {
+            0
+        }
+    }
+
+    /// Check if the page containing `vaddr` is shared copy-on-write
+    pub fn is_cow(&self, vaddr: A) -> bool {
+        self.cow.lock().contains(vaddr.align_down(self.align))
+    }
+
+    /// Check if the page containing `vaddr` was written in a private file
+    /// mapping and no longer matches the file
+    pub fn is_private(&self, vaddr: A) -> bool {
+        self.private.lock().contains(vaddr.align_down(self.align))
+    }
+
+    /// Resolve a write fault at `vaddr`, deciding how the page becomes writable
+    /// Unpopulated pages are loaded like `get_buf`; pages shared copy-on-write
+    /// are copied like `break_cow`, and unwritten pages of private file
+    /// mappings are copied from the file. The written page is then marked
+    /// dirty in shared mappings and private in private file mappings
+    /// Returns OutOfRange if `vaddr` lies outside the region, AccessDenied if
+    /// the region is not writable, or the same errors as `get_buf`
+    pub fn handle_write_fault(&self, vaddr: A) -> VmaResult<WriteFaultAction, A> {
+        if !self.contains(vaddr) {
+            return Err(VmaError::OutOfRange {
+                vaddr,
+                range: self.range,
+            });
+        }
+        if !self.prot.allows(AccessFlags::WRITE) {
+            return Err(VmaError::AccessDenied);
+        }
+
+        let page_addr = vaddr.align_down(self.align);
+        let action = if !self.is_populated(page_addr) {
+            WriteFaultAction::Load(self.get_buf(page_addr)?)
+        } else if self.is_cow(page_addr) {
+            WriteFaultAction::Copy(self.break_cow(page_addr)?)
+        } else if self.is_shared() || self.is_anonymous() || self.is_private(page_addr) {
+            WriteFaultAction::MakeWritable
+        } else {
+            let buf = self.load_page(page_addr, self.align as usize)?;
+            WriteFaultAction::Copy(CowCopy::Data(buf))
+        };
+        self.mark_written(page_addr);
+        Ok(action)
+    }
+
+    /// Record a write to the populated page at `page_addr`
+    fn mark_written(&self, page_addr: A) {
+        let populated = self.populated.lock();
+        if !populated.contains(page_addr) {
+            return;
+        }
+        if self.is_shared() {
+            self.dirty.lock().insert(page_addr);
+        } else if !self.is_anonymous() {
+            self.private.lock().insert(page_addr);
+        }
+        drop(populated);
+        self.touch(page_addr);
+    }
+
+    /// Mark every populated page as shared copy-on-write
+    fn share_populated_cow(&self) {
+        let populated = self.populated.lock();
+        let _dirty = self.dirty.lock();
+        self.cow.lock().union_with(&populated);
+    }
+
+    /// Break copy-on-write sharing of the page containing `vaddr`
+    /// Called by the write fault handler to obtain the contents of the new
+    /// private page; unmodified pages are reloaded from the backing store
+    /// Returns InvalidArgument if the page is not shared copy-on-write
+    pub fn break_cow(&self, vaddr: A) -> VmaResult<CowCopy, A> {
+        let page_addr = vaddr.align_down(self.align);
+        if !self.cow.lock().contains(page_addr) {
+            return Err(VmaError::InvalidArgument);
+        }
+
+        let copy = if self.is_dirty(page_addr) || self.is_private(page_addr) {
+            CowCopy::CopyFrame
+        } else {
+            let buf = self.load_page(page_addr, self.align as usize)?;
+            CowCopy::Data(buf)
+        };
+        self.cow.lock().remove(page_addr);
+        Ok(copy)
+    }
+
+    /// Write the contents of the page containing `vaddr` back to the file
+    /// Data beyond the page, the file-backed length, the file limit or the
+    /// end of the file is not written, so pages past the file-backed length
+    /// write nothing
+    /// Returns the number of bytes written, Anonymous for anonymous regions,
+    /// or OffsetOutOfFile if the page lies outside the file
+    pub fn flush_page(&self, vaddr: A, data: &[u8]) -> VmaResult<usize, A> {
+        let page_addr = vaddr.align_down(self.align);
+        let (file, file_offset) = self.page_file_offset(page_addr)?;
+        let backed = self.file_backed_bytes(page_addr, self.page_size_at(page_addr));
+        if backed == 0 {
+            return Ok(0);
+        }
+        let file_len = file.len()?;
+        if file_offset >= file_len {
+            return Err(VmaError::OffsetOutOfFile {
+                offset: file_offset as i64,
+                file_len,
+            });
+        }
+        let file_remaining = file_len - file_offset;
+        let write_size = data
+            .len()
+            .min(backed)
+            .min(usize::try_from(file_remaining).unwrap_or(usize::MAX));
+        let write_size = self.readable_len(file_offset, write_size);
+        if write_size == 0 {
+            return Ok(0);
+        }
+        Ok(file.write_at(&data[..write_size], file_offset)?)
+    }
+}
+
+impl<F: VmFile, A: MemoryAddr, R: RawMutex> MmapRegion<F, A, R> {
+    /// Take the range, file and populated pages of a region that left its
+    /// manager, see `RemovedRegions::into_teardown`
+    pub fn into_teardown(self) -> RegionTeardown<F, A> {
+        let file = match self.backing {
+            RegionBacking::File { file, .. } => Some(file),
+            RegionBacking::Anonymous | RegionBacking::Reserved => None,
+        };
+        RegionTeardown {
+            range: self.range,
+            file,
+            populated: Arc::try_unwrap(self.populated)
+                .map_or_else(|shared| shared.lock().clone(), Mutex::into_inner),
+        }
+    }
+
+    /// Copy the region like `clone`, keeping its counters
+    fn detached(&self) -> Self {
+        let copy = self.clone();
+        copy.metrics.add(self.metrics.get());
+        copy
+    }
+}
+
+impl<F: VmFile, A: MemoryAddr, R: RawMutex> Clone for MmapRegion<F, A, R> {
+    /// Copy the region along with its page state, as when forking a private
+    /// mapping; the copy loads and evicts its pages independently, see
+    /// `share` for a handle to the same mapping
+    fn clone(&self) -> Self {
+        let populated = self.populated.lock();
+        let dirty = self.dirty.lock();
+        Self {
+            range: self.range,
+            backing: self.backing.clone(),
+            populated: Arc::new(Mutex::new(populated.clone())),
+            dirty: Mutex::new(dirty.clone()),
+            cow: Mutex::new(self.cow.lock().clone()),
+            private: Mutex::new(self.private.lock().clone()),
+            lazy_free: Mutex::new(self.lazy_free.lock().clone()),
+            transitions: Arc::new(Mutex::new(BTreeMap::new())),
+            deferred: Mutex::new(PageSet::with_page_size(self.align)),
+            present: Arc::new(AtomicUsize::new(populated.len())),
+            align: self.align,
+            prot: self.prot,
+            flags: self.flags,
+            eof_policy: self.eof_policy,
+            name: self.name.clone(),
+            readahead: AtomicUsize::new(self.readahead()),
+            access_hint: AtomicU8::new(self.access_hint() as u8),
+            pinned: self.pinned,
+            locked: self.locked,
+            external_handler: self.external_handler,
+            file_limit: self.file_limit,
+            file_backed_len: self.file_backed_len,
+            guard_below: self.guard_below,
+            guard_above: self.guard_above,
+            page_offsets: self.page_offsets.clone(),
+            holes: self.holes.clone(),
+            id: self.id,
+            metrics: MetricCounters::default(),
+        }
+    }
+}
+
+impl<F: VmFile, A: MemoryAddr, R: RawMutex> fmt::Debug for MmapRegion<F, A, R> {
+    /// Format the region as `start-end align name`, or with `{:#?}` as a
+    /// struct of its layout and number of populated pages
+    /// The populated lock is only tried, so formatting never blocks and shows
+    /// `<locked>` while another caller holds it
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let range = format_args!(
+            "{:#x}-{:#x}",
+            self.range.start.into(),
+            self.range.end.into()
+        );
+        if !f.alternate() {
+            write!(f, "{range} {:#x}", self.align as usize)?;
+            if let Some(name) = &self.name {
+                write!(f, " {name}")?;
+            }
+            return Ok(());
+        }
+        let mut region = f.debug_struct("MmapRegion");
+        region
+            .field("range", &range)
+            .field("align", &format_args!("{:#x}", self.align as usize))
+            .field("prot", &self.prot)
+            .field("flags", &self.flags)
+            .field("name", &self.name);
+        match &self.backing {
+            RegionBacking::File { offset, .. } => region.field("offset", offset),
+            RegionBacking::Anonymous => region.field("backing", &format_args!("anonymous")),
+            RegionBacking::Reserved => region.field("backing", &format_args!("reserved")),
+        };
+        match self.populated.try_lock() {
+            Some(populated) => region.field("populated", &populated.len()),
+            None => region.field("populated", &format_args!("<locked>")),
+        };
+        region.finish()
+    }
+}
+
+/// Segments produced by splitting a region: (before, overlap, after)
+pub type SplitSegments<F, A = VirtAddr, R = DefaultRawMutex> = (
+    Option<MmapRegion<F, A, R>>,
+    Option<MmapRegion<F, A, R>>,
+    Option<MmapRegion<F, A, R>>,
+);
+
+/// Keys of the regions overlapping a range, along with their segments
+type RegionSplits<F, A, R> = (Vec<A>, Vec<SplitSegments<F, A, R>>);
+
+/// Check that a region range is non-empty and that it and its file offset are
+/// aligned to `align`
+/// Returns InvalidArgument for an empty range, or Unaligned
+fn validate_geometry<A: MemoryAddr>(
+    range: AddrRange<A>,
+    offset: i64,
+    align: PageSize,
+) -> VmaResult<(), A> {
+    if range.is_empty() {
+        return Err(VmaError::InvalidArgument);
+    }
+    if !range.start.is_aligned(align)
+        || !range.end.is_aligned(align)
+        || offset.rem_euclid(align as i64) != 0
+    {
+        return Err(VmaError::Unaligned);
+    }
+    Ok(())
+}
+
+/// State of the page at `page_addr`, given a region's populated pages and
+/// pages in flight
+fn state_in<A: MemoryAddr>(
+    populated: &PageSet<A>,
+    transitions: &BTreeMap<A, PageState>,
+    page_addr: A,
+) -> PageState {
+    match transitions.get(&page_addr) {
+        Some(&state) => state,
+        None if populated.contains(page_addr) => PageState::Present,
+        None => PageState::NotPresent,
+    }
+}
+
+/// File data of a page to be loaded, as located by `MmapRegion::fill_source`
+pub(crate) struct FillSource<'a, F> {
+    /// Backing file of the page
+    pub(crate) file: &'a F,
+    /// Offset of the page in the file
+    pub(crate) file_offset: u64,
+    /// Number of bytes of the page before the file limit
+    pub(crate) readable: usize,
+}
+
+/// Check the outcome of reading into a page that has `filled` of its
+/// `readable` bytes, of which `available` lie before the end of the file
+/// Returns the number of bytes read, zero to retry an interrupted read, None
+/// once the file ends, or Backend if the file fails or ends early
+pub(crate) fn read_progress<A: MemoryAddr>(
+    read: LinuxResult<usize>,
+    filled: usize,
+    readable: usize,
+    available: usize,
+) -> VmaResult<Option<usize>, A> {
+    match read {
+        Ok(0) if filled < available => Err(VmaError::Backend(LinuxError::EIO)),
+        Ok(0) => Ok(None),
+        Ok(read) if read > readable - filled => Err(VmaError::Backend(LinuxError::EIO)),
+        Ok(read) => Ok(Some(read)),
+        Err(LinuxError::EINTR) => Ok(Some(0)),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Zero `buf` and view it as initialized
+fn zeroed(buf: &mut [MaybeUninit<u8>]) -> &mut [u8] {
+    buf.fill(MaybeUninit::new(0));
+    // SAFETY: every byte of `buf` was just initialized
+    unsafe { buf.assume_init_mut() }
+}
+
+/// Number of pages in the Present state, given the populated pages and the
+/// pages in flight
+fn present_in<A: MemoryAddr>(
+    populated: &PageSet<A>,
+    transitions: &BTreeMap<A, PageState>,
+) -> usize {
+    let evicting = transitions
+        .values()
+        .filter(|&&state| state == PageState::Evicting)
+        .count();
+    populated.len() - evicting
+}
+
+/// Add a byte delta to a signed file offset, returning Overflow on overflow
+fn checked_offset_add<A: MemoryAddr>(offset: i64, delta: usize) -> VmaResult<i64, A> {
+    i64::try_from(delta)
+        .ok()
+        .and_then(|delta| offset.checked_add(delta))
+        .ok_or(VmaError::Overflow)
+}
+
+/// Align `vaddr` up to `align`, returning None on overflow
+pub(crate) fn checked_align_up<A: MemoryAddr>(vaddr: A, align: PageSize) -> Option<A> {
+    let mask = align as usize - 1;
+    let aligned = vaddr.into().checked_add(mask)? & !mask;
+    Some(A::from(aligned))
+}
+
+/// Get exclusive access to a region stored by a manager, first replacing it by
+/// a copy if handles share it
+fn unique_mut<F: VmFile, A: MemoryAddr, R: RawMutex>(
+    region: &mut Arc<MmapRegion<F, A, R>>,
+) -> &mut MmapRegion<F, A, R> {
+    if Arc::get_mut(region).is_none() {
+        *region = Arc::new(region.detached());
+    }
+    Arc::make_mut(region)
+}
+
+/// Take a region out of its `Arc`, copying it if handles still share it
+fn into_owned<F: VmFile, A: MemoryAddr, R: RawMutex>(
+    region: Arc<MmapRegion<F, A, R>>,
+) -> MmapRegion<F, A, R> {
+    Arc::try_unwrap(region).unwrap_or_else(|shared| shared.detached())
+}
+
+/// Result of removing an address range from a VmaManager
+pub struct RemovedRegions<F: VmFile, A: MemoryAddr = VirtAddr, R: RawMutex = DefaultRawMutex> {
+    /// Segments removed from the manager, in address order
+    pub regions: Vec<MmapRegion<F, A, R>>,
+    /// Populated pages of the removed segments, in address order
+    pub pages: Vec<(A, PageSize)>,
+    /// Populated pages each segment of the overlapping regions inherited when
+    /// they were split, one report per region in address order
+    pub splits: Vec<SplitReport<A>>,
+}
+
+impl<F: VmFile, A: MemoryAddr, R: RawMutex> Default for RemovedRegions<F, A, R> {
+    fn default() -> Self {
+        Self {
+            regions: Vec::new(),
+            pages: Vec::new(),
+            splits: Vec::new(),
+        }
+    }
+}
+
+impl<F: VmFile, A: MemoryAddr, R: RawMutex> RemovedRegions<F, A, R> {
+    /// Record a region removed from the manager together with its populated
+    /// pages, so the caller can unmap them
+    pub(crate) fn push(&mut self, region: MmapRegion<F, A, R>) {
+        self.pages.extend(region.populated_in(&region.range));
+        self.regions.push(region);
+    }
+
+    /// Take the resources of every removed segment by value, in address order
+    /// Only segments that left the manager are included, never the parts of a
+    /// split region that were retained
+    pub fn into_teardown(self) -> Vec<RegionTeardown<F, A>> {
+        self.regions
+            .into_iter()
+            .map(MmapRegion::into_teardown)
+            .collect()
+    }
+}
+
+/// Resources of a region removed from a manager, so that the caller can
+/// release its file handle and free its frames in a deterministic order
+pub struct RegionTeardown<F: VmFile, A: MemoryAddr = VirtAddr> {
+    /// Address range the region mapped
+    pub range: AddrRange<A>,
+    /// Backing file of the region, or None for anonymous and reserved regions
+    pub file: Option<F>,
+    /// Pages that were populated, whose frames can be freed
+    pub populated: PageSet<A>,
+}
+
+/// Outcome of resizing or moving a mapping with `VmaManager::remap`
+pub struct Remapped<F: VmFile, A: MemoryAddr = VirtAddr, R: RawMutex = DefaultRawMutex> {
+    /// Start address of the mapping after the call
+    /// Populated pages of a moved mapping keep their offset from the start
+    pub start: A,
+    /// Regions and pages dropped by shrinking, to be unmapped by the caller
+    pub removed: RemovedRegions<F, A, R>,
+}
+
+/// Outcome of looking up an address with `VmaManager::lookup`
+pub enum Lookup<'a, F: VmFile, A: MemoryAddr = VirtAddr, R: RawMutex = DefaultRawMutex> {
+    /// The region containing the address
+    Hit(&'a MmapRegion<F, A, R>),
+    /// No region contains the address; these are the regions directly below
+    /// and above it
+    Miss {
+        prev: Option<&'a MmapRegion<F, A, R>>,
+        next: Option<&'a MmapRegion<F, A, R>>,
+    },
+}
+
+impl<F: VmFile, A: MemoryAddr, R: RawMutex> Lookup<'_, F, A, R> {
+    /// Region containing the address, if any
+    pub fn hit(&self) -> Option<&MmapRegion<F, A, R>> {
+        match self {
+            Self::Hit(region) => Some(region),
+            Self::Miss { .. } => None,
+        }
+    }
+
+    /// Unmapped range around a missed address, from the end of the region
+    /// below (or zero) to the start of the region above (or the highest
+    /// address)
+    pub fn hole(&self) -> Option<AddrRange<A>> {
+        match self {
+            Self::Hit(_) => None,
+            Self::Miss { prev, next } => {
+                let start = prev.map_or(A::from(0), |prev| prev.range.end);
+                let end = next.map_or(A::from(usize::MAX), |next| next.range.start);
+                Some(AddrRange::new(start, end))
+            }
+        }
+    }
+}
+
+/// Memory accounting of a `VmaManager`, as reported by `VmaManager::stats`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct VmaStats {
+    /// Number of bytes mapped by all regions
+    pub total_mapped: usize,
+    /// Number of bytes backed by populated pages
+    pub total_resident: usize,
+    /// Number of bytes backed by dirty pages
+    pub total_dirty: usize,
+    /// Number of regions
+    pub region_count: usize,
+    /// Size of the largest unmapped gap between two regions
+    pub largest_gap: usize,
+}
+
+impl VmaStats {
+    /// Collect the accounting of regions ordered by start address, which
+    /// may overlap
+    fn of<'a, F: VmFile + 'a, A: MemoryAddr + 'a, R: RawMutex + 'a>(
+        regions: impl Iterator<Item = &'a MmapRegion<F, A, R>>,
+    ) -> Self {
+        let mut stats = Self::default();
+        let mut prev_end: Option<A> = None;
+        for region in regions {
+            stats.total_mapped += region.mapped_bytes();
+            stats.total_resident += region.resident_bytes();
+            stats.total_dirty += region.dirty_bytes();
+            stats.region_count += 1;
+            if let Some(end) = prev_end
+                && region.range.start > end
+            {
+                stats.largest_gap = stats.largest_gap.max(region.range.start.sub_addr(end));
+            }
+            prev_end = Some(prev_end.map_or(region.range.end, |end| end.max(region.range.end)));
+        }
+        stats
+    }
+}
+
+/// Manager for Virtual Memory Areas with file backing
+/// Like `MmapRegion`, it can manage any `MemoryAddr` address space
+pub struct VmaManager<F: VmFile, A: MemoryAddr = VirtAddr, R: RawMutex = DefaultRawMutex> {
+    /// Memory-mapped regions keyed by the end address of their range
+    /// Regions never overlap, so the first region ending above an address is
+    /// the only one that can contain it; they are shared with the handles
+    /// returned by `find_region_arc`
+    regions: BTreeMap<A, Arc<MmapRegion<F, A, R>>>,
+    /// Observer notified of page and region changes
+    observer: Option<Arc<dyn VmaObserver<A>>>,
+    /// Furthest distance below a grows-down region at which a fault extends it
+    stack_max_distance: usize,
+    /// Unmapped space kept between a grows-down region and the region below it
+    stack_guard_gap: usize,
+    /// Maximum number of bytes of locked regions, as set by RLIMIT_MEMLOCK
+    memlock_limit: Option<usize>,
+    /// Maximum number of regions, as set by vm.max_map_count
+    max_regions: Option<usize>,
+    /// Write-xor-execute policy of region protections
+    wx_policy: WxPolicy,
+    /// Address range all regions must lie in, as the user part of an address
+    /// space
+    window: Option<AddrRange<A>>,
+    /// Maximum number of bytes all regions may map, as set by RLIMIT_AS
+    max_total_bytes: Option<usize>,
+    /// Number of bytes all regions map
+    total_bytes: usize,
+    /// Counts of regions that were removed, split or replaced, and of region
+    /// lookups
+    retired_metrics: MetricCounters,
+    /// End address of the region last found by `find_region_cached`
+    lookup_cache: LookupCache,
+    /// End addresses of the regions keyed by their ids
+    ids: BTreeMap<RegionId, A>,
+    /// Id given to the next region stored without one
+    next_id: u64,
+    /// Whether mappings are charged against a commit limit
+    commit_policy: CommitPolicy,
+    /// Number of bytes of accountable regions, charged against the commit
+    /// limit under the strict policy
+    committed: usize,
+    /// Manager whose regions show through at addresses no local region maps
+    fallback: Option<Arc<VmaManager<F, A, R>>>,
+    /// Locally unmapped ranges hiding the fallback, as start addresses keyed
+    /// by end address
+    shadows: BTreeMap<A, A>,
+    /// Number of regions from which unmaps inside a region punch holes
+    /// instead of splitting it, or None to always split
+    hole_threshold: Option<usize>,
+    /// Policy placing the mappings whose address the manager chooses
+    placement: PlacementPolicy<A>,
+}
+
+/// One-entry cache of the end address of the last region found, which keys
+/// the region in the map
+/// Zero means empty, since no region ends at address zero; clones start empty
+#[derive(Default)]
+struct LookupCache(AtomicUsize);
+
+impl LookupCache {
+    fn get(&self) -> Option<usize> {
+        Some(self.0.load(Ordering::Relaxed)).filter(|&end| end != 0)
+    }
+
+    fn set(&self, end: usize) {
+        self.0.store(end, Ordering::Relaxed);
+    }
+
+    fn clear(&self) {
+        self.0.store(0, Ordering::Relaxed);
+    }
+}
+
+impl Clone for LookupCache {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+/// Default furthest distance below a stack at which a fault extends it
+const DEFAULT_STACK_MAX_DISTANCE: usize = 64 * 1024;
+
+/// Default unmapped space kept below a growing stack, as on Linux
+const DEFAULT_STACK_GUARD_GAP: usize = 256 * PageSize::Size4K as usize;
+
+impl<F: VmFile, A: MemoryAddr, R: RawMutex> Default for VmaManager<F, A, R> {
+    fn default() -> Self {
+        Self {
+            regions: BTreeMap::new(),
+            observer: None,
+            stack_max_distance: DEFAULT_STACK_MAX_DISTANCE,
+            stack_guard_gap: DEFAULT_STACK_GUARD_GAP,
+            memlock_limit: None,
+            max_regions: None,
+            wx_policy: WxPolicy::Allow,
+            window: None,
+            max_total_bytes: None,
+            total_bytes: 0,
+            retired_metrics: MetricCounters::default(),
+            lookup_cache: LookupCache::default(),
+            ids: BTreeMap::new(),
+            next_id: 0,
+            commit_policy: CommitPolicy::Overcommit,
+            committed: 0,
+            fallback: None,
+            shadows: BTreeMap::new(),
+            hole_threshold: None,
+            placement: PlacementPolicy::default(),
+        }
+    }
+}
+
+impl<F: VmFile, A: MemoryAddr, R: RawMutex> Clone for VmaManager<F, A, R> {
+    /// Copy the manager and all of its regions, which are not shared with the
+    /// original
+    /// Under the strict commit policy the copy charges its regions again,
+    /// even past the limit
+    fn clone(&self) -> Self {
+        if let Some(limit) = self.commit_policy.limit() {
+            limit.charge(self.committed);
+        }
+        Self {
+            regions: self
+                .regions
+                .iter()
+                .map(|(&end, r)| (end, Arc::new(MmapRegion::clone(r))))
+                .collect(),
+            observer: self.observer.clone(),
+            stack_max_distance: self.stack_max_distance,
+            stack_guard_gap: self.stack_guard_gap,
+            memlock_limit: self.memlock_limit,
+            max_regions: self.max_regions,
+            wx_policy: self.wx_policy,
+            window: self.window,
+            max_total_bytes: self.max_total_bytes,
+            total_bytes: self.total_bytes,
+            retired_metrics: self.retired_metrics.clone(),
+            lookup_cache: self.lookup_cache.clone(),
+            ids: self.ids.clone(),
+            next_id: self.next_id,
+            commit_policy: self.commit_policy.clone(),
+            committed: self.committed,
+            fallback: self.fallback.clone(),
+            shadows: self.shadows.clone(),
+            hole_threshold: self.hole_threshold,
+            placement: self.placement,
+        }
+    }
+}
+
+impl<F: VmFile, A: MemoryAddr, R: RawMutex> Drop for VmaManager<F, A, R> {
+    /// Release the commit charge of the remaining regions
+    fn drop(&mut self) {
+        if let Some(limit) = self.commit_policy.limit() {
+            limit.release(self.committed);
+        }
+    }
+}
+
+impl<F: VmFile, A: MemoryAddr, R: RawMutex> fmt::Debug for VmaManager<F, A, R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.regions.values()).finish()
+    }
+}
+
+impl<F: VmFile> VmaManager<F> {
+    /// Create a new VMA manager of virtual addresses
+    /// Managers of other address types are created with `default`
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<F: VmFile, A: MemoryAddr, R: RawMutex> VmaManager<F, A, R> {
+    /// Create a manager whose regions must lie within `window` and map at
+    /// most `max_total_bytes` bytes together, as for a process address space
+    /// limited by RLIMIT_AS
+    pub fn with_limits(window: AddrRange<A>, max_total_bytes: Option<usize>) -> Self {
+        let mut manager = Self::default();
+        manager.window = Some(window);
+        manager.max_total_bytes = max_total_bytes;
+        manager
+    }
+
+    /// Set how mappings are charged against a commit limit
+    /// The charge of the existing regions moves to the new limit, even past it
+    pub fn set_commit_policy(&mut self, policy: CommitPolicy) {
+        if let Some(limit) = self.commit_policy.limit() {
+            limit.release(self.committed);
+        }
+        if let Some(limit) = policy.limit() {
+            limit.charge(self.committed);
+        }
+        self.commit_policy = policy;
+    }
+
+    /// Commit charge of the manager's accountable regions
+    /// Under the strict policy this is their full size, and under overcommit
+    /// only their resident pages count
+    pub fn commit_charge(&self) -> usize {
+        match self.commit_policy {
+            CommitPolicy::Strict(_) => self.committed,
+            CommitPolicy::Overcommit => self
+                .regions
+                .values()
+                .filter(|r| r.is_accountable())
+                .map(|r| r.resident_bytes())
+                .sum(),
+        }
+    }
+
+    /// Number of bytes that may still be mapped before reaching the address
+    /// space limit, or None if there is no limit
+    pub fn remaining_quota(&self) -> Option<usize> {
+        self.max_total_bytes
+            .map(|max| max.saturating_sub(self.total_bytes))
+    }
+
+    /// Configure how grows-down regions are extended by `handle_fault_or_grow`
+    /// Faults at most `max_distance` bytes below such a region extend it, as
+    /// long as `guard_gap` bytes stay unmapped above the region below it
+    pub fn set_stack_growth(&mut self, max_distance: usize, guard_gap: usize) {
+        self.stack_max_distance = max_distance;
+        self.stack_guard_gap = guard_gap;
+    }
+
+    /// Set the maximum number of regions, or remove the limit
+    /// Operations that would raise the region count above it fail with
+    /// RegionLimit without changing anything; lowering the limit below the
+    /// current count only blocks further growth
+    pub fn set_max_regions(&mut self, max: Option<usize>) {
+        self.max_regions = max;
+    }
+
+    /// Set the write-xor-execute policy checked when regions are added,
+    /// committed or change protection
+    /// Existing regions are not checked against a newly set policy
+    pub fn set_wx_policy(&mut self, policy: WxPolicy) {
+        self.wx_policy = policy;
+    }
+
+    /// Check if giving the given range the protection `prot` violates the
+    /// write-xor-execute policy, either because `prot` is writable and
+    /// executable or because it changes a region overlapping the range in a
+    /// way the policy forbids
+    pub fn would_violate_wx(&self, vaddr_range: AddrRange<A>, prot: MmapProt) -> bool {
+        !self.wx_policy.permits(None, prot)
+            || self
+                .overlapping(vaddr_range)
+                .any(|r| !self.wx_policy.permits(Some(r.prot), prot))
+    }
+
+    /// Check that the manager may hold `count` regions after an operation
+    /// Returns RegionLimit if the count would grow above the limit
+    fn check_region_count(&self, count: usize) -> VmaResult<(), A> {
+        match self.max_regions {
+            Some(max) if count > max && count > self.regions.len() => {
+                Err(VmaError::RegionLimit(max))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Check that the given range lies within the address space window
+    /// Returns InvalidArgument if it does not
+    fn check_window(&self, vaddr_range: AddrRange<A>) -> VmaResult<(), A> {
+        match self.window {
+            Some(window) if !window.contains_range(vaddr_range) => Err(VmaError::InvalidArgument),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check that mapping `added` more bytes after unmapping `removed` ones
+    /// keeps the total within the address space limit
+    /// Returns AddressSpaceLimit if the total would grow above the limit
+    fn check_total(&self, added: usize, removed: usize) -> VmaResult<(), A> {
+        let total = (self.total_bytes - removed).saturating_add(added);
+        match self.max_total_bytes {
+            Some(max) if total > max && added > removed => Err(VmaError::AddressSpaceLimit(max)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check that charging `added` more bytes after releasing `removed` ones
+    /// keeps the commit charge within the limit of the strict policy, and
+    /// reserve the growth until the returned reservation is dropped
+    /// The operation must charge the bytes itself while holding it
+    /// Returns CommitLimit if the charge would grow above the limit
+    fn check_commit(&self, added: usize, removed: usize) -> VmaResult<CommitReservation, A> {
+        self.commit_policy.reserve(added, removed).ok_or_else(|| {
+            VmaError::CommitLimit(self.commit_policy.limit().map_or(0, CommitLimit::limit))
+        })
+    }
+
+    /// Add `bytes` to the commit charge
+    fn charge(&mut self, bytes: usize) {
+        self.committed += bytes;
+        if let Some(limit) = self.commit_policy.limit() {
+            limit.charge(bytes);
+        }
+    }
+
+    /// Remove `bytes` from the commit charge
+    fn uncharge(&mut self, bytes: usize) {
+        self.committed -= bytes;
+        if let Some(limit) = self.commit_policy.limit() {
+            limit.release(bytes);
+        }
+    }
+
+    /// Number of bytes of the given range mapped by regions
+    fn mapped_in(&self, vaddr_range: AddrRange<A>) -> usize {
+        self.bytes_in(vaddr_range, |_| true)
+    }
+
+    /// Number of bytes of the given range mapped by accountable regions
+    fn committed_in(&self, vaddr_range: AddrRange<A>) -> usize {
+        self.bytes_in(vaddr_range, MmapRegion::is_accountable)
+    }
+
+    /// Number of bytes of the given range mapped by regions matching `pred`
+    fn bytes_in(
+        &self,
+        vaddr_range: AddrRange<A>,
+        pred: impl Fn(&MmapRegion<F, A, R>) -> bool,
+    ) -> usize {
+        self.overlapping(vaddr_range)
+            .filter(|r| pred(r))
+            .flat_map(|r| r.mapped_parts(vaddr_range))
+            .map(|part| part.size())
+            .sum()
+    }
+
+    /// Extend the region ending at `old_end` up to `new_end` if the space is
+    /// free, outside the guard gaps of other regions and within the window
+    /// The address space and commit limits are left to the caller
+    /// Returns whether the region was extended
+    pub(crate) fn grow_in_place(&mut self, old_end: A, new_end: A) -> bool {
+        let Some(mut region) = self.regions.remove(&old_end) else {
+            return false;
+        };
+        let range = AddrRange::new(region.range.start, new_end);
+        let free = self.overlapping(range).next().is_none()
+            && self
+                .check_guards(range, region.guard_below, region.guard_above)
+                .is_ok()
+            && self.check_window(range).is_ok();
+        if free {
+            self.lookup_cache.clear();
+            self.total_bytes += new_end.sub_addr(old_end);
+            if region.is_accountable() {
+                self.charge(new_end.sub_addr(old_end));
+            }
+            unique_mut(&mut region).range = range;
+        }
+        if let Some(id) = region.id {
+            self.ids.insert(id, region.range.end);
+        }
+        self.regions.insert(region.range.end, region);
+        free
+    }
+
+    /// Number of extra segments created by splitting the regions overlapping
+    /// the given range at its boundaries
+    fn split_count(&self, vaddr_range: AddrRange<A>) -> usize {
+        self.overlapping(vaddr_range)
+            .map(|r| {
+                (r.range.start < vaddr_range.start) as usize
+                    + (vaddr_range.end < r.range.end) as usize
+            })
+            .sum()
+    }
+
+    /// Set the observer notified of page and region changes, or remove it
+    /// A forked manager inherits the observer
+    pub fn set_observer(&mut self, observer: Option<Arc<dyn VmaObserver<A>>>) {
+        self.observer = observer;
+    }
+
+    /// Invoke `f` on the observer, if any
+    pub(crate) fn notify(&self, f: impl FnOnce(&dyn VmaObserver<A>)) {
+        if let Some(observer) = &self.observer {
+            f(observer.as_ref());
+        }
+    }
+
+    /// Notify the observer that a region was split into the given segments
+    /// Nothing is reported if the region was left whole
+    fn notify_split(&self, segments: &SplitSegments<F, A, R>) {
+        self.notify(|observer| {
+            let (before, overlap, after) = segments;
+            let parts: Vec<AddrRange<A>> = [before, overlap, after]
+                .into_iter()
+                .flatten()
+                .map(|segment| segment.range)
+                .collect();
+            if let (true, Some(first), Some(last)) = (parts.len() > 1, parts.first(), parts.last())
+            {
+                observer.on_split(AddrRange::new(first.start, last.end), &parts);
+            }
+        });
+    }
+
+    /// Clear all managed regions except pinned ones
+    /// Returns the removed regions and their populated pages, which the caller
+    /// still has to unmap
+    pub fn clear(&mut self) -> RemovedRegions<F, A, R> {
+        let mut removed = RemovedRegions::default();
+        self.lookup_cache.clear();
+        let (pinned, unpinned) = core::mem::take(&mut self.regions)
+            .into_iter()
+            .partition(|(_, r)| r.pinned);
+        self.regions = pinned;
+        self.reindex();
+        for (_, region) in unpinned {
+            self.total_bytes -= region.mapped_bytes();
+            self.uncharge(region.commit_size());
+            self.notify(|observer| observer.on_remove(region.range));
+            self.retired_metrics.add(region.metrics());
+            removed.push(into_owned(region));
+        }
+        removed
+    }
+
+    /// Number of managed regions
+    pub fn len(&self) -> usize {
+        self.regions.len()
+    }
+
+    /// Is the manager empty?
+    pub fn is_empty(&self) -> bool {
+        self.regions.is_empty()
+    }
+
+    /// Iterate over all regions in address order
+    pub fn iter(&self) -> impl Iterator<Item = &MmapRegion<F, A, R>> {
+        self.regions.values().map(|r| &**r)
+    }
+
+    /// Iterate mutably over all regions in address order
+    /// The ranges of the regions must not be changed through this iterator
+    /// Regions shared with handles from `find_region_arc` are replaced by
+    /// copies first, so the handles no longer follow them
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut MmapRegion<F, A, R>> {
+        self.regions.values_mut().map(unique_mut)
+    }
+
+    /// Iterate over the regions overlapping the given range in address order
+    pub fn regions_in(
+        &self,
+        vaddr_range: AddrRange<A>,
+    ) -> impl Iterator<Item = &MmapRegion<F, A, R>> {
+        self.overlapping(vaddr_range)
+    }
+
+    /// Iterate over the maximal unmapped ranges within the given range in
+    /// address order
+    /// Holes punched into regions are unmapped and so are reported too
+    pub fn gaps(&self, within: AddrRange<A>) -> impl Iterator<Item = AddrRange<A>> + '_ {
+        let mut cursor = within.start;
+        let end = AddrRange::new(within.end, within.end);
+        self.regions_in(within)
+            .flat_map(move |r| r.mapped_parts(within))
+            .chain(core::iter::once(end))
+            .filter_map(move |range| {
+                let gap = (cursor < range.start).then(|| AddrRange::new(cursor, range.start));
+                cursor = cursor.max(range.end);
+                gap
+            })
+    }
+
+    /// Add a new memory-mapped region to the manager
+    /// Returns InvalidArgument for an empty or file-backed grows-down region,
+    /// one that is both shared and private, or one outside the address space
+    /// window, AccessDenied if its protection violates the write-xor-execute
+    /// policy, Overlap if it overlaps an existing one, GuardGap if it lies in
+    /// the guard gap of a neighbour or a neighbour lies in its own guard gap,
+    /// AddressSpaceLimit if it would map more than the address space limit,
+    /// and CommitLimit if the strict commit policy cannot charge it
+    /// The region gets a fresh id, replacing any id it had in another manager,
+    /// and observers see it added
+    pub fn add_region(&mut self, mut region: MmapRegion<F, A, R>) -> VmaResult<(), A> {
+        region.id = None;
+        self.insert_region(region)
+    }
+
+    /// Add a region like `add_region`, keeping its id if it has one
+    fn insert_region(&mut self, region: MmapRegion<F, A, R>) -> VmaResult<(), A> {
+        self.insert_reserved(region, None)
+    }
+
+    /// Add a region like `insert_region`, charging it under `reserved` if the
+    /// caller already reserved its commit charge
+    fn insert_reserved(
+        &mut self,
+        mut region: MmapRegion<F, A, R>,
+        reserved: Option<CommitReservation>,
+    ) -> VmaResult<(), A> {
+        self.check_insertable(&region)?;
+        if self.overlapping(region.range).next().is_some() {
+            return Err(VmaError::Overlap(region.range));
+        }
+        self.check_guard_gaps(&region)?;
+        self.check_total(region.mapped_bytes(), 0)?;
+        let _reserved = match reserved {
+            Some(reserved) => reserved,
+            None => self.check_commit(region.commit_size(), 0)?,
+        };
+        // Regions spanning the range only with their holes are split around it
+        let (keys, splits) = self.split_spanning(region.range)?;
+        let retained: Vec<_> = splits
+            .into_iter()
+            .flat_map(|(before, _, after)| before.into_iter().chain(after))
+            .collect();
+        self.check_region_count(self.regions.len() - keys.len() + retained.len() + 1)?;
+        self.replace_regions(keys, retained);
+        self.total_bytes += region.mapped_bytes();
+        self.assign_id(&mut region);
+        vma_debug!("add {}", Label(&region));
+        let range = region.range;
+        self.store(region);
+        self.notify(|observer| observer.on_add(range));
+        Ok(())
+    }
+
+    /// Check that `region` may be added on its own terms, regardless of the
+    /// other regions
+    /// Returns InvalidArgument for an empty or file-backed grows-down region,
+    /// one that is both shared and private, or one outside the address space
+    /// window, and AccessDenied if its protection violates the
+    /// write-xor-execute policy
+    fn check_insertable(&self, region: &MmapRegion<F, A, R>) -> VmaResult<(), A> {
+        let file_stack = region.flags.contains(MmapFlags::GROWSDOWN) && !region.is_anonymous();
+        if region.range.is_empty() || file_stack || !region.flags.is_valid() {
+            return Err(VmaError::InvalidArgument);
+        }
+        self.check_window(region.range)?;
+        if !self.wx_policy.permits(None, region.prot) {
+            return Err(VmaError::AccessDenied);
+        }
+        Ok(())
+    }
+
+    /// Add a new memory-mapped region, replacing any overlapping parts of
+    /// existing regions (MAP_FIXED semantics)
+    /// Returns the displaced segments so the caller can unmap them, or the
+    /// errors of `add_region` other than Overlap, leaving the existing
+    /// regions in place
+    pub fn add_region_replace(
+        &mut self,
+        mut region: MmapRegion<F, A, R>,
+    ) -> VmaResult<RemovedRegions<F, A, R>, A> {
+        // Check everything `add_region` does before displacing anything
+        self.check_insertable(&region)?;
+        let overlapping = self.overlapping(region.range).count();
+        self.check_region_count(
+            self.regions.len() - overlapping + self.split_count(region.range) + 1,
+        )?;
+        self.check_guard_gaps(&region)?;
+        self.check_total(region.range.size(), self.mapped_in(region.range))?;
+        let reserved = self.check_commit(region.commit_size(), self.committed_in(region.range))?;
+        let removed = self.remove_overlapped(region.range)?;
+        region.id = None;
+        self.insert_reserved(region, Some(reserved))?;
+        Ok(removed)
+    }
+
+    /// Turn the given range of reserved address space into a mapping with the
+    /// given backing and protection, leaving the rest of the reservation
+    /// reserved
+    /// The range may span several adjacent reservations of the same page size;
+    /// the mapping takes the name and flags of the first one and the guard
+    /// gaps of the reservations at its ends, and observers see it added
+    /// Returns Hole if part of the range is unmapped, Overlap if it is not
+    /// entirely reserved, InvalidArgument for reservations of different page
+    /// sizes, Unaligned if the range or the file offset is not aligned to the
+    /// page size, RegionLimit if the split would exceed the region limit,
+    /// AccessDenied if `prot` violates the write-xor-execute policy, or
+    /// CommitLimit if the strict commit policy cannot charge the mapping
+    pub fn commit(
+        &mut self,
+        vaddr_range: AddrRange<A>,
+        backing: RegionBacking<F>,
+        prot: MmapProt,
+    ) -> VmaResult<(), A> {
+        if vaddr_range.is_empty() {
+            return Err(VmaError::InvalidArgument);
+        }
+        if !self.wx_policy.permits(None, prot) {
+            return Err(VmaError::AccessDenied);
+        }
+        if !self.is_covered(vaddr_range) {
+            return Err(VmaError::Hole(vaddr_range));
+        }
+        if self.overlapping(vaddr_range).any(|r| !r.is_reserved()) {
+            return Err(VmaError::Overlap(vaddr_range));
+        }
+        let align = self
+            .overlapping(vaddr_range)
+            .map(|r| r.align)
+            .next()
+            .ok_or(VmaError::Hole(vaddr_range))?;
+        if self.overlapping(vaddr_range).any(|r| r.align != align) {
+            return Err(VmaError::InvalidArgument);
+        }
+        let offset = match &backing {
+            RegionBacking::File { offset, .. } => *offset,
+            _ => 0,
+        };
+        validate_geometry(vaddr_range, offset, align)?;
+        let mut region = MmapRegion::with_backing(vaddr_range, backing, align);
+        region.prot = prot;
+        if let Some(first) = self.overlapping(vaddr_range).next() {
+            region.name.clone_from(&first.name);
+            region.flags = first.flags;
+            if first.range.start == vaddr_range.start {
+                region.guard_below = first.guard_below;
+            }
+        }
+        if let Some(last) = self.overlapping(vaddr_range).last()
+            && last.range.end == vaddr_range.end
+        {
+            region.guard_above = last.guard_above;
+        }
+        self.check_insertable(&region)?;
+        let _reserved = self.check_commit(region.commit_size(), 0)?;
+
+        let (keys, splits) = self.split_overlapping(vaddr_range)?;
+        let overlapping = keys.len();
+        self.check_region_count(
+            self.regions.len() - overlapping + self.split_count(vaddr_range) + 1,
+        )?;
+        let mut retained = Vec::new();
+        for segments in splits {
+            self.notify_split(&segments);
+            let (before, _, after) = segments;
+            retained.extend(before);
+            retained.extend(after);
+        }
+        retained.push(region);
+        self.replace_regions(keys, retained);
+        self.notify(|observer| observer.on_add(vaddr_range));
+        Ok(())
+    }
+
+    /// Visit every populated page whose extent overlaps `range`, in ascending
+    /// address order, together with its region
+    /// Each region's pages are copied out before `f` is called, so no
+    /// populated lock is held while `f` runs
+    /// Returns the value `f` breaks with, or None if it visits every page
+    pub fn for_each_populated<T>(
+        &self,
+        range: AddrRange<A>,
+        mut f: impl FnMut(&MmapRegion<F, A, R>, A) -> ControlFlow<T>,
+    ) -> Option<T> {
+        for region in self.overlapping(range) {
+            for (page, _) in region.populated_in(&range) {
+                if let ControlFlow::Break(value) = f(region, page) {
+                    return Some(value);
+                }
+            }
+        }
+        None
+    }
+
+    /// Find the region containing the given virtual address, or the regions
+    /// directly below and above it if none does
+    /// A region's end is exclusive, so an address at the end of a region
+    /// misses with that region below it
+    pub fn lookup(&self, vaddr: A) -> Lookup<'_, F, A, R> {
+        let next = self
+            .regions
+            .range((Bound::Excluded(vaddr), Bound::Unbounded))
+            .next()
+            .map(|(_, r)| &**r);
+        if let Some(region) = next
+            && region.contains(vaddr)
+        {
+            return Lookup::Hit(region);
+        }
+        let prev = self.regions.range(..=vaddr).next_back().map(|(_, r)| &**r);
+        Lookup::Miss { prev, next }
+    }
+
+    /// Find the region with the given id
+    /// Returns None for ids of regions that were removed, or split off into
+    /// segments that did not keep the id
+    pub fn find_by_id(&self, id: RegionId) -> Option<&MmapRegion<F, A, R>> {
+        let end = self.ids.get(&id)?;
+        self.regions.get(end).map(|r| &**r)
+    }
+
+    /// Find the region containing the given virtual address
+    /// A local region takes precedence; otherwise the fallback is searched
+    /// unless the address was unmapped locally
+    pub fn find_region(&self, vaddr: A) -> Option<&MmapRegion<F, A, R>> {
+        self.find_region_arc_ref(vaddr).map(|r| &**r)
+    }
+
+    /// Find the region containing the given virtual address like
+    /// `find_region`, returning an owned handle that does not borrow the
+    /// manager, so that a caller can drop its manager lock before loading
+    /// pages from the file
+    /// The handle shares the region's page state with the manager until the
+    /// manager splits, removes, replaces or changes the region; pages populated
+    /// through the handle after that are not seen by the manager
+    pub fn find_region_arc(&self, vaddr: A) -> Option<Arc<MmapRegion<F, A, R>>> {
+        self.find_region_arc_ref(vaddr).cloned()
+    }
+
+    /// Find the shared region containing the given virtual address
+    fn find_region_arc_ref(&self, vaddr: A) -> Option<&Arc<MmapRegion<F, A, R>>> {
+        self.find_local(vaddr).or_else(|| self.find_fallback(vaddr))
+    }
+
+    /// Find the local region containing the given virtual address
+    fn find_local(&self, vaddr: A) -> Option<&Arc<MmapRegion<F, A, R>>> {
+        self.regions
+            .range((Bound::Excluded(vaddr), Bound::Unbounded))
+            .next()
+            .map(|(_, r)| r)
+            .filter(|r| r.contains(vaddr))
+    }
+
+    /// Find the region containing the given virtual address like
+    /// `find_region`, first checking the region found by the previous call
+    /// Faults tend to hit the same region repeatedly, so this is used on the
+    /// fault path; a cached region is only returned if it still contains
+    /// `vaddr`, and every change to the region map empties the cache
+    pub fn find_region_cached(&self, vaddr: A) -> Option<&MmapRegion<F, A, R>> {
+        if let Some(end) = self.lookup_cache.get()
+            && let Some(region) = self.regions.get(&A::from(end))
+            && region.contains(vaddr)
+        {
+            self.retired_metrics.record_lookup(true);
+            return Some(region);
+        }
+        self.retired_metrics.record_lookup(false);
+        let region = self.find_region(vaddr)?;
+        self.lookup_cache.set(region.range.end.into());
+        Some(region)
+    }
+
+    /// Find a free address range of at least `size` bytes aligned to `align`
+    /// The range is placed by the placement policy, keeping clear of guard
+    /// gaps and never outside `limits`; bottom-up placement searches upwards
+    /// from `hint` first, while the other policies use the hint only if the
+    /// range there is free. Every policy ends with a search upwards from the
+    /// start of `limits`
+    pub fn find_free_range(
+        &self,
+        hint: A,
+        size: usize,
+        align: PageSize,
+        limits: AddrRange<A>,
+    ) -> Option<A> {
+        if size == 0 {
+            return None;
+        }
+        self.place(hint, size, align, limits)
+    }
+
+    /// Check that the neighbours of `region` keep clear of its guard gaps and
+    /// that it keeps clear of theirs
+    /// Parts of regions overlapping `region` count as neighbours flush against
+    /// it, as they remain when it replaces the overlapped part
+    fn check_guard_gaps(&self, region: &MmapRegion<F, A, R>) -> VmaResult<(), A> {
+        self.check_guards(region.range, region.guard_below, region.guard_above)
+    }
+
+    /// Check the guard gaps of a region covering `range` with the given gaps
+    /// below and above it, see `check_guard_gaps`
+    fn check_guards(
+        &self,
+        range: AddrRange<A>,
+        guard_below: usize,
+        guard_above: usize,
+    ) -> VmaResult<(), A> {
+        let split_below = self
+            .regions
+            .range((Bound::Excluded(range.start), Bound::Unbounded))
+            .next()
+            .is_some_and(|(_, r)| r.range.start < range.start);
+        let below = if split_below {
+            Some((range.start, 0))
+        } else {
+            self.regions
+                .range(..=range.start)
+                .next_back()
+                .map(|(_, r)| (r.range.end, r.guard_above))
+        };
+        if let Some((below_end, guard)) = below
+            && range.start.sub_addr(below_end) < guard.max(guard_below)
+        {
+            return Err(VmaError::GuardGap(range));
+        }
+        let above = self
+            .regions
+            .range((Bound::Excluded(range.end), Bound::Unbounded))
+            .next()
+            .map(|(_, r)| {
+                if r.range.start < range.end {
+                    (range.end, 0)
+                } else {
+                    (r.range.start, r.guard_below)
+                }
+            });
+        if let Some((above_start, guard)) = above
+            && above_start.sub_addr(range.end) < guard.max(guard_above)
+        {
+            return Err(VmaError::GuardGap(range));
+        }
+        Ok(())
+    }
+
+    /// Shrink a free range by the guard gaps of the regions around it
+    /// A hole punched into a region lies inside its guard gaps, so the region
+    /// holding the hole does not shrink it
+    /// Returns None if nothing of the range is left
+    fn unguarded(&self, gap: AddrRange<A>) -> Option<AddrRange<A>> {
+        let mut start = gap.start.into();
+        let mut end = gap.end.into();
+        if let Some((_, below)) = self.regions.range(..=gap.start).next_back() {
+            start = start.max(below.range.end.into().checked_add(below.guard_above)?);
+        }
+        if let Some((_, above)) = self
+            .regions
+            .range((Bound::Excluded(gap.end), Bound::Unbounded))
+            .next()
+            .filter(|(_, above)| above.range.start >= gap.end)
+        {
+            end = end.min(above.range.start.into().checked_sub(above.guard_below)?);
+        }
+        (start < end).then(|| AddrRange::new(A::from(start), A::from(end)))
+    }
+
+    /// Find the first free aligned range of `size` bytes at or above `start`
+    fn find_free_from(
+        &self,
+        start: A,
+        size: usize,
+        align: PageSize,
+        limits: AddrRange<A>,
+    ) -> Option<A> {
+        if start >= limits.end {
+            return None;
+        }
+        self.gaps(AddrRange::new(start, limits.end))
+            .find_map(|gap| {
+                let gap = self.unguarded(gap)?;
+                let start = checked_align_up(gap.start, align)?;
+                (start.into().checked_add(size)? <= gap.end.into()).then_some(start)
+            })
+    }
+
+    /// Range a mapping placed by the manager may be chosen from: the address
+    /// space window without its first page, so that a mapping never starts at
+    /// address zero
+    fn mmap_limits(&self, align: PageSize) -> AddrRange<A> {
+        let start = A::from(align as usize);
+        match self.window {
+            Some(window) => AddrRange::new(start.max(window.start).min(window.end), window.end),
+            None => AddrRange::new(start, A::from(usize::MAX)),
+        }
+    }
+
+    /// Map `size` bytes of `file` at `offset` into a free address range
+    /// The range is chosen by `find_free_range` starting at `hint` within the
+    /// address space window, leaving the first page unmapped so that a valid
+    /// mapping never starts at address zero
+    /// Returns the start address of the new mapping, NoSpace if no space is left,
+    /// or Unaligned if `size` or `offset` is not aligned to `align`
+    pub fn mmap(
+        &mut self,
+        hint: A,
+        size: usize,
+        file: F,
+        offset: i64,
+        align: PageSize,
+    ) -> VmaResult<A, A> {
+        let start = self
+            .find_free_range(hint, size, align, self.mmap_limits(align))
+            .ok_or(VmaError::NoSpace)?;
+        let range = AddrRange::from_start_size(start, size);
+        validate_geometry(range, offset, align)?;
+        let backing = RegionBacking::File { file, offset };
+        self.add_region(MmapRegion::with_backing(range, backing, align))?;
+        Ok(start)
+    }
+
+    /// Resolve a page fault at the given virtual address
+    /// Returns Unmapped for unmapped addresses, or the same errors as
+    /// `MmapRegion::get_buf` and AccessDenied if the region's protection does
+    /// not allow the access
+    pub fn handle_fault(&self, vaddr: A, access: AccessFlags) -> VmaResult<FaultResolution<A>, A> {
+        let region = self
+            .find_region_cached(vaddr)
+            .ok_or(VmaError::Unmapped(vaddr))?;
+        let resolution = region.resolve_fault(vaddr, access)?;
+        if resolution.data != FaultData::Deferred {
+            self.notify(|observer| {
+                observer.on_populate(region.range, resolution.vaddr, resolution.size)
+            });
+        }
+        Ok(resolution)
+    }
+
+    /// Write the dirty pages of shared file-backed regions overlapping the
+    /// given range back to their files, as msync does
+    /// `data_source` supplies the current contents of each dirty page; private
+    /// and anonymous regions are never written back, and pages lying past the
+    /// end of their file are skipped
+    /// Returns the number of pages written; on a write error the page stays
+    /// dirty and the error is returned
+    pub fn sync<'a>(
+        &self,
+        vaddr_range: AddrRange<A>,
+        data_source: impl Fn(A) -> &'a [u8],
+    ) -> VmaResult<usize, A> {
+        let mut written = 0;
+        let regions = self.regions_in(vaddr_range);
+        for region in regions.filter(|r| r.is_shared() && !r.is_anonymous()) {
+            for page in region.dirty_in(&vaddr_range) {
+                // Clear first so that writes racing with the flush stay dirty
+                if !region.clear_dirty(page) {
+                    continue;
+                }
+                match region.flush_page(page, data_source(page)) {
+                    Ok(_) => written += 1,
+                    Err(VmaError::OffsetOutOfFile { .. }) => {}
+                    Err(err) => {
+                        region.mark_dirty(page);
+                        return Err(err);
+                    }
+                }
+            }
+        }
+        Ok(written)
+    }
+
+    /// Resolve a page fault like `handle_fault`, first extending a grows-down
+    /// region downwards by whole pages if the address lies just below it
+    /// The address must lie within the configured distance of the region, the
+    /// guard gap above the region below must stay unmapped, and the grown
+    /// stack must stay within the address space window and limit
+    /// Returns Unmapped if the address is unmapped and cannot be reached by
+    /// extending a stack, or the same errors as `handle_fault`
+    pub fn handle_fault_or_grow(
+        &mut self,
+        vaddr: A,
+        access: AccessFlags,
+    ) -> VmaResult<FaultResolution<A>, A> {
+        if self.find_region(vaddr).is_none() {
+            self.grow_stack(vaddr)?;
+        }
+        self.handle_fault(vaddr, access)
+    }
+
+    /// Extend the grows-down region directly above `vaddr` to cover it
+    fn grow_stack(&mut self, vaddr: A) -> VmaResult<(), A> {
+        let Lookup::Miss {
+            prev,
+            next: Some(stack),
+        } = self.lookup(vaddr)
+        else {
+            return Err(VmaError::Unmapped(vaddr));
+        };
+        if !stack.flags.contains(MmapFlags::GROWSDOWN) || !stack.is_anonymous() {
+            return Err(VmaError::Unmapped(vaddr));
+        }
+        // An address in a hole of the stack misses with the stack above it
+        if vaddr >= stack.range.start {
+            return Err(VmaError::Unmapped(vaddr));
+        }
+        if stack.range.start.sub_addr(vaddr) > self.stack_max_distance {
+            return Err(VmaError::Unmapped(vaddr));
+        }
+        let key = stack.range.end;
+        let new_start = vaddr.align_down(stack.align);
+        let grown = stack.range.start.sub_addr(new_start);
+        let charged = if stack.is_accountable() { grown } else { 0 };
+        if self.check_total(grown, 0).is_err()
+            || self
+                .check_window(AddrRange::new(new_start, stack.range.end))
+                .is_err()
+        {
+            return Err(VmaError::Unmapped(vaddr));
+        }
+        let Ok(_reserved) = self.check_commit(charged, 0) else {
+            return Err(VmaError::Unmapped(vaddr));
+        };
+        if let Some(below) = prev {
+            let guard_gap = self
+                .stack_guard_gap
+                .max(below.guard_above)
+                .max(stack.guard_below);
+            let guard_end = below.range.end.checked_add(guard_gap);
+            if guard_end.is_none_or(|guard_end| guard_end > new_start) {
+                return Err(VmaError::Unmapped(vaddr));
+            }
+        }
+        let stack = self
+            .regions
+            .get_mut(&key)
+            .map(unique_mut)
+            .ok_or(VmaError::Unmapped(vaddr))?;
+        stack.range = AddrRange::new(new_start, stack.range.end);
+        self.total_bytes += grown;
+        self.charge(charged);
+        Ok(())
+    }
+
+    /// Collect memory accounting over all local regions in one pass
+    pub fn stats(&self) -> VmaStats {
+        VmaStats::of(self.iter())
+    }
+
+    /// Fault, load and eviction counts of all regions, including regions
+    /// since removed, split or replaced, and the lookup cache counts
+    /// Always zero without the `metrics` feature
+    pub fn metrics(&self) -> VmaMetrics {
+        self.iter()
+            .map(MmapRegion::metrics)
+            .fold(self.retired_metrics.get(), VmaMetrics::plus)
+    }
+
+    /// Check if the given address range is fully covered by regions
+    fn is_covered(&self, vaddr_range: AddrRange<A>) -> bool {
+        self.first_hole(vaddr_range).is_none()
+    }
+
+    /// Find the lowest address of the given range that no region maps
+    fn first_hole(&self, vaddr_range: AddrRange<A>) -> Option<A> {
+        let mut cursor = vaddr_range.start;
+        for part in self
+            .overlapping(vaddr_range)
+            .flat_map(|r| r.mapped_parts(vaddr_range))
+        {
+            if part.start > cursor {
+                return Some(cursor);
+            }
+            cursor = cursor.max(part.end);
+        }
+        (cursor < vaddr_range.end).then_some(cursor)
+    }
+
+    /// Report which pages of the given range are populated, as mincore does
+    /// Residency is reported per 4 KiB page regardless of region alignment, so
+    /// every 4 KiB part of a populated huge page is reported as resident; the
+    /// range is widened to 4 KiB boundaries
+    /// Returns Hole if part of the range is unmapped
+    pub fn residency(&self, vaddr_range: AddrRange<A>) -> VmaResult<Vec<bool>, A> {
+        const PAGE_SIZE: usize = PageSize::Size4K as usize;
+        let start = vaddr_range.start.align_down(PageSize::Size4K);
+        let end = checked_align_up(vaddr_range.end, PageSize::Size4K)
+            .ok_or(VmaError::Hole(vaddr_range))?;
+        let vaddr_range = AddrRange::new(start, end);
+        if !self.is_covered(vaddr_range) {
+            return Err(VmaError::Hole(vaddr_range));
+        }
+
+        let mut resident = Vec::with_capacity(vaddr_range.size() / PAGE_SIZE);
+        for region in self.overlapping(vaddr_range) {
+            let start = region.range.start.max(vaddr_range.start);
+            let end = region.range.end.min(vaddr_range.end);
+            let populated = region.populated.lock();
+            resident.extend(
+                (start.into()..end.into())
+                    .step_by(PAGE_SIZE)
+                    .map(|addr| populated.contains(A::from(addr).align_down(region.align))),
+            );
+        }
+        Ok(resident)
+    }
+
+    /// Duplicate this manager for a forked child
+    /// Private regions are copied and become copy-on-write in both parent and
+    /// child, shared regions become handles sharing their population state,
+    /// see `MmapRegion::share`, and DONTFORK regions are skipped;
+    /// as on Linux, the child's regions are not locked
+    /// Under the strict commit policy the child charges its accountable
+    /// regions against the same limit, even past it
+    pub fn fork(&self) -> VmaManager<F, A, R> {
+        let regions: BTreeMap<_, _> = self
+            .regions
+            .iter()
+            .filter(|(_, r)| !r.flags.contains(MmapFlags::DONTFORK))
+            .map(|(&end, r)| {
+                let mut child = if r.is_shared() {
+                    r.share()
+                } else {
+                    r.share_populated_cow();
+                    MmapRegion::clone(r)
+                };
+                child.locked = false;
+                (end, Arc::new(child))
+            })
+            .collect();
+        let total_bytes = regions.values().map(|r| r.mapped_bytes()).sum();
+        let committed = regions.values().map(|r| r.commit_size()).sum();
+        if let Some(limit) = self.commit_policy.limit() {
+            limit.charge(committed);
+        }
+        let ids = regions
+            .values()
+            .filter_map(|r| Some((r.id?, r.range.end)))
+            .collect();
+        Self {
+            regions,
+            observer: self.observer.clone(),
+            stack_max_distance: self.stack_max_distance,
+            stack_guard_gap: self.stack_guard_gap,
+            memlock_limit: self.memlock_limit,
+            max_regions: self.max_regions,
+            wx_policy: self.wx_policy,
+            window: self.window,
+            max_total_bytes: self.max_total_bytes,
+            total_bytes,
+            retired_metrics: MetricCounters::default(),
+            lookup_cache: LookupCache::default(),
+            ids,
+            next_id: self.next_id,
+            commit_policy: self.commit_policy.clone(),
+            committed,
+            fallback: self.fallback.clone(),
+            shadows: self.shadows.clone(),
+            hole_threshold: self.hole_threshold,
+            placement: self.placement,
+        }
+    }
+
+    /// Merge runs of adjacent compatible regions into single regions
+    pub fn coalesce(&mut self) {
+        self.lookup_cache.clear();
+        let mut merged: Vec<MmapRegion<F, A, R>> = Vec::with_capacity(self.regions.len());
+        for region in core::mem::take(&mut self.regions).into_values() {
+            let region = into_owned(region);
+            match merged.last_mut() {
+                Some(last) if last.can_merge_with(&region) => last.absorb(region),
+                _ => merged.push(region),
+            }
+        }
+        self.regions = merged
+            .into_iter()
+            .map(|r| (r.range.end, Arc::new(r)))
+            .collect();
+        self.reindex();
+    }
+
+    /// Eagerly load every unpopulated page overlapping the given range
+    /// The range may span several regions and cover them partially; unmapped
+    /// holes are skipped
+    /// Returns the loaded pages in ascending order, or the pages loaded before
+    /// the first failure together with the error
+    pub fn populate(&self, vaddr_range: AddrRange<A>) -> PopulateResult<A> {
+        let mut loaded = Vec::new();
+        for region in self.overlapping(vaddr_range) {
+            let result = region.populate_range(&vaddr_range);
+            let pages = match &result {
+                Ok(pages) => pages,
+                Err(partial) => &partial.loaded,
+            };
+            for (page, _) in pages {
+                self.notify(|observer| observer.on_populate(region.range, *page, region.align));
+            }
+            match result {
+                Ok(pages) => loaded.extend(pages),
+                Err(mut partial) => {
+                    loaded.append(&mut partial.loaded);
+                    partial.loaded = loaded;
+                    return Err(partial);
+                }
+            }
+        }
+        Ok(loaded)
+    }
+
+    /// Check that the `len` bytes at `start` are mapped with permissions that
+    /// allow `access`, as a kernel does before copying to or from a user
+    /// buffer
+    /// Nothing is loaded and no page state changes; an empty span always passes
+    /// Returns Unmapped at the first address of the span no region maps,
+    /// AccessDenied if a region covering the span does not allow the access,
+    /// or Overflow if the span wraps around
+    pub fn check_access(&self, start: A, len: usize, access: AccessFlags) -> VmaResult<(), A> {
+        let end = start.checked_add(len).ok_or(VmaError::Overflow)?;
+        let span = AddrRange::new(start, end);
+        if let Some(hole) = self.first_hole(span) {
+            return Err(VmaError::Unmapped(hole));
+        }
+        if self
+            .overlapping(span)
+            .any(|region| !region.prot.allows(access))
+        {
+            return Err(VmaError::AccessDenied);
+        }
+        Ok(())
+    }
+
+    /// Load every unpopulated page of the `len` bytes at `start`, as a kernel
+    /// does before copying to or from a user buffer
+    /// Pages already populated or being populated by another caller are
+    /// skipped, and adjacent pages of one region are read from the file
+    /// together, as with `MmapRegion::populate_range`
+    /// Returns the loaded pages in ascending order, Unmapped at the first
+    /// address of the span no region maps, Overflow if the span wraps around,
+    /// or the error of a failed load; no page is left populated on failure
+    pub fn populate_contiguous(&self, start: A, len: usize) -> VmaResult<Vec<(A, PageData)>, A> {
+        let end = start.checked_add(len).ok_or(VmaError::Overflow)?;
+        let span = AddrRange::new(start, end);
+        if let Some(hole) = self.first_hole(span) {
+            return Err(VmaError::Unmapped(hole));
+        }
+
+        let mut loaded = Vec::new();
+        for region in self.overlapping(span) {
+            match region.populate_range(&span) {
+                Ok(pages) => loaded.push((region, pages)),
+                Err(partial) => {
+                    loaded.push((region, partial.loaded));
+                    for (region, pages) in loaded {
+                        region.release_pages(pages.iter().map(|(page, _)| *page));
+                    }
+                    return Err(partial.error);
+                }
+            }
+        }
+
+        let mut pages = Vec::new();
+        for (region, run) in loaded {
+            for (page, data) in run {
+                self.notify(|observer| observer.on_populate(region.range, page, region.align));
+                pages.push((page, PageData::Owned(data)));
+            }
+        }
+        Ok(pages)
+    }
+
+    /// Set the maximum number of bytes `lock_range` may keep locked, or remove
+    /// the limit
+    pub fn set_memlock_limit(&mut self, limit: Option<usize>) {
+        self.memlock_limit = limit;
+    }
+
+    /// Number of bytes mapped by locked regions
+    pub fn locked_bytes(&self) -> usize {
+        self.iter()
+            .filter(|r| r.locked)
+            .map(MmapRegion::mapped_bytes)
+            .sum()
+    }
+
+    /// Lock all regions within the given address range in memory, as mlock does
+    /// Splits regions at the range boundaries, then loads every unpopulated
+    /// page of the range; locked pages are skipped by eviction and reclaim
+    /// Returns the loaded pages in ascending order, or the pages loaded before
+    /// the first failure together with the error, which is Hole if the range
+    /// contains unmapped holes or LockLimit if the memlock limit would be
+    /// exceeded
+    pub fn lock_range(&mut self, vaddr_range: AddrRange<A>) -> PopulateResult<A> {
+        let fail = |error| PartialPopulate {
+            loaded: Vec::new(),
+            error,
+        };
+        if !self.is_covered(vaddr_range) {
+            return Err(fail(VmaError::Hole(vaddr_range)));
+        }
+        if let Some(limit) = self.memlock_limit {
+            let newly_locked: usize = self
+                .overlapping(vaddr_range)
+                .filter(|r| !r.locked)
+                .map(|r| {
+                    let end = r.range.end.min(vaddr_range.end);
+                    end.sub_addr(r.range.start.max(vaddr_range.start))
+                })
+                .sum();
+            if self.locked_bytes().saturating_add(newly_locked) > limit {
+                return Err(fail(VmaError::LockLimit(limit)));
+            }
+        }
+        self.update_range(vaddr_range, |region| region.locked = true)
+            .map_err(fail)?;
+        self.populate(vaddr_range)
+    }
+
+    /// Unlock all regions within the given address range, as munlock does
+    /// Splits regions at the range boundaries; populated pages stay resident
+    /// until they are evicted
+    /// Returns the affected sub-ranges, or Hole if the range contains unmapped holes
+    pub fn unlock_range(&mut self, vaddr_range: AddrRange<A>) -> VmaResult<Vec<AddrRange<A>>, A> {
+        self.update_range(vaddr_range, |region| region.locked = false)
+    }
+
+    /// Switch every region overlapping the given range to the smaller page
+    /// size `new_align`; see `MmapRegion::demote`
+    /// Whole regions are demoted, as the range may end inside a huge page
+    /// Returns InvalidArgument without changing any region if one of them has
+    /// a smaller page size than `new_align`; otherwise returns the previously
+    /// populated huge pages in ascending order
+    pub fn demote_range(
+        &mut self,
+        vaddr_range: AddrRange<A>,
+        new_align: PageSize,
+    ) -> VmaResult<Vec<(A, PageSize)>, A> {
+        if self
+            .overlapping(vaddr_range)
+            .any(|region| (region.align as usize) < (new_align as usize))
+        {
+            return Err(VmaError::InvalidArgument);
+        }
+        let mut demoted = Vec::new();
+        for (_, region) in self
+            .regions
+            .range_mut((Bound::Excluded(vaddr_range.start), Bound::Unbounded))
+            .take_while(|(_, region)| region.range.start < vaddr_range.end)
+        {
+            demoted.extend(unique_mut(region).demote(new_align)?);
+        }
+        Ok(demoted)
+    }
+
+    /// Iterate over the regions backed by the file with the given identity
+    pub fn regions_backed_by(&self, id: u64) -> impl Iterator<Item = &MmapRegion<F, A, R>> {
+        self.iter()
+            .filter(move |r| r.backing.file().and_then(VmFile::file_id) == Some(id))
+    }
+
+    /// Drop the populated pages of regions backed by the file with the given
+    /// identity whose file offset lies at or past `new_len`, after the file
+    /// was truncated to that length; see `MmapRegion::release_past`
+    /// Returns the dropped pages so that the caller can unmap and free them
+    pub fn invalidate_file(&self, id: u64, new_len: u64) -> Vec<(A, PageSize)> {
+        let mut released = Vec::new();
+        for region in self.regions_backed_by(id) {
+            let pages = region.release_past(new_len);
+            for &page in &pages {
+                self.notify(|observer| observer.on_evict(region.range, page, region.align));
+            }
+            released.extend(pages.into_iter().map(|page| (page, region.align)));
+        }
+        released
+    }
+
+    /// Apply madvise-style advice to the given address range
+    /// The range may span several regions and cover them partially; regions
+    /// are never split and unmapped holes are skipped
+    /// WillNeed stops at the first page that fails to load and returns the
+    /// pages loaded so far, and DontNeed returns InvalidArgument if a locked
+    /// region overlaps the range; so does Free if a locked or shared one does
+    pub fn advise(
+        &self,
+        vaddr_range: AddrRange<A>,
+        advice: Advice,
+    ) -> VmaResult<AdviceOutcome<A>, A> {
+        let regions = self.overlapping(vaddr_range);
+        match advice {
+            Advice::Normal => {
+                regions.for_each(|r| r.set_access_hint(AccessHint::Normal));
+                Ok(AdviceOutcome::Unchanged)
+            }
+            Advice::Sequential => {
+                regions.for_each(|r| r.set_access_hint(AccessHint::Sequential));
+                Ok(AdviceOutcome::Unchanged)
+            }
+            Advice::Random => {
+                regions.for_each(|r| r.set_access_hint(AccessHint::Random));
+                Ok(AdviceOutcome::Unchanged)
+            }
+            Advice::DontNeed => {
+                if self.overlapping(vaddr_range).any(|r| r.locked) {
+                    return Err(VmaError::InvalidArgument);
+                }
+                let mut released = Vec::new();
+                for region in regions {
+                    let pages = region.release_range(&vaddr_range);
+                    for &page in &pages {
+                        self.notify(|observer| observer.on_evict(region.range, page, region.align));
+                    }
+                    released.extend(pages);
+                }
+                Ok(AdviceOutcome::Released(released))
+            }
+            Advice::Free => {
+                if self
+                    .overlapping(vaddr_range)
+                    .any(|r| r.locked || r.is_shared())
+                {
+                    return Err(VmaError::InvalidArgument);
+                }
+                regions.for_each(|r| r.free_lazily(&vaddr_range));
+                Ok(AdviceOutcome::Unchanged)
+            }
+            Advice::WillNeed => {
+                let loaded = match self.populate(vaddr_range) {
+                    Ok(loaded) => loaded,
+                    Err(partial) => partial.loaded,
+                };
+                Ok(AdviceOutcome::Loaded(loaded))
+            }
+        }
+    }
+
+    /// Iterate over the regions overlapping the given address range
+    /// Only the map entries overlapping the range are visited
+    fn overlapping(&self, vaddr_range: AddrRange<A>) -> impl Iterator<Item = &MmapRegion<F, A, R>> {
+        self.spanning(vaddr_range)
+            .filter(move |r| r.maps_any(vaddr_range))
+    }
+
+    /// Iterate over the regions whose range overlaps the given range, even
+    /// if only with their holes
+    fn spanning(&self, vaddr_range: AddrRange<A>) -> impl Iterator<Item = &MmapRegion<F, A, R>> {
+        self.regions
+            .range((Bound::Excluded(vaddr_range.start), Bound::Unbounded))
+            .map(|(_, r)| &**r)
+            .take_while(move |r| r.range.start < vaddr_range.end)
+    }
+
+    /// Split every region overlapping the given range without modifying the
+    /// manager, so that a failing split leaves all regions untouched
+    /// Returns the keys of the split regions along with their segments
+    fn split_overlapping(&self, vaddr_range: AddrRange<A>) -> VmaResult<RegionSplits<F, A, R>, A> {
+        Self::split_all(self.overlapping(vaddr_range), vaddr_range)
+    }
+
+    /// Split every region spanning the given range like `split_overlapping`,
+    /// including regions that overlap it only with their holes
+    fn split_spanning(&self, vaddr_range: AddrRange<A>) -> VmaResult<RegionSplits<F, A, R>, A> {
+        Self::split_all(self.spanning(vaddr_range), vaddr_range)
+    }
+
+    /// Split each of `regions` at the given range
+    fn split_all<'a>(
+        regions: impl Iterator<Item = &'a MmapRegion<F, A, R>>,
+        vaddr_range: AddrRange<A>,
+    ) -> VmaResult<RegionSplits<F, A, R>, A>
+    where
+        F: 'a,
+        A: 'a,
+        R: 'a,
+    {
+        regions
+            .map(|region| Ok((region.range.end, region.split_at_range(&vaddr_range)?)))
+            .collect::<VmaResult<Vec<_>, A>>()
+            .map(|splits| splits.into_iter().unzip())
+    }
+
+    /// Replace the regions stored under `keys` with `regions`
+    /// The metrics of the replaced regions are kept in the manager totals
+    fn replace_regions(&mut self, keys: Vec<A>, regions: Vec<MmapRegion<F, A, R>>) {
+        self.lookup_cache.clear();
+        for key in keys {
+            if let Some(region) = self.regions.remove(&key) {
+                self.total_bytes -= region.mapped_bytes();
+                self.uncharge(region.commit_size());
+                self.retired_metrics.add(region.metrics());
+                if let Some(id) = region.id {
+                    self.ids.remove(&id);
+                }
+            }
+        }
+        self.total_bytes += regions.iter().map(|r| r.mapped_bytes()).sum::<usize>();
+        for region in regions {
+            self.store(region);
+        }
+    }
+
+    /// Insert a region into the map, giving it a fresh id if it has none and
+    /// charging it against the commit limit
+    fn store(&mut self, mut region: MmapRegion<F, A, R>) {
+        let id = self.assign_id(&mut region);
+        self.ids.insert(id, region.range.end);
+        self.charge(region.commit_size());
+        self.regions.insert(region.range.end, Arc::new(region));
+    }
+
+    /// Give `region` a fresh id unless it has one, returning its id
+    fn assign_id(&mut self, region: &mut MmapRegion<F, A, R>) -> RegionId {
+        *region.id.get_or_insert_with(|| {
+            let id = RegionId(self.next_id);
+            self.next_id += 1;
+            id
+        })
+    }
+
+    /// Rebuild the id index from the regions
+    fn reindex(&mut self) {
+        self.ids = self
+            .regions
+            .values()
+            .filter_map(|r| Some((r.id?, r.range.end)))
+            .collect();
+    }
+
+    /// Remove all regions that overlap with the given address range
+    /// Splits overlapping regions and retains non-overlapping parts, or punches
+    /// a hole into the region around the range if the hole-punching policy
+    /// applies, and shadows the range so that the fallback no longer shows
+    /// through it
+    /// Returns the removed segments and their pages along with a split report
+    /// per overlapping region, or Pinned without changing anything if a
+    /// pinned region overlaps
+    /// Only the overlapping regions are taken out of the map, so unmapping a
+    /// range nothing maps leaves every region and the lookup cache untouched
+    pub fn remove_overlapped(
+        &mut self,
+        vaddr_range: AddrRange<A>,
+    ) -> VmaResult<RemovedRegions<F, A, R>, A> {
+        if self.overlapping(vaddr_range).next().is_none() {
+            self.shadow(vaddr_range);
+            return Ok(RemovedRegions::default());
+        }
+        if self.overlapping(vaddr_range).any(|r| r.pinned) {
+            return Err(VmaError::Pinned);
+        }
+        if let Some(removed) = self.try_punch(vaddr_range) {
+            self.shadow(vaddr_range);
+            return Ok(removed);
+        }
+        let (keys, splits) = self.split_overlapping(vaddr_range)?;
+        self.check_region_count(self.regions.len() - keys.len() + self.split_count(vaddr_range))?;
+        let mut removed = RemovedRegions::default();
+        let mut retained = Vec::new();
+
+        for segments in splits {
+            self.notify_split(&segments);
+            removed.splits.push(SplitReport::of(&segments));
+            let (before, overlap, after) = segments;
+            if let Some(overlap) = overlap {
+                self.notify(|observer| observer.on_remove(overlap.range));
+                removed.push(overlap);
+            }
+            retained.extend(before);
+            retained.extend(after);
+        }
+        vma_debug!(
+            "remove range={} removed={} retained={}",
+            Span(vaddr_range),
+            Spans(removed.regions.iter().map(|region| region.range)),
+            Spans(retained.iter().map(|region| region.range)),
+        );
+        self.replace_regions(keys, retained);
+        self.shadow(vaddr_range);
+        Ok(removed)
+    }
+
+    /// Unmap `len` bytes starting at `start`, following munmap(2) semantics
+    /// `start` must be page-aligned and `len` is rounded up to a page multiple;
+    /// unmapping a range that is not mapped succeeds without effect
+    /// Returns Unaligned for an unaligned start or a range that would split a
+    /// region apart from its page alignment, and InvalidArgument for a zero
+    /// or overflowing length
+    pub fn munmap(&mut self, start: A, len: usize) -> VmaResult<RemovedRegions<F, A, R>, A> {
+        if !start.is_aligned(PageSize::Size4K) {
+            return Err(VmaError::Unaligned);
+        }
+        if len == 0 {
+            return Err(VmaError::InvalidArgument);
+        }
+        let end = checked_align_up(A::from(len), PageSize::Size4K)
+            .and_then(|len| start.checked_add(len.into()))
+            .ok_or(VmaError::InvalidArgument)?;
+        self.remove_overlapped(AddrRange::new(start, end))
+    }
+
+    /// Resize the mapping at `old_start`, following mremap(2) semantics
+    /// `old_start` must be aligned to the region's page size, and both lengths
+    /// are rounded up to a 4 KiB multiple; the old range must lie within one
+    /// region. Shrinking drops the tail and returns its pages for unmapping.
+    /// Growing extends the region in place if the following range is free, or
+    /// otherwise moves it to a range found by `find_free_range` when MAYMOVE is
+    /// set, keeping its file offset, populated pages and guard gaps clear
+    /// Returns InvalidArgument for zero lengths, Unaligned for misaligned ones,
+    /// Unmapped if the old range is not within a single region, NoSpace if
+    /// the mapping cannot grow, AddressSpaceLimit or CommitLimit if growing
+    /// would exceed the address space or commit limit, RegionLimit if moving
+    /// would exceed the region limit, and Pinned if a pinned region would be
+    /// shrunk or moved; a failed move leaves the mapping in place
+    pub fn remap(
+        &mut self,
+        old_start: A,
+        old_len: usize,
+        new_len: usize,
+        flags: RemapFlags,
+    ) -> VmaResult<Remapped<F, A, R>, A> {
+        let round = |len: usize| {
+            checked_align_up(A::from(len), PageSize::Size4K)
+                .map(Into::into)
+                .filter(|&len| len != 0)
+                .ok_or(VmaError::InvalidArgument)
+        };
+        let (old_len, new_len) = (round(old_len)?, round(new_len)?);
+        let old_end = old_start
+            .checked_add(old_len)
+            .ok_or(VmaError::InvalidArgument)?;
+        let old_range = AddrRange::new(old_start, old_end);
+
+        let region = match self.find_local(old_start) {
+            Some(region)
+                if region.range.contains_range(old_range) && !region.has_hole_in(old_range) =>
+            {
+                region
+            }
+            _ => return Err(VmaError::Unmapped(old_start)),
+        };
+        let (align, region_end, pinned) = (region.align, region.range.end, region.pinned);
+        let accountable = region.is_accountable();
+        // Guard gaps the moved part carries along, as `split_at_range` passes
+        // them on
+        let guard_below = if old_start == region.range.start {
+            region.guard_below
+        } else {
+            0
+        };
+        let guard_above = if old_end == region_end {
+            region.guard_above
+        } else {
+            0
+        };
+        if !old_start.is_aligned(align)
+            || !old_len.is_multiple_of(align as usize)
+            || !new_len.is_multiple_of(align as usize)
+        {
+            return Err(VmaError::Unaligned);
+        }
+
+        let mut remapped = Remapped {
+            start: old_start,
+            removed: RemovedRegions::default(),
+        };
+        if new_len <= old_len {
+            if new_len < old_len {
+                remapped.removed = self.munmap(old_start.add(new_len), old_len - new_len)?;
+            }
+            return Ok(remapped);
+        }
+
+        self.check_total(new_len - old_len, 0)?;
+        let reserved = self.check_commit(if accountable { new_len - old_len } else { 0 }, 0)?;
+        // Grow in place when the old range ends the region and the gap is free
+        let new_end = old_start.checked_add(new_len);
+        if old_end == region_end
+            && let Some(new_end) = new_end
+            && self.grow_in_place(old_end, new_end)
+        {
+            return Ok(remapped);
+        }
+        if !flags.contains(RemapFlags::MAYMOVE) {
+            return Err(VmaError::NoSpace);
+        }
+        if pinned {
+            return Err(VmaError::Pinned);
+        }
+
+        // Leave room for the moved part's own guard gaps around the new range
+        let padding_below = checked_align_up(A::from(guard_below), align)
+            .ok_or(VmaError::NoSpace)?
+            .into();
+        let padded_len = padding_below
+            .checked_add(new_len)
+            .and_then(|len| len.checked_add(guard_above))
+            .ok_or(VmaError::NoSpace)?;
+        let new_start = self
+            .find_free_range(old_start, padded_len, align, self.mmap_limits(align))
+            .ok_or(VmaError::NoSpace)?
+            .add(padding_below);
+        let new_range = AddrRange::from_start_size(new_start, new_len);
+        let (keys, splits) = self.split_overlapping(old_range)?;
+        // Check the destination while the old range is still mapped, so that
+        // a failed move leaves the mapping where it was
+        let retained_len: usize = splits
+            .iter()
+            .map(|(before, _, after)| before.iter().chain(after).count())
+            .sum();
+        self.check_window(new_range)?;
+        self.check_guards(new_range, guard_below, guard_above)?;
+        self.check_region_count(self.regions.len() - keys.len() + retained_len + 1)?;
+        let mut retained = Vec::new();
+        let mut moved = None;
+        for segments in splits {
+            self.notify_split(&segments);
+            let (before, overlap, after) = segments;
+            retained.extend(before);
+            moved = overlap;
+            retained.extend(after);
+        }
+        let mut moved = moved.ok_or(VmaError::Unmapped(old_start))?;
+        self.replace_regions(keys, retained);
+
+        moved.rebase(new_start);
+        moved.range = new_range;
+        self.insert_reserved(moved, Some(reserved))?;
+        remapped.start = new_start;
+        Ok(remapped)
+    }
+
+    /// Change the protection of all regions within the given address range
+    /// Splits regions at the range boundaries and updates only the overlapping parts
+    /// Returns the affected sub-ranges, Hole if the range contains unmapped
+    /// holes, or AccessDenied without changing anything if the change violates
+    /// the write-xor-execute policy for any region; see `would_violate_wx`
+    /// Making private regions writable charges them against the commit limit,
+    /// failing with CommitLimit if it is reached, and making them read-only
+    /// releases their charge
+    pub fn protect(
+        &mut self,
+        vaddr_range: AddrRange<A>,
+        prot: MmapProt,
+    ) -> VmaResult<Vec<AddrRange<A>>, A> {
+        if self.would_violate_wx(vaddr_range, prot) {
+            return Err(VmaError::AccessDenied);
+        }
+        let (mut added, mut removed) = (0, 0);
+        for region in self.overlapping(vaddr_range) {
+            let start = region.range.start.max(vaddr_range.start);
+            let size = region.range.end.min(vaddr_range.end).sub_addr(start);
+            match (region.is_accountable(), region.accountable_with(prot)) {
+                (false, true) => added += size,
+                (true, false) => removed += size,
+                _ => {}
+            }
+        }
+        let _reserved = self.check_commit(added, removed)?;
+        let updated = self.update_range(vaddr_range, |region| region.prot = prot)?;
+        vma_debug!(
+            "protect range={} prot={prot:?} updated={}",
+            Span(vaddr_range),
+            Spans(updated.iter().copied()),
+        );
+        Ok(updated)
+    }
+
+    /// Name all regions within the given address range, or clear their names
+    /// Splits regions at the range boundaries and updates only the overlapping parts
+    /// Returns the affected sub-ranges, or Hole if the range contains unmapped holes
+    pub fn set_name(
+        &mut self,
+        vaddr_range: AddrRange<A>,
+        name: Option<String>,
+    ) -> VmaResult<Vec<AddrRange<A>>, A> {
+        self.update_range(vaddr_range, |region| region.name.clone_from(&name))
+    }
+
+    /// Apply `update` to the parts of the regions within the given address range
+    /// Returns the affected sub-ranges, or Hole if the range contains unmapped holes
+    fn update_range(
+        &mut self,
+        vaddr_range: AddrRange<A>,
+        mut update: impl FnMut(&mut MmapRegion<F, A, R>),
+    ) -> VmaResult<Vec<AddrRange<A>>, A> {
+        if vaddr_range.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        if !self.is_covered(vaddr_range) {
+            return Err(VmaError::Hole(vaddr_range));
+        }
+
+        let (keys, splits) = self.split_overlapping(vaddr_range)?;
+        self.check_region_count(self.regions.len() + self.split_count(vaddr_range))?;
+        let mut affected = Vec::new();
+        let mut retained = Vec::new();
+        for segments in splits {
+            self.notify_split(&segments);
+            let (before, overlap, after) = segments;
+            retained.extend(before);
+            if let Some(mut overlap) = overlap {
+                update(&mut overlap);
+                affected.push(overlap.range);
+                retained.push(overlap);
+            }
+            retained.extend(after);
+        }
+        self.replace_regions(keys, retained);
+        Ok(affected)
+    }
+}
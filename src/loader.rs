@@ -0,0 +1,60 @@
+//! Mapping arithmetic for program loaders.
+
+use memory_addr::{MemoryAddr, VirtAddr, VirtAddrRange};
+use page_table_multiarch::PageSize;
+
+use crate::{MmapRegion, VmFile, VmaError, VmaManager, VmaResult, checked_align_up};
+
+/// Map a loadable ELF segment (PT_LOAD) into `manager`
+///
+/// The first `filesz` bytes at `vaddr` come from `file` at `file_offset` and
+/// the remaining `memsz - filesz` bytes are zero. The segment forms a single
+/// region keeping the default protection, backed by the file up to the end of
+/// the file data so that the tail of the boundary page and the pages past it
+/// read as zero, or anonymous if `filesz` is zero. Returns the page-aligned
+/// range of the segment, InvalidArgument if `memsz` is zero or smaller than
+/// `filesz`, Unaligned if `vaddr` and `file_offset` are not congruent modulo
+/// `align`, NoSpace if the segment does not fit in the address space, and
+/// Overlap if it overlaps an existing region.
+pub fn map_elf_segment<F: VmFile>(
+    manager: &mut VmaManager<F>,
+    vaddr: VirtAddr,
+    file: F,
+    file_offset: u64,
+    filesz: usize,
+    memsz: usize,
+    align: PageSize,
+) -> VmaResult<VirtAddrRange> {
+    let page_offset = vaddr.align_offset(align);
+    if memsz == 0 || filesz > memsz {
+        return Err(VmaError::InvalidArgument);
+    }
+    if file_offset % align as u64 != page_offset as u64 {
+        return Err(VmaError::Unaligned);
+    }
+
+    let start = vaddr.align_down(align);
+    let end_of = |size: usize| {
+        vaddr
+            .checked_add(size)
+            .and_then(|end| checked_align_up(end, align))
+            .ok_or(VmaError::NoSpace)
+    };
+    let mem_end = end_of(memsz)?;
+    let segment = VirtAddrRange::new(start, mem_end);
+    if manager.regions_in(segment).next().is_some() {
+        return Err(VmaError::Overlap(segment));
+    }
+
+    let region = if filesz > 0 {
+        let offset =
+            i64::try_from(file_offset - page_offset as u64).map_err(|_| VmaError::Overflow)?;
+        let mut region = MmapRegion::try_new(segment, file, offset, align)?;
+        region.file_backed_len = Some(page_offset + filesz);
+        region
+    } else {
+        MmapRegion::new_anonymous(segment, align)
+    };
+    manager.add_region(region)?;
+    Ok(segment)
+}
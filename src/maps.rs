@@ -0,0 +1,71 @@
+//! Rendering of a manager's regions in the format of `/proc/self/maps`.
+
+use core::fmt;
+use memory_addr::{MemoryAddr, VirtAddr};
+
+use crate::{DefaultRawMutex, MmapProt, MmapRegion, RawMutex, RegionBacking, VmFile, VmaManager};
+
+/// Name shown for anonymous regions and reservations without a name
+const ANON_NAME: &str = "[anon]";
+
+/// Display adapter rendering a manager's regions like `/proc/self/maps`,
+/// obtained from `VmaManager::maps`
+/// Each region takes one line, in ascending address order, of the form
+/// `start-end perms offset resident name`, where `perms` ends in `s` for
+/// shared and `p` for private mappings and `resident` counts the populated
+/// pages; unnamed file-backed regions leave out the name
+pub struct MapsDisplay<'a, F: VmFile, A: MemoryAddr = VirtAddr, R: RawMutex = DefaultRawMutex> {
+    manager: &'a VmaManager<F, A, R>,
+}
+
+impl<F: VmFile, A: MemoryAddr, R: RawMutex> VmaManager<F, A, R> {
+    /// Render the regions like `/proc/self/maps`, see `MapsDisplay`
+    pub fn maps(&self) -> MapsDisplay<'_, F, A, R> {
+        MapsDisplay { manager: self }
+    }
+}
+
+impl<F: VmFile, A: MemoryAddr, R: RawMutex> fmt::Display for MapsDisplay<'_, F, A, R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for region in self.manager.iter() {
+            write_region(f, region)?;
+        }
+        Ok(())
+    }
+}
+
+/// Write the maps line of one region
+fn write_region<F: VmFile, A: MemoryAddr, R: RawMutex>(
+    f: &mut fmt::Formatter<'_>,
+    region: &MmapRegion<F, A, R>,
+) -> fmt::Result {
+    let start: usize = region.range.start.into();
+    let end: usize = region.range.end.into();
+    let perm = |prot: MmapProt, c: char| if region.prot.contains(prot) { c } else { '-' };
+    let sharing = if region.is_shared() { 's' } else { 'p' };
+    write!(
+        f,
+        "{start:08x}-{end:08x} {}{}{}{sharing} ",
+        perm(MmapProt::READ, 'r'),
+        perm(MmapProt::WRITE, 'w'),
+        perm(MmapProt::EXEC, 'x'),
+    )?;
+
+    let offset = match &region.backing {
+        RegionBacking::File { offset, .. } => *offset,
+        RegionBacking::Anonymous | RegionBacking::Reserved => 0,
+    };
+    let sign = if offset < 0 { "-" } else { "" };
+    write!(
+        f,
+        "{sign}{:08x} {}",
+        offset.unsigned_abs(),
+        region.populated.lock().len()
+    )?;
+
+    match (&region.name, &region.backing) {
+        (Some(name), _) => writeln!(f, " {name}"),
+        (None, RegionBacking::File { .. }) => writeln!(f),
+        (None, RegionBacking::Anonymous | RegionBacking::Reserved) => writeln!(f, " {ANON_NAME}"),
+    }
+}
@@ -0,0 +1,149 @@
+//! In-memory `VmFile` implementations for testing and ramfs-style backends.
+
+use alloc::{sync::Arc, vec::Vec};
+use axerrno::{LinuxError, LinuxResult};
+use core::mem::MaybeUninit;
+use spin::Mutex;
+
+use crate::{PageRef, VmFile};
+
+/// Copy as much of `data[offset..]` as fits into `buf`
+/// Returns 0 at or past the end of the data
+fn read_slice(data: &[u8], buf: &mut [u8], offset: u64) -> usize {
+    let Some(remaining) = usize::try_from(offset)
+        .ok()
+        .and_then(|offset| data.get(offset..))
+    else {
+        return 0;
+    };
+    let read = buf.len().min(remaining.len());
+    buf[..read].copy_from_slice(&remaining[..read]);
+    read
+}
+
+/// Copy as much of `data[offset..]` as fits into the uninitialized `buf`
+/// Returns the part of `buf` copied into
+fn read_slice_uninit<'a>(data: &[u8], buf: &'a mut [MaybeUninit<u8>], offset: u64) -> &'a mut [u8] {
+    let remaining = usize::try_from(offset)
+        .ok()
+        .and_then(|offset| data.get(offset..))
+        .unwrap_or_default();
+    let read = buf.len().min(remaining.len());
+    buf[..read].write_copy_of_slice(&remaining[..read])
+}
+
+/// Read-only file whose contents are an immutable shared byte slice
+/// Clones share the same contents
+#[derive(Clone)]
+pub struct SliceFile {
+    data: Arc<[u8]>,
+}
+
+impl SliceFile {
+    /// Create a file with the given contents
+    pub fn new(data: impl Into<Arc<[u8]>>) -> Self {
+        Self { data: data.into() }
+    }
+
+    /// Get the contents of the file
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl VmFile for SliceFile {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> LinuxResult<usize> {
+        Ok(read_slice(&self.data, buf, offset))
+    }
+
+    fn read_uninit_at<'a>(
+        &self,
+        buf: &'a mut [MaybeUninit<u8>],
+        offset: u64,
+    ) -> LinuxResult<&'a mut [u8]> {
+        Ok(read_slice_uninit(&self.data, buf, offset))
+    }
+
+    fn len(&self) -> LinuxResult<u64> {
+        Ok(self.data.len() as u64)
+    }
+
+    fn file_id(&self) -> Option<u64> {
+        Some(Arc::as_ptr(&self.data).cast::<u8>() as usize as u64)
+    }
+
+    fn same_file(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.data, &other.data)
+    }
+
+    fn read_page_ref(&self, offset: u64, len: usize) -> Option<PageRef> {
+        let offset = usize::try_from(offset).ok()?;
+        if offset.checked_add(len)? > self.data.len() {
+            return None;
+        }
+        Some(PageRef::Shared {
+            data: self.data.clone(),
+            offset,
+            len,
+        })
+    }
+}
+
+/// Writable file whose contents live in a shared growable buffer
+/// Clones share the same contents
+#[derive(Clone, Default)]
+pub struct MemFile {
+    data: Arc<Mutex<Vec<u8>>>,
+}
+
+impl MemFile {
+    /// Create a file with the given contents
+    pub fn new(data: Vec<u8>) -> Self {
+        Self {
+            data: Arc::new(Mutex::new(data)),
+        }
+    }
+
+    /// Get a copy of the contents of the file
+    pub fn to_vec(&self) -> Vec<u8> {
+        self.data.lock().clone()
+    }
+}
+
+impl VmFile for MemFile {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> LinuxResult<usize> {
+        Ok(read_slice(&self.data.lock(), buf, offset))
+    }
+
+    fn read_uninit_at<'a>(
+        &self,
+        buf: &'a mut [MaybeUninit<u8>],
+        offset: u64,
+    ) -> LinuxResult<&'a mut [u8]> {
+        Ok(read_slice_uninit(&self.data.lock(), buf, offset))
+    }
+
+    /// Writes past the end of the file extend it, zero-filling any gap
+    fn write_at(&self, buf: &[u8], offset: u64) -> LinuxResult<usize> {
+        let offset = usize::try_from(offset).map_err(|_| LinuxError::EFBIG)?;
+        let end = offset.checked_add(buf.len()).ok_or(LinuxError::EFBIG)?;
+        let mut data = self.data.lock();
+        if data.len() < end {
+            data.resize(end, 0);
+        }
+        data[offset..end].copy_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn len(&self) -> LinuxResult<u64> {
+        Ok(self.data.lock().len() as u64)
+    }
+
+    fn file_id(&self) -> Option<u64> {
+        Some(Arc::as_ptr(&self.data) as usize as u64)
+    }
+
+    fn same_file(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.data, &other.data)
+    }
+}
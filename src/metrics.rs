@@ -0,0 +1,126 @@
+//! Fault and eviction counters, recorded only with the `metrics` feature.
+
+#[cfg(feature = "metrics")]
+use core::sync::atomic::{AtomicU64, Ordering};
+
+/// Counters of a region or manager, as reported by `MmapRegion::metrics` and
+/// `VmaManager::metrics`
+/// All counters stay zero unless the `metrics` feature is enabled
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct VmaMetrics {
+    /// Number of resolved page faults
+    pub faults: u64,
+    /// Number of bytes read from backing files
+    pub bytes_loaded: u64,
+    /// Number of clean pages evicted
+    pub evictions: u64,
+    /// Number of `VmaManager::find_region_cached` lookups served by the cache
+    pub lookup_hits: u64,
+    /// Number of `VmaManager::find_region_cached` lookups that searched the
+    /// regions
+    pub lookup_misses: u64,
+}
+
+/// Relaxed atomic counters behind `VmaMetrics`
+/// Without the `metrics` feature this is zero-sized and recording is a no-op
+#[derive(Default)]
+pub(crate) struct MetricCounters {
+    #[cfg(feature = "metrics")]
+    faults: AtomicU64,
+    #[cfg(feature = "metrics")]
+    bytes_loaded: AtomicU64,
+    #[cfg(feature = "metrics")]
+    evictions: AtomicU64,
+    #[cfg(feature = "metrics")]
+    lookup_hits: AtomicU64,
+    #[cfg(feature = "metrics")]
+    lookup_misses: AtomicU64,
+}
+
+#[cfg(not(feature = "metrics"))]
+const _: () = assert!(core::mem::size_of::<MetricCounters>() == 0);
+
+#[cfg(feature = "metrics")]
+impl MetricCounters {
+    pub(crate) fn record_fault(&self) {
+        self.faults.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_load(&self, bytes: usize) {
+        self.bytes_loaded.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_evictions(&self, pages: usize) {
+        self.evictions.fetch_add(pages as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_lookup(&self, hit: bool) {
+        let counter = if hit {
+            &self.lookup_hits
+        } else {
+            &self.lookup_misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Add the counts of `metrics` to these counters
+    pub(crate) fn add(&self, metrics: VmaMetrics) {
+        self.faults.fetch_add(metrics.faults, Ordering::Relaxed);
+        self.bytes_loaded
+            .fetch_add(metrics.bytes_loaded, Ordering::Relaxed);
+        self.evictions
+            .fetch_add(metrics.evictions, Ordering::Relaxed);
+        self.lookup_hits
+            .fetch_add(metrics.lookup_hits, Ordering::Relaxed);
+        self.lookup_misses
+            .fetch_add(metrics.lookup_misses, Ordering::Relaxed);
+    }
+
+    pub(crate) fn get(&self) -> VmaMetrics {
+        VmaMetrics {
+            faults: self.faults.load(Ordering::Relaxed),
+            bytes_loaded: self.bytes_loaded.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            lookup_hits: self.lookup_hits.load(Ordering::Relaxed),
+            lookup_misses: self.lookup_misses.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(not(feature = "metrics"))]
+impl MetricCounters {
+    pub(crate) fn record_fault(&self) {}
+
+    pub(crate) fn record_load(&self, _bytes: usize) {}
+
+    pub(crate) fn record_evictions(&self, _pages: usize) {}
+
+    pub(crate) fn record_lookup(&self, _hit: bool) {}
+
+    pub(crate) fn add(&self, _metrics: VmaMetrics) {}
+
+    pub(crate) fn get(&self) -> VmaMetrics {
+        VmaMetrics::default()
+    }
+}
+
+impl Clone for MetricCounters {
+    fn clone(&self) -> Self {
+        let counters = Self::default();
+        counters.add(self.get());
+        counters
+    }
+}
+
+impl VmaMetrics {
+    /// Sum of these counts and `other`
+    pub(crate) fn plus(self, other: Self) -> Self {
+        Self {
+            faults: self.faults + other.faults,
+            bytes_loaded: self.bytes_loaded + other.bytes_loaded,
+            evictions: self.evictions + other.evictions,
+            lookup_hits: self.lookup_hits + other.lookup_hits,
+            lookup_misses: self.lookup_misses + other.lookup_misses,
+        }
+    }
+}
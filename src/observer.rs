@@ -0,0 +1,27 @@
+//! Callbacks notified of changes made by a `VmaManager`.
+
+use memory_addr::{AddrRange, MemoryAddr, VirtAddr};
+use page_table_multiarch::PageSize;
+
+/// Observer of the page and region changes made by a `VmaManager`
+///
+/// Lets external structures such as frame reference counts, reverse maps or
+/// tracing follow the manager. Only changes made through the manager are
+/// reported; pages populated directly through `MmapRegion` methods are not.
+/// All callbacks default to doing nothing.
+pub trait VmaObserver<A: MemoryAddr = VirtAddr>: Send + Sync {
+    /// A page of the region covering `region` was populated
+    fn on_populate(&self, _region: AddrRange<A>, _vaddr: A, _size: PageSize) {}
+
+    /// A populated page of the region covering `region` was dropped
+    fn on_evict(&self, _region: AddrRange<A>, _vaddr: A, _size: PageSize) {}
+
+    /// The region covering `original` was split into `parts`, in address order
+    fn on_split(&self, _original: AddrRange<A>, _parts: &[AddrRange<A>]) {}
+
+    /// A region covering `range` was added to the manager
+    fn on_add(&self, _range: AddrRange<A>) {}
+
+    /// The region covering `range` was removed from the manager
+    fn on_remove(&self, _range: AddrRange<A>) {}
+}
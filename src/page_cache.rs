@@ -0,0 +1,250 @@
+//! Page cache shared by every region mapping the same file.
+
+use alloc::{collections::BTreeMap, collections::VecDeque, sync::Arc, vec};
+use axerrno::{LinuxError, LinuxResult};
+#[cfg(feature = "metrics")]
+use core::sync::atomic::{AtomicU64, Ordering};
+use spin::Mutex;
+
+use crate::{PageRef, VmFile};
+
+/// Size of the file pages the cache holds
+const CACHE_PAGE_SIZE: usize = 0x1000;
+
+/// Hit and miss counts of a `CachedFile`, as reported by `CachedFile::metrics`
+/// Both stay zero unless the `metrics` feature is enabled
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheMetrics {
+    /// Number of file pages served from the cache
+    pub hits: u64,
+    /// Number of file pages read from the underlying file
+    pub misses: u64,
+}
+
+/// `VmFile` wrapper keeping the pages read from the underlying file in a cache
+/// Clones share the cache, so regions backed by clones of one `CachedFile`
+/// read every file page only once while it stays cached. Pages are evicted
+/// in the order they were cached once the capacity is reached; writes go
+/// through to the file and drop the pages they change
+#[derive(Clone)]
+pub struct CachedFile<F: VmFile> {
+    file: F,
+    cache: Arc<PageCache>,
+}
+
+/// Cached pages of a `CachedFile` and their counters
+struct PageCache {
+    /// Maximum number of cached pages
+    capacity: usize,
+    /// Cached pages by file offset, with the offsets in insertion order
+    state: Mutex<CacheState>,
+    #[cfg(feature = "metrics")]
+    hits: AtomicU64,
+    #[cfg(feature = "metrics")]
+    misses: AtomicU64,
+}
+
+/// Cached pages along with the order they are evicted in
+#[derive(Default)]
+struct CacheState {
+    /// Contents of each cached page by its file offset, shorter than a page
+    /// only at the end of the file
+    pages: BTreeMap<u64, Arc<[u8]>>,
+    /// Offsets of the cached pages, oldest first
+    order: VecDeque<u64>,
+}
+
+impl CacheState {
+    /// Cache `page` at `offset`, evicting the oldest pages to stay within
+    /// `capacity`
+    /// A page cached meanwhile by another reader is kept instead
+    fn insert(&mut self, capacity: usize, offset: u64, page: Arc<[u8]>) {
+        if capacity == 0 || self.pages.contains_key(&offset) {
+            return;
+        }
+        while self.pages.len() >= capacity {
+            let Some(oldest) = self.order.pop_front() else {
+                break;
+            };
+            self.pages.remove(&oldest);
+        }
+        self.pages.insert(offset, page);
+        self.order.push_back(offset);
+    }
+
+    /// Drop the cached pages selected by `drop`
+    fn remove_where(&mut self, drop: impl Fn(u64, &[u8]) -> bool) {
+        self.pages.retain(|&offset, page| !drop(offset, page));
+        let pages = &self.pages;
+        self.order.retain(|offset| pages.contains_key(offset));
+    }
+}
+
+impl PageCache {
+    #[cfg(feature = "metrics")]
+    fn record(&self, hit: bool) {
+        let counter = if hit { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[cfg(not(feature = "metrics"))]
+    fn record(&self, _hit: bool) {}
+
+    #[cfg(feature = "metrics")]
+    fn metrics(&self) -> CacheMetrics {
+        CacheMetrics {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+
+    #[cfg(not(feature = "metrics"))]
+    fn metrics(&self) -> CacheMetrics {
+        CacheMetrics::default()
+    }
+}
+
+impl<F: VmFile> CachedFile<F> {
+    /// Wrap `file` in a cache holding up to `capacity` pages
+    pub fn new(file: F, capacity: usize) -> Self {
+        Self {
+            file,
+            cache: Arc::new(PageCache {
+                capacity,
+                state: Mutex::new(CacheState::default()),
+                #[cfg(feature = "metrics")]
+                hits: AtomicU64::new(0),
+                #[cfg(feature = "metrics")]
+                misses: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Get the underlying file
+    pub fn file(&self) -> &F {
+        &self.file
+    }
+
+    /// Maximum number of cached pages
+    pub fn capacity(&self) -> usize {
+        self.cache.capacity
+    }
+
+    /// Number of pages currently cached
+    pub fn cached_pages(&self) -> usize {
+        self.cache.state.lock().pages.len()
+    }
+
+    /// Drop every cached page, as when the file changed behind the cache
+    pub fn invalidate(&self) {
+        *self.cache.state.lock() = CacheState::default();
+    }
+
+    /// Hit and miss counts of the cache shared by all clones
+    pub fn metrics(&self) -> CacheMetrics {
+        self.cache.metrics()
+    }
+
+    /// Get the file page at the page-aligned `offset`, reading and caching it
+    /// on a miss
+    /// The file is read without holding the cache lock
+    fn page(&self, offset: u64) -> LinuxResult<Arc<[u8]>> {
+        if let Some(page) = self.cache.state.lock().pages.get(&offset) {
+            self.cache.record(true);
+            return Ok(page.clone());
+        }
+        self.cache.record(false);
+        let mut data = vec![0u8; CACHE_PAGE_SIZE];
+        let mut filled = 0;
+        while filled < CACHE_PAGE_SIZE {
+            match self
+                .file
+                .read_at(&mut data[filled..], offset + filled as u64)
+            {
+                Ok(0) => break,
+                Ok(read) => filled += read.min(CACHE_PAGE_SIZE - filled),
+                Err(LinuxError::EINTR) => continue,
+                Err(err) => return Err(err),
+            }
+        }
+        data.truncate(filled);
+        let page: Arc<[u8]> = data.into();
+        self.cache
+            .state
+            .lock()
+            .insert(self.cache.capacity, offset, page.clone());
+        Ok(page)
+    }
+}
+
+/// Page-aligned file offset of the cache page containing `offset`
+fn page_offset(offset: u64) -> u64 {
+    offset - offset % CACHE_PAGE_SIZE as u64
+}
+
+impl<F: VmFile> VmFile for CachedFile<F> {
+    /// Reads stop short at the end of the file, or at a page that fails to
+    /// load once some data was read
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> LinuxResult<usize> {
+        let mut read = 0;
+        while read < buf.len() {
+            let pos = offset + read as u64;
+            let page = match self.page(page_offset(pos)) {
+                Ok(page) => page,
+                Err(err) if read == 0 => return Err(err),
+                Err(_) => break,
+            };
+            let data = page
+                .get((pos - page_offset(pos)) as usize..)
+                .unwrap_or_default();
+            let len = data.len().min(buf.len() - read);
+            buf[read..read + len].copy_from_slice(&data[..len]);
+            read += len;
+            if page.len() < CACHE_PAGE_SIZE {
+                break;
+            }
+        }
+        Ok(read)
+    }
+
+    /// Written pages are dropped from the cache, along with the cached end
+    /// of the file, which the write may extend
+    fn write_at(&self, buf: &[u8], offset: u64) -> LinuxResult<usize> {
+        let written = self.file.write_at(buf, offset);
+        let (start, end) = (page_offset(offset), offset.saturating_add(buf.len() as u64));
+        self.cache.state.lock().remove_where(|page_start, page| {
+            (start..end).contains(&page_start) || page.len() < CACHE_PAGE_SIZE
+        });
+        written
+    }
+
+    fn len(&self) -> LinuxResult<u64> {
+        self.file.len()
+    }
+
+    fn file_id(&self) -> Option<u64> {
+        self.file.file_id()
+    }
+
+    fn same_file(&self, other: &Self) -> bool {
+        self.file.same_file(&other.file)
+    }
+
+    /// Pages within one cache page are borrowed from the cache, loading it on
+    /// a miss, unless the underlying file lends them itself
+    fn read_page_ref(&self, offset: u64, len: usize) -> Option<PageRef> {
+        if let Some(page) = self.file.read_page_ref(offset, len) {
+            return Some(page);
+        }
+        let start = (offset - page_offset(offset)) as usize;
+        if start + len > CACHE_PAGE_SIZE {
+            return None;
+        }
+        let data = self.page(page_offset(offset)).ok()?;
+        (start + len <= data.len()).then_some(PageRef::Shared {
+            data,
+            offset: start,
+            len,
+        })
+    }
+}
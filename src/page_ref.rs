@@ -0,0 +1,63 @@
+//! Page contents that are either owned or borrowed from the backing file.
+
+use alloc::{sync::Arc, vec::Vec};
+use core::ops::Deref;
+
+/// Page contents borrowed from a backend that already holds them in memory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PageRef {
+    /// Contents living for the whole program, as for ROM-resident files
+    Static(&'static [u8]),
+    /// `len` bytes at `offset` of a shared buffer, as for a page cache
+    Shared {
+        data: Arc<[u8]>,
+        offset: usize,
+        len: usize,
+    },
+}
+
+impl Deref for PageRef {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Self::Static(data) => data,
+            Self::Shared { data, offset, len } => &data[*offset..*offset + *len],
+        }
+    }
+}
+
+/// Contents of a loaded page
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PageData {
+    /// Contents copied into a fresh buffer
+    Owned(Vec<u8>),
+    /// Contents borrowed from the backend without copying
+    Borrowed(PageRef),
+}
+
+impl PageData {
+    /// Is the page borrowed from the backend?
+    pub fn is_borrowed(&self) -> bool {
+        matches!(self, Self::Borrowed(_))
+    }
+
+    /// Take the contents as an owned buffer, copying borrowed ones
+    pub fn into_vec(self) -> Vec<u8> {
+        match self {
+            Self::Owned(data) => data,
+            Self::Borrowed(page) => page.to_vec(),
+        }
+    }
+}
+
+impl Deref for PageData {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Self::Owned(data) => data,
+            Self::Borrowed(page) => page,
+        }
+    }
+}
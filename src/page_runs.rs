@@ -0,0 +1,210 @@
+//! Compact set of page addresses stored as runs of consecutive pages.
+
+use alloc::collections::BTreeMap;
+use core::{fmt, marker::PhantomData};
+use memory_addr::{AddrRange, MemoryAddr, VirtAddr};
+use page_table_multiarch::PageSize;
+
+/// Set of page-aligned addresses, stored as runs of consecutive pages
+///
+/// Runs are keyed by the absolute page number of their first page and never
+/// overlap or touch, so a fully populated mapping of any size takes a single
+/// entry. Lookups, inserts and removals cost O(log runs), and splitting or
+/// merging sets costs O(runs) instead of O(pages).
+pub struct PageSet<A: MemoryAddr = VirtAddr> {
+    /// log2 of the page size
+    shift: u32,
+    /// End page number (exclusive) of every run, by the run's first page number
+    runs: BTreeMap<usize, usize>,
+    /// Number of pages in all runs
+    len: usize,
+    _addr: PhantomData<A>,
+}
+
+impl<A: MemoryAddr> Clone for PageSet<A> {
+    fn clone(&self) -> Self {
+        Self {
+            shift: self.shift,
+            runs: self.runs.clone(),
+            len: self.len,
+            _addr: PhantomData,
+        }
+    }
+}
+
+impl PageSet {
+    /// Create an empty set of virtual pages of the given size
+    pub fn new(align: PageSize) -> Self {
+        Self::with_page_size(align)
+    }
+}
+
+impl<A: MemoryAddr> PageSet<A> {
+    /// Create an empty set of pages of the given size, for any address type
+    pub fn with_page_size(align: PageSize) -> Self {
+        Self {
+            shift: (align as usize).trailing_zeros(),
+            runs: BTreeMap::new(),
+            len: 0,
+            _addr: PhantomData,
+        }
+    }
+
+    /// Page size of the tracked pages
+    pub fn page_size(&self) -> usize {
+        1 << self.shift
+    }
+
+    /// Number of pages in the set
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Is the set empty?
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Check if the page at `page` is in the set
+    pub fn contains(&self, page: A) -> bool {
+        self.run_of(page.into() >> self.shift).is_some()
+    }
+
+    /// Add the page at `page` to the set, merging it with adjacent runs
+    /// Returns whether the page was newly inserted
+    pub fn insert(&mut self, page: A) -> bool {
+        let index = page.into() >> self.shift;
+        if self.run_of(index).is_some() {
+            return false;
+        }
+        self.insert_run(index, index + 1);
+        true
+    }
+
+    /// Remove the page at `page` from the set, splitting its run
+    /// Returns whether the page was present
+    pub fn remove(&mut self, page: A) -> bool {
+        let index = page.into() >> self.shift;
+        let Some((start, end)) = self.run_of(index) else {
+            return false;
+        };
+        self.runs.remove(&start);
+        if start < index {
+            self.runs.insert(start, index);
+        }
+        if index + 1 < end {
+            self.runs.insert(index + 1, end);
+        }
+        self.len -= 1;
+        true
+    }
+
+    /// Remove all pages from the set
+    pub fn clear(&mut self) {
+        self.runs.clear();
+        self.len = 0;
+    }
+
+    /// Iterate over the pages in the set in ascending order
+    pub fn iter(&self) -> impl Iterator<Item = A> + '_ {
+        self.runs
+            .iter()
+            .flat_map(move |(&start, &end)| (start..end).map(move |i| A::from(i << self.shift)))
+    }
+
+    /// Copy out the pages whose address lies within `range`
+    pub fn subset(&self, range: AddrRange<A>) -> Self {
+        let mut subset = Self::with_shift(self.shift);
+        let (lo, hi) = self.index_range(range);
+        if lo >= hi {
+            return subset;
+        }
+        // The run containing `lo` may start before it
+        let first = self.run_of(lo).map_or(lo, |(start, _)| start);
+        for (&start, &end) in self.runs.range(first..hi) {
+            let (start, end) = (start.max(lo), end.min(hi));
+            subset.runs.insert(start, end);
+            subset.len += end - start;
+        }
+        subset
+    }
+
+    /// Copy out the pages of the set moved so that `from` lands on `to`
+    /// Every page must lie at or above `from`, and both bases must be aligned
+    /// to the page size
+    pub fn rebased(&self, from: A, to: A) -> Self {
+        let (from, to) = (from.into() >> self.shift, to.into() >> self.shift);
+        let mut rebased = Self::with_shift(self.shift);
+        rebased.runs = self
+            .runs
+            .iter()
+            .map(|(&start, &end)| (start - from + to, end - from + to))
+            .collect();
+        rebased.len = self.len;
+        rebased
+    }
+
+    /// Add all pages of `other` to this set
+    pub fn union_with(&mut self, other: &Self) {
+        debug_assert_eq!(self.shift, other.shift);
+        for (&start, &end) in &other.runs {
+            self.insert_run(start, end);
+        }
+    }
+
+    fn with_shift(shift: u32) -> Self {
+        Self {
+            shift,
+            runs: BTreeMap::new(),
+            len: 0,
+            _addr: PhantomData,
+        }
+    }
+
+    /// Absolute page index range `[lo, hi)` of pages lying within `range`
+    fn index_range(&self, range: AddrRange<A>) -> (usize, usize) {
+        let page_size = self.page_size();
+        let lo = range.start.into().div_ceil(page_size);
+        let hi = range.end.into().div_ceil(page_size);
+        (lo, hi)
+    }
+
+    /// Run `[start, end)` containing the page numbered `index`
+    fn run_of(&self, index: usize) -> Option<(usize, usize)> {
+        self.runs
+            .range(..=index)
+            .next_back()
+            .map(|(&start, &end)| (start, end))
+            .filter(|&(_, end)| index < end)
+    }
+
+    /// Add the pages numbered `[start, end)`, merging every run they overlap
+    /// or touch into one
+    fn insert_run(&mut self, mut start: usize, mut end: usize) {
+        if let Some((prev_start, prev_end)) = self
+            .runs
+            .range(..=start)
+            .next_back()
+            .map(|(&s, &e)| (s, e))
+            .filter(|&(_, prev_end)| prev_end >= start)
+        {
+            start = prev_start;
+            end = end.max(prev_end);
+        }
+        while let Some((next_start, next_end)) =
+            self.runs.range(start..=end).next().map(|(&s, &e)| (s, e))
+        {
+            self.runs.remove(&next_start);
+            self.len -= next_end - next_start;
+            end = end.max(next_end);
+        }
+        self.runs.insert(start, end);
+        self.len += end - start;
+    }
+}
+
+impl<A: MemoryAddr + fmt::Debug> fmt::Debug for PageSet<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
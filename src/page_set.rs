@@ -0,0 +1,266 @@
+//! Compact set of page addresses backed by a bitmap.
+
+use alloc::{vec, vec::Vec};
+use core::{fmt, marker::PhantomData};
+use memory_addr::{AddrRange, MemoryAddr, VirtAddr};
+use page_table_multiarch::PageSize;
+
+const WORD_BITS: usize = u64::BITS as usize;
+
+/// Set of page-aligned addresses, stored as one bit per page
+///
+/// Bits are indexed by absolute page number, and only the words between the
+/// lowest and highest inserted page are allocated. A fully populated 1 GiB
+/// mapping of 4 KiB pages takes 32 KiB, where a `BTreeSet<VirtAddr>` needs
+/// several megabytes of tree nodes.
+pub struct PageSet<A: MemoryAddr = VirtAddr> {
+    /// log2 of the page size
+    shift: u32,
+    /// Absolute index of the first page tracked by `words[0]`, divided by 64
+    first_word: usize,
+    /// Bitmap words, covering pages from `first_word * 64` upwards
+    words: Vec<u64>,
+    /// Number of set bits
+    len: usize,
+    _addr: PhantomData<A>,
+}
+
+impl<A: MemoryAddr> Clone for PageSet<A> {
+    fn clone(&self) -> Self {
+        Self {
+            shift: self.shift,
+            first_word: self.first_word,
+            words: self.words.clone(),
+            len: self.len,
+            _addr: PhantomData,
+        }
+    }
+}
+
+impl PageSet {
+    /// Create an empty set of virtual pages of the given size
+    pub fn new(align: PageSize) -> Self {
+        Self::with_page_size(align)
+    }
+}
+
+impl<A: MemoryAddr> PageSet<A> {
+    /// Create an empty set of pages of the given size, for any address type
+    pub fn with_page_size(align: PageSize) -> Self {
+        Self {
+            shift: (align as usize).trailing_zeros(),
+            first_word: 0,
+            words: Vec::new(),
+            len: 0,
+            _addr: PhantomData,
+        }
+    }
+
+    /// Page size of the tracked pages
+    pub fn page_size(&self) -> usize {
+        1 << self.shift
+    }
+
+    /// Number of pages in the set
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Is the set empty?
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Check if the page at `page` is in the set
+    pub fn contains(&self, page: A) -> bool {
+        let index = page.into() >> self.shift;
+        match self.word(index) {
+            Some(word) => word & bit(index) != 0,
+            None => false,
+        }
+    }
+
+    /// Add the page at `page` to the set
+    /// Returns whether the page was newly inserted
+    pub fn insert(&mut self, page: A) -> bool {
+        let index = page.into() >> self.shift;
+        self.reserve(index / WORD_BITS, index / WORD_BITS + 1);
+        let word = &mut self.words[index / WORD_BITS - self.first_word];
+        let inserted = *word & bit(index) == 0;
+        *word |= bit(index);
+        self.len += inserted as usize;
+        inserted
+    }
+
+    /// Remove the page at `page` from the set
+    /// Returns whether the page was present
+    pub fn remove(&mut self, page: A) -> bool {
+        let index = page.into() >> self.shift;
+        let Some(word) = self.word_mut(index) else {
+            return false;
+        };
+        let removed = *word & bit(index) != 0;
+        *word &= !bit(index);
+        self.len -= removed as usize;
+        removed
+    }
+
+    /// Remove all pages from the set, releasing the bitmap
+    pub fn clear(&mut self) {
+        self.words = Vec::new();
+        self.first_word = 0;
+        self.len = 0;
+    }
+
+    /// Iterate over the pages in the set in ascending order
+    pub fn iter(&self) -> impl Iterator<Item = A> + '_ {
+        self.words.iter().enumerate().flat_map(move |(i, &word)| {
+            let base = (self.first_word + i) * WORD_BITS;
+            BitIter(word).map(move |b| A::from((base + b) << self.shift))
+        })
+    }
+
+    /// Copy out the pages whose address lies within `range`
+    pub fn subset(&self, range: AddrRange<A>) -> Self {
+        let mut subset = Self {
+            shift: self.shift,
+            first_word: 0,
+            words: Vec::new(),
+            len: 0,
+            _addr: PhantomData,
+        };
+        let (lo, hi) = self.index_range(range);
+        if lo >= hi || self.words.is_empty() {
+            return subset;
+        }
+
+        let lo_word = (lo / WORD_BITS).max(self.first_word);
+        let hi_word = hi
+            .div_ceil(WORD_BITS)
+            .min(self.first_word + self.words.len());
+        if lo_word >= hi_word {
+            return subset;
+        }
+        subset.first_word = lo_word;
+        subset.words = self.words[lo_word - self.first_word..hi_word - self.first_word].to_vec();
+        subset.mask_outside(lo, hi);
+        subset.len = subset.count();
+        subset
+    }
+
+    /// Copy out the pages of the set moved so that `from` lands on `to`
+    /// Every page must lie at or above `from`, and both bases must be aligned
+    /// to the page size
+    pub fn rebased(&self, from: A, to: A) -> Self {
+        let mut rebased = Self {
+            shift: self.shift,
+            first_word: 0,
+            words: Vec::new(),
+            len: 0,
+            _addr: PhantomData,
+        };
+        for page in self.iter() {
+            rebased.insert(to.add(page.sub_addr(from)));
+        }
+        rebased
+    }
+
+    /// Add all pages of `other` to this set
+    pub fn union_with(&mut self, other: &Self) {
+        debug_assert_eq!(self.shift, other.shift);
+        if other.words.is_empty() {
+            return;
+        }
+        self.reserve(other.first_word, other.first_word + other.words.len());
+        let offset = other.first_word - self.first_word;
+        for (dst, src) in self.words[offset..].iter_mut().zip(&other.words) {
+            *dst |= src;
+        }
+        self.len = self.count();
+    }
+
+    /// Absolute page index range `[lo, hi)` of pages lying within `range`
+    fn index_range(&self, range: AddrRange<A>) -> (usize, usize) {
+        let page_size = self.page_size();
+        let lo = range.start.into().div_ceil(page_size);
+        let hi = range.end.into().div_ceil(page_size);
+        (lo, hi)
+    }
+
+    /// Clear all bits outside the absolute page index range `[lo, hi)`
+    fn mask_outside(&mut self, lo: usize, hi: usize) {
+        for (i, word) in self.words.iter_mut().enumerate() {
+            let base = (self.first_word + i) * WORD_BITS;
+            let start = lo.saturating_sub(base).min(WORD_BITS);
+            let end = hi.saturating_sub(base).min(WORD_BITS);
+            *word &= bits_between(start, end);
+        }
+    }
+
+    /// Grow the bitmap to cover the absolute word range `[lo, hi)`
+    fn reserve(&mut self, lo: usize, hi: usize) {
+        if self.words.is_empty() {
+            self.first_word = lo;
+            self.words = vec![0; hi - lo];
+            return;
+        }
+        if lo < self.first_word {
+            let extra = self.first_word - lo;
+            self.words.splice(0..0, core::iter::repeat_n(0, extra));
+            self.first_word = lo;
+        }
+        if hi > self.first_word + self.words.len() {
+            self.words.resize(hi - self.first_word, 0);
+        }
+    }
+
+    fn word(&self, index: usize) -> Option<u64> {
+        let word = (index / WORD_BITS).checked_sub(self.first_word)?;
+        self.words.get(word).copied()
+    }
+
+    fn word_mut(&mut self, index: usize) -> Option<&mut u64> {
+        let word = (index / WORD_BITS).checked_sub(self.first_word)?;
+        self.words.get_mut(word)
+    }
+
+    fn count(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+}
+
+impl<A: MemoryAddr + fmt::Debug> fmt::Debug for PageSet<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+/// Bit of a page index within its bitmap word
+fn bit(index: usize) -> u64 {
+    1 << (index % WORD_BITS)
+}
+
+/// Mask with bits `[start, end)` set, where both bounds are at most 64
+fn bits_between(start: usize, end: usize) -> u64 {
+    if start >= end {
+        return 0;
+    }
+    let upper = if end == WORD_BITS { !0 } else { (1 << end) - 1 };
+    upper & !((1u64 << start) - 1)
+}
+
+/// Iterator over the set bit positions of a word
+struct BitIter(u64);
+
+impl Iterator for BitIter {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if self.0 == 0 {
+            return None;
+        }
+        let b = self.0.trailing_zeros() as usize;
+        self.0 &= self.0 - 1;
+        Some(b)
+    }
+}
@@ -0,0 +1,144 @@
+//! Placement of mappings at addresses chosen by the manager.
+
+use alloc::vec::Vec;
+use memory_addr::{AddrRange, MemoryAddr, VirtAddr};
+use page_table_multiarch::PageSize;
+
+use crate::{RawMutex, VmFile, VmaManager, checked_align_up};
+
+/// Number of random placements tried before falling back on a bottom-up
+/// search
+const RANDOM_ATTEMPTS: usize = 16;
+
+/// Where `VmaManager::find_free_range` places a mapping
+#[derive(Debug, Clone, Copy)]
+pub enum PlacementPolicy<A: MemoryAddr = VirtAddr> {
+    /// Lowest free range at or above the hint, then at or above `base`, then
+    /// anywhere, as legacy layouts do; hints below the limits are ignored
+    BottomUp {
+        /// Address the search starts from without a usable hint
+        base: A,
+    },
+    /// Highest free range ending at or below `ceiling`, as Linux places
+    /// mappings below the stack, or the lowest one if none is left there
+    TopDown {
+        /// Address no mapping placed this way extends above
+        ceiling: A,
+    },
+    /// Random aligned address in a gap chosen with `rng`, for address space
+    /// layout randomization
+    /// Falls back on the lowest free range if the chosen gaps keep being too
+    /// small
+    Random {
+        /// Entropy source supplied by the host
+        rng: fn() -> u64,
+    },
+}
+
+impl<A: MemoryAddr> Default for PlacementPolicy<A> {
+    fn default() -> Self {
+        Self::BottomUp { base: A::from(0) }
+    }
+}
+
+impl<F: VmFile, A: MemoryAddr, R: RawMutex> VmaManager<F, A, R> {
+    /// Set how `find_free_range` places mappings, and with it `mmap` and
+    /// moving remaps
+    pub fn set_placement(&mut self, policy: PlacementPolicy<A>) {
+        self.placement = policy;
+    }
+
+    /// Policy `find_free_range` places mappings by
+    pub fn placement(&self) -> PlacementPolicy<A> {
+        self.placement
+    }
+
+    /// Place `size` bytes aligned to `align` within `limits` by the placement
+    /// policy, see `find_free_range`
+    pub(crate) fn place(
+        &self,
+        hint: A,
+        size: usize,
+        align: PageSize,
+        limits: AddrRange<A>,
+    ) -> Option<A> {
+        match self.placement {
+            PlacementPolicy::BottomUp { base } => (hint >= limits.start)
+                .then(|| self.find_free_from(hint, size, align, limits))
+                .flatten()
+                .or_else(|| self.find_free_from(base.max(limits.start), size, align, limits)),
+            PlacementPolicy::TopDown { ceiling } => self
+                .free_at(hint, size, align, limits)
+                .or_else(|| self.find_free_below(ceiling.min(limits.end), size, align, limits)),
+            PlacementPolicy::Random { rng } => self
+                .free_at(hint, size, align, limits)
+                .or_else(|| self.find_free_random(rng, size, align, limits)),
+        }
+        .or_else(|| self.find_free_from(limits.start, size, align, limits))
+    }
+
+    /// Check if `size` bytes at `hint`, aligned up to `align`, are free and
+    /// within `limits`
+    /// Hints below the limits, such as zero, are never used, as under every
+    /// policy
+    fn free_at(&self, hint: A, size: usize, align: PageSize, limits: AddrRange<A>) -> Option<A> {
+        if hint < limits.start {
+            return None;
+        }
+        let start = checked_align_up(hint, align)?;
+        let end = A::from(start.into().checked_add(size)?);
+        if end > limits.end {
+            return None;
+        }
+        self.find_free_from(start, size, align, AddrRange::new(start, end))
+    }
+
+    /// Find the last free aligned range of `size` bytes ending at or below
+    /// `ceiling`
+    fn find_free_below(
+        &self,
+        ceiling: A,
+        size: usize,
+        align: PageSize,
+        limits: AddrRange<A>,
+    ) -> Option<A> {
+        if ceiling <= limits.start {
+            return None;
+        }
+        let gaps: Vec<_> = self.gaps(AddrRange::new(limits.start, ceiling)).collect();
+        gaps.into_iter().rev().find_map(|gap| {
+            let gap = self.unguarded(gap)?;
+            let start = A::from(gap.end.into().checked_sub(size)?).align_down(align);
+            (start >= gap.start).then_some(start)
+        })
+    }
+
+    /// Pick a free aligned range of `size` bytes at random, trying up to
+    /// `RANDOM_ATTEMPTS` gaps chosen with `rng`
+    fn find_free_random(
+        &self,
+        rng: fn() -> u64,
+        size: usize,
+        align: PageSize,
+        limits: AddrRange<A>,
+    ) -> Option<A> {
+        let gaps: Vec<_> = self
+            .gaps(limits)
+            .filter_map(|gap| self.unguarded(gap))
+            .collect();
+        if gaps.is_empty() {
+            return None;
+        }
+        (0..RANDOM_ATTEMPTS).find_map(|_| {
+            let gap = gaps[(rng() % gaps.len() as u64) as usize];
+            let first = checked_align_up(gap.start, align)?;
+            let spare = gap
+                .end
+                .into()
+                .checked_sub(first.into())?
+                .checked_sub(size)?;
+            let slots = (spare / align as usize) as u64 + 1;
+            Some(first.add((rng() % slots) as usize * align as usize))
+        })
+    }
+}
@@ -0,0 +1,180 @@
+//! Victim selection for reclaim by host-supplied policies.
+
+use alloc::{collections::BTreeSet, vec, vec::Vec};
+use core::cmp::Reverse;
+use memory_addr::{MemoryAddr, VirtAddr};
+use page_table_multiarch::PageSize;
+
+use crate::{MmapRegion, RawMutex, RegionView, VmFile, VmaManager};
+
+/// Region along with the pages reclaim picked from it
+type Victims<'a, F, A, R> = (&'a MmapRegion<F, A, R>, Vec<A>);
+
+/// Policy ordering the pages `VmaManager::reclaim_with` evicts
+/// Reclaim scores every page it may evict and takes the highest scoring ones
+/// first, leaving dirty, anonymous, locked and in-flight pages out as before.
+/// The notifications default to doing nothing
+pub trait ReclaimPolicy<A: MemoryAddr = VirtAddr> {
+    /// Score the page at `vaddr` of `region`; higher scores are evicted sooner
+    fn score(&self, region: &RegionView<A>, vaddr: A) -> u64;
+
+    /// A fault at `vaddr` was resolved, reported by the host after
+    /// `VmaManager::handle_fault` so that the policy can track recency
+    fn on_fault(&mut self, _vaddr: A) {}
+
+    /// The page at `vaddr` of `region` was scored by a reclaim pass, whether
+    /// it was evicted or not
+    fn on_scan(&mut self, _region: &RegionView<A>, _vaddr: A) {}
+}
+
+/// Policy evicting pages in ascending address order
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AddressOrder;
+
+impl<A: MemoryAddr> ReclaimPolicy<A> for AddressOrder {
+    fn score(&self, _region: &RegionView<A>, vaddr: A) -> u64 {
+        u64::MAX - vaddr.into() as u64
+    }
+}
+
+/// Policy evicting the pages not faulted at since they were last scanned
+/// first, in the manner of the clock algorithm
+/// Faults set the accessed flag of their page and scans clear it, so a page
+/// survives the first reclaim pass after it is faulted in while others are
+/// left; ties go to the lower address
+#[derive(Debug, Clone)]
+pub struct ClockPolicy<A: MemoryAddr = VirtAddr> {
+    /// Addresses faulted at since the page containing them was last scanned
+    accessed: BTreeSet<A>,
+}
+
+impl<A: MemoryAddr> Default for ClockPolicy<A> {
+    fn default() -> Self {
+        Self {
+            accessed: BTreeSet::new(),
+        }
+    }
+}
+
+impl<A: MemoryAddr> ClockPolicy<A> {
+    /// Create a policy with no page marked accessed
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check if the page of `size` bytes at `vaddr` was faulted at since it
+    /// was last scanned
+    pub fn is_accessed(&self, vaddr: A, size: PageSize) -> bool {
+        self.accessed
+            .range(vaddr..vaddr.add(size as usize))
+            .next()
+            .is_some()
+    }
+}
+
+impl<A: MemoryAddr> ReclaimPolicy<A> for ClockPolicy<A> {
+    fn score(&self, region: &RegionView<A>, vaddr: A) -> u64 {
+        u64::from(!self.is_accessed(vaddr, region.align))
+    }
+
+    fn on_fault(&mut self, vaddr: A) {
+        self.accessed.insert(vaddr);
+    }
+
+    fn on_scan(&mut self, region: &RegionView<A>, vaddr: A) {
+        let page: Vec<A> = self
+            .accessed
+            .range(vaddr..vaddr.add(region.align as usize))
+            .copied()
+            .collect();
+        for vaddr in page {
+            self.accessed.remove(&vaddr);
+        }
+    }
+}
+
+impl<F: VmFile, A: MemoryAddr, R: RawMutex> VmaManager<F, A, R> {
+    /// Evict up to `target_pages` clean file-backed pages to relieve memory
+    /// pressure, lowest addresses first
+    /// Lazily freed pages of every region are taken first without writeback,
+    /// even if dirty or anonymous; other dirty, anonymous and locked pages are
+    /// kept
+    /// Returns the evicted pages, grouped by region in address order within
+    /// each of the two passes, so that the caller can unmap and free them
+    pub fn reclaim(&self, target_pages: usize) -> Vec<(A, PageSize)> {
+        self.reclaim_with(target_pages, &mut AddressOrder)
+    }
+
+    /// Evict pages like `reclaim`, picking the highest scoring pages of
+    /// `policy` first
+    pub fn reclaim_with(
+        &self,
+        target_pages: usize,
+        policy: &mut impl ReclaimPolicy<A>,
+    ) -> Vec<(A, PageSize)> {
+        let mut evicted = Vec::new();
+        for lazy in [true, false] {
+            let max = target_pages - evicted.len();
+            for (region, pages) in self.victims(max, lazy, policy) {
+                let pages = if lazy {
+                    region.reclaim_lazy(&pages)
+                } else {
+                    region.evict_clean(&pages)
+                };
+                for &page in &pages {
+                    self.notify(|observer| observer.on_evict(region.range, page, region.align));
+                }
+                evicted.extend(pages.into_iter().map(|page| (page, region.align)));
+            }
+        }
+        evicted
+    }
+
+    /// Score the pages of every region that the lazy or the clean pass of
+    /// reclaim may drop, and pick the `max` highest scoring ones, ties going
+    /// to the lower address
+    /// Returns the picked pages of each region holding some, in address order
+    fn victims(
+        &self,
+        max: usize,
+        lazy: bool,
+        policy: &mut impl ReclaimPolicy<A>,
+    ) -> Vec<Victims<'_, F, A, R>> {
+        if max == 0 {
+            return Vec::new();
+        }
+        let regions: Vec<&MmapRegion<F, A, R>> = self.regions.values().map(|r| &**r).collect();
+        let mut scored = Vec::new();
+        for (index, region) in regions.iter().enumerate() {
+            let candidates = if lazy {
+                region.lazy_candidates()
+            } else {
+                region.clean_candidates()
+            };
+            if candidates.is_empty() {
+                continue;
+            }
+            let view = region.view();
+            for page in candidates {
+                scored.push((policy.score(&view, page), index, page));
+                policy.on_scan(&view, page);
+            }
+        }
+        // Candidates were gathered in address order, which the stable sort keeps
+        scored.sort_by_key(|&(score, ..)| Reverse(score));
+        scored.truncate(max);
+        let mut picked = vec![Vec::new(); regions.len()];
+        for (_, index, page) in scored {
+            picked[index].push(page);
+        }
+        regions
+            .into_iter()
+            .zip(picked)
+            .filter(|(_, pages)| !pages.is_empty())
+            .map(|(region, mut pages)| {
+                pages.sort_unstable();
+                (region, pages)
+            })
+            .collect()
+    }
+}
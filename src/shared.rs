@@ -0,0 +1,94 @@
+//! `VmaManager` behind a reader-writer lock for concurrent fault handling.
+
+use alloc::sync::Arc;
+use memory_addr::{AddrRange, MemoryAddr, VirtAddr};
+use spin::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use crate::{
+    AccessFlags, DefaultRawMutex, FaultResolution, MmapRegion, RawMutex, RemovedRegions, VmFile,
+    VmaManager, VmaResult,
+};
+
+/// VMA manager that can be shared between the fault path and mmap callers
+///
+/// Faults, lookups and advice only take the read lock, so faults on different
+/// regions run concurrently and rely on the per-region page locks; operations
+/// that change the set of regions take the write lock. The regions guard
+/// their page state with the raw lock `R`, as in `VmaManager`.
+pub struct SharedVmaManager<F: VmFile, A: MemoryAddr = VirtAddr, R: RawMutex = DefaultRawMutex> {
+    inner: RwLock<VmaManager<F, A, R>>,
+}
+
+impl<F: VmFile, A: MemoryAddr, R: RawMutex> Default for SharedVmaManager<F, A, R> {
+    fn default() -> Self {
+        VmaManager::default().into()
+    }
+}
+
+impl<F: VmFile, A: MemoryAddr, R: RawMutex> From<VmaManager<F, A, R>>
+    for SharedVmaManager<F, A, R>
+{
+    fn from(manager: VmaManager<F, A, R>) -> Self {
+        Self {
+            inner: RwLock::new(manager),
+        }
+    }
+}
+
+impl<F: VmFile> SharedVmaManager<F> {
+    /// Create a new shared VMA manager of virtual addresses
+    /// Managers of other address types are created with `default`
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<F: VmFile, A: MemoryAddr, R: RawMutex> SharedVmaManager<F, A, R> {
+    /// Lock the manager for reading
+    /// Regions borrowed from the guard stay valid until it is dropped
+    pub fn read(&self) -> RwLockReadGuard<'_, VmaManager<F, A, R>> {
+        self.inner.read()
+    }
+
+    /// Lock the manager for writing
+    pub fn write(&self) -> RwLockWriteGuard<'_, VmaManager<F, A, R>> {
+        self.inner.write()
+    }
+
+    /// Unwrap the inner manager
+    pub fn into_inner(self) -> VmaManager<F, A, R> {
+        self.inner.into_inner()
+    }
+
+    /// Resolve a page fault under the read lock, see `VmaManager::handle_fault`
+    pub fn handle_fault(&self, vaddr: A, access: AccessFlags) -> VmaResult<FaultResolution<A>, A> {
+        self.read().handle_fault(vaddr, access)
+    }
+
+    /// Find the region containing the given address under the read lock,
+    /// returning a handle that outlives the lock; see
+    /// `VmaManager::find_region_arc`
+    pub fn find_region_arc(&self, vaddr: A) -> Option<Arc<MmapRegion<F, A, R>>> {
+        self.read().find_region_arc(vaddr)
+    }
+
+    /// Add a new region under the write lock, see `VmaManager::add_region`
+    pub fn add_region(&self, region: MmapRegion<F, A, R>) -> VmaResult<(), A> {
+        self.write().add_region(region)
+    }
+
+    /// Remove overlapping regions under the write lock, see
+    /// `VmaManager::remove_overlapped`
+    pub fn remove_overlapped(
+        &self,
+        vaddr_range: AddrRange<A>,
+    ) -> VmaResult<RemovedRegions<F, A, R>, A> {
+        self.write().remove_overlapped(vaddr_range)
+    }
+
+    /// Remove all regions except pinned ones under the write lock, see
+    /// `VmaManager::clear`
+    pub fn clear(&self) -> RemovedRegions<F, A, R> {
+        self.write().clear()
+    }
+}
@@ -0,0 +1,79 @@
+//! Per-region memory accounting in the manner of `/proc/self/smaps`.
+
+use core::fmt;
+use memory_addr::{AddrRange, MemoryAddr};
+
+use crate::{MmapRegion, RawMutex, VmFile, VmaManager};
+
+/// Memory accounting of one region, obtained from `MmapRegion::smaps`
+/// All sizes are in bytes; evicted pages are reloaded from their file rather
+/// than swapped, so no swap usage is reported
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RegionStats {
+    /// Bytes mapped by the region, leaving out its holes
+    pub size: usize,
+    /// Bytes backed by populated pages
+    pub resident: usize,
+    /// Bytes of populated pages that differ from the backing store: dirty
+    /// pages of shared mappings and written pages of private file mappings
+    pub dirty: usize,
+    /// Bytes of populated pages not backed by the file: all pages of
+    /// anonymous regions and written pages of private file mappings
+    pub anonymous: usize,
+    /// Bytes of populated pages freed lazily with `Advice::Free`
+    pub lazy_free: usize,
+    /// Bytes of populated pages locked in memory
+    pub locked: usize,
+}
+
+impl fmt::Display for RegionStats {
+    /// Render the accounting like the fields of an smaps entry, in kB
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (field, bytes) in [
+            ("Size:", self.size),
+            ("Rss:", self.resident),
+            ("Dirty:", self.dirty),
+            ("Anonymous:", self.anonymous),
+            ("LazyFree:", self.lazy_free),
+            ("Locked:", self.locked),
+        ] {
+            writeln!(f, "{field:<16}{:>8} kB", bytes / 1024)?;
+        }
+        Ok(())
+    }
+}
+
+impl<F: VmFile, A: MemoryAddr, R: RawMutex> MmapRegion<F, A, R> {
+    /// Account for the memory of this region, reading its page state under
+    /// the populated lock so that the counts are consistent with each other
+    pub fn smaps(&self) -> RegionStats {
+        let populated = self.populated.lock();
+        let dirty = self.dirty.lock();
+        let private = self.private.lock();
+        let lazy_free = self.lazy_free.lock();
+        let resident = self.page_bytes(&populated);
+        let private_bytes = self.page_bytes(&private);
+        let mut modified = dirty.clone();
+        modified.union_with(&private);
+        RegionStats {
+            size: self.mapped_bytes(),
+            resident,
+            dirty: self.page_bytes(&modified),
+            anonymous: if self.is_anonymous() {
+                resident
+            } else {
+                private_bytes
+            },
+            lazy_free: self.page_bytes(&lazy_free),
+            locked: if self.locked { resident } else { 0 },
+        }
+    }
+}
+
+impl<F: VmFile, A: MemoryAddr, R: RawMutex> VmaManager<F, A, R> {
+    /// Account for the memory of every region like `MmapRegion::smaps`, in
+    /// address order
+    pub fn smaps(&self) -> impl Iterator<Item = (AddrRange<A>, RegionStats)> + '_ {
+        self.iter().map(|region| (region.range, region.smaps()))
+    }
+}
@@ -0,0 +1,193 @@
+//! Copy-on-write region list for read-mostly fault handling.
+
+use alloc::{sync::Arc, vec::Vec};
+use memory_addr::{AddrRange, MemoryAddr, VirtAddr};
+use spin::Mutex;
+
+use crate::{
+    AccessFlags, DefaultRawMutex, FaultResolution, MmapRegion, RawMutex, RemovedRegions, VmFile,
+    VmaError, VmaResult,
+};
+
+type RegionList<F, A, R> = Arc<Vec<Arc<MmapRegion<F, A, R>>>>;
+
+/// Immutable view of the regions of a `SnapshotVmaManager` at one point in time
+///
+/// Regions are shared with the manager, so faults resolved through an old
+/// snapshot update the same populated state as long as the region was not
+/// split or removed since.
+pub struct VmaSnapshot<F: VmFile, A: MemoryAddr = VirtAddr, R: RawMutex = DefaultRawMutex> {
+    regions: RegionList<F, A, R>,
+}
+
+impl<F: VmFile, A: MemoryAddr, R: RawMutex> Clone for VmaSnapshot<F, A, R> {
+    fn clone(&self) -> Self {
+        Self {
+            regions: self.regions.clone(),
+        }
+    }
+}
+
+impl<F: VmFile, A: MemoryAddr, R: RawMutex> VmaSnapshot<F, A, R> {
+    /// Number of regions in the snapshot
+    pub fn len(&self) -> usize {
+        self.regions.len()
+    }
+
+    /// Does the snapshot contain no regions?
+    pub fn is_empty(&self) -> bool {
+        self.regions.is_empty()
+    }
+
+    /// Iterate over the regions in ascending address order
+    pub fn iter(&self) -> impl Iterator<Item = &Arc<MmapRegion<F, A, R>>> {
+        self.regions.iter()
+    }
+
+    /// Find the region containing the given virtual address
+    pub fn find_region(&self, vaddr: A) -> Option<&Arc<MmapRegion<F, A, R>>> {
+        let index = self.regions.partition_point(|r| r.range.start <= vaddr);
+        self.regions[..index].last().filter(|r| r.contains(vaddr))
+    }
+
+    /// Resolve a page fault against the layout of this snapshot
+    /// See `VmaManager::handle_fault` for the errors
+    pub fn handle_fault(&self, vaddr: A, access: AccessFlags) -> VmaResult<FaultResolution<A>, A> {
+        self.find_region(vaddr)
+            .ok_or(VmaError::Unmapped(vaddr))?
+            .resolve_fault(vaddr, access)
+    }
+}
+
+/// VMA manager whose readers never wait for map or unmap operations
+///
+/// The region list is published behind an `Arc`: readers only hold a lock for
+/// as long as it takes to clone that `Arc`, and then search their snapshot
+/// without any manager lock. Mutations copy the list, which costs O(n) per
+/// operation, and publish the new list when done. The regions guard their
+/// page state with the raw lock `R`, as in `VmaManager`.
+pub struct SnapshotVmaManager<F: VmFile, A: MemoryAddr = VirtAddr, R: RawMutex = DefaultRawMutex> {
+    /// Currently published region list
+    current: Mutex<RegionList<F, A, R>>,
+    /// Serializes mutations so that none of them is lost
+    writer: Mutex<()>,
+}
+
+impl<F: VmFile, A: MemoryAddr, R: RawMutex> Default for SnapshotVmaManager<F, A, R> {
+    fn default() -> Self {
+        Self {
+            current: Mutex::new(Arc::new(Vec::new())),
+            writer: Mutex::new(()),
+        }
+    }
+}
+
+impl<F: VmFile> SnapshotVmaManager<F> {
+    /// Create a new manager of virtual addresses without any regions
+    /// Managers of other address types are created with `default`
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<F: VmFile, A: MemoryAddr, R: RawMutex> SnapshotVmaManager<F, A, R> {
+    /// Take a snapshot of the current region list
+    pub fn snapshot(&self) -> VmaSnapshot<F, A, R> {
+        VmaSnapshot {
+            regions: self.current.lock().clone(),
+        }
+    }
+
+    /// Find the region containing the given virtual address
+    pub fn find_region(&self, vaddr: A) -> Option<Arc<MmapRegion<F, A, R>>> {
+        self.snapshot().find_region(vaddr).cloned()
+    }
+
+    /// Resolve a page fault against the current layout
+    /// See `VmaManager::handle_fault` for the errors
+    pub fn handle_fault(&self, vaddr: A, access: AccessFlags) -> VmaResult<FaultResolution<A>, A> {
+        self.snapshot().handle_fault(vaddr, access)
+    }
+
+    /// Add a new memory-mapped region
+    /// Returns InvalidArgument for an empty region or Overlap if it overlaps an
+    /// existing one
+    pub fn add_region(&self, region: MmapRegion<F, A, R>) -> VmaResult<(), A> {
+        if region.range.is_empty() {
+            return Err(VmaError::InvalidArgument);
+        }
+        self.update(|regions| {
+            let index = regions.partition_point(|r| r.range.start <= region.range.start);
+            let overlaps_prev = index > 0 && regions[index - 1].overlaps(&region.range);
+            let overlaps_next = regions
+                .get(index)
+                .is_some_and(|r| r.overlaps(&region.range));
+            if overlaps_prev || overlaps_next {
+                return Err(VmaError::Overlap(region.range));
+            }
+            regions.insert(index, Arc::new(region));
+            Ok(())
+        })
+    }
+
+    /// Remove all regions that overlap with the given address range
+    /// Splits overlapping regions and retains non-overlapping parts; snapshots
+    /// taken before keep resolving faults for the old layout
+    /// Returns Pinned without changing anything if a pinned region overlaps
+    pub fn remove_overlapped(
+        &self,
+        vaddr_range: AddrRange<A>,
+    ) -> VmaResult<RemovedRegions<F, A, R>, A> {
+        self.update(|regions| {
+            let start = regions.partition_point(|r| r.range.end <= vaddr_range.start);
+            let end = regions.partition_point(|r| r.range.start < vaddr_range.end);
+            if regions[start..end].iter().any(|r| r.pinned) {
+                return Err(VmaError::Pinned);
+            }
+            let splits = regions[start..end]
+                .iter()
+                .map(|region| region.split_at_range(&vaddr_range))
+                .collect::<VmaResult<Vec<_>, A>>()?;
+
+            let mut removed = RemovedRegions::default();
+            let mut retained = Vec::new();
+            for (before, overlap, after) in splits {
+                retained.extend(before.map(Arc::new));
+                if let Some(overlap) = overlap {
+                    removed.push(overlap);
+                }
+                retained.extend(after.map(Arc::new));
+            }
+            regions.splice(start..end, retained);
+            Ok(removed)
+        })
+    }
+
+    /// Remove all regions except pinned ones
+    /// Returns copies of the removed regions, which snapshots may still share,
+    /// and their populated pages
+    pub fn clear(&self) -> RemovedRegions<F, A, R> {
+        let removed = self.update(|regions| {
+            let mut removed = RemovedRegions::default();
+            let (pinned, unpinned) = core::mem::take(regions).into_iter().partition(|r| r.pinned);
+            *regions = pinned;
+            for region in unpinned {
+                removed.push(MmapRegion::clone(&region));
+            }
+            Ok(removed)
+        });
+        removed.unwrap_or_default()
+    }
+
+    /// Apply `f` to a copy of the region list and publish it if `f` succeeds
+    fn update<T>(
+        &self,
+        f: impl FnOnce(&mut Vec<Arc<MmapRegion<F, A, R>>>) -> VmaResult<T, A>,
+    ) -> VmaResult<T, A> {
+        let _writer = self.writer.lock();
+        let mut regions = Vec::clone(&self.current.lock());
+        let result = f(&mut regions)?;
+        *self.current.lock() = Arc::new(regions);
+        Ok(result)
+    }
+}
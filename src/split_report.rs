@@ -0,0 +1,69 @@
+//! Reports of the populated pages each segment of a split inherited.
+
+use alloc::vec::Vec;
+use memory_addr::{AddrRange, MemoryAddr, VirtAddr};
+
+use crate::{DefaultRawMutex, MmapRegion, RawMutex, SplitSegments, VmFile, VmaResult};
+
+/// Range of a segment produced by a split and the populated pages it holds
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SegmentPages<A: MemoryAddr = VirtAddr> {
+    /// Address range of the segment
+    pub range: AddrRange<A>,
+    /// Populated pages of the segment, in ascending order
+    pub pages: Vec<A>,
+}
+
+/// Populated pages each segment of a split region inherited, so that
+/// structures keyed by region and page can be moved to the new segments
+/// Obtained from `MmapRegion::split_at_range_detailed` and
+/// `RemovedRegions::splits`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SplitReport<A: MemoryAddr = VirtAddr> {
+    /// Segment below the split range, if any
+    pub before: Option<SegmentPages<A>>,
+    /// Segment within the split range, if any
+    pub overlap: Option<SegmentPages<A>>,
+    /// Segment above the split range, if any
+    pub after: Option<SegmentPages<A>>,
+}
+
+impl<A: MemoryAddr> SplitReport<A> {
+    /// Report the populated pages of freshly split segments
+    /// The segments are not shared yet, so their pages are exactly the ones
+    /// taken from the region under its page-state locks
+    pub(crate) fn of<F: VmFile, R: RawMutex>(segments: &SplitSegments<F, A, R>) -> Self {
+        let pages = |segment: &Option<MmapRegion<F, A, R>>| {
+            segment.as_ref().map(|segment| SegmentPages {
+                range: segment.range,
+                pages: segment.populated_iter().collect(),
+            })
+        };
+        let (before, overlap, after) = segments;
+        Self {
+            before: pages(before),
+            overlap: pages(overlap),
+            after: pages(after),
+        }
+    }
+}
+
+/// Segments of a split along with the report of their populated pages
+pub type DetailedSplit<F, A = VirtAddr, R = DefaultRawMutex> =
+    (SplitSegments<F, A, R>, SplitReport<A>);
+
+impl<F: VmFile, A: MemoryAddr, R: RawMutex> MmapRegion<F, A, R> {
+    /// Split this region at the given range like `split_at_range`, also
+    /// reporting the populated pages every segment inherited
+    /// All segments are built under one acquisition of the page-state locks,
+    /// so a page populated concurrently ends up in neither the segments nor
+    /// the report
+    pub fn split_at_range_detailed(
+        &self,
+        range: &AddrRange<A>,
+    ) -> VmaResult<DetailedSplit<F, A, R>, A> {
+        let segments = self.split_at_range(range)?;
+        let report = SplitReport::of(&segments);
+        Ok((segments, report))
+    }
+}
@@ -0,0 +1,81 @@
+//! `VmFile` implementation for host files, available with the `std` feature.
+
+use std::{fs::File, io, sync::Arc};
+
+use axerrno::{LinuxError, LinuxResult};
+
+use crate::VmFile;
+
+/// Convert a host I/O error into the closest Linux error code
+/// OS errors keep their original errno; other errors are mapped by kind
+pub fn io_error_to_linux(err: &io::Error) -> LinuxError {
+    use io::ErrorKind::*;
+
+    if let Some(errno) = err
+        .raw_os_error()
+        .and_then(|code| LinuxError::try_from(code).ok())
+    {
+        return errno;
+    }
+    match err.kind() {
+        NotFound => LinuxError::ENOENT,
+        PermissionDenied => LinuxError::EACCES,
+        AlreadyExists => LinuxError::EEXIST,
+        WouldBlock => LinuxError::EAGAIN,
+        InvalidInput | InvalidData => LinuxError::EINVAL,
+        Interrupted => LinuxError::EINTR,
+        OutOfMemory => LinuxError::ENOMEM,
+        Unsupported => LinuxError::EOPNOTSUPP,
+        BrokenPipe => LinuxError::EPIPE,
+        TimedOut => LinuxError::ETIMEDOUT,
+        _ => LinuxError::EIO,
+    }
+}
+
+#[cfg(unix)]
+fn file_read_at(file: &File, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+    std::os::unix::fs::FileExt::read_at(file, buf, offset)
+}
+
+#[cfg(unix)]
+fn file_write_at(file: &File, buf: &[u8], offset: u64) -> io::Result<usize> {
+    std::os::unix::fs::FileExt::write_at(file, buf, offset)
+}
+
+#[cfg(not(unix))]
+fn file_read_at(mut file: &File, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+    use std::io::{Read, Seek, SeekFrom};
+    file.seek(SeekFrom::Start(offset))?;
+    file.read(buf)
+}
+
+#[cfg(not(unix))]
+fn file_write_at(mut file: &File, buf: &[u8], offset: u64) -> io::Result<usize> {
+    use std::io::{Seek, SeekFrom, Write};
+    file.seek(SeekFrom::Start(offset))?;
+    file.write(buf)
+}
+
+impl VmFile for Arc<File> {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> LinuxResult<usize> {
+        file_read_at(self, buf, offset).map_err(|err| io_error_to_linux(&err))
+    }
+
+    fn write_at(&self, buf: &[u8], offset: u64) -> LinuxResult<usize> {
+        file_write_at(self, buf, offset).map_err(|err| io_error_to_linux(&err))
+    }
+
+    fn len(&self) -> LinuxResult<u64> {
+        self.metadata()
+            .map(|metadata| metadata.len())
+            .map_err(|err| io_error_to_linux(&err))
+    }
+
+    fn file_id(&self) -> Option<u64> {
+        Some(Arc::as_ptr(self) as usize as u64)
+    }
+
+    fn same_file(&self, other: &Self) -> bool {
+        Arc::ptr_eq(self, other)
+    }
+}
@@ -0,0 +1,83 @@
+//! Log events of region operations, emitted only with the `log` feature.
+//! Every event names the operation followed by `key=value` fields under the
+//! `axvma` target; regions are written as `start-end` followed by their id
+//! and name if they have them, and address ranges as `start-end`
+
+/// Emit a debug event with the `log` feature, expanding to nothing without it
+macro_rules! vma_debug {
+    ($($arg:tt)*) => {{
+        #[cfg(feature = "log")]
+        log::debug!(target: "axvma", $($arg)*);
+    }};
+}
+
+/// Emit a trace event with the `log` feature, expanding to nothing without it
+macro_rules! vma_trace {
+    ($($arg:tt)*) => {{
+        #[cfg(feature = "log")]
+        log::trace!(target: "axvma", $($arg)*);
+    }};
+}
+
+#[cfg(feature = "log")]
+pub(crate) use log_fmt::{Addr, Label, Span, Spans};
+
+#[cfg(feature = "log")]
+mod log_fmt {
+    use core::fmt;
+    use memory_addr::{AddrRange, MemoryAddr};
+
+    use crate::{MmapRegion, RawMutex, VmFile};
+
+    /// Address written in hexadecimal
+    pub(crate) struct Addr<A>(pub(crate) A);
+
+    impl<A: MemoryAddr> fmt::Display for Addr<A> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{:#x}", self.0.into())
+        }
+    }
+
+    /// Address range written as `start-end`
+    pub(crate) struct Span<A: MemoryAddr>(pub(crate) AddrRange<A>);
+
+    impl<A: MemoryAddr> fmt::Display for Span<A> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{}-{}", Addr(self.0.start), Addr(self.0.end))
+        }
+    }
+
+    /// Address ranges written as `[start-end,...]`
+    pub(crate) struct Spans<I>(pub(crate) I);
+
+    impl<A: MemoryAddr, I: Iterator<Item = AddrRange<A>> + Clone> fmt::Display for Spans<I> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("[")?;
+            for (i, range) in self.0.clone().enumerate() {
+                if i > 0 {
+                    f.write_str(",")?;
+                }
+                write!(f, "{}", Span(range))?;
+            }
+            f.write_str("]")
+        }
+    }
+
+    /// Region written as `start-end` followed by its id and name
+    pub(crate) struct Label<'a, F: VmFile, A: MemoryAddr, R: RawMutex>(
+        pub(crate) &'a MmapRegion<F, A, R>,
+    );
+
+    impl<F: VmFile, A: MemoryAddr, R: RawMutex> fmt::Display for Label<'_, F, A, R> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{}", Span(self.0.range))?;
+            if let Some(id) = self.0.id {
+                write!(f, " id={}", id.0)?;
+            }
+            if let Some(name) = &self.0.name {
+                write!(f, " name={name}")?;
+            }
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,52 @@
+//! Plain snapshots of regions for comparisons and assertions.
+
+use alloc::vec::Vec;
+use memory_addr::{AddrRange, MemoryAddr, VirtAddr};
+use page_table_multiarch::PageSize;
+
+use crate::{MmapFlags, MmapProt, MmapRegion, RawMutex, RegionBacking, VmFile, VmaManager};
+
+/// Snapshot of the layout and populated pages of a region, obtained from
+/// `MmapRegion::view`
+/// Unlike the region itself it compares by value, so whole managers can be
+/// checked against an expected list with `VmaManager::views`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegionView<A: MemoryAddr = VirtAddr> {
+    /// Virtual address range of the region
+    pub range: AddrRange<A>,
+    /// File offset of the region start, or None if it is not file-backed
+    pub offset: Option<i64>,
+    /// Page alignment of the region
+    pub align: PageSize,
+    /// Access permissions of the region
+    pub prot: MmapProt,
+    /// Sharing and inheritance flags of the region
+    pub flags: MmapFlags,
+    /// Populated pages in ascending order
+    pub populated: Vec<A>,
+}
+
+impl<F: VmFile, A: MemoryAddr, R: RawMutex> MmapRegion<F, A, R> {
+    /// Take a snapshot of the region's layout and populated pages
+    pub fn view(&self) -> RegionView<A> {
+        let offset = match &self.backing {
+            RegionBacking::File { offset, .. } => Some(*offset),
+            _ => None,
+        };
+        RegionView {
+            range: self.range,
+            offset,
+            align: self.align,
+            prot: self.prot,
+            flags: self.flags,
+            populated: self.populated_iter().collect(),
+        }
+    }
+}
+
+impl<F: VmFile, A: MemoryAddr, R: RawMutex> VmaManager<F, A, R> {
+    /// Take a snapshot of every region in address order
+    pub fn views(&self) -> Vec<RegionView<A>> {
+        self.iter().map(MmapRegion::view).collect()
+    }
+}
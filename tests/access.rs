@@ -0,0 +1,103 @@
+mod common;
+
+use axerrno::LinuxError;
+use axvma::*;
+use common::{TestFile, range};
+use page_table_multiarch::PageSize;
+
+#[test]
+fn check_access_walks_every_covering_region() {
+    let file = TestFile::new(0x10000);
+    let mut manager = VmaManager::new();
+    let mut writable = MmapRegion::new(range(0x10000, 0x2000), file.clone(), 0, PageSize::Size4K);
+    writable.prot = MmapProt::READ | MmapProt::WRITE;
+    manager.add_region(writable).unwrap();
+    let mut read_only = MmapRegion::new(range(0x12000, 0x2000), file, 0x2000, PageSize::Size4K);
+    read_only.prot = MmapProt::READ;
+    manager.add_region(read_only).unwrap();
+
+    // Zero-length spans need no mapping
+    assert_eq!(
+        manager.check_access(0x50000.into(), 0, AccessFlags::WRITE),
+        Ok(())
+    );
+    // Across both regions
+    assert_eq!(
+        manager.check_access(0x10800.into(), 0x3000, AccessFlags::READ),
+        Ok(())
+    );
+    let err = manager
+        .check_access(0x10800.into(), 0x3000, AccessFlags::WRITE)
+        .unwrap_err();
+    assert_eq!(err, VmaError::AccessDenied);
+    assert_eq!(LinuxError::from(err), LinuxError::EACCES);
+    assert_eq!(
+        manager.check_access(0x10800.into(), 0x1800, AccessFlags::WRITE),
+        Ok(())
+    );
+    // Ending exactly at the end of a region
+    assert_eq!(
+        manager.check_access(0x13000.into(), 0x1000, AccessFlags::READ),
+        Ok(())
+    );
+}
+
+#[test]
+fn check_access_faults_on_holes() {
+    let mut manager: VmaManager<TestFile> = VmaManager::new();
+    manager
+        .add_region(MmapRegion::new_anonymous(
+            range(0x10000, 0x2000),
+            PageSize::Size4K,
+        ))
+        .unwrap();
+    manager
+        .add_region(MmapRegion::new_anonymous(
+            range(0x14000, 0x1000),
+            PageSize::Size4K,
+        ))
+        .unwrap();
+
+    let err = manager
+        .check_access(0x11000.into(), 0x1001, AccessFlags::READ)
+        .unwrap_err();
+    assert_eq!(err, VmaError::Unmapped(0x12000.into()));
+    assert_eq!(LinuxError::from(err), LinuxError::EFAULT);
+    // Across the hole into the next region
+    assert_eq!(
+        manager.check_access(0x11000.into(), 0x4000, AccessFlags::WRITE),
+        Err(VmaError::Unmapped(0x12000.into()))
+    );
+    assert_eq!(
+        manager.check_access(0x8000.into(), 0x9000, AccessFlags::READ),
+        Err(VmaError::Unmapped(0x8000.into()))
+    );
+    assert_eq!(
+        manager.check_access(usize::MAX.into(), 2, AccessFlags::READ),
+        Err(VmaError::Overflow)
+    );
+}
+
+#[test]
+fn check_access_touches_no_page() {
+    let file = TestFile::new(0x10000);
+    let mut manager = VmaManager::new();
+    manager
+        .add_region(MmapRegion::new(
+            range(0x10000, 0x4000),
+            file.clone(),
+            0,
+            PageSize::Size4K,
+        ))
+        .unwrap();
+    assert_eq!(
+        manager.check_access(0x10000.into(), 0x4000, AccessFlags::WRITE),
+        Ok(())
+    );
+    assert!(
+        manager
+            .iter()
+            .all(|region| region.populated_iter().next().is_none())
+    );
+    assert!(file.writes().is_empty());
+}
@@ -0,0 +1,138 @@
+mod common;
+
+use axerrno::LinuxError;
+use axvma::*;
+use common::{TestFile, range};
+use memory_addr::VirtAddrRange;
+use page_table_multiarch::PageSize;
+
+fn anon(start: usize, size: usize) -> MmapRegion<TestFile> {
+    MmapRegion::new_anonymous(range(start, size), PageSize::Size4K)
+}
+
+fn ranges(manager: &VmaManager<TestFile>) -> Vec<VirtAddrRange> {
+    manager.iter().map(|region| region.range).collect()
+}
+
+/// Manager with regions at 0x20000 and 0x40000
+fn manager() -> VmaManager<TestFile> {
+    let mut manager = VmaManager::new();
+    manager
+        .add_region(MmapRegion::new(
+            range(0x20000, 0x1000),
+            TestFile::new(0x1000),
+            0,
+            PageSize::Size4K,
+        ))
+        .unwrap();
+    manager.add_region(anon(0x40000, 0x1000)).unwrap();
+    manager
+}
+
+#[test]
+fn batch_interleaves_with_existing_regions() {
+    let mut manager = manager();
+    // Given out of order
+    manager
+        .add_regions(vec![
+            anon(0x50000, 0x2000),
+            MmapRegion::new(
+                range(0x10000, 0x1000),
+                TestFile::new(0x1000),
+                0,
+                PageSize::Size4K,
+            ),
+            anon(0x30000, 0x1000),
+        ])
+        .unwrap();
+    assert_eq!(
+        ranges(&manager),
+        vec![
+            range(0x10000, 0x1000),
+            range(0x20000, 0x1000),
+            range(0x30000, 0x1000),
+            range(0x40000, 0x1000),
+            range(0x50000, 0x2000),
+        ]
+    );
+    assert_eq!(manager.stats().total_mapped, 0x6000);
+    assert!(manager.iter().all(|region| region.id().is_some()));
+}
+
+#[test]
+fn batch_with_an_internal_overlap_adds_nothing() {
+    let mut manager = manager();
+    let before = ranges(&manager);
+    let err = manager
+        .add_regions(vec![
+            anon(0x60000, 0x2000),
+            anon(0x70000, 0x1000),
+            anon(0x61000, 0x2000),
+        ])
+        .unwrap_err();
+    assert_eq!(err, VmaError::Overlap(range(0x61000, 0x2000)));
+    assert_eq!(LinuxError::from(err), LinuxError::EEXIST);
+    assert_eq!(ranges(&manager), before);
+}
+
+#[test]
+fn batch_conflicting_with_the_manager_adds_nothing() {
+    let mut manager = manager();
+    let before = ranges(&manager);
+    let err = manager
+        .add_regions(vec![anon(0x60000, 0x1000), anon(0x3f000, 0x2000)])
+        .unwrap_err();
+    assert_eq!(err, VmaError::Overlap(range(0x3f000, 0x2000)));
+    assert_eq!(ranges(&manager), before);
+
+    let mut guarded = anon(0x42000, 0x1000);
+    guarded.guard_below = 0x2000;
+    assert_eq!(
+        manager.add_regions(vec![anon(0x80000, 0x1000), guarded]),
+        Err(VmaError::GuardGap(range(0x42000, 0x1000)))
+    );
+    assert_eq!(ranges(&manager), before);
+    assert_eq!(manager.stats().total_mapped, 0x2000);
+}
+
+#[test]
+fn batch_regions_in_holes_split_the_holed_region() {
+    let mut manager = VmaManager::new();
+    manager.set_hole_punching(Some(0));
+    manager.add_region(anon(0x10_0000, 0x8000)).unwrap();
+    manager.remove_overlapped(range(0x10_1000, 0x1000)).unwrap();
+    manager.remove_overlapped(range(0x10_5000, 0x2000)).unwrap();
+    assert_eq!(manager.len(), 1);
+
+    manager
+        .add_regions(vec![anon(0x10_1000, 0x1000), anon(0x10_5000, 0x1000)])
+        .unwrap();
+    assert_eq!(
+        ranges(&manager),
+        vec![
+            range(0x10_0000, 0x1000),
+            range(0x10_1000, 0x1000),
+            range(0x10_2000, 0x3000),
+            range(0x10_5000, 0x1000),
+            range(0x10_7000, 0x1000),
+        ]
+    );
+    assert_eq!(manager.stats().total_mapped, 0x7000);
+    assert!(manager.iter().all(|region| region.holes().next().is_none()));
+}
+
+#[test]
+fn limits_apply_to_the_batch_as_a_whole() {
+    let mut manager = VmaManager::with_limits(range(0, 0x100_0000), Some(0x3000));
+    let batch = || vec![anon(0x10000, 0x2000), anon(0x20000, 0x2000)];
+    assert!(matches!(
+        manager.add_regions(batch()),
+        Err(VmaError::AddressSpaceLimit(_))
+    ));
+    assert!(manager.is_empty());
+
+    let mut one = batch();
+    one.pop();
+    manager.add_regions(one).unwrap();
+    assert!(manager.add_region(anon(0x30000, 0x2000)).is_err());
+}
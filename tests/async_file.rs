@@ -0,0 +1,136 @@
+#![cfg(feature = "async-backend")]
+
+mod common;
+
+use axerrno::LinuxResult;
+use axvma::*;
+use common::{TestFile, range};
+use page_table_multiarch::PageSize;
+use std::{
+    future::Future,
+    pin::{Pin, pin},
+    task::{Context, Poll, RawWaker, RawWakerVTable, Waker},
+};
+
+fn noop_waker() -> Waker {
+    fn clone(_: *const ()) -> RawWaker {
+        RawWaker::new(std::ptr::null(), &VTABLE)
+    }
+    fn noop(_: *const ()) {}
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+    unsafe { Waker::from_raw(clone(std::ptr::null())) }
+}
+
+/// Poll `future` until it completes
+fn block_on<T>(future: impl Future<Output = T>) -> T {
+    let waker = noop_waker();
+    let mut cx = Context::from_waker(&waker);
+    let mut future = pin!(future);
+    loop {
+        if let Poll::Ready(value) = future.as_mut().poll(&mut cx) {
+            return value;
+        }
+    }
+}
+
+/// Future that is pending once before it completes
+struct YieldOnce(bool);
+
+impl Future for YieldOnce {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.0 {
+            return Poll::Ready(());
+        }
+        self.0 = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+/// File whose async reads yield before returning at most 100 bytes
+#[derive(Clone)]
+struct SlowFile(TestFile);
+
+impl VmFile for SlowFile {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> LinuxResult<usize> {
+        self.0.read_at(buf, offset)
+    }
+
+    fn len(&self) -> LinuxResult<u64> {
+        self.0.len()
+    }
+}
+
+impl AsyncVmFile for SlowFile {
+    async fn read_at(&self, buf: &mut [u8], offset: u64) -> LinuxResult<usize> {
+        YieldOnce(false).await;
+        let len = buf.len().min(100);
+        self.0.read_at(&mut buf[..len], offset)
+    }
+
+    async fn len(&self) -> LinuxResult<u64> {
+        self.0.len()
+    }
+}
+
+fn slow_region(file_len: usize) -> MmapRegion<SlowFile> {
+    let file = SlowFile(TestFile::new(file_len));
+    MmapRegion::new(range(0x10000, 0x2000), file, 0, PageSize::Size4K)
+}
+
+#[test]
+fn async_faults_load_what_sync_faults_do() {
+    let region = slow_region(0x1800);
+    let sync = MmapRegion::new(
+        range(0x10000, 0x2000),
+        TestFile::new(0x1800),
+        0,
+        PageSize::Size4K,
+    );
+    for vaddr in [0x10000, 0x11800] {
+        let page = block_on(region.get_buf_async(vaddr.into())).unwrap();
+        assert_eq!(page, sync.get_buf(vaddr.into()).unwrap());
+    }
+    assert!(region.is_populated(0x10000.into()));
+    assert_eq!(
+        block_on(region.get_buf_async(0x10000.into())),
+        Err(VmaError::AlreadyPopulated)
+    );
+
+    let short = slow_region(0x1000);
+    assert!(matches!(
+        block_on(short.get_buf_async(0x11000.into())),
+        Err(VmaError::BeyondEof { .. })
+    ));
+}
+
+#[test]
+fn dropping_the_future_mid_read_leaves_the_page_unpopulated() {
+    let region = slow_region(0x2000);
+    {
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut future = Box::pin(region.get_buf_async(0x11000.into()));
+        assert!(future.as_mut().poll(&mut cx).is_pending());
+        assert_eq!(region.page_state(0x11000.into()), PageState::Loading);
+    }
+    assert_eq!(region.page_state(0x11000.into()), PageState::NotPresent);
+    assert!(block_on(region.get_buf_async(0x11000.into())).is_ok());
+    assert!(region.is_populated(0x11000.into()));
+}
+
+#[test]
+fn handle_fault_async_resolves_through_the_manager() {
+    let mut manager = VmaManager::new();
+    manager.add_region(slow_region(0x2000)).unwrap();
+    let resolution =
+        block_on(manager.handle_fault_async(0x10000.into(), AccessFlags::READ)).unwrap();
+    assert!(matches!(resolution.data, FaultData::Loaded(_)));
+    assert_eq!(resolution.vaddr, 0x10000.into());
+    assert_eq!(
+        block_on(manager.handle_fault_async(0x30000.into(), AccessFlags::READ)).err(),
+        Some(VmaError::Unmapped(0x30000.into()))
+    );
+}
@@ -0,0 +1,177 @@
+mod common;
+
+use axerrno::{LinuxError, LinuxResult};
+use axvma::*;
+use common::{TestFile, range};
+use memory_addr::{VirtAddr, VirtAddrRange};
+use page_table_multiarch::PageSize;
+
+#[derive(Debug, PartialEq, Eq)]
+enum Call {
+    Map(usize, PageSize, MmapProt),
+    Unmap(usize, PageSize),
+    Protect(usize, MmapProt),
+    Flush(usize, usize),
+}
+
+/// Backend recording the page-table operations it is asked for
+#[derive(Default)]
+struct Recorder {
+    calls: Vec<Call>,
+    fail_maps: bool,
+}
+
+impl MapBackend for Recorder {
+    fn map_page(
+        &mut self,
+        vaddr: VirtAddr,
+        _data: FaultData,
+        size: PageSize,
+        prot: MmapProt,
+    ) -> LinuxResult<()> {
+        if self.fail_maps {
+            return Err(LinuxError::ENOMEM);
+        }
+        self.calls.push(Call::Map(vaddr.as_usize(), size, prot));
+        Ok(())
+    }
+
+    fn unmap_page(&mut self, vaddr: VirtAddr, size: PageSize) {
+        self.calls.push(Call::Unmap(vaddr.as_usize(), size));
+    }
+
+    fn protect_page(&mut self, vaddr: VirtAddr, _size: PageSize, prot: MmapProt) {
+        self.calls.push(Call::Protect(vaddr.as_usize(), prot));
+    }
+
+    fn flush_range(&mut self, range: VirtAddrRange) {
+        self.calls
+            .push(Call::Flush(range.start.as_usize(), range.end.as_usize()));
+    }
+}
+
+fn manager() -> VmaManager<TestFile> {
+    let mut manager = VmaManager::new();
+    manager
+        .add_region(MmapRegion::new(
+            range(0x10000, 0x3000),
+            TestFile::new(0x4000),
+            0,
+            PageSize::Size4K,
+        ))
+        .unwrap();
+    manager
+}
+
+#[test]
+fn fault_then_unmap_drives_the_backend_in_order() {
+    let mut manager = manager();
+    let mut backend = Recorder::default();
+    manager
+        .handle_fault_with(0x10010.into(), AccessFlags::READ, &mut backend)
+        .unwrap();
+    manager
+        .handle_fault_with(0x12010.into(), AccessFlags::WRITE, &mut backend)
+        .unwrap();
+    manager
+        .protect_with(range(0x12000, 0x1000), MmapProt::READ, &mut backend)
+        .unwrap();
+    let removed = manager
+        .munmap_with(0x10000.into(), 0x2000, &mut backend)
+        .unwrap();
+    assert_eq!(removed.len(), 1);
+
+    use Call::*;
+    assert_eq!(
+        backend.calls,
+        vec![
+            Map(0x10000, PageSize::Size4K, MmapProt::all()),
+            Map(0x12000, PageSize::Size4K, MmapProt::all()),
+            Protect(0x12000, MmapProt::READ),
+            Flush(0x12000, 0x13000),
+            Unmap(0x10000, PageSize::Size4K),
+            Flush(0x10000, 0x12000),
+        ]
+    );
+}
+
+#[test]
+fn failed_map_releases_the_page_for_a_retry() {
+    let manager = manager();
+    let mut backend = Recorder {
+        fail_maps: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        manager.handle_fault_with(0x11000.into(), AccessFlags::READ, &mut backend),
+        Err(VmaError::Backend(LinuxError::ENOMEM))
+    );
+    assert!(
+        !manager
+            .find_region(0x11000.into())
+            .unwrap()
+            .is_populated(0x11000.into())
+    );
+
+    backend.fail_maps = false;
+    manager
+        .handle_fault_with(0x11000.into(), AccessFlags::READ, &mut backend)
+        .unwrap();
+    assert_eq!(
+        backend.calls,
+        vec![Call::Map(0x11000, PageSize::Size4K, MmapProt::all())]
+    );
+}
+
+#[test]
+fn unmap_all_reports_every_populated_page() {
+    let mut manager = manager();
+    manager
+        .add_region(MmapRegion::new(
+            range(0x20000, 0x2000),
+            TestFile::new(0x4000),
+            0,
+            PageSize::Size4K,
+        ))
+        .unwrap();
+    let mut backend = Recorder::default();
+    for vaddr in [0x10000, 0x12000, 0x21000] {
+        manager
+            .handle_fault_with(vaddr.into(), AccessFlags::READ, &mut backend)
+            .unwrap();
+    }
+    backend.calls.clear();
+
+    let regions = manager.unmap_all(&mut backend);
+    assert_eq!(regions.len(), 2);
+    assert!(manager.is_empty());
+    assert!(manager.find_region(0x10000.into()).is_none());
+
+    use Call::*;
+    assert_eq!(
+        backend.calls,
+        vec![
+            Unmap(0x10000, PageSize::Size4K),
+            Unmap(0x12000, PageSize::Size4K),
+            Unmap(0x21000, PageSize::Size4K),
+            Flush(0x10000, 0x13000),
+            Flush(0x20000, 0x22000),
+        ]
+    );
+}
+
+#[test]
+fn clear_hands_back_regions_and_their_pages() {
+    let mut manager = manager();
+    manager
+        .handle_fault(0x11000.into(), AccessFlags::READ)
+        .unwrap();
+    let removed = manager.clear();
+    assert_eq!(removed.regions.len(), 1);
+    assert_eq!(removed.regions[0].range, range(0x10000, 0x3000));
+    assert_eq!(
+        removed.pages,
+        vec![(VirtAddr::from(0x11000), PageSize::Size4K)]
+    );
+    assert!(manager.is_empty());
+}
@@ -0,0 +1,84 @@
+mod common;
+
+use axvma::*;
+use common::{TestFile, range};
+use page_table_multiarch::PageSize;
+
+fn build_err(builder: MmapRegionBuilder<TestFile>) -> VmaError {
+    builder.build().map(|_| ()).unwrap_err()
+}
+
+#[test]
+fn builds_a_named_huge_read_only_mapping_in_one_expression() {
+    let region = MmapRegionBuilder::new(range(0x20_0000, 0x20_0000))
+        .file(TestFile::new(0x40_0000), 0x20_0000)
+        .align(PageSize::Size2M)
+        .prot(MmapProt::READ)
+        .name("libc.so")
+        .build()
+        .unwrap();
+    assert_eq!(region.range, range(0x20_0000, 0x20_0000));
+    assert_eq!(region.align, PageSize::Size2M);
+    assert_eq!(region.prot, MmapProt::READ);
+    assert_eq!(region.flags, MmapFlags::PRIVATE);
+    assert_eq!(region.name.as_deref(), Some("libc.so"));
+    assert!(!region.is_anonymous());
+    assert_eq!(region.file_offset_of(0x20_0000.into()), Ok(0x20_0000));
+}
+
+#[test]
+fn build_rejects_each_invalid_configuration() {
+    let small = || MmapRegionBuilder::new(range(0x10000, 0x1000));
+    assert_eq!(
+        build_err(MmapRegionBuilder::new(range(0x10000, 0))),
+        VmaError::InvalidArgument
+    );
+    assert_eq!(
+        build_err(MmapRegionBuilder::new(range(0x10800, 0x1000))),
+        VmaError::Unaligned
+    );
+    assert_eq!(
+        build_err(small().align(PageSize::Size2M)),
+        VmaError::Unaligned
+    );
+    assert_eq!(
+        build_err(small().file(TestFile::new(0x1000), 12)),
+        VmaError::Unaligned
+    );
+    assert_eq!(
+        build_err(small().file(TestFile::new(0x1000), 0).grows_down()),
+        VmaError::InvalidArgument
+    );
+    assert_eq!(
+        build_err(small().flags(MmapFlags::SHARED | MmapFlags::PRIVATE)),
+        VmaError::InvalidArgument
+    );
+    assert_eq!(
+        build_err(small().flags(MmapFlags::empty())),
+        VmaError::InvalidArgument
+    );
+}
+
+#[test]
+fn builder_defaults_match_new_anonymous() {
+    let built: MmapRegion<TestFile> = MmapRegionBuilder::new(range(0x10000, 0x1000))
+        .build()
+        .unwrap();
+    let plain: MmapRegion<TestFile> =
+        MmapRegion::new_anonymous(range(0x10000, 0x1000), PageSize::Size4K);
+    assert!(built.is_anonymous());
+    assert_eq!(built.align, plain.align);
+    assert_eq!(built.prot, plain.prot);
+    assert_eq!(built.flags, plain.flags);
+    assert!(!built.pinned);
+
+    let stack: MmapRegion<TestFile> = MmapRegionBuilder::new(range(0x10000, 0x1000))
+        .file(TestFile::new(0x1000), 0)
+        .anonymous()
+        .grows_down()
+        .pinned()
+        .build()
+        .unwrap();
+    assert!(stack.is_anonymous() && stack.pinned);
+    assert!(stack.flags.contains(MmapFlags::GROWSDOWN));
+}
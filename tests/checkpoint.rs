@@ -0,0 +1,117 @@
+mod common;
+
+use axerrno::LinuxError;
+use axvma::*;
+use common::{TestFile, range};
+use page_table_multiarch::PageSize;
+
+/// Manager with a file-backed, an anonymous and a reserved region
+fn three_regions(file: &TestFile) -> VmaManager<TestFile> {
+    let mut manager = VmaManager::new();
+    let mut data = MmapRegion::new(
+        range(0x10000, 0x4000),
+        file.clone(),
+        0x1000,
+        PageSize::Size4K,
+    );
+    data.name = Some("data".into());
+    data.flags = MmapFlags::SHARED;
+    manager.add_region(data).unwrap();
+    manager
+        .add_region(MmapRegion::new_anonymous(
+            range(0x20000, 0x2000),
+            PageSize::Size4K,
+        ))
+        .unwrap();
+    manager
+        .add_region(MmapRegion::new_reserved(
+            range(0x40000, 0x10000),
+            PageSize::Size4K,
+        ))
+        .unwrap();
+    manager
+}
+
+#[test]
+fn checkpoint_round_trips_the_layout_and_populated_pages() {
+    let file = TestFile::new(0x10000);
+    let manager = three_regions(&file);
+    for vaddr in [0x11000, 0x13000, 0x21000] {
+        manager
+            .handle_fault(vaddr.into(), AccessFlags::READ)
+            .unwrap();
+    }
+    manager
+        .find_region(0x13000.into())
+        .unwrap()
+        .mark_dirty(0x13000.into());
+
+    let descriptors = manager.checkpoint();
+    assert_eq!(descriptors.len(), 3);
+    assert_eq!(descriptors[0].start, 0x10000);
+    assert_eq!(
+        descriptors[0].backing,
+        BackingDescriptor::File { offset: 0x1000 }
+    );
+    assert_eq!(descriptors[0].name.as_deref(), Some("data"));
+    assert_eq!(descriptors[0].populated, vec![1, 3]);
+    assert_eq!(descriptors[0].dirty, vec![3]);
+    assert_eq!(descriptors[1].backing, BackingDescriptor::Anonymous);
+    assert_eq!(descriptors[1].populated, vec![1]);
+    assert_eq!(descriptors[2].backing, BackingDescriptor::Reserved);
+
+    let mut opened = 0;
+    let restored = VmaManager::restore(&descriptors, |descriptor| {
+        assert_eq!(descriptor.start, 0x10000);
+        opened += 1;
+        Ok(file.clone())
+    })
+    .unwrap();
+    assert_eq!(opened, 1);
+    assert!(manager.same_layout(&restored));
+    assert_eq!(restored.checkpoint(), descriptors);
+    let data = restored.find_region(0x10000.into()).unwrap();
+    assert_eq!(
+        data.populated_iter().collect::<Vec<_>>(),
+        vec![0x11000.into(), 0x13000.into()]
+    );
+    assert!(data.is_dirty(0x13000.into()));
+    assert!(restored.find_region(0x40000.into()).unwrap().is_reserved());
+}
+
+#[test]
+fn layouts_differ_after_a_change() {
+    let file = TestFile::new(0x10000);
+    let manager = three_regions(&file);
+    let other = three_regions(&file);
+    assert!(manager.same_layout(&other));
+    other
+        .handle_fault(0x20000.into(), AccessFlags::READ)
+        .unwrap();
+    assert!(!manager.same_layout(&other));
+}
+
+#[test]
+fn restore_rejects_malformed_descriptors() {
+    let file = TestFile::new(0x10000);
+    let descriptors = three_regions(&file).checkpoint();
+
+    let mut bad = descriptors.clone();
+    bad[1].populated.push(2);
+    assert_eq!(
+        VmaManager::<TestFile>::restore(&bad, |_| Ok(file.clone())).err(),
+        Some(VmaError::InvalidArgument)
+    );
+    assert_eq!(
+        VmaManager::<TestFile>::restore(&descriptors, |_| Err(LinuxError::ENOENT)).err(),
+        Some(VmaError::Backend(LinuxError::ENOENT))
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn descriptors_are_serializable() {
+    fn serializable<T: serde::Serialize + serde::de::DeserializeOwned>() {}
+    serializable::<RegionDescriptor>();
+    serializable::<BackingDescriptor>();
+}
@@ -0,0 +1,156 @@
+mod common;
+
+use axerrno::LinuxError;
+use axvma::*;
+use common::{TestFile, range};
+use page_table_multiarch::PageSize;
+use std::{sync::Arc, thread};
+
+fn anon(start: usize, size: usize) -> MmapRegion<TestFile> {
+    let mut region = MmapRegion::new_anonymous(range(start, size), PageSize::Size4K);
+    region.prot = MmapProt::READ | MmapProt::WRITE;
+    region
+}
+
+fn strict(limit: usize) -> (VmaManager<TestFile>, Arc<CommitLimit>) {
+    let pool = Arc::new(CommitLimit::new(limit));
+    let mut manager = VmaManager::new();
+    manager.set_commit_policy(CommitPolicy::Strict(pool.clone()));
+    (manager, pool)
+}
+
+#[test]
+fn strict_accounting_charges_writable_private_mappings() {
+    let pool = Arc::new(CommitLimit::new(0x8000));
+    let mut manager = VmaManager::new();
+    manager.add_region(anon(0x10000, 0x2000)).unwrap();
+    assert_eq!(manager.commit_charge(), 0);
+    // Switching policy charges the existing mappings
+    manager.set_commit_policy(CommitPolicy::Strict(pool.clone()));
+    assert_eq!(pool.charged(), 0x2000);
+    manager.add_region(anon(0x20000, 0x6000)).unwrap();
+    assert_eq!(manager.commit_charge(), 0x8000);
+
+    let err = manager.add_region(anon(0x40000, 0x1000)).unwrap_err();
+    assert_eq!(err, VmaError::CommitLimit(0x8000));
+    assert_eq!(LinuxError::from(err), LinuxError::ENOMEM);
+    assert!(manager.find_region(0x40000.into()).is_none());
+
+    // Read-only and NORESERVE mappings are free
+    let mut read_only = anon(0x40000, 0x1000);
+    read_only.prot = MmapProt::READ;
+    manager.add_region(read_only).unwrap();
+    let mut noreserve = anon(0x50000, 0x1000);
+    noreserve.flags |= MmapFlags::NORESERVE;
+    manager.add_region(noreserve).unwrap();
+    assert_eq!(pool.charged(), 0x8000);
+
+    // Making a mapping writable charges it
+    assert_eq!(
+        manager.protect(range(0x40000, 0x1000), MmapProt::READ | MmapProt::WRITE),
+        Err(VmaError::CommitLimit(0x8000))
+    );
+    assert_eq!(
+        manager.find_region(0x40000.into()).unwrap().prot,
+        MmapProt::READ
+    );
+}
+
+#[test]
+fn partial_unmaps_free_exactly_their_share() {
+    let (mut manager, pool) = strict(0x8000);
+    manager.add_region(anon(0x10000, 0x2000)).unwrap();
+    manager.add_region(anon(0x20000, 0x6000)).unwrap();
+    manager.munmap(0x22000.into(), 0x3000).unwrap();
+    assert_eq!(pool.charged(), 0x5000);
+    assert!(manager.add_region(anon(0x60000, 0x4000)).is_err());
+    manager.add_region(anon(0x60000, 0x3000)).unwrap();
+    assert_eq!(pool.charged(), 0x8000);
+
+    assert_eq!(
+        manager
+            .remap(0x60000.into(), 0x3000, 0x4000, RemapFlags::MAYMOVE)
+            .err(),
+        Some(VmaError::CommitLimit(0x8000))
+    );
+    manager
+        .protect(range(0x60000, 0x1000), MmapProt::READ)
+        .unwrap();
+    assert_eq!(pool.charged(), 0x7000);
+
+    manager.clear();
+    assert_eq!(pool.charged(), 0);
+    assert_eq!(manager.commit_charge(), 0);
+}
+
+#[test]
+fn forks_and_clones_charge_again_until_dropped() {
+    let (mut manager, pool) = strict(0x8000);
+    manager.add_region(anon(0x10000, 0x3000)).unwrap();
+    let child = manager.fork();
+    assert_eq!(child.commit_charge(), 0x3000);
+    assert_eq!(pool.charged(), 0x6000);
+    assert!(manager.add_region(anon(0x20000, 0x3000)).is_err());
+    drop(child);
+    assert_eq!(pool.charged(), 0x3000);
+
+    let clone = manager.clone();
+    assert_eq!(pool.charged(), 0x6000);
+    drop(clone);
+    assert_eq!(pool.charged(), 0x3000);
+}
+
+#[test]
+fn overcommit_counts_resident_pages() {
+    let mut manager = VmaManager::new();
+    manager.add_region(anon(0x10000, 0x4000)).unwrap();
+    assert_eq!(manager.commit_charge(), 0);
+    manager
+        .handle_fault(0x10000.into(), AccessFlags::WRITE)
+        .unwrap();
+    assert_eq!(manager.commit_charge(), 0x1000);
+}
+
+#[test]
+fn replacing_a_mapping_reuses_its_charge() {
+    let (mut manager, pool) = strict(0x2000);
+    manager.add_region(anon(0x10000, 0x2000)).unwrap();
+    manager.add_region_replace(anon(0x10000, 0x2000)).unwrap();
+    assert_eq!(pool.charged(), 0x2000);
+
+    assert_eq!(
+        manager.add_region_replace(anon(0x11000, 0x2000)).err(),
+        Some(VmaError::CommitLimit(0x2000))
+    );
+    assert_eq!(pool.charged(), 0x2000);
+    assert_eq!(manager.len(), 1);
+}
+
+#[test]
+fn concurrent_managers_never_overshoot_a_shared_limit() {
+    for _ in 0..50 {
+        let (base, pool) = strict(0x10000);
+        let workers: Vec<_> = (0..4)
+            .map(|i| {
+                let mut manager = base.fork();
+                thread::spawn(move || {
+                    let added = (0..8)
+                        .filter(|j| {
+                            let start = 0x10_0000 * (i + 1) + j * 0x1_0000;
+                            manager.add_region(anon(start, 0x1000)).is_ok()
+                        })
+                        .count();
+                    (manager, added)
+                })
+            })
+            .collect();
+        let results: Vec<_> = workers
+            .into_iter()
+            .map(|worker| worker.join().unwrap())
+            .collect();
+        assert_eq!(results.iter().map(|(_, added)| added).sum::<usize>(), 16);
+        assert_eq!(pool.charged(), 0x10000);
+        drop(results);
+        assert_eq!(pool.charged(), 0);
+    }
+}
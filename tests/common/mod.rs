@@ -0,0 +1,85 @@
+//! Fixtures shared by the integration tests.
+
+#![allow(dead_code)]
+
+use axerrno::{LinuxError, LinuxResult};
+use axvma::VmFile;
+use memory_addr::{VirtAddr, VirtAddrRange};
+use std::sync::{Arc, Mutex};
+
+/// Byte stored at `offset` of a `TestFile::new` file
+pub fn pattern(offset: usize) -> u8 {
+    (offset % 251) as u8
+}
+
+/// Offset and data of a write made to a `TestFile`
+pub type Write = (u64, Vec<u8>);
+
+/// Writable in-memory file that records every write made to it
+/// Clones share the same contents and write log
+#[derive(Clone)]
+pub struct TestFile {
+    data: Arc<Mutex<Vec<u8>>>,
+    writes: Arc<Mutex<Vec<Write>>>,
+}
+
+impl TestFile {
+    /// File of `len` bytes filled with `pattern`
+    pub fn new(len: usize) -> Self {
+        Self::from_bytes((0..len).map(pattern).collect())
+    }
+
+    /// File holding `data`
+    pub fn from_bytes(data: Vec<u8>) -> Self {
+        Self {
+            data: Arc::new(Mutex::new(data)),
+            writes: Default::default(),
+        }
+    }
+
+    /// Current contents of the file
+    pub fn contents(&self) -> Vec<u8> {
+        self.data.lock().unwrap().clone()
+    }
+
+    /// Offsets and data of the writes made so far, in order
+    pub fn writes(&self) -> Vec<Write> {
+        self.writes.lock().unwrap().clone()
+    }
+}
+
+impl VmFile for TestFile {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> LinuxResult<usize> {
+        let data = self.data.lock().unwrap();
+        let Some(remaining) = data.get(offset as usize..) else {
+            return Ok(0);
+        };
+        let read = buf.len().min(remaining.len());
+        buf[..read].copy_from_slice(&remaining[..read]);
+        Ok(read)
+    }
+
+    fn write_at(&self, buf: &[u8], offset: u64) -> LinuxResult<usize> {
+        let mut data = self.data.lock().unwrap();
+        let end = offset as usize + buf.len();
+        if end > data.len() {
+            return Err(LinuxError::EINVAL);
+        }
+        data[offset as usize..end].copy_from_slice(buf);
+        self.writes.lock().unwrap().push((offset, buf.to_vec()));
+        Ok(buf.len())
+    }
+
+    fn len(&self) -> LinuxResult<u64> {
+        Ok(self.data.lock().unwrap().len() as u64)
+    }
+
+    fn file_id(&self) -> Option<u64> {
+        Some(Arc::as_ptr(&self.data) as u64)
+    }
+}
+
+/// Range of `size` bytes starting at `start`
+pub fn range(start: usize, size: usize) -> VirtAddrRange {
+    VirtAddrRange::from_start_size(VirtAddr::from(start), size)
+}
@@ -0,0 +1,107 @@
+mod common;
+
+use axvma::*;
+use common::{TestFile, range};
+use memory_addr::VirtAddr;
+
+/// Manager holding a region of four pages registered for external handling
+fn manager() -> VmaManager<TestFile> {
+    let region = MmapRegionBuilder::new(range(0x10000, 0x4000))
+        .file(TestFile::new(0x10000), 0)
+        .external_handler()
+        .build()
+        .unwrap();
+    let mut manager = VmaManager::new();
+    manager.add_region(region).unwrap();
+    manager
+}
+
+#[test]
+fn parked_fault_is_resolved_by_the_handler() {
+    let manager = manager();
+    let resolution = manager
+        .handle_fault(0x10010.into(), AccessFlags::READ)
+        .unwrap();
+    assert_eq!(resolution.data, FaultData::Deferred);
+    assert_eq!(resolution.vaddr, 0x10000.into());
+    // Faulting again parks the page again
+    let resolution = manager
+        .handle_fault(0x10020.into(), AccessFlags::READ)
+        .unwrap();
+    assert_eq!(resolution.data, FaultData::Deferred);
+    assert_eq!(manager.deferred_faults(), vec![VirtAddr::from(0x10000)]);
+
+    // No other path loads the page from the file meanwhile
+    let region = manager.find_region(0x10000.into()).unwrap();
+    assert_eq!(region.get_buf(0x10000.into()), Err(VmaError::Busy));
+    assert_eq!(
+        manager.resolve_fault(0x10000.into(), vec![7; 0x800]),
+        Err(VmaError::InvalidArgument)
+    );
+
+    let resolution = manager
+        .resolve_fault(0x10100.into(), vec![7; 0x1000])
+        .unwrap();
+    assert_eq!(resolution.vaddr, 0x10000.into());
+    assert_eq!(resolution.data, FaultData::Loaded(vec![7; 0x1000]));
+    assert!(manager.deferred_faults().is_empty());
+    assert!(region.is_populated(0x10000.into()));
+    assert!(region.is_private(0x10000.into()));
+
+    // The retried fault finds the page populated
+    assert_eq!(
+        manager.handle_fault(0x10000.into(), AccessFlags::READ),
+        Err(VmaError::AlreadyPopulated)
+    );
+    assert_eq!(
+        manager.resolve_fault(0x10000.into(), vec![7; 0x1000]),
+        Err(VmaError::AlreadyPopulated)
+    );
+    assert_eq!(
+        manager.resolve_fault(0x11000.into(), vec![7; 0x1000]),
+        Err(VmaError::InvalidArgument)
+    );
+}
+
+#[test]
+fn cancelled_fault_parks_anew() {
+    let manager = manager();
+    manager
+        .handle_fault(0x11000.into(), AccessFlags::READ)
+        .unwrap();
+    assert!(manager.cancel_fault(0x11000.into()));
+    assert!(!manager.cancel_fault(0x11000.into()));
+    assert_eq!(
+        manager.resolve_fault(0x11000.into(), vec![7; 0x1000]),
+        Err(VmaError::InvalidArgument)
+    );
+
+    let resolution = manager
+        .handle_fault(0x11000.into(), AccessFlags::READ)
+        .unwrap();
+    assert_eq!(resolution.data, FaultData::Deferred);
+    assert_eq!(manager.deferred_faults(), vec![VirtAddr::from(0x11000)]);
+}
+
+#[test]
+fn resolving_an_unmapped_page_fails() {
+    let mut manager = manager();
+    for vaddr in [0x12000, 0x13000] {
+        manager
+            .handle_fault(vaddr.into(), AccessFlags::READ)
+            .unwrap();
+    }
+    manager.remove_overlapped(range(0x12000, 0x1000)).unwrap();
+    assert_eq!(
+        manager.resolve_fault(0x12000.into(), vec![7; 0x1000]),
+        Err(VmaError::Unmapped(0x12000.into()))
+    );
+    assert!(!manager.cancel_fault(0x12000.into()));
+
+    // The part left by the split keeps its parked page
+    assert_eq!(manager.deferred_faults(), vec![VirtAddr::from(0x13000)]);
+    manager
+        .resolve_fault(0x13000.into(), vec![1; 0x1000])
+        .unwrap();
+    assert!(manager.deferred_faults().is_empty());
+}
@@ -0,0 +1,81 @@
+mod common;
+
+use axvma::*;
+use common::{TestFile, pattern, range};
+use memory_addr::VirtAddr;
+use page_table_multiarch::PageSize;
+
+fn huge_manager(file: &TestFile) -> VmaManager<TestFile> {
+    let mut manager = VmaManager::new();
+    manager
+        .add_region(MmapRegion::new(
+            range(0x20_0000, 0x40_0000),
+            file.clone(),
+            0,
+            PageSize::Size2M,
+        ))
+        .unwrap();
+    manager
+}
+
+#[test]
+fn demote_expands_each_huge_page() {
+    let file = TestFile::new(0x80_0000);
+    let mut manager = huge_manager(&file);
+    manager
+        .handle_fault(0x40_0000.into(), AccessFlags::READ)
+        .unwrap();
+    let region = manager.find_region(0x40_0000.into()).unwrap();
+    assert!(region.mark_dirty(0x40_0000.into()));
+
+    let demoted = manager
+        .demote_range(range(0x30_0000, 0x1000), PageSize::Size4K)
+        .unwrap();
+    assert_eq!(demoted, vec![(VirtAddr::from(0x40_0000), PageSize::Size2M)]);
+    let region = manager.find_region(0x20_0000.into()).unwrap();
+    assert_eq!(region.align, PageSize::Size4K);
+    assert_eq!(region.populated_iter().count(), 512);
+    assert_eq!(
+        region.populated_iter().last(),
+        Some(VirtAddr::from(0x5f_f000))
+    );
+    assert_eq!(region.dirty_in(&region.range).len(), 512);
+    assert!(region.is_populated(0x40_1000.into()));
+    assert!(!region.is_populated(0x20_0000.into()));
+}
+
+#[test]
+fn faults_after_demotion_use_the_small_page_size() {
+    let file = TestFile::new(0x80_0000);
+    let mut manager = huge_manager(&file);
+    manager
+        .demote_range(range(0x20_0000, 0x40_0000), PageSize::Size4K)
+        .unwrap();
+    let region = manager.find_region(0x20_0000.into()).unwrap();
+    let page = region.get_buf(0x20_1010.into()).unwrap();
+    assert_eq!(page.len(), 0x1000);
+    assert_eq!(page[0], pattern(0x1000));
+    assert_eq!(region.file_offset_of(0x20_1000.into()), Ok(0x1000));
+    assert!(!region.is_populated(0x20_0000.into()));
+}
+
+#[test]
+fn demote_refuses_to_promote() {
+    let file = TestFile::new(0x80_0000);
+    let mut manager = huge_manager(&file);
+    assert_eq!(
+        manager.demote_range(range(0x20_0000, 0x1000), PageSize::Size1G),
+        Err(VmaError::InvalidArgument)
+    );
+    assert_eq!(
+        manager.find_region(0x20_0000.into()).unwrap().align,
+        PageSize::Size2M
+    );
+
+    let mut small = MmapRegion::new(range(0x20_0000, 0x20_0000), file, 0, PageSize::Size4K);
+    assert_eq!(
+        small.demote(PageSize::Size2M),
+        Err(VmaError::InvalidArgument)
+    );
+    assert_eq!(small.demote(PageSize::Size4K), Ok(Vec::new()));
+}
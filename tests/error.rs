@@ -0,0 +1,129 @@
+mod common;
+
+use axerrno::LinuxError;
+use axvma::*;
+use common::{TestFile, range};
+use memory_addr::VirtAddr;
+use page_table_multiarch::PageSize;
+
+#[test]
+fn region_errors_name_the_precise_cause() {
+    let region = MmapRegion::new(
+        range(0x10000, 0x4000),
+        TestFile::new(0x2000),
+        -0x1000,
+        PageSize::Size4K,
+    );
+    assert_eq!(
+        region.get_buf(0x10000.into()).err(),
+        Some(VmaError::OffsetOutOfFile {
+            offset: -0x1000,
+            file_len: 0x2000,
+        })
+    );
+    assert_eq!(
+        region.get_buf(0x13000.into()).err(),
+        Some(VmaError::BeyondEof {
+            offset: 0x2000,
+            file_len: 0x2000,
+        })
+    );
+    region.get_buf(0x11000.into()).unwrap();
+    assert_eq!(
+        region.get_buf(0x11000.into()).err(),
+        Some(VmaError::AlreadyPopulated)
+    );
+    assert_eq!(
+        region.file_offset_of(0x20000.into()),
+        Err(VmaError::OutOfRange {
+            vaddr: 0x20000.into(),
+            range: range(0x10000, 0x4000),
+        })
+    );
+    assert_eq!(
+        region.split_at_range(&range(0x10800, 0x1000)).err(),
+        Some(VmaError::Unaligned)
+    );
+}
+
+#[test]
+fn manager_errors_name_the_precise_cause() {
+    let mut manager: VmaManager<TestFile> = VmaManager::new();
+    manager
+        .add_region(MmapRegion::new_anonymous(
+            range(0x10000, 0x4000),
+            PageSize::Size4K,
+        ))
+        .unwrap();
+    assert_eq!(
+        manager.add_region(MmapRegion::new_anonymous(
+            range(0x12000, 0x4000),
+            PageSize::Size4K,
+        )),
+        Err(VmaError::Overlap(range(0x12000, 0x4000)))
+    );
+    assert_eq!(
+        manager
+            .handle_fault(0x30000.into(), AccessFlags::READ)
+            .err(),
+        Some(VmaError::Unmapped(0x30000.into()))
+    );
+    assert_eq!(
+        manager.protect(range(0x10000, 0x8000), MmapProt::READ),
+        Err(VmaError::Hole(range(0x10000, 0x8000)))
+    );
+}
+
+#[test]
+fn errors_keep_their_syscall_codes() {
+    type Error = VmaError<VirtAddr>;
+    for (err, code) in [
+        (Error::AlreadyPopulated, LinuxError::EFAULT),
+        (
+            Error::OffsetOutOfFile {
+                offset: -1,
+                file_len: 0,
+            },
+            LinuxError::EINVAL,
+        ),
+        (Error::Unaligned, LinuxError::EINVAL),
+        (Error::Unmapped(0x1000.into()), LinuxError::EFAULT),
+        (Error::Hole(range(0, 0x1000)), LinuxError::ENOMEM),
+        (Error::Overlap(range(0, 0x1000)), LinuxError::EEXIST),
+        (Error::Overflow, LinuxError::EOVERFLOW),
+        (Error::Backend(LinuxError::EIO), LinuxError::EIO),
+    ] {
+        assert_eq!(LinuxError::from(err), code);
+    }
+}
+
+#[test]
+fn errors_display_their_details() {
+    type Error = VmaError<VirtAddr>;
+    let display = |err: Error| err.to_string();
+    assert_eq!(
+        display(Error::OffsetOutOfFile {
+            offset: 0x2000,
+            file_len: 0x2000,
+        }),
+        "file offset 0x2000 outside file of 0x2000 bytes"
+    );
+    assert_eq!(
+        display(Error::OffsetOutOfFile {
+            offset: -0x1000,
+            file_len: 0x2000,
+        }),
+        "file offset -0x1000 outside file of 0x2000 bytes"
+    );
+    assert_eq!(
+        display(Error::OutOfRange {
+            vaddr: 0x20000.into(),
+            range: range(0x10000, 0x4000),
+        }),
+        "address 0x20000 outside region 0x10000-0x14000"
+    );
+    assert_eq!(
+        display(Error::Unmapped(0x30000.into())),
+        "address 0x30000 is not mapped"
+    );
+}
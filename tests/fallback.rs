@@ -0,0 +1,113 @@
+mod common;
+
+use axvma::*;
+use common::{TestFile, range};
+use page_table_multiarch::PageSize;
+use std::sync::Arc;
+
+/// Global layer holding a named library and an anonymous page
+fn global() -> Arc<VmaManager<TestFile>> {
+    let mut manager = VmaManager::new();
+    let mut lib = MmapRegion::new(
+        range(0x10000, 0x4000),
+        TestFile::new(0x4000),
+        0,
+        PageSize::Size4K,
+    );
+    lib.name = Some("libc".into());
+    manager.add_region(lib).unwrap();
+    manager
+        .add_region(MmapRegion::new_anonymous(
+            range(0x40000, 0x1000),
+            PageSize::Size4K,
+        ))
+        .unwrap();
+    Arc::new(manager)
+}
+
+fn anon(start: usize, size: usize) -> MmapRegion<TestFile> {
+    MmapRegion::new_anonymous(range(start, size), PageSize::Size4K)
+}
+
+#[test]
+fn local_regions_take_precedence_over_the_fallback() {
+    let global = global();
+    let mut local = VmaManager::with_fallback(global.clone());
+    assert!(local.is_empty());
+    assert_eq!(
+        local.find_region(0x11000.into()).unwrap().name.as_deref(),
+        Some("libc")
+    );
+    // Faults populate the shared global region
+    local
+        .handle_fault(0x11000.into(), AccessFlags::READ)
+        .unwrap();
+    assert!(
+        global
+            .find_region(0x11000.into())
+            .unwrap()
+            .is_populated(0x11000.into())
+    );
+
+    local.add_region(anon(0x12000, 0x1000)).unwrap();
+    assert!(local.find_region(0x12000.into()).unwrap().is_anonymous());
+    assert!(!local.find_region(0x13000.into()).unwrap().is_anonymous());
+    assert!(!global.find_region(0x12000.into()).unwrap().is_anonymous());
+}
+
+#[test]
+fn unmapping_shadows_the_fallback_locally() {
+    let global = global();
+    let mut local = VmaManager::with_fallback(global.clone());
+    local.add_region(anon(0x12000, 0x1000)).unwrap();
+
+    local.munmap(0x13000.into(), 0x1000).unwrap();
+    assert!(local.find_region(0x13000.into()).is_none());
+    assert_eq!(
+        local.handle_fault(0x13000.into(), AccessFlags::READ).err(),
+        Some(VmaError::Unmapped(0x13000.into()))
+    );
+    assert!(global.find_region(0x13000.into()).is_some());
+
+    // Unmapping a local region does not reveal the global one below it
+    local.munmap(0x12000.into(), 0x1000).unwrap();
+    assert!(local.find_region(0x12000.into()).is_none());
+    assert!(local.find_region(0x10000.into()).is_some());
+
+    // Other mutations see only the local layer
+    assert_eq!(
+        local.protect(range(0x10000, 0x1000), MmapProt::READ),
+        Err(VmaError::Hole(range(0x10000, 0x1000)))
+    );
+}
+
+#[test]
+fn iteration_and_stats_can_include_the_fallback() {
+    let mut local = VmaManager::with_fallback(global());
+    assert_eq!(local.iter().count(), 0);
+    assert_eq!(local.iter_with_fallback().count(), 2);
+    let ranges = |local: &VmaManager<TestFile>| -> Vec<_> {
+        local.iter_with_fallback().map(|r| r.range).collect()
+    };
+    // A partly hidden fallback region is still yielded whole
+    local.munmap(0x10000.into(), 0x2000).unwrap();
+    assert_eq!(
+        ranges(&local),
+        vec![range(0x10000, 0x4000), range(0x40000, 0x1000)]
+    );
+    local.munmap(0x12000.into(), 0x2000).unwrap();
+    assert_eq!(ranges(&local), vec![range(0x40000, 0x1000)]);
+
+    local.add_region(anon(0x20000, 0x1000)).unwrap();
+    assert_eq!(local.stats().region_count, 1);
+    let stats = local.stats_with_fallback();
+    assert_eq!(stats.region_count, 2);
+    assert_eq!(stats.total_mapped, 0x2000);
+    assert_eq!(stats.largest_gap, 0x1f000);
+
+    let top = VmaManager::with_fallback(Arc::new(local));
+    assert!(top.find_region(0x40000.into()).is_some());
+    assert!(top.find_region(0x20000.into()).is_some());
+    assert!(top.find_region(0x10000.into()).is_none());
+    assert!(top.fork().find_region(0x40000.into()).is_some());
+}
@@ -0,0 +1,358 @@
+mod common;
+
+use axerrno::{LinuxError, LinuxResult};
+use axvma::*;
+use common::{TestFile, pattern, range};
+use memory_addr::VirtAddr;
+use page_table_multiarch::PageSize;
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+};
+
+/// File whose reads return at most `cap` bytes at a time
+#[derive(Clone)]
+struct CappedFile {
+    inner: TestFile,
+    cap: usize,
+}
+
+impl VmFile for CappedFile {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> LinuxResult<usize> {
+        let len = buf.len().min(self.cap);
+        self.inner.read_at(&mut buf[..len], offset)
+    }
+
+    fn len(&self) -> LinuxResult<u64> {
+        self.inner.len()
+    }
+}
+
+/// File whose first `failures` reads fail
+#[derive(Clone)]
+struct FlakyFile {
+    inner: TestFile,
+    failures: Arc<AtomicUsize>,
+}
+
+impl VmFile for FlakyFile {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> LinuxResult<usize> {
+        let failed = self
+            .failures
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |left| {
+                left.checked_sub(1)
+            });
+        if failed.is_ok() {
+            return Err(LinuxError::EIO);
+        }
+        self.inner.read_at(buf, offset)
+    }
+
+    fn len(&self) -> LinuxResult<u64> {
+        self.inner.len()
+    }
+}
+
+/// File claiming more data than it returns
+#[derive(Clone)]
+struct TruncatedFile;
+
+impl VmFile for TruncatedFile {
+    fn read_at(&self, _buf: &mut [u8], _offset: u64) -> LinuxResult<usize> {
+        Ok(0)
+    }
+
+    fn len(&self) -> LinuxResult<u64> {
+        Ok(0x4000)
+    }
+}
+
+#[test]
+fn anonymous_fault_returns_zero_page() {
+    let region: MmapRegion<TestFile> =
+        MmapRegion::new_anonymous(range(0x10000, 0x3000), PageSize::Size4K);
+    let page = region.get_buf(0x11234.into()).unwrap();
+    assert_eq!(page.len(), 0x1000);
+    assert!(page.iter().all(|&byte| byte == 0));
+    assert!(region.is_populated(0x11000.into()));
+
+    let huge: MmapRegion<TestFile> =
+        MmapRegion::new_anonymous(range(0x20_0000, 0x40_0000), PageSize::Size2M);
+    let page = huge.get_buf(0x30_0000.into()).unwrap();
+    assert_eq!(page.len(), 0x20_0000);
+    assert!(page.iter().all(|&byte| byte == 0));
+}
+
+#[test]
+fn anonymous_backing_survives_split_and_clone() {
+    let region: MmapRegion<TestFile> =
+        MmapRegion::new_anonymous(range(0x10000, 0x3000), PageSize::Size4K);
+    region.get_buf(0x11000.into()).unwrap();
+
+    let (before, overlap, after) = region.split_at_range(&range(0x11000, 0x1000)).unwrap();
+    let (before, overlap, after) = (before.unwrap(), overlap.unwrap(), after.unwrap());
+    assert!(before.is_anonymous() && overlap.is_anonymous() && after.is_anonymous());
+    assert!(overlap.is_populated(0x11000.into()));
+    assert_eq!(
+        overlap.file_offset_of(0x11000.into()),
+        Err(VmaError::Anonymous)
+    );
+
+    let clone = after.clone();
+    assert!(clone.is_anonymous());
+    assert!(
+        clone
+            .get_buf(0x12000.into())
+            .unwrap()
+            .iter()
+            .all(|&byte| byte == 0)
+    );
+}
+
+#[test]
+fn short_file_page_is_zero_filled_past_eof() {
+    let file = TestFile::new(100);
+    let region = MmapRegion::new(range(0x10000, 0x1000), file, 0, PageSize::Size4K);
+    let page = region.get_buf(0x10000.into()).unwrap();
+    assert_eq!(page.len(), 0x1000);
+    assert!((0..100).all(|i| page[i] == pattern(i)));
+    assert!(page[100..].iter().all(|&byte| byte == 0));
+}
+
+#[test]
+fn page_entirely_past_eof_follows_eof_policy() {
+    let file = TestFile::new(100);
+    let region = MmapRegion::new(range(0x10000, 0x2000), file.clone(), 0, PageSize::Size4K);
+    let page = region.get_buf(0x10000.into()).unwrap();
+    assert_eq!(page[99], pattern(99));
+    assert!(page[100..].iter().all(|&byte| byte == 0));
+    assert_eq!(
+        region.get_buf(0x11000.into()).err(),
+        Some(VmaError::BeyondEof {
+            offset: 0x1000,
+            file_len: 100,
+        })
+    );
+    assert!(!region.is_populated(0x11000.into()));
+
+    let mut region = MmapRegion::new(range(0x10000, 0x2000), file, 0, PageSize::Size4K);
+    region.eof_policy = EofPolicy::ZeroFill;
+    assert_eq!(region.get_buf(0x10000.into()).unwrap()[99], pattern(99));
+    let page = region.get_buf(0x11000.into()).unwrap();
+    assert_eq!(page.len(), 0x1000);
+    assert!(page.iter().all(|&byte| byte == 0));
+}
+
+#[test]
+fn both_eof_policies_zero_the_tail_of_the_last_page() {
+    for policy in [EofPolicy::Bus, EofPolicy::ZeroFill] {
+        let file = TestFile::new(0x1100);
+        let mut region = MmapRegion::new(range(0x10000, 0x3000), file, 0, PageSize::Size4K);
+        region.eof_policy = policy;
+        let page = region.get_buf(0x11000.into()).unwrap();
+        assert!((0..0x100).all(|i| page[i] == pattern(0x1000 + i)));
+        assert!(page[0x100..].iter().all(|&byte| byte == 0));
+
+        let beyond = region.get_buf(0x12000.into());
+        match policy {
+            EofPolicy::Bus => {
+                let err = beyond.unwrap_err();
+                assert_eq!(
+                    err,
+                    VmaError::BeyondEof {
+                        offset: 0x2000,
+                        file_len: 0x1100,
+                    }
+                );
+                assert_eq!(LinuxError::from(err), LinuxError::EFAULT);
+            }
+            EofPolicy::ZeroFill => assert!(beyond.unwrap().iter().all(|&byte| byte == 0)),
+        }
+    }
+}
+
+#[test]
+fn offsets_near_the_limit_overflow_instead_of_wrapping() {
+    let file = TestFile::new(0x1000);
+    let region = MmapRegion::new(
+        range(0x10000, 0x3000),
+        file,
+        i64::MAX - 0x1000,
+        PageSize::Size4K,
+    );
+    assert_eq!(
+        region.file_offset_of(0x10000.into()),
+        Ok(i64::MAX as u64 - 0x1000)
+    );
+    assert_eq!(
+        region.get_buf(0x12000.into()).err(),
+        Some(VmaError::Overflow)
+    );
+    assert!(!region.is_populated(0x12000.into()));
+    assert_eq!(
+        region.split_at_range(&range(0x12000, 0x1000)).err(),
+        Some(VmaError::Overflow)
+    );
+}
+
+#[test]
+fn get_buf_into_populates_like_get_buf() {
+    let file = TestFile::new(0x3000);
+    let copied = MmapRegion::new(range(0x10000, 0x2000), file.clone(), 0, PageSize::Size4K);
+    let loaded = MmapRegion::new(range(0x10000, 0x2000), file, 0, PageSize::Size4K);
+
+    let mut dst = vec![0xff; 0x1800];
+    assert_eq!(
+        copied.get_buf_into(0x11000.into(), &mut dst[..0x800]),
+        Err(VmaError::InvalidArgument)
+    );
+    assert!(!copied.is_populated(0x11000.into()));
+
+    assert_eq!(copied.get_buf_into(0x11010.into(), &mut dst), Ok(0x1000));
+    let page = loaded.get_buf(0x11010.into()).unwrap();
+    assert_eq!(dst[..0x1000], page[..]);
+    assert!(dst[0x1000..].iter().all(|&byte| byte == 0));
+    assert_eq!(
+        copied.populated_iter().collect::<Vec<_>>(),
+        loaded.populated_iter().collect::<Vec<_>>()
+    );
+
+    assert_eq!(
+        copied.get_buf_into(0x11000.into(), &mut dst),
+        Err(VmaError::AlreadyPopulated)
+    );
+    assert_eq!(
+        loaded.get_buf(0x11000.into()).err(),
+        Some(VmaError::AlreadyPopulated)
+    );
+}
+
+#[test]
+fn population_phases_interleave_across_shared_handles() {
+    let file = TestFile::new(0x10000);
+    let first = MmapRegion::new(range(0x10000, 0x2000), file, 0, PageSize::Size4K);
+    let second = first.share();
+
+    let guard = first.begin_populate(0x10000.into()).unwrap();
+    assert_eq!(guard.page_addr(), VirtAddr::from(0x10000));
+    assert_eq!(
+        second.begin_populate(0x10010.into()).err(),
+        Some(VmaError::Busy)
+    );
+    assert_eq!(second.get_buf(0x10010.into()).err(), Some(VmaError::Busy));
+    guard.commit();
+    assert!(second.is_populated(0x10000.into()));
+    assert_eq!(
+        second.begin_populate(0x10010.into()).err(),
+        Some(VmaError::AlreadyPopulated)
+    );
+    assert_eq!(
+        second.get_buf(0x10010.into()).err(),
+        Some(VmaError::AlreadyPopulated)
+    );
+
+    first.begin_populate(0x11000.into()).unwrap().abort();
+    assert!(!second.is_populated(0x11000.into()));
+    drop(second.begin_populate(0x11000.into()).unwrap());
+    assert!(!first.is_populated(0x11000.into()));
+    assert!(first.get_buf(0x11000.into()).is_ok());
+    assert!(second.is_populated(0x11000.into()));
+}
+
+#[test]
+fn short_reads_still_fill_a_huge_page() {
+    let file = CappedFile {
+        inner: TestFile::new(0x30_0000),
+        cap: 512,
+    };
+    let region = MmapRegion::new(range(0x20_0000, 0x20_0000), file, 0x1000, PageSize::Size2M);
+    let page = region.get_buf(0x20_0000.into()).unwrap();
+    assert_eq!(page.len(), 0x20_0000);
+    assert!(
+        page.iter()
+            .enumerate()
+            .all(|(i, &byte)| byte == pattern(i + 0x1000))
+    );
+
+    let mut dst = vec![0xff; 0x20_0000];
+    let short = CappedFile {
+        inner: TestFile::new(0x1_0000),
+        cap: 512,
+    };
+    let region = MmapRegion::new(range(0x20_0000, 0x20_0000), short, 0, PageSize::Size2M);
+    assert_eq!(
+        region.get_buf_into(0x20_0000.into(), &mut dst),
+        Ok(0x20_0000)
+    );
+    assert!(
+        dst[..0x1_0000]
+            .iter()
+            .enumerate()
+            .all(|(i, &byte)| byte == pattern(i))
+    );
+    assert!(dst[0x1_0000..].iter().all(|&byte| byte == 0));
+}
+
+#[test]
+fn unaligned_offset_maps_each_page_at_its_delta() {
+    let file = TestFile::new(0x4000);
+    let contents = file.contents();
+    let region = MmapRegion::new(range(0x10000, 0x2000), file, 1234, PageSize::Size4K);
+    assert_eq!(
+        region.get_buf(0x10000.into()).unwrap()[..],
+        contents[1234..1234 + 0x1000]
+    );
+    assert_eq!(
+        region.get_buf(0x11000.into()).unwrap()[..],
+        contents[1234 + 0x1000..1234 + 0x2000]
+    );
+    assert_eq!(region.file_offset_of(0x11000.into()), Ok(1234 + 0x1000));
+
+    let (_, _, after) = region.split_at_range(&range(0x10000, 0x1000)).unwrap();
+    assert_eq!(
+        after.unwrap().file_offset_of(0x11000.into()),
+        Ok(1234 + 0x1000)
+    );
+}
+
+#[test]
+fn unaligned_offset_zero_fills_past_eof() {
+    let file = TestFile::new(3000);
+    let contents = file.contents();
+    let region = MmapRegion::new(range(0x10000, 0x1000), file, 1234, PageSize::Size4K);
+    let page = region.get_buf(0x10000.into()).unwrap();
+    let available = 3000 - 1234;
+    assert_eq!(page[..available], contents[1234..]);
+    assert!(page[available..].iter().all(|&byte| byte == 0));
+}
+
+#[test]
+fn failed_read_leaves_the_page_unpopulated_for_a_retry() {
+    let file = FlakyFile {
+        inner: TestFile::new(0x4000),
+        failures: Arc::new(AtomicUsize::new(1)),
+    };
+    let region = MmapRegion::new(range(0x10000, 0x2000), file, 0, PageSize::Size4K);
+    assert_eq!(
+        region.get_buf(0x10000.into()).err(),
+        Some(VmaError::Backend(LinuxError::EIO))
+    );
+    assert!(!region.is_populated(0x10000.into()));
+    assert!(region.populated_iter().next().is_none());
+
+    let page = region.get_buf(0x10000.into()).unwrap();
+    assert!(page.iter().enumerate().all(|(i, &byte)| byte == pattern(i)));
+    assert!(region.is_populated(0x10000.into()));
+}
+
+#[test]
+fn read_ending_before_the_file_does_never_populates() {
+    let region = MmapRegion::new(range(0x10000, 0x2000), TruncatedFile, 0, PageSize::Size4K);
+    assert_eq!(
+        region.get_buf(0x11000.into()).err(),
+        Some(VmaError::Backend(LinuxError::EIO))
+    );
+    assert!(!region.is_populated(0x11000.into()));
+    // The page is not left reserved either
+    region.begin_populate(0x11000.into()).unwrap().abort();
+}
@@ -0,0 +1,98 @@
+mod common;
+
+use axvma::*;
+use common::{TestFile, pattern, range};
+use memory_addr::VirtAddrRange;
+use page_table_multiarch::PageSize;
+
+/// Five pages at 0x10000 of which the first 0x2234 bytes come from the file
+/// at offset 0x1000
+fn elf_segment() -> MmapRegion<TestFile> {
+    MmapRegionBuilder::new(range(0x10000, 0x5000))
+        .file(TestFile::new(0x10000), 0x1000)
+        .file_backed_len(0x2234)
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn pages_are_file_backed_up_to_the_boundary_then_zero() {
+    let region = elf_segment();
+    let page = region.get_buf(0x11000.into()).unwrap();
+    assert!(
+        page.iter()
+            .enumerate()
+            .all(|(i, &byte)| byte == pattern(0x2000 + i))
+    );
+
+    let boundary = region.get_buf(0x12000.into()).unwrap();
+    assert!((0..0x234).all(|i| boundary[i] == pattern(0x3000 + i)));
+    assert!(boundary[0x234..].iter().all(|&byte| byte == 0));
+
+    let anonymous = region.get_buf(0x13000.into()).unwrap();
+    assert!(matches!(anonymous, PageData::Owned(_)));
+    assert!(anonymous.iter().all(|&byte| byte == 0));
+}
+
+#[test]
+fn pages_past_the_boundary_never_reach_the_file() {
+    let file = TestFile::new(0x1000);
+    let region = MmapRegionBuilder::new(range(0x40000, 0x3000))
+        .file(file.clone(), 0)
+        .file_backed_len(0x800)
+        .build()
+        .unwrap();
+    // Even past the end of the file
+    let page = region.get_buf(0x42000.into()).unwrap();
+    assert!(page.iter().all(|&byte| byte == 0));
+
+    assert_eq!(region.flush_page(0x42000.into(), &[1; 0x1000]), Ok(0));
+    assert_eq!(region.flush_page(0x40000.into(), &[1; 0x1000]), Ok(0x800));
+    assert_eq!(file.writes(), vec![(0, vec![1; 0x800])]);
+}
+
+#[test]
+fn splits_clamp_the_file_backed_length_of_each_segment() {
+    let region = elf_segment();
+    let lens = |at: VirtAddrRange| {
+        let (before, overlap, after) = region.split_at_range(&at).unwrap();
+        [before, overlap, after].map(|segment| segment.map(|s| s.file_backed_len))
+    };
+    // Right after the last whole file page
+    assert_eq!(
+        lens(range(0x12000, 0x3000)),
+        [Some(Some(0x2000)), Some(Some(0x234)), None]
+    );
+    // Right after the boundary page
+    assert_eq!(
+        lens(range(0x13000, 0x1000)),
+        [Some(Some(0x2234)), Some(Some(0)), Some(Some(0))]
+    );
+    assert_eq!(
+        lens(range(0x10000, 0x1000)),
+        [None, Some(Some(0x1000)), Some(Some(0x1234))]
+    );
+
+    let (_, overlap, after) = region.split_at_range(&range(0x11000, 0x2000)).unwrap();
+    let boundary = overlap.unwrap().get_buf(0x12000.into()).unwrap();
+    assert_eq!(boundary[0], pattern(0x3000));
+    assert!(boundary[0x234..].iter().all(|&byte| byte == 0));
+    let tail = after.unwrap().get_buf(0x13000.into()).unwrap();
+    assert!(tail.iter().all(|&byte| byte == 0));
+}
+
+#[test]
+fn the_boundary_blocks_merges_and_survives_checkpoints() {
+    let file = TestFile::new(0x10000);
+    let left = MmapRegionBuilder::new(range(0x50000, 0x1000))
+        .file(file.clone(), 0)
+        .file_backed_len(0x800)
+        .build()
+        .unwrap();
+    let right = MmapRegion::new(range(0x51000, 0x1000), file, 0x1000, PageSize::Size4K);
+    assert!(!left.can_merge_with(&right));
+
+    let mut manager = VmaManager::new();
+    manager.add_region(left).unwrap();
+    assert_eq!(manager.checkpoint()[0].file_backed_len, Some(0x800));
+}
@@ -0,0 +1,81 @@
+mod common;
+
+use axerrno::LinuxError;
+use axvma::*;
+use common::{TestFile, range};
+use page_table_multiarch::PageSize;
+
+#[test]
+fn flags_render_one_letter_each() {
+    assert_eq!(MmapFlags::empty().to_string(), "-------");
+    assert_eq!(
+        (MmapFlags::SHARED | MmapFlags::LOCKED).to_string(),
+        "S---L--"
+    );
+    assert_eq!(MmapFlags::all().to_string(), "SPFNLDG");
+    assert_eq!(
+        (MmapFlags::PRIVATE | MmapFlags::NORESERVE | MmapFlags::GROWSDOWN).to_string(),
+        "-P-N--G"
+    );
+}
+
+#[test]
+fn shared_and_private_are_mutually_exclusive() {
+    assert!(MmapFlags::SHARED.is_valid());
+    assert!(MmapFlags::PRIVATE.is_valid());
+    assert!(!(MmapFlags::SHARED | MmapFlags::PRIVATE).is_valid());
+
+    let mut manager = VmaManager::new();
+    let mut region = MmapRegion::new(
+        range(0x10000, 0x2000),
+        TestFile::new(0x2000),
+        0,
+        PageSize::Size4K,
+    );
+    region.flags = MmapFlags::SHARED | MmapFlags::PRIVATE;
+    let err = manager.add_region(region).unwrap_err();
+    assert_eq!(err, VmaError::InvalidArgument);
+    assert_eq!(LinuxError::from(err), LinuxError::EINVAL);
+    assert!(manager.is_empty());
+
+    let mut descriptors = {
+        let mut manager = VmaManager::new();
+        let region: MmapRegion<TestFile> =
+            MmapRegion::new_anonymous(range(0x10000, 0x1000), PageSize::Size4K);
+        manager.add_region(region).unwrap();
+        manager.checkpoint()
+    };
+    descriptors[0].flags = (MmapFlags::SHARED | MmapFlags::PRIVATE).bits();
+    assert_eq!(
+        VmaManager::<TestFile>::restore(&descriptors, |_| Err(LinuxError::ENOENT)).err(),
+        Some(VmaError::InvalidArgument)
+    );
+}
+
+#[test]
+fn flags_round_trip_through_split_fork_and_restore() {
+    let file = TestFile::new(0x10000);
+    let flags = MmapFlags::SHARED | MmapFlags::NORESERVE | MmapFlags::FIXED;
+    let mut region = MmapRegion::new(range(0x10000, 0x3000), file.clone(), 0, PageSize::Size4K);
+    region.flags = flags;
+
+    let (before, overlap, after) = region.split_at_range(&range(0x11000, 0x1000)).unwrap();
+    for part in [before, overlap, after] {
+        assert_eq!(part.unwrap().flags, flags);
+    }
+    assert_eq!(region.clone().flags, flags);
+
+    let mut manager = VmaManager::new();
+    manager.add_region(region).unwrap();
+    manager.munmap(0x11000.into(), 0x1000).unwrap();
+    assert!(manager.iter().all(|region| region.flags == flags));
+
+    let child = manager.fork();
+    assert!(child.iter().all(|region| region.flags == flags));
+
+    let descriptors = manager.checkpoint();
+    assert!(descriptors.iter().all(|d| d.flags == flags.bits()));
+    let restored = VmaManager::<TestFile>::restore(&descriptors, |_| Ok(file.clone())).unwrap();
+    assert_eq!(restored.len(), 2);
+    assert!(restored.iter().all(|region| region.flags == flags));
+}
@@ -0,0 +1,75 @@
+mod common;
+
+use axvma::*;
+use common::{TestFile, pattern, range};
+use memory_addr::VirtAddr;
+use page_table_multiarch::PageSize;
+
+fn populated(region: &MmapRegion<TestFile>) -> Vec<usize> {
+    region.populated_iter().map(VirtAddr::as_usize).collect()
+}
+
+#[test]
+fn fork_applies_the_sharing_policy_of_each_region() {
+    let file = TestFile::new(0x10000);
+    let mut manager = VmaManager::new();
+    let private = MmapRegion::new(range(0x10000, 0x2000), file.clone(), 0, PageSize::Size4K);
+    private.get_buf(0x10000.into()).unwrap();
+    manager.add_region(private).unwrap();
+    let mut shared = MmapRegion::new(range(0x20000, 0x2000), file, 0, PageSize::Size4K);
+    shared.flags = MmapFlags::SHARED;
+    shared.get_buf(0x20000.into()).unwrap();
+    manager.add_region(shared).unwrap();
+    let mut dontfork = MmapRegion::new_anonymous(range(0x30000, 0x2000), PageSize::Size4K);
+    dontfork.flags |= MmapFlags::DONTFORK;
+    manager.add_region(dontfork).unwrap();
+
+    let child = manager.fork();
+    assert_eq!(child.len(), 2);
+    assert!(child.find_region(0x30000.into()).is_none());
+    assert!(manager.find_region(0x30000.into()).is_some());
+
+    let parent_private = manager.find_region(0x10000.into()).unwrap();
+    let child_private = child.find_region(0x10000.into()).unwrap();
+    assert_eq!(populated(child_private), vec![0x10000]);
+    assert!(child_private.is_cow(0x10000.into()));
+    assert!(parent_private.is_cow(0x10000.into()));
+    assert!(!child_private.is_cow(0x11000.into()));
+
+    let child_shared = child.find_region(0x20000.into()).unwrap();
+    assert_eq!(populated(child_shared), vec![0x20000]);
+    assert!(!child_shared.is_cow(0x20000.into()));
+    assert!(
+        !manager
+            .find_region(0x20000.into())
+            .unwrap()
+            .is_cow(0x20000.into())
+    );
+}
+
+#[test]
+fn break_cow_hands_out_a_private_copy_once() {
+    let file = TestFile::new(0x10000);
+    let mut manager = VmaManager::new();
+    let region = MmapRegion::new(range(0x10000, 0x1000), file, 0x1000, PageSize::Size4K);
+    region.get_buf(0x10000.into()).unwrap();
+    manager.add_region(region).unwrap();
+
+    let child = manager.fork();
+    let region = child.find_region(0x10000.into()).unwrap();
+    let CowCopy::Data(data) = region.break_cow(0x10800.into()).unwrap() else {
+        panic!("an unmodified page should be copied from the file");
+    };
+    assert_eq!(data[3], pattern(0x1003));
+    assert!(!region.is_cow(0x10000.into()));
+    assert_eq!(
+        region.break_cow(0x10000.into()),
+        Err(VmaError::InvalidArgument)
+    );
+    assert!(
+        manager
+            .find_region(0x10000.into())
+            .unwrap()
+            .is_cow(0x10000.into())
+    );
+}
@@ -0,0 +1,79 @@
+mod common;
+
+use axvma::*;
+use common::{TestFile, range};
+use memory_addr::VirtAddrRange;
+use page_table_multiarch::PageSize;
+
+fn anon(start: usize, size: usize) -> MmapRegion<TestFile> {
+    MmapRegion::new_anonymous(range(start, size), PageSize::Size4K)
+}
+
+fn gaps(manager: &VmaManager<TestFile>, within: VirtAddrRange) -> Vec<VirtAddrRange> {
+    manager.gaps(within).collect()
+}
+
+/// Manager with regions at 0x10000..0x12000, 0x12000..0x13000 and
+/// 0x18000..0x1a000
+fn manager() -> VmaManager<TestFile> {
+    let mut manager = VmaManager::new();
+    for (start, size) in [(0x10000, 0x2000), (0x12000, 0x1000), (0x18000, 0x2000)] {
+        manager.add_region(anon(start, size)).unwrap();
+    }
+    manager
+}
+
+#[test]
+fn empty_manager_has_one_gap_equal_to_within() {
+    let manager = VmaManager::<TestFile>::new();
+    let within = range(0x10000, 0x5000);
+    assert_eq!(gaps(&manager, within), vec![within]);
+}
+
+#[test]
+fn adjacent_regions_leave_no_empty_gap() {
+    let manager = manager();
+    assert_eq!(
+        gaps(&manager, range(0x8000, 0x14000)),
+        vec![
+            range(0x8000, 0x8000),
+            range(0x13000, 0x5000),
+            range(0x1a000, 0x2000),
+        ]
+    );
+    assert!(gaps(&manager, range(0x10000, 0x3000)).is_empty());
+}
+
+#[test]
+fn within_may_start_and_end_inside_regions() {
+    let manager = manager();
+    assert_eq!(
+        gaps(&manager, range(0x11000, 0x8000)),
+        vec![range(0x13000, 0x5000)]
+    );
+    assert!(gaps(&manager, range(0x18800, 0x1000)).is_empty());
+}
+
+#[test]
+fn find_free_range_takes_the_first_fitting_gap() {
+    let manager = manager();
+    let limits = range(0x10000, 0x10000);
+    for size in [0x1000, 0x5000, 0x6000] {
+        let expected = manager
+            .gaps(limits)
+            .find(|gap| gap.size() >= size)
+            .map(|gap| gap.start);
+        assert_eq!(
+            manager.find_free_range(0x10000.into(), size, PageSize::Size4K, limits),
+            expected
+        );
+    }
+    assert_eq!(
+        manager.find_free_range(0x10000.into(), 0x5000, PageSize::Size4K, limits),
+        Some(0x13000.into())
+    );
+    assert_eq!(
+        manager.find_free_range(0x10000.into(), 0x6000, PageSize::Size4K, limits),
+        Some(0x1a000.into())
+    );
+}
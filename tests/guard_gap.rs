@@ -0,0 +1,108 @@
+mod common;
+
+use axerrno::LinuxError;
+use axvma::*;
+use common::{TestFile, range};
+use memory_addr::VirtAddr;
+use page_table_multiarch::PageSize;
+
+fn anon(start: usize, size: usize) -> MmapRegion<TestFile> {
+    MmapRegion::new_anonymous(range(start, size), PageSize::Size4K)
+}
+
+/// Manager with a region at 0x100000 keeping 64 KiB below it unmapped
+fn guarded() -> VmaManager<TestFile> {
+    let mut manager = VmaManager::new();
+    let mut stack = anon(0x100000, 0x10000);
+    stack.guard_below = 0x10000;
+    manager.add_region(stack).unwrap();
+    manager
+}
+
+#[test]
+fn mapping_into_a_guard_gap_fails() {
+    let mut manager = guarded();
+    let err = manager.add_region(anon(0xf8000, 0x4000)).unwrap_err();
+    assert!(matches!(err, VmaError::GuardGap(_)));
+    assert_eq!(LinuxError::from(err), LinuxError::ENOMEM);
+    assert!(matches!(
+        manager.add_region_replace(anon(0xf0000, 0x1000)).err(),
+        Some(VmaError::GuardGap(_))
+    ));
+
+    // Right below the gap is fine, and the gap is not a region itself
+    manager.add_region(anon(0xef000, 0x1000)).unwrap();
+    assert_eq!(manager.len(), 2);
+    assert!(manager.find_region(0xf8000.into()).is_none());
+}
+
+#[test]
+fn free_range_search_skips_guard_gaps() {
+    let manager = guarded();
+    let limits = range(0xf0000, 0x100000);
+    assert_eq!(
+        manager.find_free_range(0xf4000.into(), 0x1000, PageSize::Size4K, limits),
+        Some(VirtAddr::from(0x110000))
+    );
+    assert_eq!(
+        manager.find_free_range(
+            0xf0000.into(),
+            0x1000,
+            PageSize::Size4K,
+            range(0xf0000, 0x10000)
+        ),
+        None
+    );
+    assert!(
+        manager
+            .find_free_range(
+                0xe0000.into(),
+                0x1000,
+                PageSize::Size4K,
+                range(0, 0x10_0000)
+            )
+            .is_some_and(|start| start < VirtAddr::from(0xf0000))
+    );
+}
+
+#[test]
+fn new_regions_keep_their_own_gaps_clear() {
+    let mut manager = VmaManager::new();
+    manager.add_region(anon(0x203000, 0x1000)).unwrap();
+    let mut region = anon(0x200000, 0x1000);
+    region.guard_above = 0x2000;
+    manager.add_region(region.clone()).unwrap();
+    manager.munmap(0x200000.into(), 0x1000).unwrap();
+
+    region.guard_above = 0x2001;
+    assert!(matches!(
+        manager.add_region(region),
+        Err(VmaError::GuardGap(_))
+    ));
+}
+
+#[test]
+fn stack_growth_stops_at_the_gap_above_the_region_below() {
+    let mut manager = VmaManager::new();
+    manager.set_stack_growth(0x100000, 0);
+    let mut low = anon(0x10000, 0x1000);
+    low.guard_above = 0x8000;
+    manager.add_region(low).unwrap();
+    let mut stack = anon(0x20000, 0x1000);
+    stack.flags |= MmapFlags::GROWSDOWN;
+    manager.add_region(stack).unwrap();
+
+    assert_eq!(
+        manager
+            .handle_fault_or_grow(0x18000.into(), AccessFlags::READ)
+            .err(),
+        Some(VmaError::Unmapped(0x18000.into()))
+    );
+    manager
+        .handle_fault_or_grow(0x19000.into(), AccessFlags::READ)
+        .unwrap();
+    assert_eq!(
+        manager.find_region(0x19000.into()).unwrap().range,
+        range(0x19000, 0x8000)
+    );
+}
@@ -0,0 +1,70 @@
+mod common;
+
+use axvma::*;
+use common::{TestFile, pattern};
+use memory_addr::AddrRange;
+use page_table_multiarch::PageSize;
+
+/// Guest physical address, as a hypervisor would define it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct GuestPhysAddr(usize);
+
+impl From<usize> for GuestPhysAddr {
+    fn from(addr: usize) -> Self {
+        Self(addr)
+    }
+}
+
+impl From<GuestPhysAddr> for usize {
+    fn from(addr: GuestPhysAddr) -> Self {
+        addr.0
+    }
+}
+
+fn gpa_range(start: usize, size: usize) -> AddrRange<GuestPhysAddr> {
+    AddrRange::from_start_size(GuestPhysAddr(start), size)
+}
+
+#[test]
+fn manager_works_over_guest_physical_addresses() {
+    let mut manager: VmaManager<TestFile, GuestPhysAddr> = VmaManager::default();
+    manager
+        .add_region(MmapRegion::new(
+            gpa_range(0x8000_0000, 0x4000),
+            TestFile::new(0x4000),
+            0,
+            PageSize::Size4K,
+        ))
+        .unwrap();
+
+    let resolution = manager
+        .handle_fault(GuestPhysAddr(0x8000_1010), AccessFlags::READ)
+        .unwrap();
+    assert_eq!(resolution.vaddr, GuestPhysAddr(0x8000_1000));
+    let FaultData::Loaded(data) = resolution.data else {
+        panic!("file-backed fault was not loaded");
+    };
+    assert_eq!(data[0], pattern(0x1000));
+    assert_eq!(
+        manager
+            .handle_fault(GuestPhysAddr(0x8000_1010), AccessFlags::READ)
+            .err(),
+        Some(VmaError::AlreadyPopulated)
+    );
+
+    let removed = manager.munmap(GuestPhysAddr(0x8000_1000), 0x1000).unwrap();
+    assert_eq!(
+        removed.pages,
+        vec![(GuestPhysAddr(0x8000_1000), PageSize::Size4K)]
+    );
+    assert_eq!(manager.len(), 2);
+    let after = manager.find_region(GuestPhysAddr(0x8000_2000)).unwrap();
+    assert_eq!(after.range, gpa_range(0x8000_2000, 0x2000));
+    assert_eq!(after.file_offset_of(GuestPhysAddr(0x8000_2000)), Ok(0x2000));
+    assert_eq!(
+        manager
+            .handle_fault(GuestPhysAddr(0x9000_0000), AccessFlags::READ)
+            .err(),
+        Some(VmaError::Unmapped(GuestPhysAddr(0x9000_0000)))
+    );
+}
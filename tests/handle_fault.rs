@@ -0,0 +1,111 @@
+mod common;
+
+use axerrno::{LinuxError, LinuxResult};
+use axvma::*;
+use common::{TestFile, pattern, range};
+use memory_addr::VirtAddr;
+use page_table_multiarch::PageSize;
+
+#[derive(Clone)]
+struct FailingFile;
+
+impl VmFile for FailingFile {
+    fn read_at(&self, _buf: &mut [u8], _offset: u64) -> LinuxResult<usize> {
+        Err(LinuxError::EIO)
+    }
+
+    fn len(&self) -> LinuxResult<u64> {
+        Ok(0x10000)
+    }
+}
+
+#[test]
+fn handle_fault_resolves_denies_and_reports_holes() {
+    let mut manager = VmaManager::new();
+    let file = TestFile::new(0x10000);
+    let writable = MmapRegion::new(
+        range(0x10000, 0x2000),
+        file.clone(),
+        0x1000,
+        PageSize::Size4K,
+    );
+    let mut read_only = MmapRegion::new(range(0x14000, 0x1000), file, 0, PageSize::Size4K);
+    read_only.prot = MmapProt::READ;
+    manager.add_region(writable).unwrap();
+    manager.add_region(read_only).unwrap();
+
+    let resolution = manager
+        .handle_fault(0x11234.into(), AccessFlags::WRITE)
+        .unwrap();
+    assert_eq!(resolution.vaddr, VirtAddr::from(0x11000));
+    assert_eq!(resolution.size, PageSize::Size4K);
+    assert_eq!(resolution.prot, MmapProt::all());
+    let FaultData::Loaded(data) = resolution.data else {
+        panic!("expected loaded data");
+    };
+    assert_eq!(data.len(), 0x1000);
+    assert_eq!(data[0], pattern(0x2000));
+
+    assert_eq!(
+        manager.handle_fault(0x12000.into(), AccessFlags::READ),
+        Err(VmaError::Unmapped(0x12000.into()))
+    );
+    assert_eq!(
+        manager.handle_fault(0x13fff.into(), AccessFlags::READ),
+        Err(VmaError::Unmapped(0x13fff.into()))
+    );
+
+    assert_eq!(
+        manager.handle_fault(0x14000.into(), AccessFlags::WRITE),
+        Err(VmaError::AccessDenied)
+    );
+    assert!(
+        !manager
+            .find_region(0x14000.into())
+            .unwrap()
+            .is_populated(0x14000.into())
+    );
+    let resolution = manager
+        .handle_fault(0x14000.into(), AccessFlags::READ)
+        .unwrap();
+    assert_eq!(resolution.prot, MmapProt::READ);
+}
+
+#[test]
+fn handle_fault_passes_through_file_errors() {
+    let mut manager = VmaManager::new();
+    manager
+        .add_region(MmapRegion::new(
+            range(0x10000, 0x1000),
+            FailingFile,
+            0,
+            PageSize::Size4K,
+        ))
+        .unwrap();
+    assert_eq!(
+        manager.handle_fault(0x10000.into(), AccessFlags::READ),
+        Err(VmaError::Backend(LinuxError::EIO))
+    );
+    assert!(
+        !manager
+            .find_region(0x10000.into())
+            .unwrap()
+            .is_populated(0x10000.into())
+    );
+}
+
+#[test]
+fn handle_fault_maps_anonymous_pages_as_zero() {
+    let mut manager: VmaManager<TestFile> = VmaManager::new();
+    manager
+        .add_region(MmapRegion::new_anonymous(
+            range(0x10000, 0x1000),
+            PageSize::Size4K,
+        ))
+        .unwrap();
+    let resolution = manager
+        .handle_fault(0x10800.into(), AccessFlags::READ)
+        .unwrap();
+    assert_eq!(resolution.vaddr, VirtAddr::from(0x10000));
+    assert_eq!(resolution.data, FaultData::Zero);
+}
@@ -0,0 +1,79 @@
+mod common;
+
+use axvma::*;
+use common::{TestFile, range};
+use memory_addr::VirtAddr;
+use page_table_multiarch::PageSize;
+
+fn heap_region(manager: &VmaManager<TestFile>) -> &MmapRegion<TestFile> {
+    manager.find_region(0x100000.into()).unwrap()
+}
+
+#[test]
+fn brk_grows_shrinks_and_regrows_with_fresh_pages() {
+    let mut manager = VmaManager::new();
+    let mut heap = HeapRegion::new(VirtAddr::from(0x100000), 0x10000);
+    assert_eq!(heap.brk(), VirtAddr::from(0x100000));
+    assert!(manager.is_empty());
+
+    assert!(
+        heap.set_brk(&mut manager, 0x100800.into())
+            .unwrap()
+            .is_empty()
+    );
+    assert_eq!(heap_region(&manager).range, range(0x100000, 0x1000));
+    heap.set_brk(&mut manager, 0x104000.into()).unwrap();
+    assert_eq!(manager.len(), 1);
+    let region = heap_region(&manager);
+    assert_eq!(region.range, range(0x100000, 0x4000));
+    assert_eq!(region.name.as_deref(), Some("[heap]"));
+    assert!(region.is_anonymous());
+
+    for vaddr in [0x101000, 0x103000] {
+        let resolution = manager
+            .handle_fault(vaddr.into(), AccessFlags::WRITE)
+            .unwrap();
+        assert_eq!(resolution.data, FaultData::Zero);
+    }
+
+    let dropped = heap.set_brk(&mut manager, 0x101800.into()).unwrap();
+    assert_eq!(dropped, vec![(VirtAddr::from(0x103000), PageSize::Size4K)]);
+    assert_eq!(heap.brk(), VirtAddr::from(0x101800));
+    assert_eq!(heap_region(&manager).range, range(0x100000, 0x2000));
+    assert!(heap_region(&manager).is_populated(0x101000.into()));
+
+    heap.set_brk(&mut manager, 0x104000.into()).unwrap();
+    assert!(!heap_region(&manager).is_populated(0x103000.into()));
+    let resolution = manager
+        .handle_fault(0x103000.into(), AccessFlags::READ)
+        .unwrap();
+    assert_eq!(resolution.data, FaultData::Zero);
+
+    heap.set_brk(&mut manager, 0x100000.into()).unwrap();
+    assert_eq!(heap.brk(), VirtAddr::from(0x100000));
+    assert!(manager.is_empty());
+}
+
+#[test]
+fn brk_refuses_to_collide_or_exceed_the_limit() {
+    let mut manager = VmaManager::new();
+    let mut heap = HeapRegion::new(VirtAddr::from(0x100000), 0x10000);
+    heap.set_brk(&mut manager, 0x104000.into()).unwrap();
+    manager
+        .add_region(MmapRegion::new_anonymous(
+            range(0x106000, 0x1000),
+            PageSize::Size4K,
+        ))
+        .unwrap();
+
+    for new_brk in [0x106001, 0x110001, 0xff000] {
+        assert_eq!(
+            heap.set_brk(&mut manager, new_brk.into()),
+            Err(VmaError::NoSpace)
+        );
+    }
+    assert_eq!(heap.brk(), VirtAddr::from(0x104000));
+    assert_eq!(heap_region(&manager).range, range(0x100000, 0x4000));
+    heap.set_brk(&mut manager, 0x106000.into()).unwrap();
+    assert_eq!(heap_region(&manager).range, range(0x100000, 0x6000));
+}
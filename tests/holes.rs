@@ -0,0 +1,185 @@
+mod common;
+
+use axerrno::LinuxError;
+use axvma::*;
+use common::{TestFile, range};
+use memory_addr::VirtAddr;
+use page_table_multiarch::PageSize;
+
+/// Manager that punches holes, holding one fully populated six-page region
+fn punching(file: &TestFile) -> VmaManager<TestFile> {
+    let mut manager = VmaManager::new();
+    manager.set_hole_punching(Some(1));
+    let region = MmapRegion::new(range(0x10000, 0x6000), file.clone(), 0, PageSize::Size4K);
+    region.populate_range(&region.range).unwrap();
+    manager.add_region(region).unwrap();
+    manager
+}
+
+#[test]
+fn unmapping_inside_a_region_punches_a_hole() {
+    let file = TestFile::new(0x10000);
+    let mut manager = punching(&file);
+    let removed = manager.remove_overlapped(range(0x12000, 0x2000)).unwrap();
+    assert!(removed.regions.is_empty());
+    assert_eq!(
+        removed.pages,
+        vec![
+            (VirtAddr::from(0x12000), PageSize::Size4K),
+            (VirtAddr::from(0x13000), PageSize::Size4K),
+        ]
+    );
+    assert_eq!(manager.len(), 1);
+    assert_eq!(manager.stats().total_mapped, 0x4000);
+
+    let region = manager.find_region(0x10000.into()).unwrap();
+    assert_eq!(
+        region.holes().collect::<Vec<_>>(),
+        vec![range(0x12000, 0x2000)]
+    );
+    assert!(region.has_hole_in(range(0x11000, 0x2000)));
+    assert!(!region.has_hole_in(range(0x14000, 0x2000)));
+    assert!(region.is_fully_populated());
+    assert!(manager.find_region(0x12000.into()).is_none());
+    assert!(manager.find_region(0x14000.into()).is_some());
+}
+
+#[test]
+fn faults_in_a_hole_are_unmapped() {
+    let file = TestFile::new(0x10000);
+    let mut manager = punching(&file);
+    manager.remove_overlapped(range(0x12000, 0x2000)).unwrap();
+    let err = manager
+        .handle_fault(0x13800.into(), AccessFlags::READ)
+        .unwrap_err();
+    assert_eq!(LinuxError::from(err), LinuxError::EFAULT);
+    assert!(
+        manager
+            .find_region(0x10000.into())
+            .unwrap()
+            .get_buf(0x13000.into())
+            .is_err()
+    );
+}
+
+#[test]
+fn mapping_over_a_hole_splits_around_it() {
+    let file = TestFile::new(0x10000);
+    let mut manager = punching(&file);
+    manager.remove_overlapped(range(0x12000, 0x2000)).unwrap();
+    manager
+        .add_region(MmapRegion::new(
+            range(0x12000, 0x1000),
+            file.clone(),
+            0,
+            PageSize::Size4K,
+        ))
+        .unwrap();
+    let ranges: Vec<_> = manager.iter().map(|region| region.range).collect();
+    assert_eq!(
+        ranges,
+        vec![
+            range(0x10000, 0x2000),
+            range(0x12000, 0x1000),
+            range(0x14000, 0x2000)
+        ]
+    );
+    assert!(manager.iter().all(|region| region.holes().next().is_none()));
+    assert_eq!(manager.stats().total_mapped, 0x5000);
+}
+
+#[test]
+fn splits_partition_the_holes() {
+    let mut region = MmapRegion::new(
+        range(0x10000, 0x8000),
+        TestFile::new(0x10000),
+        0,
+        PageSize::Size4K,
+    );
+    region.punch_hole(range(0x12000, 0x1000)).unwrap();
+    region.punch_hole(range(0x15000, 0x2000)).unwrap();
+
+    let (before, overlap, after) = region.split_at_range(&range(0x11000, 0x5000)).unwrap();
+    let (before, overlap, after) = (before.unwrap(), overlap.unwrap(), after.unwrap());
+    assert_eq!(before.range, range(0x10000, 0x1000));
+    assert_eq!(overlap.range, range(0x11000, 0x4000));
+    assert_eq!(
+        overlap.holes().collect::<Vec<_>>(),
+        vec![range(0x12000, 0x1000)]
+    );
+    // The after segment would start in a hole, so it is trimmed
+    assert_eq!(after.range, range(0x17000, 0x1000));
+    assert!(before.holes().chain(after.holes()).next().is_none());
+
+    // A range lying in a hole has no overlap segment
+    assert!(
+        region
+            .split_at_range(&range(0x12000, 0x1000))
+            .unwrap()
+            .1
+            .is_none()
+    );
+}
+
+#[test]
+fn punch_hole_needs_a_range_strictly_inside() {
+    let mut region = MmapRegion::new(
+        range(0x10000, 0x4000),
+        TestFile::new(0x10000),
+        0,
+        PageSize::Size4K,
+    );
+    for hole in [
+        range(0x10000, 0x1000),
+        range(0x13000, 0x1000),
+        range(0x11000, 0),
+    ] {
+        assert_eq!(region.punch_hole(hole), Err(VmaError::InvalidArgument));
+    }
+    assert_eq!(
+        region.punch_hole(range(0x11800, 0x1000)),
+        Err(VmaError::Unaligned)
+    );
+    assert!(region.holes().next().is_none());
+}
+
+#[test]
+fn checkpoints_keep_the_holes() {
+    let file = TestFile::new(0x10000);
+    let mut manager = punching(&file);
+    manager.remove_overlapped(range(0x12000, 0x1000)).unwrap();
+    let descriptors = manager.checkpoint();
+    assert_eq!(descriptors[0].holes, vec![(0x2000, 0x3000)]);
+
+    let restored = VmaManager::<TestFile>::restore(&descriptors, |_| Ok(file.clone())).unwrap();
+    assert!(restored.find_region(0x12000.into()).is_none());
+    assert_eq!(restored.stats().total_mapped, 0x5000);
+}
+
+#[test]
+fn holes_are_free_for_placement() {
+    let file = TestFile::new(0x10000);
+    let mut manager = punching(&file);
+    manager.remove_overlapped(range(0x12000, 0x2000)).unwrap();
+
+    let within = range(0x10000, 0x6000);
+    assert_eq!(
+        manager.gaps(within).collect::<Vec<_>>(),
+        vec![range(0x12000, 0x2000)]
+    );
+    assert_eq!(
+        manager.find_free_range(0x10000.into(), 0x2000, PageSize::Size4K, within),
+        Some(0x12000.into())
+    );
+    assert_eq!(
+        manager.find_free_range(0x10000.into(), 0x3000, PageSize::Size4K, within),
+        None
+    );
+    manager
+        .add_region(MmapRegion::new_anonymous(
+            range(0x12000, 0x2000),
+            PageSize::Size4K,
+        ))
+        .unwrap();
+    assert_eq!(manager.gaps(within).next(), None);
+}
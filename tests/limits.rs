@@ -0,0 +1,180 @@
+mod common;
+
+use axerrno::LinuxError;
+use axvma::*;
+use common::{TestFile, range};
+use memory_addr::VirtAddr;
+use page_table_multiarch::PageSize;
+
+fn file_region(start: usize, size: usize) -> MmapRegion<TestFile> {
+    MmapRegion::new(
+        range(start, size),
+        TestFile::new(0x8000),
+        0,
+        PageSize::Size4K,
+    )
+}
+
+fn ranges(manager: &VmaManager<TestFile>) -> Vec<(usize, usize)> {
+    manager
+        .iter()
+        .map(|region| (region.range.start.as_usize(), region.range.end.as_usize()))
+        .collect()
+}
+
+#[test]
+fn splits_past_the_region_limit_fail_without_changes() {
+    let mut manager = VmaManager::new();
+    manager.set_max_regions(Some(3));
+    manager.add_region(file_region(0x10000, 0x4000)).unwrap();
+    manager.add_region(file_region(0x20000, 0x4000)).unwrap();
+
+    // Protecting the middle of a region would make four
+    assert_eq!(
+        manager.protect(range(0x11000, 0x1000), MmapProt::READ),
+        Err(VmaError::RegionLimit(3))
+    );
+    assert_eq!(
+        LinuxError::from(VmaError::<VirtAddr>::RegionLimit(3)),
+        LinuxError::ENOMEM
+    );
+    assert_eq!(manager.len(), 2);
+    assert!(manager.iter().all(|region| region.prot == MmapProt::all()));
+
+    // Trimming an edge reaches the limit exactly
+    manager
+        .protect(range(0x10000, 0x1000), MmapProt::READ)
+        .unwrap();
+    assert_eq!(manager.len(), 3);
+    let before = ranges(&manager);
+
+    assert_eq!(
+        manager.add_region(file_region(0x30000, 0x1000)),
+        Err(VmaError::RegionLimit(3))
+    );
+    assert_eq!(
+        manager.protect(range(0x21000, 0x1000), MmapProt::READ),
+        Err(VmaError::RegionLimit(3))
+    );
+    assert_eq!(
+        manager.munmap(0x22000.into(), 0x1000).err(),
+        Some(VmaError::RegionLimit(3))
+    );
+    assert_eq!(
+        manager
+            .add_region_replace(file_region(0x21000, 0x1000))
+            .err(),
+        Some(VmaError::RegionLimit(3))
+    );
+    assert_eq!(ranges(&manager), before);
+    assert!(
+        manager
+            .iter()
+            .all(|region| region.range.start != 0x21000.into())
+    );
+}
+
+#[test]
+fn operations_that_keep_the_count_still_succeed_at_the_limit() {
+    let mut manager = VmaManager::new();
+    manager.set_max_regions(Some(2));
+    manager.add_region(file_region(0x10000, 0x4000)).unwrap();
+    manager.add_region(file_region(0x20000, 0x4000)).unwrap();
+
+    manager
+        .add_region_replace(file_region(0x20000, 0x4000))
+        .unwrap();
+    manager.munmap(0x13000.into(), 0x1000).unwrap();
+    assert_eq!(manager.len(), 2);
+    manager.munmap(0x20000.into(), 0x4000).unwrap();
+    assert_eq!(manager.len(), 1);
+    manager.add_region(file_region(0x30000, 0x1000)).unwrap();
+
+    manager.set_max_regions(None);
+    manager
+        .protect(range(0x11000, 0x1000), MmapProt::READ)
+        .unwrap();
+    assert_eq!(manager.len(), 4);
+}
+
+#[test]
+fn window_rejects_mappings_outside_it() {
+    let mut manager = VmaManager::with_limits(range(0x10000, 0x100000), None);
+    for (start, size) in [(0x8000, 0x1000), (0xf000, 0x2000), (0x10f000, 0x2000)] {
+        assert_eq!(
+            manager.add_region(MmapRegion::new_anonymous(
+                range(start, size),
+                PageSize::Size4K
+            )),
+            Err(VmaError::InvalidArgument)
+        );
+    }
+    let start = manager
+        .mmap(0.into(), 0x4000, TestFile::new(0x4000), 0, PageSize::Size4K)
+        .unwrap();
+    assert!(start >= VirtAddr::from(0x10000));
+    assert_eq!(manager.remaining_quota(), None);
+}
+
+#[test]
+fn quota_is_reclaimed_exactly_by_unmapping() {
+    let mut manager = VmaManager::with_limits(range(0x10000, 0x100000), Some(0x8000));
+    assert_eq!(manager.remaining_quota(), Some(0x8000));
+    manager
+        .mmap(0.into(), 0x4000, TestFile::new(0x4000), 0, PageSize::Size4K)
+        .unwrap();
+    manager
+        .add_region(MmapRegion::new_anonymous(
+            range(0x80000, 0x4000),
+            PageSize::Size4K,
+        ))
+        .unwrap();
+    assert_eq!(manager.remaining_quota(), Some(0));
+
+    let err = manager
+        .mmap(0.into(), 0x1000, TestFile::new(0x1000), 0, PageSize::Size4K)
+        .unwrap_err();
+    assert_eq!(err, VmaError::AddressSpaceLimit(0x8000));
+    assert_eq!(LinuxError::from(err), LinuxError::ENOMEM);
+    assert!(matches!(
+        manager.remap(0x80000.into(), 0x4000, 0x5000, RemapFlags::MAYMOVE),
+        Err(VmaError::AddressSpaceLimit(_))
+    ));
+
+    // Splitting keeps the total, removing frees exactly the overlap
+    manager
+        .protect(range(0x81000, 0x1000), MmapProt::READ)
+        .unwrap();
+    assert_eq!(manager.remaining_quota(), Some(0));
+    manager.munmap(0x81000.into(), 0x2000).unwrap();
+    assert_eq!(manager.remaining_quota(), Some(0x2000));
+    manager
+        .add_region_replace(MmapRegion::new_anonymous(
+            range(0x80000, 0x3000),
+            PageSize::Size4K,
+        ))
+        .unwrap();
+    assert_eq!(manager.remaining_quota(), Some(0));
+    manager.munmap(0x83000.into(), 0x1000).unwrap();
+    assert_eq!(manager.remaining_quota(), Some(0x1000));
+    manager
+        .remap(0x80000.into(), 0x3000, 0x4000, RemapFlags::empty())
+        .unwrap();
+    assert_eq!(manager.remaining_quota(), Some(0));
+    manager.clear();
+    assert_eq!(manager.remaining_quota(), Some(0x8000));
+}
+
+#[test]
+fn heap_growth_counts_against_the_quota() {
+    let mut manager: VmaManager<TestFile> =
+        VmaManager::with_limits(range(0x10000, 0x100000), Some(0x8000));
+    let mut heap = HeapRegion::new(VirtAddr::from(0x20000), 0x100000);
+    heap.set_brk(&mut manager, 0x28000.into()).unwrap();
+    assert!(matches!(
+        heap.set_brk(&mut manager, 0x29000.into()),
+        Err(VmaError::AddressSpaceLimit(_))
+    ));
+    heap.set_brk(&mut manager, 0x20000.into()).unwrap();
+    assert_eq!(manager.remaining_quota(), Some(0x8000));
+}
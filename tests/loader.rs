@@ -0,0 +1,123 @@
+mod common;
+
+use axvma::{loader::map_elf_segment, *};
+use common::{TestFile, pattern, range};
+use page_table_multiarch::PageSize;
+
+const PAGE: PageSize = PageSize::Size4K;
+
+#[test]
+fn segment_without_bss_is_file_backed() {
+    let mut manager = VmaManager::new();
+    let segment = map_elf_segment(
+        &mut manager,
+        0x10100.into(),
+        TestFile::new(0x10000),
+        0x1100,
+        0x1000,
+        0x1000,
+        PAGE,
+    )
+    .unwrap();
+    assert_eq!(segment, range(0x10000, 0x2000));
+    assert_eq!(manager.len(), 1);
+    let region = manager.find_region(0x10000.into()).unwrap();
+    assert_eq!(region.file_offset_of(0x10100.into()), Ok(0x1100));
+    let last = region.get_buf(0x11000.into()).unwrap();
+    assert!((0..0x100).all(|i| last[i] == pattern(0x2000 + i)));
+    assert!(last[0x100..].iter().all(|&byte| byte == 0));
+}
+
+#[test]
+fn bss_is_zero_from_the_boundary_page_on() {
+    let mut manager = VmaManager::new();
+    let segment = map_elf_segment(
+        &mut manager,
+        0x20010.into(),
+        TestFile::new(0x10000),
+        0x3010,
+        0x1100,
+        0x3000,
+        PAGE,
+    )
+    .unwrap();
+    assert_eq!(segment, range(0x20000, 0x4000));
+    assert_eq!(manager.len(), 1);
+    let region = manager.find_region(0x21000.into()).unwrap();
+    assert_eq!(region.file_backed_len, Some(0x1110));
+
+    let first = region.get_buf(0x20000.into()).unwrap();
+    assert_eq!(first[0x10], pattern(0x3010));
+    // The boundary page holds the last file bytes followed by zeros
+    let boundary = region.get_buf(0x21000.into()).unwrap();
+    assert_eq!(boundary[0], pattern(0x4000));
+    assert_eq!(boundary[0x10f], pattern(0x410f));
+    assert!(boundary[0x110..].iter().all(|&byte| byte == 0));
+    for page in [0x22000, 0x23000] {
+        assert!(
+            region
+                .get_buf(page.into())
+                .unwrap()
+                .iter()
+                .all(|&byte| byte == 0)
+        );
+    }
+}
+
+#[test]
+fn segment_without_file_data_is_anonymous() {
+    let mut manager = VmaManager::new();
+    let segment = map_elf_segment(
+        &mut manager,
+        0x30800.into(),
+        TestFile::new(0),
+        0x800,
+        0,
+        0x1000,
+        PAGE,
+    )
+    .unwrap();
+    assert_eq!(segment, range(0x30000, 0x2000));
+    assert!(manager.find_region(0x30000.into()).unwrap().is_anonymous());
+}
+
+#[test]
+fn invalid_segments_are_rejected() {
+    let file = TestFile::new(0x10000);
+    let mut manager = VmaManager::new();
+    map_elf_segment(
+        &mut manager,
+        0x21000.into(),
+        file.clone(),
+        0,
+        0x20,
+        0x20,
+        PAGE,
+    )
+    .unwrap();
+
+    let mut map = |vaddr: usize, file_offset, filesz, memsz| {
+        map_elf_segment(
+            &mut manager,
+            vaddr.into(),
+            file.clone(),
+            file_offset,
+            filesz,
+            memsz,
+            PAGE,
+        )
+        .err()
+    };
+    assert_eq!(map(0x40000, 0, 0, 0), Some(VmaError::InvalidArgument));
+    assert_eq!(map(0x40000, 0, 0x20, 0x10), Some(VmaError::InvalidArgument));
+    assert_eq!(map(0x40010, 0x20, 0x10, 0x10), Some(VmaError::Unaligned));
+    assert_eq!(
+        map(0x21010, 0x10, 0x20, 0x20),
+        Some(VmaError::Overlap(range(0x21000, 0x1000)))
+    );
+    assert_eq!(
+        map(usize::MAX - 0xfff, 0, 0x20, 0x2000),
+        Some(VmaError::NoSpace)
+    );
+    assert_eq!(manager.len(), 1);
+}
@@ -0,0 +1,91 @@
+mod common;
+
+use axvma::*;
+use common::{TestFile, range};
+use memory_addr::VirtAddr;
+use page_table_multiarch::PageSize;
+use std::cell::Cell;
+
+thread_local! {
+    static LOCKS: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Default lock that counts the acquisitions made on the current thread
+struct CountingRawMutex(DefaultRawMutex);
+
+unsafe impl RawMutex for CountingRawMutex {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const INIT: Self = Self(DefaultRawMutex::INIT);
+    type GuardMarker = <DefaultRawMutex as RawMutex>::GuardMarker;
+
+    fn lock(&self) {
+        LOCKS.set(LOCKS.get() + 1);
+        RawMutex::lock(&self.0)
+    }
+
+    fn try_lock(&self) -> bool {
+        LOCKS.set(LOCKS.get() + 1);
+        RawMutex::try_lock(&self.0)
+    }
+
+    unsafe fn unlock(&self) {
+        unsafe { RawMutex::unlock(&self.0) }
+    }
+}
+
+fn file_region() -> MmapRegion<TestFile, VirtAddr, CountingRawMutex> {
+    let backing = RegionBacking::File {
+        file: TestFile::new(0x4000),
+        offset: 0,
+    };
+    MmapRegion::with_backing(range(0x10000, 0x4000), backing, PageSize::Size4K)
+}
+
+/// Lock acquisitions made by `f`
+fn locks_taken<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    LOCKS.set(0);
+    let result = f();
+    (result, LOCKS.get())
+}
+
+#[test]
+fn fault_path_takes_the_lock_a_fixed_number_of_times() {
+    let mut manager: VmaManager<TestFile, VirtAddr, CountingRawMutex> = VmaManager::default();
+    manager.add_region(file_region()).unwrap();
+
+    let (fault, locks) = locks_taken(|| manager.handle_fault(0x10004.into(), AccessFlags::READ));
+    assert!(fault.is_ok());
+    assert_eq!(locks, 4);
+    // A fault on a present page stops at the populated set
+    let (fault, locks) = locks_taken(|| manager.handle_fault(0x10004.into(), AccessFlags::READ));
+    assert_eq!(fault.err(), Some(VmaError::AlreadyPopulated));
+    assert_eq!(locks, 2);
+}
+
+#[test]
+fn custom_locks_survive_clone_and_split() {
+    let mut manager: VmaManager<TestFile, VirtAddr, CountingRawMutex> = VmaManager::default();
+    manager.add_region(file_region()).unwrap();
+    manager.populate(range(0x10000, 0x3000)).unwrap();
+
+    let clone = manager.clone();
+    manager.munmap(0x11000.into(), 0x1000).unwrap();
+    assert_eq!(manager.len(), 2);
+    let after = manager.find_region(0x12000.into()).unwrap();
+    assert_eq!(
+        after.populated_iter().collect::<Vec<_>>(),
+        vec![VirtAddr::from(0x12000)]
+    );
+    assert_eq!(
+        clone
+            .find_region(0x10000.into())
+            .unwrap()
+            .populated_iter()
+            .count(),
+        3
+    );
+
+    // The default lock needs no annotation
+    let default: VmaManager<TestFile> = VmaManager::new();
+    assert!(default.is_empty());
+}
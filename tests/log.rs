@@ -0,0 +1,88 @@
+#![cfg(feature = "log")]
+
+mod common;
+
+use axvma::*;
+use common::{TestFile, range};
+use page_table_multiarch::PageSize;
+use std::sync::Mutex;
+
+static EVENTS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Logger keeping the messages of the crate's events
+struct Capture;
+
+impl log::Log for Capture {
+    fn enabled(&self, _metadata: &log::Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record) {
+        if record.target() == "axvma" {
+            EVENTS.lock().unwrap().push(record.args().to_string());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Events logged since the last call
+fn take_events() -> Vec<String> {
+    std::mem::take(&mut EVENTS.lock().unwrap())
+}
+
+#[test]
+fn operations_log_one_greppable_event_each() {
+    log::set_logger(&Capture).unwrap();
+    log::set_max_level(log::LevelFilter::Trace);
+    let mut manager = VmaManager::new();
+    manager
+        .add_region(MmapRegion::new(
+            range(0x30000, 0x4000),
+            TestFile::new(0x10000),
+            0x1000,
+            PageSize::Size4K,
+        ))
+        .unwrap();
+    assert_eq!(take_events(), vec!["add 0x30000-0x34000 id=0"]);
+
+    manager
+        .set_name(range(0x30000, 0x4000), Some("heap".into()))
+        .unwrap();
+    take_events();
+    manager
+        .handle_fault(0x31234.into(), AccessFlags::READ)
+        .unwrap();
+    let events = take_events();
+    let populates: Vec<_> = events
+        .iter()
+        .filter(|event| event.starts_with("populate "))
+        .collect();
+    assert_eq!(populates.len(), 1, "{events:?}");
+    let populate = populates[0];
+    assert!(populate.contains("0x30000-0x34000"), "{populate}");
+    assert!(populate.contains("name=heap"), "{populate}");
+    assert!(
+        populate.contains("vaddr=0x31000 offset=0x2000 read=4096"),
+        "{populate}"
+    );
+
+    manager.remove_overlapped(range(0x31000, 0x1000)).unwrap();
+    manager
+        .protect(range(0x33000, 0x1000), MmapProt::READ)
+        .unwrap();
+    let events = take_events();
+    assert!(
+        events.iter().any(|event| event.starts_with(
+            "remove range=0x31000-0x32000 removed=[0x31000-0x32000] \
+             retained=[0x30000-0x31000,0x32000-0x34000]"
+        )),
+        "{events:?}"
+    );
+    assert!(events.iter().any(|event| event.starts_with("split ")));
+    assert!(
+        events
+            .iter()
+            .any(|event| event.starts_with("protect range=0x33000-0x34000"))
+    );
+}
@@ -0,0 +1,88 @@
+mod common;
+
+use axvma::*;
+use common::{TestFile, range};
+use page_table_multiarch::PageSize;
+
+fn manager() -> VmaManager<TestFile> {
+    let mut manager = VmaManager::new();
+    for start in [0x10000, 0x20000] {
+        manager
+            .add_region(MmapRegion::new(
+                range(start, 0x2000),
+                TestFile::new(0x8000),
+                0,
+                PageSize::Size4K,
+            ))
+            .unwrap();
+    }
+    manager
+}
+
+fn cached(manager: &VmaManager<TestFile>, vaddr: usize) -> Option<(usize, usize)> {
+    manager
+        .find_region_cached(vaddr.into())
+        .map(|region| (region.range.start.as_usize(), region.range.end.as_usize()))
+}
+
+#[test]
+fn cached_lookups_match_the_search() {
+    let manager = manager();
+    assert_eq!(cached(&manager, 0x10000), Some((0x10000, 0x12000)));
+    assert_eq!(cached(&manager, 0x11fff), Some((0x10000, 0x12000)));
+    assert_eq!(cached(&manager, 0x12000), None);
+    assert_eq!(cached(&manager, 0x21000), Some((0x20000, 0x22000)));
+    assert_eq!(cached(&manager, 0x10000), Some((0x10000, 0x12000)));
+}
+
+#[test]
+fn unmapping_the_cached_region_misses() {
+    let mut manager = manager();
+    assert!(cached(&manager, 0x21000).is_some());
+    manager.munmap(0x20000.into(), 0x2000).unwrap();
+    assert_eq!(cached(&manager, 0x21000), None);
+
+    assert!(cached(&manager, 0x11000).is_some());
+    manager.clear();
+    assert_eq!(cached(&manager, 0x11000), None);
+}
+
+#[test]
+fn stale_cache_never_returns_a_removed_region() {
+    let mut manager = manager();
+    assert!(cached(&manager, 0x11000).is_some());
+    manager.munmap(0x11000.into(), 0x1000).unwrap();
+    assert_eq!(cached(&manager, 0x11000), None);
+    assert_eq!(cached(&manager, 0x10000), Some((0x10000, 0x11000)));
+
+    // A new region in the freed space, ending where the old one did
+    manager
+        .add_region(MmapRegion::new_anonymous(
+            range(0x11000, 0x1000),
+            PageSize::Size4K,
+        ))
+        .unwrap();
+    assert_eq!(cached(&manager, 0x11000), Some((0x11000, 0x12000)));
+
+    // Grown in place by remap, which changes its end
+    manager
+        .remap(0x11000.into(), 0x1000, 0x3000, RemapFlags::empty())
+        .unwrap();
+    assert_eq!(cached(&manager, 0x11000), Some((0x11000, 0x14000)));
+
+    // Shrunk in place through iter_mut
+    assert!(cached(&manager, 0x10800).is_some());
+    manager.iter_mut().next().unwrap().range = range(0x10000, 0x800);
+    assert_eq!(cached(&manager, 0x10800), None);
+}
+
+#[cfg(feature = "metrics")]
+#[test]
+fn hits_and_misses_are_counted() {
+    let manager = manager();
+    for vaddr in [0x10000, 0x11000, 0x11800, 0x20000, 0x30000] {
+        manager.find_region_cached(vaddr.into());
+    }
+    let metrics = manager.metrics();
+    assert_eq!((metrics.lookup_hits, metrics.lookup_misses), (2, 3));
+}
@@ -0,0 +1,355 @@
+mod common;
+
+use axvma::*;
+use common::{TestFile, range};
+use memory_addr::VirtAddr;
+use page_table_multiarch::PageSize;
+
+fn anon(start: usize, size: usize) -> MmapRegion<TestFile> {
+    MmapRegion::new_anonymous(range(start, size), PageSize::Size4K)
+}
+
+fn ranges(manager: &VmaManager<TestFile>) -> Vec<(usize, usize)> {
+    manager
+        .iter()
+        .map(|region| (region.range.start.as_usize(), region.range.end.as_usize()))
+        .collect()
+}
+
+#[test]
+fn lookups_stay_correct_across_thousands_of_regions() {
+    const COUNT: usize = 3000;
+    let start_of = |i: usize| 0x10_0000 + i * 0x3000;
+    let mut manager = VmaManager::new();
+    // Insert out of order so that the map has to keep itself sorted
+    for i in (0..COUNT).map(|i| i * 7919 % COUNT) {
+        manager.add_region(anon(start_of(i), 0x2000)).unwrap();
+    }
+    assert_eq!(manager.len(), COUNT);
+    let starts: Vec<_> = manager.iter().map(|region| region.range.start).collect();
+    assert!(starts.windows(2).all(|pair| pair[0] < pair[1]));
+
+    for i in [0, 1, COUNT / 2, COUNT - 2, COUNT - 1] {
+        let start = start_of(i);
+        let first = manager.find_region(start.into()).unwrap();
+        assert_eq!(first.range, range(start, 0x2000));
+        let last = manager.find_region((start + 0x1fff).into()).unwrap();
+        assert_eq!(last.range, range(start, 0x2000));
+        assert!(manager.find_region((start + 0x2000).into()).is_none());
+        assert!(manager.find_region((start + 0x2fff).into()).is_none());
+    }
+    assert!(manager.find_region(VirtAddr::from(0x10_0000 - 1)).is_none());
+    assert!(manager.find_region(start_of(COUNT).into()).is_none());
+}
+
+#[test]
+fn removal_keeps_split_parts_in_order() {
+    let mut manager = VmaManager::new();
+    manager.add_region(anon(0x30000, 0x1000)).unwrap();
+    manager.add_region(anon(0x10000, 0x3000)).unwrap();
+    manager.add_region(anon(0x20000, 0x1000)).unwrap();
+
+    manager.remove_overlapped(range(0x11000, 0x1000)).unwrap();
+    assert_eq!(
+        ranges(&manager),
+        vec![
+            (0x10000, 0x11000),
+            (0x12000, 0x13000),
+            (0x20000, 0x21000),
+            (0x30000, 0x31000),
+        ]
+    );
+    assert_eq!(
+        manager.find_region(0x12000.into()).unwrap().range,
+        range(0x12000, 0x1000)
+    );
+    assert!(manager.find_region(0x11000.into()).is_none());
+}
+
+#[test]
+fn add_region_rejects_overlaps() {
+    let mut manager = VmaManager::new();
+    manager.add_region(anon(0x10000, 0x4000)).unwrap();
+
+    for (start, size) in [
+        (0xf000, 0x2000),
+        (0x13000, 0x2000),
+        (0x11000, 0x1000),
+        (0xf000, 0x6000),
+    ] {
+        assert_eq!(
+            manager.add_region(anon(start, size)),
+            Err(VmaError::Overlap(range(start, size)))
+        );
+    }
+    assert_eq!(ranges(&manager), vec![(0x10000, 0x14000)]);
+
+    manager.add_region(anon(0xf000, 0x1000)).unwrap();
+    manager.add_region(anon(0x14000, 0x1000)).unwrap();
+    assert_eq!(manager.len(), 3);
+
+    assert_eq!(
+        manager.add_region(anon(0x20000, 0)),
+        Err(VmaError::InvalidArgument)
+    );
+}
+
+#[test]
+fn add_region_replace_displaces_overlapped_parts() {
+    let mut manager = VmaManager::new();
+    manager.add_region(anon(0x10000, 0x4000)).unwrap();
+
+    // Front
+    let removed = manager.add_region_replace(anon(0xf000, 0x2000)).unwrap();
+    assert_eq!(removed.regions.len(), 1);
+    assert_eq!(removed.regions[0].range, range(0x10000, 0x1000));
+    assert_eq!(
+        ranges(&manager),
+        vec![(0xf000, 0x11000), (0x11000, 0x14000)]
+    );
+
+    // Back
+    let removed = manager.add_region_replace(anon(0x13000, 0x2000)).unwrap();
+    assert_eq!(removed.regions[0].range, range(0x13000, 0x1000));
+    assert_eq!(
+        ranges(&manager),
+        vec![(0xf000, 0x11000), (0x11000, 0x13000), (0x13000, 0x15000)]
+    );
+
+    // Inside
+    manager.add_region(anon(0x20000, 0x3000)).unwrap();
+    manager
+        .find_region(0x21000.into())
+        .unwrap()
+        .get_buf(0x21000.into())
+        .unwrap();
+    let removed = manager.add_region_replace(anon(0x21000, 0x1000)).unwrap();
+    assert_eq!(removed.regions[0].range, range(0x21000, 0x1000));
+    assert_eq!(
+        removed.pages,
+        vec![(VirtAddr::from(0x21000), PageSize::Size4K)]
+    );
+    assert_eq!(
+        ranges(&manager)[3..],
+        [(0x20000, 0x21000), (0x21000, 0x22000), (0x22000, 0x23000)]
+    );
+    assert!(
+        !manager
+            .find_region(0x21000.into())
+            .unwrap()
+            .is_populated(0x21000.into())
+    );
+}
+
+#[test]
+fn coalesce_merges_contiguous_mappings_of_one_file() {
+    let file = TestFile::new(0x10000);
+    let mut manager = VmaManager::new();
+    for i in 0..3 {
+        let start = 0x10000 + i * 0x1000;
+        let region = MmapRegion::new(
+            range(start, 0x1000),
+            file.clone(),
+            (i * 0x1000) as i64,
+            PageSize::Size4K,
+        );
+        if i != 1 {
+            region.get_buf(start.into()).unwrap();
+        }
+        manager.add_region(region).unwrap();
+    }
+    // Discontiguous offset, then another file
+    manager
+        .add_region(MmapRegion::new(
+            range(0x13000, 0x1000),
+            file,
+            0,
+            PageSize::Size4K,
+        ))
+        .unwrap();
+    manager
+        .add_region(MmapRegion::new(
+            range(0x14000, 0x1000),
+            TestFile::new(0x10000),
+            0x4000,
+            PageSize::Size4K,
+        ))
+        .unwrap();
+
+    manager.coalesce();
+    assert_eq!(
+        ranges(&manager),
+        vec![(0x10000, 0x13000), (0x13000, 0x14000), (0x14000, 0x15000)]
+    );
+    let merged = manager.find_region(0x11000.into()).unwrap();
+    assert_eq!(
+        merged.populated_iter().collect::<Vec<_>>(),
+        vec![VirtAddr::from(0x10000), VirtAddr::from(0x12000)]
+    );
+    assert_eq!(merged.file_offset_of(0x12000.into()), Ok(0x2000));
+}
+
+#[test]
+fn iteration_and_range_queries_walk_regions_in_order() {
+    let mut manager = VmaManager::new();
+    assert!(manager.is_empty());
+    for (start, size) in [(0x30000, 0x2000), (0x10000, 0x1000), (0x20000, 0x3000)] {
+        manager.add_region(anon(start, size)).unwrap();
+    }
+    assert_eq!(manager.len(), 3);
+    assert!(!manager.is_empty());
+
+    let mapped: usize = manager.iter().map(|region| region.range.size()).sum();
+    assert_eq!(mapped, 0x6000);
+
+    let overlapping = |start, size| -> Vec<usize> {
+        manager
+            .regions_in(range(start, size))
+            .map(|region| region.range.start.as_usize())
+            .collect()
+    };
+    assert_eq!(overlapping(0x10800, 0x10000), vec![0x10000, 0x20000]);
+    assert_eq!(overlapping(0x22fff, 0x10001), vec![0x20000, 0x30000]);
+    assert_eq!(overlapping(0x11000, 0xf000), Vec::<usize>::new());
+    assert_eq!(overlapping(0, usize::MAX), vec![0x10000, 0x20000, 0x30000]);
+
+    for region in manager.iter_mut() {
+        region.prot = MmapProt::READ;
+    }
+    assert!(manager.iter().all(|region| region.prot == MmapProt::READ));
+}
+
+#[test]
+fn munmap_across_adjacent_regions_rekeys_the_split_ends() {
+    let mut manager = VmaManager::new();
+    for i in 0..5 {
+        manager
+            .add_region(anon(0x10000 + i * 0x2000, 0x2000))
+            .unwrap();
+    }
+    assert_eq!(
+        manager.add_region(anon(0x11000, 0x1000)),
+        Err(VmaError::Overlap(range(0x11000, 0x1000)))
+    );
+    // Adjacent regions: the end of one is the start of the next
+    assert_eq!(
+        manager.find_region(0x12000.into()).unwrap().range,
+        range(0x12000, 0x2000)
+    );
+    assert_eq!(
+        manager.find_region(0x11fff.into()).unwrap().range,
+        range(0x10000, 0x2000)
+    );
+
+    manager.munmap(0x11000.into(), 0x6000).unwrap();
+    assert_eq!(
+        ranges(&manager),
+        vec![(0x10000, 0x11000), (0x17000, 0x18000), (0x18000, 0x1a000)]
+    );
+    assert!(manager.find_region(0x12000.into()).is_none());
+    assert!(manager.find_region(0x16fff.into()).is_none());
+    assert_eq!(
+        manager.find_region(0x17800.into()).unwrap().range,
+        range(0x17000, 0x1000)
+    );
+    assert_eq!(
+        manager.find_region(0x10fff.into()).unwrap().range,
+        range(0x10000, 0x1000)
+    );
+    assert_eq!(manager.regions_in(range(0x10800, 0x7800)).count(), 2);
+
+    // The freed hole and the space after the split fronts take new regions
+    manager.add_region(anon(0x11000, 0x6000)).unwrap();
+    assert_eq!(
+        manager.find_region(0x16fff.into()).unwrap().range,
+        range(0x11000, 0x6000)
+    );
+    assert_eq!(manager.len(), 4);
+}
+
+#[test]
+fn lookup_reports_the_regions_around_a_miss() {
+    let mut manager = VmaManager::new();
+    manager.add_region(anon(0x10000, 0x2000)).unwrap();
+    manager.add_region(anon(0x20000, 0x1000)).unwrap();
+    let start_of = |region: Option<&MmapRegion<TestFile>>| region.map(|r| r.range.start.as_usize());
+
+    // Below all regions
+    let lookup = manager.lookup(0x8000.into());
+    let Lookup::Miss { prev, next } = lookup else {
+        panic!("0x8000 is unmapped");
+    };
+    assert_eq!((start_of(prev), start_of(next)), (None, Some(0x10000)));
+    assert_eq!(lookup.hole(), Some(range(0, 0x10000)));
+
+    // Between two regions
+    let lookup = manager.lookup(0x18000.into());
+    let Lookup::Miss { prev, next } = lookup else {
+        panic!("0x18000 is unmapped");
+    };
+    assert_eq!(
+        (start_of(prev), start_of(next)),
+        (Some(0x10000), Some(0x20000))
+    );
+    assert_eq!(lookup.hole(), Some(range(0x12000, 0xe000)));
+
+    // Above all regions
+    let Lookup::Miss { prev, next } = manager.lookup(0x30000.into()) else {
+        panic!("0x30000 is unmapped");
+    };
+    assert_eq!((start_of(prev), start_of(next)), (Some(0x20000), None));
+
+    // The end of a region is exclusive
+    let lookup = manager.lookup(0x12000.into());
+    let Lookup::Miss { prev, next } = lookup else {
+        panic!("0x12000 is past the first region");
+    };
+    assert_eq!(
+        (start_of(prev), start_of(next)),
+        (Some(0x10000), Some(0x20000))
+    );
+    assert_eq!(lookup.hole().unwrap().start, VirtAddr::from(0x12000));
+
+    for vaddr in [0x10000, 0x11fff, 0x20000] {
+        let lookup = manager.lookup(vaddr.into());
+        assert!(matches!(lookup, Lookup::Hit(_)));
+        assert!(lookup.hit().unwrap().range.contains(vaddr.into()));
+        assert_eq!(lookup.hole(), None);
+    }
+}
+
+#[test]
+fn remove_overlapped_without_overlap_moves_no_region() {
+    let file = TestFile::new(0x10000);
+    let mut manager = VmaManager::new();
+    for i in 0..4 {
+        manager
+            .add_region(MmapRegion::new(
+                range(0x10000 + i * 0x2000, 0x1000),
+                file.clone(),
+                0,
+                PageSize::Size4K,
+            ))
+            .unwrap();
+    }
+    let addresses = |manager: &VmaManager<TestFile>| -> Vec<*const MmapRegion<TestFile>> {
+        manager.iter().map(|region| region as *const _).collect()
+    };
+    let before = addresses(&manager);
+    manager.find_region_cached(0x12000.into()).unwrap();
+
+    let removed = manager.remove_overlapped(range(0x11000, 0x1000)).unwrap();
+    assert!(removed.regions.is_empty() && removed.pages.is_empty());
+    assert_eq!(addresses(&manager), before);
+    // Nor is the lookup cache dropped
+    manager.find_region_cached(0x12000.into()).unwrap();
+    #[cfg(feature = "metrics")]
+    assert_eq!(manager.metrics().lookup_hits, 1);
+
+    let removed = manager.remove_overlapped(range(0x12000, 0x1000)).unwrap();
+    assert_eq!(removed.regions.len(), 1);
+    assert_eq!(
+        ranges(&manager),
+        vec![(0x10000, 0x11000), (0x14000, 0x15000), (0x16000, 0x17000)]
+    );
+}
@@ -0,0 +1,43 @@
+mod common;
+
+use axvma::*;
+use common::{TestFile, range};
+use page_table_multiarch::PageSize;
+
+#[test]
+fn maps_renders_one_line_per_region_in_address_order() {
+    let file = TestFile::new(0x10000);
+    let mut manager = VmaManager::new();
+    // Added out of order
+    let mut shared = MmapRegion::new(range(0x30000, 0x1000), file.clone(), 0, PageSize::Size4K);
+    shared.flags = MmapFlags::SHARED;
+    shared.prot = MmapProt::READ;
+    manager.add_region(shared).unwrap();
+    let mut anon = MmapRegion::new_anonymous(range(0x10000, 0x3000), PageSize::Size4K);
+    anon.prot = MmapProt::READ | MmapProt::WRITE;
+    manager.add_region(anon).unwrap();
+    let mut text = MmapRegion::new(range(0x20000, 0x2000), file, 0x3000, PageSize::Size4K);
+    text.prot = MmapProt::READ | MmapProt::EXEC;
+    text.name = Some("/bin/app".into());
+    manager.add_region(text).unwrap();
+    manager
+        .add_region(MmapRegion::new_reserved(
+            range(0x40000, 0x1000),
+            PageSize::Size4K,
+        ))
+        .unwrap();
+
+    for vaddr in [0x10000, 0x12000, 0x21000] {
+        manager
+            .handle_fault(vaddr.into(), AccessFlags::READ)
+            .unwrap();
+    }
+    assert_eq!(
+        manager.maps().to_string(),
+        "00010000-00013000 rw-p 00000000 2 [anon]\n\
+         00020000-00022000 r-xp 00003000 1 /bin/app\n\
+         00030000-00031000 r--s 00000000 0\n\
+         00040000-00041000 ---p 00000000 0 [anon]\n"
+    );
+    assert_eq!(VmaManager::<TestFile>::new().maps().to_string(), "");
+}
@@ -0,0 +1,57 @@
+#![cfg(feature = "mem-backend")]
+
+use axvma::*;
+use memory_addr::{VirtAddr, VirtAddrRange};
+use page_table_multiarch::PageSize;
+
+fn range(start: usize, size: usize) -> VirtAddrRange {
+    VirtAddrRange::from_start_size(VirtAddr::from(start), size)
+}
+
+#[test]
+fn slice_file_reads_short_at_eof() {
+    let file = SliceFile::new(vec![1; 100]);
+    let mut buf = [9; 64];
+    assert_eq!(file.read_at(&mut buf, 0), Ok(64));
+    buf.fill(9);
+    assert_eq!(file.read_at(&mut buf, 90), Ok(10));
+    assert_eq!(buf[..10], [1; 10]);
+    assert_eq!(buf[10..], [9; 54]);
+    assert_eq!(file.read_at(&mut buf, 100), Ok(0));
+    assert_eq!(file.read_at(&mut buf, u64::MAX), Ok(0));
+    assert_eq!(file.len(), Ok(100));
+    assert!(file.same_file(&file.clone()));
+    assert!(!file.same_file(&SliceFile::new(vec![1; 100])));
+}
+
+#[test]
+fn slice_file_backs_a_region() {
+    let file = SliceFile::new(vec![1; 100]);
+    let region = MmapRegion::new(range(0x1000, 0x1000), file, 0, PageSize::Size4K);
+    let page = region.get_buf(0x1000.into()).unwrap();
+    assert_eq!(page.len(), 0x1000);
+    assert!(page[..100].iter().all(|&byte| byte == 1));
+    assert!(page[100..].iter().all(|&byte| byte == 0));
+}
+
+#[test]
+fn mem_file_writes_extend_the_file() {
+    let file = MemFile::new(vec![0; 10]);
+    assert_eq!(file.write_at(&[5; 4], 12), Ok(4));
+    assert_eq!(file.to_vec(), [&[0; 12][..], &[5; 4]].concat());
+    assert_eq!(file.write_at(&[7; 2], 0), Ok(2));
+    assert_eq!(file.len(), Ok(16));
+    let mut buf = [0; 4];
+    assert_eq!(file.read_at(&mut buf, 0), Ok(4));
+    assert_eq!(buf, [7, 7, 0, 0]);
+}
+
+#[test]
+fn mem_file_takes_region_write_back() {
+    let file = MemFile::new(vec![3; 0x2000]);
+    let region = MmapRegion::new(range(0x10000, 0x2000), file.clone(), 0, PageSize::Size4K);
+    assert_eq!(region.get_buf(0x11000.into()).unwrap()[0], 3);
+    assert_eq!(region.flush_page(0x11000.into(), &[4; 0x1000]), Ok(0x1000));
+    assert_eq!(file.to_vec()[..0x1000], [3; 0x1000]);
+    assert_eq!(file.to_vec()[0x1000..], [4; 0x1000]);
+}
@@ -0,0 +1,74 @@
+mod common;
+
+use axvma::*;
+use common::{TestFile, range};
+use page_table_multiarch::PageSize;
+
+/// Manager with one 4-page region over a 3.5-page file, with three pages
+/// faulted in
+fn faulted() -> VmaManager<TestFile> {
+    let mut manager = VmaManager::new();
+    manager
+        .add_region(MmapRegion::new(
+            range(0x10000, 0x4000),
+            TestFile::new(0x3800),
+            0,
+            PageSize::Size4K,
+        ))
+        .unwrap();
+    for vaddr in [0x10000, 0x11000, 0x13000] {
+        manager
+            .handle_fault(vaddr.into(), AccessFlags::READ)
+            .unwrap();
+    }
+    manager
+}
+
+#[cfg(feature = "metrics")]
+#[test]
+fn faults_loads_and_evictions_are_counted() {
+    let mut manager = faulted();
+    let region = manager.find_region(0x10000.into()).unwrap();
+    assert_eq!(
+        region.metrics(),
+        VmaMetrics {
+            faults: 3,
+            bytes_loaded: 0x2800,
+            ..Default::default()
+        }
+    );
+
+    assert_eq!(manager.reclaim(2).len(), 2);
+    assert_eq!(manager.metrics().evictions, 2);
+    // Split parts start from zero, while the manager keeps the old counts
+    manager.munmap(0x12000.into(), 0x1000).unwrap();
+    assert_eq!(
+        manager.find_region(0x10000.into()).unwrap().metrics(),
+        VmaMetrics::default()
+    );
+    manager
+        .handle_fault(0x10000.into(), AccessFlags::READ)
+        .unwrap();
+    assert_eq!(
+        manager.metrics(),
+        VmaMetrics {
+            faults: 4,
+            bytes_loaded: 0x3800,
+            evictions: 2,
+            lookup_hits: 2,
+            lookup_misses: 2,
+        }
+    );
+}
+
+#[cfg(not(feature = "metrics"))]
+#[test]
+fn counters_stay_zero_without_the_feature() {
+    let manager = faulted();
+    manager.reclaim(2);
+    assert_eq!(
+        manager.find_region(0x10000.into()).unwrap().metrics(),
+        VmaMetrics::default()
+    );
+    assert_eq!(manager.metrics(), VmaMetrics::default());
+}
@@ -0,0 +1,102 @@
+mod common;
+
+use axerrno::LinuxError;
+use axvma::*;
+use common::{TestFile, range};
+use memory_addr::VirtAddr;
+use page_table_multiarch::PageSize;
+
+fn manager() -> VmaManager<TestFile> {
+    let mut manager = VmaManager::new();
+    manager
+        .add_region(MmapRegion::new(
+            range(0x10000, 0x4000),
+            TestFile::new(0x8000),
+            0,
+            PageSize::Size4K,
+        ))
+        .unwrap();
+    manager
+}
+
+#[test]
+fn locked_pages_survive_reclaim_until_unlocked() {
+    let mut manager = manager();
+    let loaded = manager.lock_range(range(0x11000, 0x2000)).unwrap();
+    assert_eq!(
+        loaded.iter().map(|(vaddr, _)| *vaddr).collect::<Vec<_>>(),
+        vec![VirtAddr::from(0x11000), VirtAddr::from(0x12000)]
+    );
+    assert_eq!(manager.locked_bytes(), 0x2000);
+    assert_eq!(manager.len(), 3);
+    // Locking again loads nothing
+    assert!(
+        manager
+            .lock_range(range(0x11000, 0x1000))
+            .unwrap()
+            .is_empty()
+    );
+
+    manager
+        .handle_fault(0x10000.into(), AccessFlags::READ)
+        .unwrap();
+    assert_eq!(
+        manager.reclaim(10),
+        vec![(VirtAddr::from(0x10000), PageSize::Size4K)]
+    );
+    let locked = manager.find_region(0x11000.into()).unwrap();
+    assert!(!locked.evict(0x11000.into()));
+    assert!(locked.is_populated(0x11000.into()));
+    assert_eq!(
+        manager
+            .advise(range(0x11000, 0x1000), Advice::DontNeed)
+            .err(),
+        Some(VmaError::InvalidArgument)
+    );
+
+    manager.unlock_range(range(0x10000, 0x4000)).unwrap();
+    assert_eq!(manager.locked_bytes(), 0);
+    assert_eq!(
+        manager.reclaim(10),
+        vec![
+            (VirtAddr::from(0x11000), PageSize::Size4K),
+            (VirtAddr::from(0x12000), PageSize::Size4K),
+        ]
+    );
+    manager.coalesce();
+    assert_eq!(manager.len(), 1);
+}
+
+#[test]
+fn locking_past_the_limit_fails_like_mlock() {
+    let mut manager = manager();
+    manager.set_memlock_limit(Some(0x2000));
+    manager.lock_range(range(0x11000, 0x2000)).unwrap();
+    // Already locked parts do not count twice
+    manager.lock_range(range(0x11000, 0x1000)).unwrap();
+
+    let err = manager.lock_range(range(0x10000, 0x1000)).unwrap_err();
+    assert_eq!(err.error, VmaError::LockLimit(0x2000));
+    assert!(err.loaded.is_empty());
+    assert_eq!(LinuxError::from(err.error), LinuxError::ENOMEM);
+    assert!(
+        !manager
+            .find_region(0x10000.into())
+            .unwrap()
+            .is_populated(0x10000.into())
+    );
+
+    // Without any allowance the failure is a permission error
+    manager.set_memlock_limit(Some(0));
+    let err = manager.lock_range(range(0x13000, 0x1000)).unwrap_err();
+    assert_eq!(LinuxError::from(err.error), LinuxError::EPERM);
+    assert_eq!(manager.locked_bytes(), 0x2000);
+
+    assert_eq!(
+        manager
+            .lock_range(range(0x20000, 0x1000))
+            .unwrap_err()
+            .error,
+        VmaError::Hole(range(0x20000, 0x1000))
+    );
+}
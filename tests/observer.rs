@@ -0,0 +1,164 @@
+mod common;
+
+use axvma::*;
+use common::{TestFile, range};
+use memory_addr::{VirtAddr, VirtAddrRange};
+use page_table_multiarch::PageSize;
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, PartialEq, Eq)]
+enum Event {
+    Populate(VirtAddrRange, usize),
+    Evict(VirtAddrRange, usize),
+    Split(VirtAddrRange, Vec<VirtAddrRange>),
+    Add(VirtAddrRange),
+    Remove(VirtAddrRange),
+}
+
+/// Observer recording the events it is notified of
+#[derive(Default)]
+struct Recorder(Mutex<Vec<Event>>);
+
+impl Recorder {
+    fn take(&self) -> Vec<Event> {
+        std::mem::take(&mut self.0.lock().unwrap())
+    }
+}
+
+impl VmaObserver for Recorder {
+    fn on_populate(&self, region: VirtAddrRange, vaddr: VirtAddr, _size: PageSize) {
+        self.0
+            .lock()
+            .unwrap()
+            .push(Event::Populate(region, vaddr.as_usize()));
+    }
+
+    fn on_evict(&self, region: VirtAddrRange, vaddr: VirtAddr, _size: PageSize) {
+        self.0
+            .lock()
+            .unwrap()
+            .push(Event::Evict(region, vaddr.as_usize()));
+    }
+
+    fn on_split(&self, original: VirtAddrRange, parts: &[VirtAddrRange]) {
+        self.0
+            .lock()
+            .unwrap()
+            .push(Event::Split(original, parts.to_vec()));
+    }
+
+    fn on_add(&self, range: VirtAddrRange) {
+        self.0.lock().unwrap().push(Event::Add(range));
+    }
+
+    fn on_remove(&self, range: VirtAddrRange) {
+        self.0.lock().unwrap().push(Event::Remove(range));
+    }
+}
+
+#[test]
+fn map_fault_and_partial_unmap_emit_events_in_order() {
+    let recorder = Arc::new(Recorder::default());
+    let mut manager = VmaManager::new();
+    manager.set_observer(Some(recorder.clone()));
+
+    let start = manager
+        .mmap(
+            0x10000.into(),
+            0x3000,
+            TestFile::new(0x10000),
+            0,
+            PageSize::Size4K,
+        )
+        .unwrap()
+        .as_usize();
+    manager
+        .handle_fault((start + 0x1000).into(), AccessFlags::READ)
+        .unwrap();
+    manager.munmap((start + 0x1000).into(), 0x1000).unwrap();
+
+    use Event::*;
+    let whole = range(start, 0x3000);
+    let (before, middle, after) = (
+        range(start, 0x1000),
+        range(start + 0x1000, 0x1000),
+        range(start + 0x2000, 0x1000),
+    );
+    assert_eq!(
+        recorder.take(),
+        vec![
+            Add(whole),
+            Populate(whole, start + 0x1000),
+            Split(whole, vec![before, middle, after]),
+            Remove(middle),
+        ]
+    );
+
+    manager.advise(before, Advice::WillNeed).unwrap();
+    manager.advise(before, Advice::DontNeed).unwrap();
+    manager.clear();
+    assert_eq!(
+        recorder.take(),
+        vec![
+            Populate(before, start),
+            Evict(before, start),
+            Remove(before),
+            Remove(after),
+        ]
+    );
+}
+
+#[test]
+fn removing_the_observer_stops_events() {
+    let recorder = Arc::new(Recorder::default());
+    let mut manager: VmaManager<TestFile> = VmaManager::new();
+    manager.set_observer(Some(recorder.clone()));
+    manager
+        .add_region(MmapRegion::new_anonymous(
+            range(0x10000, 0x1000),
+            PageSize::Size4K,
+        ))
+        .unwrap();
+    manager.set_observer(None);
+    manager
+        .handle_fault(0x10000.into(), AccessFlags::READ)
+        .unwrap();
+    manager.clear();
+    assert_eq!(recorder.take(), vec![Event::Add(range(0x10000, 0x1000))]);
+}
+
+#[test]
+fn commit_reports_the_split_reservation_and_the_new_mapping() {
+    let recorder = Arc::new(Recorder::default());
+    let mut manager: VmaManager<TestFile> = VmaManager::new();
+    manager
+        .add_region(MmapRegion::new_reserved(
+            range(0x10000, 0x4000),
+            PageSize::Size4K,
+        ))
+        .unwrap();
+    manager.set_observer(Some(recorder.clone()));
+
+    manager
+        .commit(
+            range(0x11000, 0x1000),
+            RegionBacking::Anonymous,
+            MmapProt::all(),
+        )
+        .unwrap();
+    use Event::*;
+    assert_eq!(
+        recorder.take(),
+        vec![
+            Split(
+                range(0x10000, 0x4000),
+                vec![
+                    range(0x10000, 0x1000),
+                    range(0x11000, 0x1000),
+                    range(0x12000, 0x2000),
+                ]
+            ),
+            Add(range(0x11000, 0x1000)),
+        ]
+    );
+}
@@ -0,0 +1,122 @@
+mod common;
+
+use axerrno::LinuxResult;
+use axvma::*;
+use common::{TestFile, pattern, range};
+use page_table_multiarch::PageSize;
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+};
+
+/// File counting the reads made from it
+#[derive(Clone)]
+struct CountingFile {
+    inner: TestFile,
+    reads: Arc<AtomicUsize>,
+}
+
+impl CountingFile {
+    fn new(len: usize) -> Self {
+        Self {
+            inner: TestFile::new(len),
+            reads: Default::default(),
+        }
+    }
+
+    fn reads(&self) -> usize {
+        self.reads.load(Ordering::SeqCst)
+    }
+}
+
+impl VmFile for CountingFile {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> LinuxResult<usize> {
+        self.reads.fetch_add(1, Ordering::SeqCst);
+        self.inner.read_at(buf, offset)
+    }
+
+    fn write_at(&self, buf: &[u8], offset: u64) -> LinuxResult<usize> {
+        self.inner.write_at(buf, offset)
+    }
+
+    fn len(&self) -> LinuxResult<u64> {
+        self.inner.len()
+    }
+}
+
+#[test]
+fn regions_over_one_cached_file_share_its_pages() {
+    let file = CountingFile::new(0x3000);
+    let cached = CachedFile::new(file.clone(), 2);
+    let mut manager = VmaManager::new();
+    for start in [0x10000, 0x20000] {
+        manager
+            .add_region(MmapRegion::new(
+                range(start, 0x2000),
+                cached.clone(),
+                0x1000,
+                PageSize::Size4K,
+            ))
+            .unwrap();
+    }
+
+    manager
+        .handle_fault(0x10000.into(), AccessFlags::READ)
+        .unwrap();
+    manager
+        .handle_fault(0x20000.into(), AccessFlags::READ)
+        .unwrap();
+    assert_eq!(file.reads(), 1);
+
+    let page = manager
+        .find_region(0x21000.into())
+        .unwrap()
+        .get_buf(0x21000.into())
+        .unwrap();
+    assert_eq!(page[0], pattern(0x2000));
+    assert_eq!(file.reads(), 2);
+    assert_eq!(cached.cached_pages(), 2);
+    #[cfg(feature = "metrics")]
+    assert_eq!(cached.metrics(), CacheMetrics { hits: 1, misses: 2 });
+    #[cfg(not(feature = "metrics"))]
+    assert_eq!(cached.metrics(), CacheMetrics::default());
+}
+
+#[test]
+fn the_oldest_page_is_evicted_at_capacity() {
+    let file = CountingFile::new(0x3000);
+    let cached = CachedFile::new(file.clone(), 2);
+    let mut buf = [0; 0x10];
+    for offset in [0, 0x1000, 0x2000] {
+        cached.read_at(&mut buf, offset).unwrap();
+    }
+    assert_eq!(file.reads(), 3);
+    assert_eq!(cached.cached_pages(), 2);
+
+    // The page at 0x1000 is still cached, the one at 0 was evicted first
+    cached.read_at(&mut buf, 0x1000).unwrap();
+    assert_eq!(file.reads(), 3);
+    cached.read_at(&mut buf, 0).unwrap();
+    assert_eq!(file.reads(), 4);
+
+    cached.invalidate();
+    assert_eq!(cached.cached_pages(), 0);
+}
+
+#[test]
+fn reads_span_pages_and_writes_drop_stale_pages() {
+    let file = CountingFile::new(0x3000);
+    let cached = CachedFile::new(file, 4);
+    let mut buf = vec![0; 0x1800];
+    assert_eq!(cached.read_at(&mut buf, 0x1800), Ok(0x1800));
+    assert!(
+        buf.iter()
+            .enumerate()
+            .all(|(i, &byte)| byte == pattern(0x1800 + i))
+    );
+
+    cached.write_at(&[7; 4], 0x2000).unwrap();
+    let mut small = [0; 4];
+    cached.read_at(&mut small, 0x2000).unwrap();
+    assert_eq!(small, [7; 4]);
+}
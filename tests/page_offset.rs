@@ -0,0 +1,88 @@
+mod common;
+
+use axvma::*;
+use common::{TestFile, range};
+use page_table_multiarch::PageSize;
+
+/// Region over 0x10000..0x14000 whose second and third pages map swapped
+/// file pages
+fn crossed(file: &TestFile) -> MmapRegion<TestFile> {
+    let mut region = MmapRegion::new(range(0x10000, 0x4000), file.clone(), 0, PageSize::Size4K);
+    region.set_page_offset(0x11000.into(), 0x2000).unwrap();
+    region.set_page_offset(0x12000.into(), 0x1000).unwrap();
+    region
+}
+
+#[test]
+fn overridden_pages_load_crossed_contents() {
+    let file = TestFile::new(0x8000);
+    let contents = file.contents();
+    let region = crossed(&file);
+    assert_eq!(
+        region.get_buf(0x11000.into()).unwrap()[..],
+        contents[0x2000..0x3000]
+    );
+    assert_eq!(
+        region.get_buf(0x12000.into()).unwrap()[..],
+        contents[0x1000..0x2000]
+    );
+    assert_eq!(region.file_offset_of(0x12010.into()), Ok(0x1010));
+    assert_eq!(region.vaddr_of_offset(0x1010), Some(0x12010.into()));
+    assert_eq!(region.vaddr_of_offset(0x3010), Some(0x13010.into()));
+
+    // Readahead stops where the file order breaks
+    region.set_readahead(4);
+    assert_eq!(
+        region.fault_with_readahead(0x10000.into()).unwrap().len(),
+        1
+    );
+
+    // Evicting keeps the override
+    assert!(region.evict(0x12000.into()));
+    assert_eq!(
+        region.get_buf(0x12000.into()).unwrap()[..],
+        contents[0x1000..0x2000]
+    );
+}
+
+#[test]
+fn overrides_follow_their_halves_across_a_split() {
+    let file = TestFile::new(0x8000);
+    let region = crossed(&file);
+    let (before, after, _) = region.split_at_range(&range(0x12000, 0x2000)).unwrap();
+    let (before, after) = (before.unwrap(), after.unwrap());
+    assert_eq!(before.file_offset_of(0x10000.into()), Ok(0));
+    assert_eq!(before.file_offset_of(0x11000.into()), Ok(0x2000));
+    assert_eq!(after.file_offset_of(0x12000.into()), Ok(0x1000));
+    assert_eq!(after.file_offset_of(0x13000.into()), Ok(0x3000));
+    assert!(!before.can_merge_with(&after));
+}
+
+#[test]
+fn set_page_offset_rejects_invalid_overrides() {
+    let file = TestFile::new(0x8000);
+    let mut region = crossed(&file);
+    assert_eq!(
+        region.set_page_offset(0x12000.into(), 0x123),
+        Err(VmaError::Unaligned)
+    );
+    assert_eq!(
+        region.set_page_offset(0x20000.into(), 0x1000),
+        Err(VmaError::OutOfRange {
+            vaddr: 0x20000.into(),
+            range: range(0x10000, 0x4000),
+        })
+    );
+    region.get_buf(0x13000.into()).unwrap();
+    assert_eq!(
+        region.set_page_offset(0x13000.into(), 0),
+        Err(VmaError::AlreadyPopulated)
+    );
+
+    let mut anonymous: MmapRegion<TestFile> =
+        MmapRegion::new_anonymous(range(0x10000, 0x1000), PageSize::Size4K);
+    assert_eq!(
+        anonymous.set_page_offset(0x10000.into(), 0),
+        Err(VmaError::Anonymous)
+    );
+}
@@ -0,0 +1,75 @@
+mod common;
+
+use axerrno::LinuxResult;
+use axvma::*;
+use common::{TestFile, pattern, range};
+use page_table_multiarch::PageSize;
+
+/// The contents of `Rom`
+static DATA: [u8; 0x2800] = {
+    let mut data = [0; 0x2800];
+    let mut i = 0;
+    while i < data.len() {
+        data[i] = (i % 251) as u8;
+        i += 1;
+    }
+    data
+};
+
+/// ROM-resident file serving whole pages straight from `DATA`
+#[derive(Clone)]
+struct Rom;
+
+impl VmFile for Rom {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> LinuxResult<usize> {
+        let remaining = DATA.get(offset as usize..).unwrap_or_default();
+        let read = buf.len().min(remaining.len());
+        buf[..read].copy_from_slice(&remaining[..read]);
+        Ok(read)
+    }
+
+    fn len(&self) -> LinuxResult<u64> {
+        Ok(DATA.len() as u64)
+    }
+
+    fn read_page_ref(&self, offset: u64, len: usize) -> Option<PageRef> {
+        DATA.get(offset as usize..offset as usize + len)
+            .map(PageRef::Static)
+    }
+}
+
+#[test]
+fn whole_pages_are_borrowed_from_the_backend() {
+    let region = MmapRegion::new(range(0x10000, 0x3000), Rom, 0, PageSize::Size4K);
+    let page = region.get_buf(0x10010.into()).unwrap();
+    assert_eq!(page, PageData::Borrowed(PageRef::Static(&DATA[..0x1000])));
+    assert_eq!(page.as_ptr(), DATA.as_ptr());
+    assert!(region.is_populated(0x10000.into()));
+    assert_eq!(
+        region.get_buf(0x10000.into()).err(),
+        Some(VmaError::AlreadyPopulated)
+    );
+}
+
+#[test]
+fn partial_last_page_falls_back_to_a_copy() {
+    let region = MmapRegion::new(range(0x10000, 0x3000), Rom, 0, PageSize::Size4K);
+    let page = region.get_buf(0x12000.into()).unwrap();
+    assert!(!page.is_borrowed());
+    assert_eq!(page[..0x800], DATA[0x2000..]);
+    assert!(page[0x800..].iter().all(|&byte| byte == 0));
+    assert_eq!(page.into_vec().len(), 0x1000);
+}
+
+#[test]
+fn backends_without_page_refs_copy() {
+    let region = MmapRegion::new(
+        range(0x10000, 0x1000),
+        TestFile::new(0x1000),
+        0,
+        PageSize::Size4K,
+    );
+    let page = region.get_buf(0x10000.into()).unwrap();
+    assert!(!page.is_borrowed());
+    assert_eq!(page[5], pattern(5));
+}
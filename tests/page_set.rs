@@ -0,0 +1,140 @@
+mod common;
+
+use axvma::*;
+use common::{TestFile, range};
+use memory_addr::{VirtAddr, VirtAddrRange};
+use page_table_multiarch::PageSize;
+use std::collections::BTreeSet;
+
+/// Xorshift generator, so failures reproduce
+struct Rng(u64);
+
+impl Rng {
+    fn below(&mut self, n: u64) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0 % n
+    }
+
+    /// Page in a window small enough for runs to form and break
+    fn page(&mut self) -> VirtAddr {
+        VirtAddr::from((0x100 + self.below(300) as usize) << 12)
+    }
+}
+
+fn pages(set: &PageSet) -> Vec<VirtAddr> {
+    set.iter().collect()
+}
+
+#[test]
+fn inserts_coalesce_and_removals_split_runs() {
+    let page = |n: usize| VirtAddr::from(n << 12);
+    let mut set = PageSet::new(PageSize::Size4K);
+    assert!(set.insert(page(10)));
+    assert!(set.insert(page(12)));
+    // Fills the gap between two runs
+    assert!(set.insert(page(11)));
+    assert!(!set.insert(page(11)));
+    assert!(set.insert(page(9)) && set.insert(page(13)));
+    assert_eq!(pages(&set), (9..14).map(page).collect::<Vec<_>>());
+
+    // Splits the run in the middle, then trims both ends
+    assert!(set.remove(page(11)));
+    assert!(!set.contains(page(11)));
+    assert!(set.contains(page(10)) && set.contains(page(12)));
+    assert!(set.remove(page(9)) && set.remove(page(13)));
+    assert!(!set.remove(page(14)));
+    assert_eq!(pages(&set), vec![page(10), page(12)]);
+    assert_eq!(set.len(), 2);
+}
+
+#[test]
+fn random_operations_match_a_btree_set() {
+    let mut rng = Rng(0x1234_5678_9abc_def0);
+    for _ in 0..200 {
+        let mut set = PageSet::new(PageSize::Size4K);
+        let mut model = BTreeSet::new();
+        for _ in 0..200 {
+            let page = rng.page();
+            match rng.below(3) {
+                0 | 1 => assert_eq!(set.insert(page), model.insert(page)),
+                _ => assert_eq!(set.remove(page), model.remove(&page)),
+            }
+        }
+        assert_eq!(set.len(), model.len());
+        assert_eq!(set.is_empty(), model.is_empty());
+        assert_eq!(pages(&set), model.iter().copied().collect::<Vec<_>>());
+        for _ in 0..20 {
+            let page = rng.page();
+            assert_eq!(set.contains(page), model.contains(&page));
+        }
+
+        let start = (0x100 + rng.below(300) as usize) << 12;
+        let end = start + ((rng.below(200) as usize) << 12);
+        let window = VirtAddrRange::new(start.into(), end.into());
+        let subset = set.subset(window);
+        let model_subset: Vec<_> = model
+            .iter()
+            .copied()
+            .filter(|&page| window.contains(page))
+            .collect();
+        assert_eq!(pages(&subset), model_subset);
+        assert_eq!(subset.len(), model_subset.len());
+        let rebased = subset.rebased(start.into(), 0x1000_0000.into());
+        assert_eq!(
+            pages(&rebased),
+            model_subset
+                .iter()
+                .map(|page| VirtAddr::from(page.as_usize() - start + 0x1000_0000))
+                .collect::<Vec<_>>()
+        );
+
+        let mut other = PageSet::new(PageSize::Size4K);
+        for _ in 0..100 {
+            let page = rng.page();
+            other.insert(page);
+            model.insert(page);
+        }
+        set.union_with(&other);
+        assert_eq!(set.len(), model.len());
+        assert_eq!(pages(&set), model.iter().copied().collect::<Vec<_>>());
+    }
+}
+
+#[test]
+fn random_region_splits_match_a_btree_set() {
+    let mut rng = Rng(0x0ddc_0ffe_e15e_a5e5);
+    let start = 0x100 << 12;
+    let size = 300 << 12;
+    for _ in 0..50 {
+        let region = MmapRegion::new(range(start, size), TestFile::new(size), 0, PageSize::Size4K);
+        let mut model = BTreeSet::new();
+        for _ in 0..300 {
+            let page = rng.page();
+            if rng.below(4) == 0 {
+                assert_eq!(region.evict(page), model.remove(&page));
+            } else if region.get_buf(page).is_ok() {
+                model.insert(page);
+            }
+        }
+        assert_eq!(
+            region.populated_iter().collect::<Vec<_>>(),
+            model.iter().copied().collect::<Vec<_>>()
+        );
+
+        let split_start = start + ((1 + rng.below(150) as usize) << 12);
+        let split = range(split_start, (1 + rng.below(140) as usize) << 12);
+        let (before, overlap, after) = region.split_at_range(&split).unwrap();
+        for part in [before, overlap, after].into_iter().flatten() {
+            assert_eq!(
+                part.populated_iter().collect::<Vec<_>>(),
+                model
+                    .iter()
+                    .copied()
+                    .filter(|&page| part.range.contains(page))
+                    .collect::<Vec<_>>()
+            );
+        }
+    }
+}
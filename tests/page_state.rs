@@ -0,0 +1,112 @@
+mod common;
+
+use axvma::*;
+use common::{TestFile, range};
+use memory_addr::VirtAddr;
+use page_table_multiarch::PageSize;
+
+fn region() -> MmapRegion<TestFile> {
+    MmapRegion::new(
+        range(0x10000, 0x4000),
+        TestFile::new(0x4000),
+        0,
+        PageSize::Size4K,
+    )
+}
+
+#[test]
+fn illegal_transitions_are_rejected_without_changing_state() {
+    let region = region();
+    let page = VirtAddr::from(0x11000);
+    assert_eq!(region.page_state(page), PageState::NotPresent);
+    assert!(!region.finish_load(page));
+    assert!(!region.fail_load(page));
+    assert!(!region.try_start_evict(page));
+    assert!(!region.finish_evict(page));
+    assert!(!region.cancel_evict(page));
+    assert_eq!(region.page_state(page), PageState::NotPresent);
+
+    assert!(region.try_start_load(page));
+    assert!(!region.try_start_load(page));
+    assert!(!region.try_start_evict(page));
+    assert!(!region.finish_evict(page));
+    assert_eq!(region.page_state(page), PageState::Loading);
+    assert!(!region.is_populated(page));
+
+    assert!(region.finish_load(page));
+    assert!(!region.finish_load(page));
+    assert!(!region.fail_load(page));
+    assert!(!region.try_start_load(page));
+    assert_eq!(region.page_state(page), PageState::Present);
+
+    assert!(region.try_start_evict(page));
+    assert!(!region.try_start_evict(page));
+    assert!(!region.try_start_load(page));
+    assert!(!region.finish_load(page));
+    assert_eq!(region.page_state(page), PageState::Evicting);
+    assert!(region.cancel_evict(page));
+    assert!(!region.cancel_evict(page));
+    assert_eq!(region.page_state(page), PageState::Present);
+
+    assert!(region.try_start_evict(page));
+    assert!(region.finish_evict(page));
+    assert!(!region.finish_evict(page));
+    assert_eq!(region.page_state(page), PageState::NotPresent);
+    assert!(region.try_start_load(page));
+    assert!(region.fail_load(page));
+    assert_eq!(region.page_state(page), PageState::NotPresent);
+
+    // Other pages never moved
+    assert_eq!(region.populated_iter().count(), 0);
+    assert_eq!(region.page_state(0x10000.into()), PageState::NotPresent);
+
+    let reserved: MmapRegion<TestFile> =
+        MmapRegion::new_reserved(range(0x10000, 0x1000), PageSize::Size4K);
+    assert!(!reserved.try_start_load(0x10000.into()));
+}
+
+#[test]
+fn pages_in_flight_hold_off_faults_eviction_and_splits() {
+    let region = region();
+    let page = VirtAddr::from(0x11000);
+    assert!(region.try_start_load(page));
+    assert_eq!(region.get_buf(page).err(), Some(VmaError::Busy));
+    assert_eq!(
+        region.split_at_range(&range(0x12000, 0x1000)).err(),
+        Some(VmaError::Busy)
+    );
+    assert!(region.finish_load(page));
+
+    assert!(region.try_start_evict(page));
+    assert_eq!(region.get_buf(page).err(), Some(VmaError::Busy));
+    assert!(!region.mark_dirty(page));
+    assert!(!region.evict(page));
+    assert_eq!(
+        region.split_at_range(&range(0x12000, 0x1000)).err(),
+        Some(VmaError::Busy)
+    );
+    assert!(region.cancel_evict(page));
+    assert!(region.split_at_range(&range(0x12000, 0x1000)).is_ok());
+}
+
+#[test]
+fn get_buf_and_evict_drive_the_same_states() {
+    let region = region();
+    let page = VirtAddr::from(0x11000);
+    region.get_buf(page).unwrap();
+    assert_eq!(region.page_state(page), PageState::Present);
+    assert!(region.evict(page));
+    assert_eq!(region.page_state(page), PageState::NotPresent);
+    region.get_buf(page).unwrap();
+    assert!(region.mark_dirty(page));
+    assert!(!region.evict(page));
+    assert_eq!(region.page_state(page), PageState::Present);
+
+    // Releasing a page drops its eviction in flight
+    let other = VirtAddr::from(0x13000);
+    region.get_buf(other).unwrap();
+    assert!(region.try_start_evict(other));
+    region.release_range(&range(0x13000, 0x1000));
+    assert!(!region.finish_evict(other));
+    assert_eq!(region.page_state(other), PageState::NotPresent);
+}
@@ -0,0 +1,108 @@
+mod common;
+
+use axvma::*;
+use common::{TestFile, range};
+use memory_addr::VirtAddr;
+use page_table_multiarch::PageSize;
+use std::cell::Cell;
+
+thread_local! {
+    static SEED: Cell<u64> = const { Cell::new(1) };
+}
+
+/// Xorshift generator over a per-thread seed, so placements reproduce
+fn fake_rng() -> u64 {
+    let mut x = SEED.get();
+    x ^= x << 13;
+    x ^= x >> 7;
+    x ^= x << 17;
+    SEED.set(x);
+    x
+}
+
+fn zero_rng() -> u64 {
+    0
+}
+
+/// Map a few hundred files of varying sizes, unmapping some along the way,
+/// and check that no two regions overlap or leave the window
+fn place_many(policy: PlacementPolicy) -> Vec<VirtAddr> {
+    SEED.set(1);
+    let file = TestFile::new(0x10000);
+    let mut manager = VmaManager::<TestFile>::with_limits(range(0x10_0000, 0x100_0000), None);
+    manager.set_placement(policy);
+    let mut placed = Vec::new();
+    for i in 0..300 {
+        let size = 0x1000 * (1 + i % 5);
+        let start = manager
+            .mmap(0.into(), size, file.clone(), 0, PageSize::Size4K)
+            .unwrap();
+        placed.push(start);
+        if i % 7 == 3 {
+            let victim = placed[i / 2].as_usize();
+            manager.remove_overlapped(range(victim, 0x1000)).unwrap();
+        }
+    }
+    let ranges: Vec<_> = manager.iter().map(|region| region.range).collect();
+    assert!(ranges.windows(2).all(|pair| pair[0].end <= pair[1].start));
+    assert!(ranges.iter().all(|r| r.start.as_usize() >= 0x10_0000));
+    assert!(ranges.iter().all(|r| r.end.as_usize() <= 0x110_0000));
+    placed
+}
+
+#[test]
+fn bottom_up_and_top_down_start_from_their_bounds() {
+    let placed = place_many(PlacementPolicy::BottomUp {
+        base: 0x20_0000.into(),
+    });
+    assert_eq!(placed[..2], [0x20_0000.into(), 0x20_1000.into()]);
+
+    let placed = place_many(PlacementPolicy::TopDown {
+        ceiling: 0x80_0000.into(),
+    });
+    assert_eq!(placed[..2], [0x7f_f000.into(), 0x7f_d000.into()]);
+}
+
+#[test]
+fn random_placement_reproduces_with_the_same_entropy() {
+    let first = place_many(PlacementPolicy::Random { rng: fake_rng });
+    let second = place_many(PlacementPolicy::Random { rng: fake_rng });
+    assert_eq!(first, second);
+    assert_ne!(first[0], 0x10_0000.into());
+}
+
+#[test]
+fn placement_respects_guard_gaps() {
+    let mut manager = VmaManager::<TestFile>::with_limits(range(0x10_0000, 0x1_0000), None);
+    manager.set_placement(PlacementPolicy::TopDown {
+        ceiling: 0x11_0000.into(),
+    });
+    let mut stack = MmapRegion::new_anonymous(range(0x10_e000, 0x2000), PageSize::Size4K);
+    stack.guard_below = 0x2000;
+    manager.add_region(stack).unwrap();
+    let start = manager
+        .mmap(0.into(), 0x1000, TestFile::new(0x1000), 0, PageSize::Size4K)
+        .unwrap();
+    assert_eq!(start, 0x10_b000.into());
+}
+
+#[test]
+fn random_placement_falls_back_on_the_lowest_fit() {
+    let mut manager = VmaManager::<TestFile>::with_limits(range(0x10_0000, 0x4000), None);
+    manager.set_placement(PlacementPolicy::Random { rng: zero_rng });
+    manager
+        .add_region(MmapRegion::new_anonymous(
+            range(0x10_1000, 0x1000),
+            PageSize::Size4K,
+        ))
+        .unwrap();
+    // Entropy of zero keeps picking the first gap, which is too small
+    let start = manager
+        .mmap(0.into(), 0x2000, TestFile::new(0x2000), 0, PageSize::Size4K)
+        .unwrap();
+    assert_eq!(start, 0x10_2000.into());
+    assert_eq!(
+        manager.mmap(0.into(), 0x2000, TestFile::new(0x2000), 0, PageSize::Size4K),
+        Err(VmaError::NoSpace)
+    );
+}
@@ -0,0 +1,140 @@
+mod common;
+
+use axerrno::{LinuxError, LinuxResult};
+use axvma::*;
+use common::{TestFile, pattern, range};
+use memory_addr::VirtAddr;
+use page_table_multiarch::PageSize;
+use std::sync::{Arc, Mutex};
+
+/// File that records the offset and length of every read
+#[derive(Clone)]
+struct ReadLog {
+    inner: TestFile,
+    reads: Arc<Mutex<Vec<(u64, usize)>>>,
+}
+
+impl ReadLog {
+    fn new(len: usize) -> Self {
+        Self {
+            inner: TestFile::new(len),
+            reads: Default::default(),
+        }
+    }
+
+    fn take(&self) -> Vec<(u64, usize)> {
+        std::mem::take(&mut self.reads.lock().unwrap())
+    }
+}
+
+impl VmFile for ReadLog {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> LinuxResult<usize> {
+        self.reads.lock().unwrap().push((offset, buf.len()));
+        self.inner.read_at(buf, offset)
+    }
+
+    fn len(&self) -> LinuxResult<u64> {
+        self.inner.len()
+    }
+}
+
+fn addrs(pages: &[(VirtAddr, PageData)]) -> Vec<usize> {
+    pages.iter().map(|(page, _)| page.as_usize()).collect()
+}
+
+#[test]
+fn span_across_a_region_boundary_reads_once_per_region() {
+    let file = ReadLog::new(0x10000);
+    let mut manager = VmaManager::new();
+    manager
+        .add_region(MmapRegion::new(
+            range(0x10000, 0x2000),
+            file.clone(),
+            0,
+            PageSize::Size4K,
+        ))
+        .unwrap();
+    manager
+        .add_region(MmapRegion::new(
+            range(0x12000, 0x2000),
+            file.clone(),
+            0x8000,
+            PageSize::Size4K,
+        ))
+        .unwrap();
+
+    let pages = manager.populate_contiguous(0x10800.into(), 0x3000).unwrap();
+    assert_eq!(addrs(&pages), vec![0x10000, 0x11000, 0x12000, 0x13000]);
+    assert_eq!(file.take(), vec![(0, 0x2000), (0x8000, 0x2000)]);
+    let PageData::Owned(data) = &pages[2].1 else {
+        panic!("populate_contiguous copies the pages");
+    };
+    assert!(
+        data.iter()
+            .enumerate()
+            .all(|(i, &byte)| byte == pattern(0x8000 + i))
+    );
+    assert!(
+        manager
+            .iter()
+            .all(|region| region.populated_iter().count() == 2)
+    );
+}
+
+#[test]
+fn span_with_a_hole_faults_and_loads_nothing() {
+    let file = ReadLog::new(0x10000);
+    let mut manager = VmaManager::new();
+    for start in [0x10000, 0x13000] {
+        manager
+            .add_region(MmapRegion::new(
+                range(start, 0x2000),
+                file.clone(),
+                0,
+                PageSize::Size4K,
+            ))
+            .unwrap();
+    }
+
+    let err = manager
+        .populate_contiguous(0x11000.into(), 0x3000)
+        .unwrap_err();
+    assert_eq!(err, VmaError::Unmapped(0x12000.into()));
+    assert_eq!(LinuxError::from(err), LinuxError::EFAULT);
+    assert!(file.take().is_empty());
+    assert!(
+        manager
+            .iter()
+            .all(|region| region.populated_iter().next().is_none())
+    );
+}
+
+#[test]
+fn resident_pages_are_skipped_and_split_the_reads() {
+    let file = ReadLog::new(0x10000);
+    let mut manager = VmaManager::new();
+    manager
+        .add_region(MmapRegion::new(
+            range(0x10000, 0x5000),
+            file.clone(),
+            0,
+            PageSize::Size4K,
+        ))
+        .unwrap();
+    let region = manager.find_region(0x10000.into()).unwrap();
+    region.get_buf(0x12000.into()).unwrap();
+    file.take();
+
+    let pages = manager.populate_contiguous(0x10000.into(), 0x5000).unwrap();
+    assert_eq!(addrs(&pages), vec![0x10000, 0x11000, 0x13000, 0x14000]);
+    assert_eq!(file.take(), vec![(0, 0x2000), (0x3000, 0x2000)]);
+    assert_eq!(region.populated_iter().count(), 5);
+
+    assert!(
+        manager
+            .populate_contiguous(0x10000.into(), 0x5000)
+            .unwrap()
+            .is_empty()
+    );
+    assert!(file.take().is_empty());
+}
@@ -0,0 +1,169 @@
+mod common;
+
+use axvma::*;
+use common::{TestFile, range};
+use core::ops::ControlFlow;
+use memory_addr::VirtAddr;
+use page_table_multiarch::PageSize;
+
+fn manager() -> VmaManager<TestFile> {
+    let mut manager = VmaManager::new();
+    manager
+        .add_region(MmapRegion::new(
+            range(0x10000, 0x4000),
+            TestFile::new(0x10000),
+            0,
+            PageSize::Size4K,
+        ))
+        .unwrap();
+    manager
+        .add_region(MmapRegion::new_anonymous(
+            range(0x20000, 0x4000),
+            PageSize::Size4K,
+        ))
+        .unwrap();
+    for vaddr in [0x10000, 0x13000, 0x21000, 0x20000] {
+        manager
+            .handle_fault(vaddr.into(), AccessFlags::READ)
+            .unwrap();
+    }
+    manager
+}
+
+#[test]
+fn populated_iter_walks_pages_in_order() {
+    let manager = manager();
+    let anonymous = manager.find_region(0x20000.into()).unwrap();
+    assert_eq!(
+        anonymous.populated_iter().collect::<Vec<_>>(),
+        vec![VirtAddr::from(0x20000), VirtAddr::from(0x21000)]
+    );
+}
+
+#[test]
+fn visits_pages_in_range_across_regions() {
+    let manager = manager();
+    let mut seen = Vec::new();
+    let result: Option<()> = manager.for_each_populated(range(0x11000, 0x20000), |region, page| {
+        assert!(region.contains(page));
+        seen.push(page.as_usize());
+        ControlFlow::Continue(())
+    });
+    assert_eq!(result, None);
+    assert_eq!(seen, vec![0x13000, 0x20000, 0x21000]);
+}
+
+#[test]
+fn break_stops_the_walk_with_its_value() {
+    let manager = manager();
+    let mut visited = 0;
+    let found = manager.for_each_populated(range(0, 0x10_0000), |_, page| {
+        visited += 1;
+        if page >= VirtAddr::from(0x20000) {
+            ControlFlow::Break(page)
+        } else {
+            ControlFlow::Continue(())
+        }
+    });
+    assert_eq!(found, Some(VirtAddr::from(0x20000)));
+    assert_eq!(visited, 3);
+}
+
+#[test]
+fn callback_runs_without_any_populated_lock_held() {
+    let manager = manager();
+    let result: Option<()> = manager.for_each_populated(range(0, 0x10_0000), |region, page| {
+        for other in manager.iter() {
+            drop(other.populated.try_lock().expect("populated lock held"));
+        }
+        // Populating another page of the same region takes its lock
+        let next = page.as_usize() + 0x1000;
+        if region.contains(next.into()) && !region.is_populated(next.into()) {
+            region.get_buf(next.into()).unwrap();
+        }
+        ControlFlow::Continue(())
+    });
+    assert_eq!(result, None);
+    assert!(
+        manager
+            .find_region(0x22000.into())
+            .unwrap()
+            .is_populated(0x22000.into())
+    );
+}
+
+#[test]
+fn fully_populated_tracks_loads_and_evictions() {
+    let region = MmapRegion::new(
+        range(0x10000, 0x4000),
+        TestFile::new(0x4000),
+        0,
+        PageSize::Size4K,
+    );
+    for vaddr in [0x10000, 0x11000, 0x12000] {
+        region.get_buf(vaddr.into()).unwrap();
+        assert!(!region.is_fully_populated());
+    }
+    region.get_buf(0x13000.into()).unwrap();
+    assert!(region.is_fully_populated());
+    assert_eq!(
+        region.get_buf(0x12000.into()).err(),
+        Some(VmaError::AlreadyPopulated)
+    );
+
+    // A page being evicted is no longer resident
+    assert!(region.try_start_evict(0x11000.into()));
+    assert!(!region.is_fully_populated());
+    assert_eq!(region.get_buf(0x11000.into()).err(), Some(VmaError::Busy));
+    assert!(region.cancel_evict(0x11000.into()));
+    assert!(region.is_fully_populated());
+    assert!(region.evict(0x11000.into()));
+    assert!(!region.is_fully_populated());
+
+    let (before, overlap, after) = region.split_at_range(&range(0x11000, 0x1000)).unwrap();
+    assert!(before.unwrap().is_fully_populated());
+    assert!(!overlap.unwrap().is_fully_populated());
+    assert!(after.unwrap().is_fully_populated());
+}
+
+#[test]
+fn fully_populated_follows_resizes() {
+    let mut manager: VmaManager<TestFile> = VmaManager::new();
+    manager
+        .add_region(MmapRegion::new_anonymous(
+            range(0x20000, 0x2000),
+            PageSize::Size4K,
+        ))
+        .unwrap();
+    for vaddr in [0x20000, 0x21000] {
+        manager
+            .handle_fault(vaddr.into(), AccessFlags::READ)
+            .unwrap();
+    }
+    let region = manager.find_region(0x20000.into()).unwrap();
+    assert!(region.is_fully_populated());
+    assert!(region.clone().is_fully_populated());
+    assert_eq!(
+        manager
+            .handle_fault(0x21000.into(), AccessFlags::READ)
+            .err(),
+        Some(VmaError::AlreadyPopulated)
+    );
+
+    manager
+        .remap(0x20000.into(), 0x2000, 0x3000, RemapFlags::empty())
+        .unwrap();
+    assert!(
+        !manager
+            .find_region(0x20000.into())
+            .unwrap()
+            .is_fully_populated()
+    );
+    manager.munmap(0x22000.into(), 0x1000).unwrap();
+    assert!(
+        manager
+            .find_region(0x20000.into())
+            .unwrap()
+            .is_fully_populated()
+    );
+}
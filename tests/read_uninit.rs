@@ -0,0 +1,99 @@
+mod common;
+
+use axerrno::{LinuxError, LinuxResult};
+use axvma::*;
+use common::{TestFile, range};
+use page_table_multiarch::PageSize;
+use std::{mem::MaybeUninit, sync::Arc};
+
+/// File reading at most 100 bytes at a time, with its own `read_uninit_at`;
+/// its data has no zero byte, so stray zeroes are padding
+#[derive(Clone)]
+struct UninitFile {
+    data: Arc<Vec<u8>>,
+    /// Return data outside the start of the buffer, breaking the contract
+    misplaced: bool,
+}
+
+impl UninitFile {
+    fn new(len: usize, misplaced: bool) -> Self {
+        Self {
+            data: Arc::new((0..len).map(|i| (i % 250 + 1) as u8).collect()),
+            misplaced,
+        }
+    }
+}
+
+impl VmFile for UninitFile {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> LinuxResult<usize> {
+        let data = self.data.get(offset as usize..).unwrap_or_default();
+        let len = data.len().min(buf.len()).min(100);
+        buf[..len].copy_from_slice(&data[..len]);
+        Ok(len)
+    }
+
+    fn read_uninit_at<'a>(
+        &self,
+        buf: &'a mut [MaybeUninit<u8>],
+        offset: u64,
+    ) -> LinuxResult<&'a mut [u8]> {
+        let data = self.data.get(offset as usize..).unwrap_or_default();
+        let data = &data[..data.len().min(buf.len()).min(100)];
+        let start = usize::from(self.misplaced);
+        Ok(buf[start..start + data.len()].write_copy_of_slice(data))
+    }
+
+    fn len(&self) -> LinuxResult<u64> {
+        Ok(self.data.len() as u64)
+    }
+}
+
+#[test]
+fn every_byte_is_file_data_or_an_explicit_zero() {
+    let file = UninitFile::new(0x1800, false);
+    let data = file.data.clone();
+    let mut region = MmapRegion::new(range(0x10000, 0x3000), file, 0, PageSize::Size4K);
+    region.eof_policy = EofPolicy::ZeroFill;
+
+    assert_eq!(region.get_buf(0x10000.into()).unwrap()[..], data[..0x1000]);
+    let partial = region.get_buf(0x11000.into()).unwrap();
+    assert_eq!(partial[..0x800], data[0x1000..]);
+    assert!(partial[0x800..].iter().all(|&byte| byte == 0));
+    let beyond = region.get_buf(0x12000.into()).unwrap();
+    assert!(beyond.iter().all(|&byte| byte == 0));
+
+    let mut dst = vec![0xff; 0x1000];
+    let copied = MmapRegion::new(
+        range(0x10000, 0x2000),
+        UninitFile::new(0x1800, false),
+        0,
+        PageSize::Size4K,
+    );
+    assert_eq!(copied.get_buf_into(0x11000.into(), &mut dst), Ok(0x1000));
+    assert_eq!(dst[..], partial[..]);
+}
+
+#[test]
+fn data_returned_elsewhere_in_the_buffer_is_rejected() {
+    let region = MmapRegion::new(
+        range(0x10000, 0x1000),
+        UninitFile::new(0x1000, true),
+        0,
+        PageSize::Size4K,
+    );
+    let err = region.get_buf(0x10000.into()).unwrap_err();
+    assert_eq!(LinuxError::from(err), LinuxError::EIO);
+    assert!(!region.is_populated(0x10000.into()));
+}
+
+#[test]
+fn the_default_read_zeroes_past_a_short_read() {
+    let region = MmapRegion::new(
+        range(0x10000, 0x1000),
+        TestFile::new(0x800),
+        0,
+        PageSize::Size4K,
+    );
+    let page = region.get_buf(0x10000.into()).unwrap();
+    assert!(page[0x800..].iter().all(|&byte| byte == 0));
+}
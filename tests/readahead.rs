@@ -0,0 +1,79 @@
+mod common;
+
+use axvma::*;
+use common::{TestFile, pattern, range};
+use memory_addr::VirtAddr;
+use page_table_multiarch::PageSize;
+
+fn region(file: &TestFile) -> MmapRegion<TestFile> {
+    MmapRegion::new(range(0x100000, 0x40000), file.clone(), 0, PageSize::Size4K)
+}
+
+fn addrs(pages: &[(VirtAddr, Vec<u8>)]) -> Vec<usize> {
+    pages.iter().map(|(vaddr, _)| vaddr.as_usize()).collect()
+}
+
+#[test]
+fn sequential_hint_prefetches_and_random_hint_does_not() {
+    let file = TestFile::new(0x40000);
+
+    let sequential = region(&file);
+    sequential.set_access_hint(AccessHint::Sequential);
+    let pages = sequential.fault_with_readahead(0x100010.into()).unwrap();
+    assert_eq!(pages.len(), 17);
+    assert_eq!(
+        addrs(&pages),
+        (0..pages.len())
+            .map(|i| 0x100000 + i * 0x1000)
+            .collect::<Vec<_>>()
+    );
+    let (vaddr, data) = &pages[3];
+    assert_eq!(data[0], pattern(vaddr.as_usize() - 0x100000));
+
+    let random = region(&file);
+    random.set_readahead(8);
+    random.set_access_hint(AccessHint::Random);
+    let pages = random.fault_with_readahead(0x100010.into()).unwrap();
+    assert_eq!(addrs(&pages), vec![0x100000]);
+    assert!(!random.is_populated(0x101000.into()));
+}
+
+#[test]
+fn sequential_hint_widens_a_large_window() {
+    let file = TestFile::new(0x40000);
+    let region = region(&file);
+    region.set_readahead(20);
+    assert_eq!(region.access_hint(), AccessHint::Normal);
+    assert_eq!(
+        region.fault_with_readahead(0x100000.into()).unwrap().len(),
+        21
+    );
+
+    region.set_access_hint(AccessHint::Sequential);
+    // Twice the window, cut at the end of the region
+    assert_eq!(
+        region.fault_with_readahead(0x120000.into()).unwrap().len(),
+        32
+    );
+}
+
+#[test]
+fn hint_survives_split_clone_and_advice() {
+    let file = TestFile::new(0x40000);
+    let sequential = region(&file);
+    sequential.set_access_hint(AccessHint::Sequential);
+    let (before, _, after) = sequential.split_at_range(&range(0x110000, 0x1000)).unwrap();
+    assert_eq!(before.unwrap().access_hint(), AccessHint::Sequential);
+    assert_eq!(after.unwrap().access_hint(), AccessHint::Sequential);
+    assert_eq!(sequential.clone().access_hint(), AccessHint::Sequential);
+
+    let mut manager = VmaManager::new();
+    manager.add_region(region(&file)).unwrap();
+    manager
+        .advise(range(0x100000, 0x1000), Advice::Random)
+        .unwrap();
+    assert_eq!(
+        manager.find_region(0x100000.into()).unwrap().access_hint(),
+        AccessHint::Random
+    );
+}
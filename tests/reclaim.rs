@@ -0,0 +1,213 @@
+mod common;
+
+use axvma::*;
+use common::{TestFile, pattern, range};
+use memory_addr::VirtAddr;
+use page_table_multiarch::PageSize;
+
+fn page(addr: usize) -> (VirtAddr, PageSize) {
+    (VirtAddr::from(addr), PageSize::Size4K)
+}
+
+#[test]
+fn evicted_page_is_reloaded_by_the_next_fault() {
+    let file = TestFile::new(0x10000);
+    let region = MmapRegion::new(range(0x10000, 0x2000), file, 0, PageSize::Size4K);
+    region.get_buf(0x10000.into()).unwrap();
+    assert_eq!(
+        region.get_buf(0x10000.into()).err(),
+        Some(VmaError::AlreadyPopulated)
+    );
+
+    assert!(region.evict(0x10800.into()));
+    assert!(!region.is_populated(0x10000.into()));
+    assert!(!region.evict(0x10000.into()));
+    let page = region.get_buf(0x10000.into()).unwrap();
+    assert_eq!(page[7], pattern(7));
+    assert!(region.is_populated(0x10000.into()));
+}
+
+#[test]
+fn dirty_and_anonymous_pages_are_not_evicted() {
+    let file = TestFile::new(0x10000);
+    let region = MmapRegion::new(range(0x10000, 0x2000), file, 0, PageSize::Size4K);
+    region.get_buf(0x10000.into()).unwrap();
+    assert!(region.mark_dirty(0x10000.into()));
+    assert!(!region.evict(0x10000.into()));
+    assert!(region.is_populated(0x10000.into()));
+
+    let anonymous: MmapRegion<TestFile> =
+        MmapRegion::new_anonymous(range(0x20000, 0x1000), PageSize::Size4K);
+    anonymous.get_buf(0x20000.into()).unwrap();
+    assert!(!anonymous.evict(0x20000.into()));
+}
+
+#[test]
+fn reclaim_evicts_clean_pages_in_address_order() {
+    let file = TestFile::new(0x10000);
+    let mut manager = VmaManager::new();
+    for start in [0x20000, 0x10000] {
+        let region = MmapRegion::new(range(start, 0x3000), file.clone(), 0, PageSize::Size4K);
+        for offset in (0..0x3000).step_by(0x1000) {
+            region.get_buf((start + offset).into()).unwrap();
+        }
+        manager.add_region(region).unwrap();
+    }
+    manager
+        .find_region(0x11000.into())
+        .unwrap()
+        .mark_dirty(0x11000.into());
+
+    assert_eq!(
+        manager.reclaim(3),
+        vec![page(0x10000), page(0x12000), page(0x20000)]
+    );
+    assert_eq!(manager.reclaim(10), vec![page(0x21000), page(0x22000)]);
+    assert!(manager.reclaim(10).is_empty());
+
+    let region = manager.find_region(0x10000.into()).unwrap();
+    assert!(region.is_populated(0x11000.into()));
+    assert_eq!(region.get_buf(0x10000.into()).unwrap()[1], pattern(1));
+}
+
+#[test]
+fn lazy_free_pages_are_reclaimed_first_without_write_back() {
+    let file = TestFile::new(0x10000);
+    let mut manager = VmaManager::new();
+    let region = MmapRegion::new(range(0x10000, 0x4000), file.clone(), 0, PageSize::Size4K);
+    region.populate_range(&region.range).unwrap();
+    region.handle_write_fault(0x13000.into()).unwrap();
+    manager.add_region(region).unwrap();
+    let anon = MmapRegion::new_anonymous(range(0x20000, 0x2000), PageSize::Size4K);
+    anon.handle_write_fault(0x20000.into()).unwrap();
+    manager.add_region(anon).unwrap();
+
+    for (start, size) in [(0x12000, 0x2000), (0x20000, 0x1000)] {
+        manager.advise(range(start, size), Advice::Free).unwrap();
+    }
+    let region = manager.find_region(0x13000.into()).unwrap();
+    assert!(region.is_lazy_free(0x12000.into()) && region.is_lazy_free(0x13000.into()));
+    let (_, overlap, _) = region.split_at_range(&range(0x13000, 0x1000)).unwrap();
+    assert!(overlap.unwrap().is_lazy_free(0x13000.into()));
+
+    // Touching a page makes it ordinary again
+    assert!(region.touch(0x12000.into()));
+    assert!(!region.is_lazy_free(0x12000.into()));
+    // Lazy-free pages go first, even dirty and anonymous ones
+    assert_eq!(manager.reclaim(2), vec![page(0x13000), page(0x20000)]);
+    assert!(file.writes().is_empty());
+    assert!(!region.is_populated(0x13000.into()));
+
+    // The touched page is reclaimed only as an ordinary clean page
+    assert_eq!(
+        manager.reclaim(10),
+        vec![page(0x10000), page(0x11000), page(0x12000)]
+    );
+    assert!(manager.reclaim(10).is_empty());
+}
+
+#[test]
+fn write_faults_cancel_lazy_free() {
+    let mut manager = VmaManager::new();
+    manager
+        .add_region(MmapRegion::new(
+            range(0x10000, 0x1000),
+            TestFile::new(0x1000),
+            0,
+            PageSize::Size4K,
+        ))
+        .unwrap();
+    let region = manager.find_region(0x10000.into()).unwrap();
+    region.get_buf(0x10000.into()).unwrap();
+    manager
+        .advise(range(0x10000, 0x1000), Advice::Free)
+        .unwrap();
+    assert!(region.is_lazy_free(0x10000.into()));
+    region.handle_write_fault(0x10000.into()).unwrap();
+    assert!(!region.is_lazy_free(0x10000.into()));
+    // Now dirty, so nothing is reclaimable
+    assert!(manager.reclaim(10).is_empty());
+}
+
+/// Fault every page in `pages` through `manager`, telling `policy` as a host would
+fn fault_all(manager: &VmaManager<TestFile>, policy: &mut impl ReclaimPolicy, pages: &[usize]) {
+    for &page in pages {
+        let vaddr = VirtAddr::from(page + 0x10);
+        manager.handle_fault(vaddr, AccessFlags::READ).unwrap();
+        policy.on_fault(vaddr);
+    }
+}
+
+/// Fault four pages, reclaim one, fault five more and reclaim three
+/// Returns the pages evicted by the two passes
+fn scripted_reclaim(policy: &mut impl ReclaimPolicy) -> (Vec<usize>, Vec<usize>) {
+    let file = TestFile::new(0x10000);
+    let mut manager = VmaManager::new();
+    for start in [0x10000, 0x20000] {
+        manager
+            .add_region(MmapRegion::new(
+                range(start, 0x4000),
+                file.clone(),
+                0,
+                PageSize::Size4K,
+            ))
+            .unwrap();
+    }
+    let addrs = |pages: Vec<(VirtAddr, PageSize)>| -> Vec<usize> {
+        pages
+            .into_iter()
+            .map(|(vaddr, _)| vaddr.as_usize())
+            .collect()
+    };
+
+    fault_all(&manager, policy, &[0x10000, 0x11000, 0x12000, 0x13000]);
+    let first = addrs(manager.reclaim_with(1, policy));
+    fault_all(
+        &manager,
+        policy,
+        &[0x20000, 0x21000, 0x22000, 0x23000, 0x10000],
+    );
+    let second = addrs(manager.reclaim_with(3, policy));
+    (first, second)
+}
+
+#[test]
+fn address_order_ignores_recency() {
+    let (first, second) = scripted_reclaim(&mut AddressOrder);
+    assert_eq!(first, vec![0x10000]);
+    // The page faulted back in last is the first to go again
+    assert_eq!(second, vec![0x10000, 0x11000, 0x12000]);
+}
+
+#[test]
+fn clock_policy_spares_pages_faulted_since_the_last_scan() {
+    let mut clock = ClockPolicy::new();
+    let (first, second) = scripted_reclaim(&mut clock);
+    // Every page was accessed, so the tie goes to the lowest address
+    assert_eq!(first, vec![0x10000]);
+    // The first scan cleared the old pages, which now go before the new ones
+    assert_eq!(second, vec![0x11000, 0x12000, 0x13000]);
+    // The second scan cleared the survivors in turn
+    for page in [0x10000, 0x20000, 0x23000] {
+        assert!(!clock.is_accessed(page.into(), PageSize::Size4K));
+    }
+}
+
+#[test]
+fn policies_cannot_evict_dirty_pages() {
+    let region = MmapRegion::new(
+        range(0x10000, 0x2000),
+        TestFile::new(0x2000),
+        0,
+        PageSize::Size4K,
+    );
+    region.populate_range(&region.range).unwrap();
+    region.mark_dirty(0x10000.into());
+    let mut manager = VmaManager::new();
+    manager.add_region(region).unwrap();
+
+    let mut clock = ClockPolicy::new();
+    clock.on_fault(0x11000.into());
+    assert_eq!(manager.reclaim_with(2, &mut clock), vec![page(0x11000)]);
+    assert!(manager.reclaim_with(2, &mut clock).is_empty());
+}
@@ -0,0 +1,208 @@
+mod common;
+
+use axerrno::{LinuxError, LinuxResult};
+use axvma::*;
+use common::{TestFile, range};
+use memory_addr::VirtAddrRange;
+use page_table_multiarch::PageSize;
+
+/// Sparse file of 8 GiB whose bytes encode their offset above 4 KiB
+#[derive(Clone)]
+struct SparseFile;
+
+impl SparseFile {
+    fn byte_at(offset: u64) -> u8 {
+        (offset >> 12) as u8 ^ (offset >> 32) as u8
+    }
+}
+
+impl VmFile for SparseFile {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> LinuxResult<usize> {
+        for (i, byte) in buf.iter_mut().enumerate() {
+            *byte = Self::byte_at(offset + i as u64);
+        }
+        Ok(buf.len())
+    }
+
+    fn len(&self) -> LinuxResult<u64> {
+        Ok(8 << 30)
+    }
+}
+
+fn try_new(range: VirtAddrRange, offset: i64, align: PageSize) -> VmaResult<MmapRegion<TestFile>> {
+    MmapRegion::try_new(range, TestFile::new(0x1000), offset, align)
+}
+
+#[test]
+fn try_new_rejects_each_invalid_geometry() {
+    let cases = [
+        (
+            range(0x10000, 0),
+            0,
+            PageSize::Size4K,
+            VmaError::InvalidArgument,
+        ),
+        (
+            range(0x10800, 0x1000),
+            0,
+            PageSize::Size4K,
+            VmaError::Unaligned,
+        ),
+        (
+            range(0x10000, 0x1800),
+            0,
+            PageSize::Size4K,
+            VmaError::Unaligned,
+        ),
+        (
+            range(0x10000, 0x1000),
+            0x800,
+            PageSize::Size4K,
+            VmaError::Unaligned,
+        ),
+        (
+            range(0x10000, 0x1000),
+            -0x800,
+            PageSize::Size4K,
+            VmaError::Unaligned,
+        ),
+        (
+            range(0x1000, 0x20_0000),
+            0,
+            PageSize::Size2M,
+            VmaError::Unaligned,
+        ),
+        (
+            range(0x20_0000, 0x1000),
+            0,
+            PageSize::Size2M,
+            VmaError::Unaligned,
+        ),
+        (
+            range(0x20_0000, 0x20_0000),
+            0x1000,
+            PageSize::Size2M,
+            VmaError::Unaligned,
+        ),
+    ];
+    for (range, offset, align, error) in cases {
+        let result = try_new(range, offset, align);
+        assert_eq!(result.as_ref().err(), Some(&error), "{range:?} {offset:#x}");
+        assert_eq!(LinuxError::from(error), LinuxError::EINVAL);
+    }
+
+    assert!(try_new(range(0x10000, 0x1000), 0x1000, PageSize::Size4K).is_ok());
+    assert!(try_new(range(0x10000, 0x1000), -0x1000, PageSize::Size4K).is_ok());
+    assert!(try_new(range(0x20_0000, 0x20_0000), 0x20_0000, PageSize::Size2M).is_ok());
+}
+
+#[test]
+fn mmap_validates_the_geometry() {
+    let mut manager = VmaManager::new();
+    let file = TestFile::new(0x10000);
+    assert_eq!(
+        manager.mmap(0x10000.into(), 0x1800, file.clone(), 0, PageSize::Size4K),
+        Err(VmaError::Unaligned)
+    );
+    assert_eq!(
+        manager.mmap(0x10000.into(), 0x1000, file.clone(), 0x10, PageSize::Size4K),
+        Err(VmaError::Unaligned)
+    );
+    assert!(manager.is_empty());
+    let start = manager
+        .mmap(0x10000.into(), 0x1000, file, 0x1000, PageSize::Size4K)
+        .unwrap();
+    assert_eq!(manager.find_region(start).unwrap().range.size(), 0x1000);
+}
+
+#[test]
+fn file_offsets_translate_both_ways() {
+    let file = TestFile::new(0x10000);
+    let region = MmapRegion::new(
+        range(0x10000, 0x3000),
+        file.clone(),
+        0x5000,
+        PageSize::Size4K,
+    );
+    assert_eq!(region.file_offset_of(0x10000.into()), Ok(0x5000));
+    assert_eq!(region.file_offset_of(0x12fff.into()), Ok(0x7fff));
+    assert_eq!(
+        region.file_offset_of(0x13000.into()),
+        Err(VmaError::OutOfRange {
+            vaddr: 0x13000.into(),
+            range: range(0x10000, 0x3000),
+        })
+    );
+    assert_eq!(region.vaddr_of_offset(0x6010), Some(0x11010.into()));
+    assert_eq!(region.vaddr_of_offset(0x4fff), None);
+    assert_eq!(region.vaddr_of_offset(0x8000), None);
+
+    let negative = MmapRegion::new(
+        range(0x10000, 0x3000),
+        file.clone(),
+        -0x1000,
+        PageSize::Size4K,
+    );
+    assert_eq!(
+        negative.file_offset_of(0x10fff.into()),
+        Err(VmaError::OffsetOutOfFile {
+            offset: -1,
+            file_len: 0x10000,
+        })
+    );
+    assert_eq!(negative.file_offset_of(0x11000.into()), Ok(0));
+    assert_eq!(negative.vaddr_of_offset(0), Some(0x11000.into()));
+
+    let huge = MmapRegion::new(
+        range(0x10000, 0x3000),
+        file,
+        i64::MAX - 0x1000,
+        PageSize::Size4K,
+    );
+    assert_eq!(huge.file_offset_of(0x12000.into()), Err(VmaError::Overflow));
+
+    let anonymous: MmapRegion<TestFile> =
+        MmapRegion::new_anonymous(range(0x10000, 0x1000), PageSize::Size4K);
+    assert_eq!(
+        anonymous.file_offset_of(0x10000.into()),
+        Err(VmaError::Anonymous)
+    );
+    assert_eq!(anonymous.vaddr_of_offset(0), None);
+}
+
+#[test]
+fn split_segments_keep_their_file_offsets() {
+    let region = MmapRegion::new(
+        range(0x10000, 0x3000),
+        TestFile::new(0x10000),
+        0x5000,
+        PageSize::Size4K,
+    );
+    let (before, overlap, after) = region.split_at_range(&range(0x11000, 0x1000)).unwrap();
+    assert_eq!(
+        before.unwrap().vaddr_of_offset(0x5000),
+        Some(0x10000.into())
+    );
+    assert_eq!(overlap.unwrap().file_offset_of(0x11000.into()), Ok(0x6000));
+    let after = after.unwrap();
+    assert_eq!(after.file_offset_of(0x12800.into()), Ok(0x7800));
+    assert_eq!(after.vaddr_of_offset(0x6000), None);
+}
+
+#[test]
+fn offsets_above_four_gib_reach_the_file() {
+    let offset: i64 = (5 << 30) + 0x3000;
+    let region = MmapRegion::new(range(0x10000, 0x2000), SparseFile, offset, PageSize::Size4K);
+    let second = offset as u64 + 0x1000;
+    assert!(second > u32::MAX as u64);
+    assert_eq!(region.file_offset_of(0x11000.into()), Ok(second));
+    let page = region.get_buf(0x11000.into()).unwrap();
+    assert_eq!(page[0], SparseFile::byte_at(second));
+    assert_eq!(page[0xfff], SparseFile::byte_at(second + 0xfff));
+
+    let (_, _, after) = region.split_at_range(&range(0x10000, 0x1000)).unwrap();
+    let after = after.unwrap();
+    assert_eq!(after.file_offset_of(0x11000.into()), Ok(second));
+    assert_eq!(after.vaddr_of_offset(second + 0x10), Some(0x11010.into()));
+    assert_eq!(after.vaddr_of_offset(second - 0x1000), None);
+}
@@ -0,0 +1,136 @@
+mod common;
+
+use axvma::*;
+use common::{TestFile, range};
+use page_table_multiarch::PageSize;
+
+fn anon(start: usize, size: usize) -> MmapRegion<TestFile> {
+    MmapRegion::new_anonymous(range(start, size), PageSize::Size4K)
+}
+
+fn id_at(manager: &VmaManager<TestFile>, vaddr: usize) -> RegionId {
+    manager.find_region(vaddr.into()).unwrap().id().unwrap()
+}
+
+#[test]
+fn splits_keep_the_id_on_the_lowest_piece() {
+    let mut manager = VmaManager::new();
+    let region = anon(0x10000, 0x8000);
+    assert_eq!(region.id(), None);
+    manager.add_region(region).unwrap();
+    manager.add_region(anon(0x30000, 0x2000)).unwrap();
+    let first = id_at(&manager, 0x10000);
+    let second = id_at(&manager, 0x30000);
+    assert!(first < second);
+
+    // The before segment keeps the id; overlap and after get fresh ones
+    manager
+        .protect(range(0x12000, 0x2000), MmapProt::READ)
+        .unwrap();
+    assert_eq!(manager.len(), 4);
+    assert_eq!(id_at(&manager, 0x10000), first);
+    let overlap = id_at(&manager, 0x12000);
+    let after = id_at(&manager, 0x14000);
+    assert!(overlap > second && after > second && overlap != after);
+    assert_eq!(
+        manager.find_by_id(first).unwrap().range,
+        range(0x10000, 0x2000)
+    );
+    assert_eq!(
+        manager.find_by_id(overlap).unwrap().range,
+        range(0x12000, 0x2000)
+    );
+    assert_eq!(
+        manager.find_by_id(after).unwrap().range,
+        range(0x14000, 0x4000)
+    );
+
+    // With no before segment, the after segment keeps the id
+    manager.munmap(0x14000.into(), 0x1000).unwrap();
+    assert_eq!(
+        manager.find_by_id(after).unwrap().range,
+        range(0x15000, 0x3000)
+    );
+
+    // Removed regions leave stale ids
+    manager.munmap(0x12000.into(), 0x2000).unwrap();
+    assert!(manager.find_by_id(overlap).is_none());
+    assert!(manager.find_by_id(RegionId(u64::MAX)).is_none());
+}
+
+#[test]
+fn ids_follow_regions_through_remap_and_copies() {
+    let mut manager = VmaManager::new();
+    manager.add_region(anon(0x10000, 0x2000)).unwrap();
+    manager.add_region(anon(0x30000, 0x2000)).unwrap();
+    let id = id_at(&manager, 0x30000);
+
+    manager
+        .remap(0x30000.into(), 0x2000, 0x4000, RemapFlags::empty())
+        .unwrap();
+    assert_eq!(
+        manager.find_by_id(id).unwrap().range,
+        range(0x30000, 0x4000)
+    );
+    manager.add_region(anon(0x34000, 0x1000)).unwrap();
+    let moved = manager
+        .remap(0x30000.into(), 0x4000, 0x8000, RemapFlags::MAYMOVE)
+        .unwrap();
+    assert_ne!(moved.start, 0x30000.into());
+    assert_eq!(manager.find_by_id(id).unwrap().range.start, moved.start);
+    assert!(manager.find_region(0x30000.into()).is_none());
+
+    let first = id_at(&manager, 0x10000);
+    assert_eq!(
+        manager.clone().find_by_id(first).unwrap().range,
+        range(0x10000, 0x2000)
+    );
+    assert_eq!(
+        manager.fork().find_by_id(id).unwrap().range.start,
+        moved.start
+    );
+}
+
+#[test]
+fn coalesce_and_clear_retire_ids() {
+    let mut manager = VmaManager::new();
+    manager.add_region(anon(0x10000, 0x2000)).unwrap();
+    manager.add_region(anon(0x12000, 0x2000)).unwrap();
+    let kept = id_at(&manager, 0x10000);
+    let absorbed = id_at(&manager, 0x12000);
+    manager.coalesce();
+    assert_eq!(manager.len(), 1);
+    assert_eq!(
+        manager.find_by_id(kept).unwrap().range,
+        range(0x10000, 0x4000)
+    );
+    assert!(manager.find_by_id(absorbed).is_none());
+
+    let mut pinned = anon(0x60000, 0x1000);
+    pinned.pinned = true;
+    manager.add_region(pinned).unwrap();
+    let pinned = id_at(&manager, 0x60000);
+    manager.clear();
+    assert!(manager.find_by_id(kept).is_none());
+    assert_eq!(
+        manager.find_by_id(pinned).unwrap().range,
+        range(0x60000, 0x1000)
+    );
+}
+
+#[test]
+fn moving_a_region_to_another_manager_gives_it_a_fresh_id() {
+    let mut manager = VmaManager::new();
+    manager.add_region(anon(0x10000, 0x2000)).unwrap();
+    manager.add_region(anon(0x20000, 0x2000)).unwrap();
+    let id = id_at(&manager, 0x20000);
+    let removed = manager.munmap(0x20000.into(), 0x2000).unwrap();
+    assert_eq!(removed.regions[0].id(), Some(id));
+
+    let mut other = VmaManager::new();
+    other.add_region(anon(0x70000, 0x1000)).unwrap();
+    other
+        .add_region(removed.regions.into_iter().next().unwrap())
+        .unwrap();
+    assert_eq!(id_at(&other, 0x20000), RegionId(1));
+}
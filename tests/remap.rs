@@ -0,0 +1,170 @@
+mod common;
+
+use axerrno::LinuxError;
+use axvma::*;
+use common::{TestFile, range};
+use memory_addr::VirtAddr;
+use page_table_multiarch::PageSize;
+
+fn manager_with(regions: &[(usize, usize, i64)]) -> VmaManager<TestFile> {
+    let file = TestFile::new(0x10_0000);
+    let mut manager = VmaManager::new();
+    for &(start, size, offset) in regions {
+        manager
+            .add_region(MmapRegion::new(
+                range(start, size),
+                file.clone(),
+                offset,
+                PageSize::Size4K,
+            ))
+            .unwrap();
+    }
+    manager
+}
+
+fn pages(removed: &RemovedRegions<TestFile>) -> Vec<usize> {
+    removed
+        .pages
+        .iter()
+        .map(|(page, _)| page.as_usize())
+        .collect()
+}
+
+#[test]
+fn shrinking_drops_the_tail_pages() {
+    let mut manager = manager_with(&[(0x10000, 0x4000, 0)]);
+    manager.populate(range(0x10000, 0x4000)).unwrap();
+
+    let remapped = manager
+        .remap(0x10000.into(), 0x4000, 0x2800, RemapFlags::empty())
+        .unwrap();
+    assert_eq!(remapped.start, VirtAddr::from(0x10000));
+    assert_eq!(pages(&remapped.removed), vec![0x13000]);
+    let region = manager.find_region(0x10000.into()).unwrap();
+    assert_eq!(region.range, range(0x10000, 0x3000));
+    assert!(region.is_populated(0x12000.into()));
+}
+
+#[test]
+fn growing_in_place_needs_the_following_range_free() {
+    let mut manager = manager_with(&[(0x10000, 0x2000, 0), (0x16000, 0x1000, 0)]);
+    manager.populate(range(0x10000, 0x2000)).unwrap();
+
+    let remapped = manager
+        .remap(0x10000.into(), 0x2000, 0x6000, RemapFlags::empty())
+        .unwrap();
+    assert_eq!(remapped.start, VirtAddr::from(0x10000));
+    assert!(remapped.removed.regions.is_empty());
+    let region = manager.find_region(0x15fff.into()).unwrap();
+    assert_eq!(region.range, range(0x10000, 0x6000));
+    assert!(region.is_populated(0x11000.into()));
+    assert!(!region.is_populated(0x12000.into()));
+
+    assert_eq!(
+        manager
+            .remap(0x10000.into(), 0x6000, 0x7000, RemapFlags::empty())
+            .err(),
+        Some(VmaError::NoSpace)
+    );
+    assert_eq!(
+        LinuxError::from(VmaError::<VirtAddr>::NoSpace),
+        LinuxError::ENOMEM
+    );
+    assert_eq!(
+        manager.find_region(0x10000.into()).unwrap().range,
+        range(0x10000, 0x6000)
+    );
+    assert_eq!(
+        manager
+            .remap(0x20000.into(), 0x1000, 0x2000, RemapFlags::MAYMOVE)
+            .err(),
+        Some(VmaError::Unmapped(0x20000.into()))
+    );
+}
+
+#[test]
+fn moving_rebases_pages_and_keeps_the_file_offset() {
+    let mut manager = manager_with(&[(0x10000, 0x3000, 0), (0x13000, 0x1000, 0)]);
+    manager.populate(range(0x10000, 0x3000)).unwrap();
+    manager
+        .find_region(0x11000.into())
+        .unwrap()
+        .mark_dirty(0x11000.into());
+
+    let remapped = manager
+        .remap(0x11000.into(), 0x1000, 0x3000, RemapFlags::MAYMOVE)
+        .unwrap();
+    let start = remapped.start;
+    assert_ne!(start, VirtAddr::from(0x11000));
+    assert!(manager.find_region(0x11000.into()).is_none());
+    assert_eq!(
+        manager.find_region(0x10000.into()).unwrap().range,
+        range(0x10000, 0x1000)
+    );
+    assert_eq!(
+        manager.find_region(0x12000.into()).unwrap().range,
+        range(0x12000, 0x1000)
+    );
+
+    let moved = manager.find_region(start).unwrap();
+    assert_eq!(moved.range, range(start.as_usize(), 0x3000));
+    assert!(moved.is_populated(start));
+    assert!(moved.is_dirty(start));
+    assert!(!moved.is_populated(start + 0x1000));
+    assert_eq!(moved.file_offset_of(start), Ok(0x1000));
+    assert_eq!(manager.len(), 4);
+}
+
+#[test]
+fn failed_move_leaves_the_mapping_in_place() {
+    let mut manager = manager_with(&[(0x10000, 0x2000, 0), (0x12000, 0x1000, 0x2000)]);
+    manager.set_max_regions(Some(2));
+    assert_eq!(
+        manager
+            .remap(0x10000.into(), 0x1000, 0x3000, RemapFlags::MAYMOVE)
+            .err(),
+        Some(VmaError::RegionLimit(2))
+    );
+    assert_eq!(manager.len(), 2);
+    assert_eq!(
+        manager.find_region(0x11000.into()).unwrap().range,
+        range(0x10000, 0x2000)
+    );
+}
+
+#[test]
+fn moved_mapping_keeps_its_guard_gaps_clear() {
+    let file = TestFile::new(0x10_0000);
+    let mut manager = manager_with(&[(0x10000, 0x2000, 0)]);
+    let guarded = MmapRegionBuilder::new(range(0x20000, 0x1000))
+        .file(file.clone(), 0x4000)
+        .guard_gaps(0x2000, 0x2000)
+        .build()
+        .unwrap();
+    manager.add_region(guarded).unwrap();
+    manager
+        .add_region(MmapRegion::new(
+            range(0x23000, 0x1000),
+            file,
+            0x8000,
+            PageSize::Size4K,
+        ))
+        .unwrap();
+
+    let start = manager
+        .remap(0x20000.into(), 0x1000, 0x2000, RemapFlags::MAYMOVE)
+        .unwrap()
+        .start;
+    let moved = manager.find_region(start).unwrap();
+    assert_eq!(moved.range.size(), 0x2000);
+    assert_eq!((moved.guard_below, moved.guard_above), (0x2000, 0x2000));
+    for other in manager.iter().filter(|other| other.range != moved.range) {
+        assert!(
+            other.range.end + 0x2000 <= moved.range.start
+                || moved.range.end + 0x2000 <= other.range.start,
+            "{:?} is within the guard gaps of {:?}",
+            other.range,
+            moved.range
+        );
+    }
+}
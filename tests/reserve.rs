@@ -0,0 +1,111 @@
+mod common;
+
+use axerrno::LinuxError;
+use axvma::*;
+use common::{TestFile, range};
+use memory_addr::VirtAddr;
+use page_table_multiarch::PageSize;
+
+const GIB: usize = 1 << 30;
+
+fn reserved() -> VmaManager<TestFile> {
+    let mut manager = VmaManager::new();
+    let mut reservation = MmapRegion::new_reserved(range(0x4000_0000, GIB), PageSize::Size4K);
+    reservation.name = Some("heap".into());
+    manager.add_region(reservation).unwrap();
+    manager
+}
+
+#[test]
+fn faults_in_a_reservation_are_denied() {
+    let manager = reserved();
+    let region = manager.find_region(0x4000_1000.into()).unwrap();
+    assert!(region.is_reserved());
+    assert_eq!(region.prot, MmapProt::empty());
+    assert_eq!(
+        region.get_buf(0x4000_1000.into()).err(),
+        Some(VmaError::AccessDenied)
+    );
+    let err = manager
+        .handle_fault(0x4000_1000.into(), AccessFlags::READ)
+        .unwrap_err();
+    assert_eq!(err, VmaError::AccessDenied);
+    assert_eq!(LinuxError::from(err), LinuxError::EACCES);
+    assert!(
+        manager
+            .populate(range(0x4000_0000, 0x3000))
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[test]
+fn commit_carves_a_mapping_out_of_the_middle() {
+    let mut manager = reserved();
+    manager
+        .commit(
+            range(0x5000_0000, 0x2000),
+            RegionBacking::File {
+                file: TestFile::new(0x4000),
+                offset: 0,
+            },
+            MmapProt::READ,
+        )
+        .unwrap();
+    assert_eq!(manager.len(), 3);
+
+    let committed = manager.find_region(0x5000_0000.into()).unwrap();
+    assert_eq!(committed.range, range(0x5000_0000, 0x2000));
+    assert_eq!(committed.prot, MmapProt::READ);
+    assert_eq!(committed.name.as_deref(), Some("heap"));
+    assert!(!committed.is_reserved() && !committed.is_anonymous());
+    manager
+        .handle_fault(0x5000_1000.into(), AccessFlags::READ)
+        .unwrap();
+
+    for vaddr in [0x4fff_f000, 0x5000_2000] {
+        assert!(manager.find_region(vaddr.into()).unwrap().is_reserved());
+        assert_eq!(
+            manager.handle_fault(vaddr.into(), AccessFlags::READ).err(),
+            Some(VmaError::AccessDenied)
+        );
+    }
+
+    assert_eq!(
+        manager.commit(
+            range(0x5000_1000, 0x2000),
+            RegionBacking::Anonymous,
+            MmapProt::all()
+        ),
+        Err(VmaError::Overlap(range(0x5000_1000, 0x2000)))
+    );
+    assert_eq!(
+        manager.commit(
+            range(0x3000_0000, 0x1000),
+            RegionBacking::Anonymous,
+            MmapProt::all()
+        ),
+        Err(VmaError::Hole(range(0x3000_0000, 0x1000)))
+    );
+}
+
+#[test]
+fn free_range_search_skips_the_whole_reservation() {
+    let mut manager = reserved();
+    manager
+        .commit(
+            range(0x5000_0000, 0x2000),
+            RegionBacking::Anonymous,
+            MmapProt::all(),
+        )
+        .unwrap();
+    let free = manager
+        .find_free_range(
+            0x4000_0000.into(),
+            0x1000,
+            PageSize::Size4K,
+            range(0x4000_0000, 0x1_0000_0000),
+        )
+        .unwrap();
+    assert_eq!(free, VirtAddr::from(0x4000_0000 + GIB));
+}
@@ -0,0 +1,84 @@
+mod common;
+
+use axerrno::LinuxError;
+use axvma::*;
+use common::{TestFile, range};
+use page_table_multiarch::PageSize;
+
+/// Manager with two adjacent three-page file-backed regions, the first
+/// populated at its first and last page and the second at its middle page
+fn manager() -> VmaManager<TestFile> {
+    let file = TestFile::new(0x10000);
+    let mut manager = VmaManager::new();
+    for (start, offset) in [(0x10000, 0), (0x13000, 0x3000)] {
+        manager
+            .add_region(MmapRegion::new(
+                range(start, 0x3000),
+                file.clone(),
+                offset,
+                PageSize::Size4K,
+            ))
+            .unwrap();
+    }
+    for vaddr in [0x10000, 0x12000, 0x14000] {
+        manager
+            .handle_fault(vaddr.into(), AccessFlags::READ)
+            .unwrap();
+    }
+    manager
+}
+
+#[test]
+fn residency_reports_each_page_across_regions() {
+    let manager = manager();
+    assert_eq!(
+        manager.residency(range(0x10000, 0x6000)),
+        Ok(vec![true, false, true, false, true, false])
+    );
+    assert_eq!(
+        manager.residency(range(0x12000, 0x2000)),
+        Ok(vec![true, false])
+    );
+}
+
+#[test]
+fn residency_aligns_a_mid_page_start_down() {
+    let manager = manager();
+    assert_eq!(
+        manager.residency(range(0x11800, 0x1000)),
+        Ok(vec![false, true])
+    );
+    assert_eq!(manager.residency(range(0x12fff, 1)), Ok(vec![true]));
+}
+
+#[test]
+fn residency_of_a_partly_unmapped_range_is_enomem() {
+    let manager = manager();
+    let err = manager.residency(range(0x14000, 0x3000)).unwrap_err();
+    assert_eq!(err, VmaError::Hole(range(0x14000, 0x3000)));
+    assert_eq!(LinuxError::from(err), LinuxError::ENOMEM);
+    assert!(manager.residency(range(0x8000, 0x9000)).is_err());
+}
+
+#[test]
+fn huge_pages_are_reported_per_4k_page() {
+    let mut manager = VmaManager::<TestFile>::new();
+    manager
+        .add_region(MmapRegion::new_anonymous(
+            range(0x20_0000, 0x40_0000),
+            PageSize::Size2M,
+        ))
+        .unwrap();
+    manager
+        .handle_fault(0x20_1234.into(), AccessFlags::READ)
+        .unwrap();
+
+    let resident = manager.residency(range(0x20_0000, 0x40_0000)).unwrap();
+    assert_eq!(resident.len(), 0x400);
+    assert!(resident[..0x200].iter().all(|&page| page));
+    assert!(resident[0x200..].iter().all(|&page| !page));
+    assert_eq!(
+        manager.residency(range(0x3f_f800, 0x1000)),
+        Ok(vec![true, false])
+    );
+}
@@ -0,0 +1,109 @@
+mod common;
+
+use axvma::*;
+use common::{TestFile, range};
+use page_table_multiarch::PageSize;
+
+fn region() -> MmapRegion<TestFile> {
+    MmapRegion::new(
+        range(0x10000, 0x4000),
+        TestFile::new(0x10000),
+        0,
+        PageSize::Size4K,
+    )
+}
+
+#[test]
+fn shared_handles_see_each_others_pages() {
+    let first = region();
+    let second = first.share();
+    assert!(first.shares_population() && second.shares_population());
+
+    first.get_buf(0x10000.into()).unwrap();
+    assert!(second.is_populated(0x10000.into()));
+    assert_eq!(
+        second.get_buf(0x10000.into()).err(),
+        Some(VmaError::AlreadyPopulated)
+    );
+
+    for vaddr in [0x11000, 0x12000, 0x13000] {
+        second.get_buf(vaddr.into()).unwrap();
+    }
+    assert!(first.is_fully_populated());
+    assert_eq!(first.resident_bytes(), 0x4000);
+
+    // Evicting through one handle drops the page from both
+    assert!(first.try_start_evict(0x13000.into()));
+    assert!(first.finish_evict(0x13000.into()));
+    assert!(!second.is_populated(0x13000.into()));
+    assert!(!second.is_fully_populated());
+}
+
+#[test]
+fn deep_clones_stay_independent() {
+    let original = region();
+    original.get_buf(0x10000.into()).unwrap();
+    let copy = original.clone();
+    assert!(!original.shares_population() && !copy.shares_population());
+    assert!(copy.is_populated(0x10000.into()));
+
+    copy.get_buf(0x11000.into()).unwrap();
+    original.get_buf(0x12000.into()).unwrap();
+    assert!(!original.is_populated(0x11000.into()));
+    assert!(!copy.is_populated(0x12000.into()));
+
+    // A clone of a shared handle does not join the sharing
+    let shared = original.share();
+    let copy = shared.clone();
+    shared.get_buf(0x13000.into()).unwrap();
+    assert!(original.is_populated(0x13000.into()));
+    assert!(!copy.is_populated(0x13000.into()));
+}
+
+#[test]
+fn punching_a_hole_unshares_the_handle() {
+    let first = region();
+    first.get_buf(0x10000.into()).unwrap();
+    let mut second = first.share();
+    second.punch_hole(range(0x11000, 0x1000)).unwrap();
+    assert!(!second.shares_population());
+    assert!(!first.shares_population());
+    assert!(second.is_populated(0x10000.into()));
+
+    second.get_buf(0x12000.into()).unwrap();
+    assert!(!first.is_populated(0x12000.into()));
+}
+
+#[test]
+fn fork_shares_population_of_shared_regions_only() {
+    let file = TestFile::new(0x10000);
+    let mut manager = VmaManager::new();
+    let mut shared = MmapRegion::new(range(0x20000, 0x2000), file.clone(), 0, PageSize::Size4K);
+    shared.flags = MmapFlags::SHARED;
+    manager.add_region(shared).unwrap();
+    manager
+        .add_region(MmapRegion::new(
+            range(0x30000, 0x2000),
+            file,
+            0x2000,
+            PageSize::Size4K,
+        ))
+        .unwrap();
+
+    let child = manager.fork();
+    for vaddr in [0x20000, 0x30000] {
+        manager
+            .handle_fault(vaddr.into(), AccessFlags::READ)
+            .unwrap();
+    }
+    let child_shared = child.find_region(0x20000.into()).unwrap();
+    assert!(child_shared.shares_population());
+    assert!(child_shared.is_populated(0x20000.into()));
+    assert_eq!(
+        child.handle_fault(0x20000.into(), AccessFlags::READ),
+        Err(VmaError::AlreadyPopulated)
+    );
+    let child_private = child.find_region(0x30000.into()).unwrap();
+    assert!(!child_private.shares_population());
+    assert!(!child_private.is_populated(0x30000.into()));
+}
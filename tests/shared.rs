@@ -0,0 +1,133 @@
+mod common;
+
+use axvma::*;
+use common::{TestFile, range};
+use page_table_multiarch::PageSize;
+use std::{sync::Arc, thread};
+
+fn file_region(start: usize, file: &TestFile) -> MmapRegion<TestFile> {
+    MmapRegion::new(range(start, 0x2000), file.clone(), 0, PageSize::Size4K)
+}
+
+#[test]
+fn region_handle_survives_a_concurrent_add() {
+    let file = TestFile::new(0x4000);
+    let manager = Arc::new(SharedVmaManager::new());
+    manager.add_region(file_region(0x10000, &file)).unwrap();
+
+    // A fault holds on to its region while another path maps elsewhere
+    let region = manager.find_region_arc(0x10000.into()).unwrap();
+    let writer = {
+        let (manager, file) = (manager.clone(), file.clone());
+        thread::spawn(move || {
+            manager.add_region(file_region(0x20000, &file)).unwrap();
+            manager
+                .handle_fault(0x20000.into(), AccessFlags::READ)
+                .unwrap();
+        })
+    };
+    writer.join().unwrap();
+
+    assert!(region.get_buf(0x10000.into()).is_ok());
+    let manager = manager.read();
+    assert_eq!(manager.len(), 2);
+    assert!(
+        manager
+            .find_region(0x10000.into())
+            .unwrap()
+            .is_populated(0x10000.into())
+    );
+    assert!(
+        manager
+            .find_region(0x20000.into())
+            .unwrap()
+            .is_populated(0x20000.into())
+    );
+}
+
+#[test]
+fn faults_only_take_the_read_lock() {
+    let file = TestFile::new(0x4000);
+    let manager = Arc::new(SharedVmaManager::new());
+    manager.add_region(file_region(0x10000, &file)).unwrap();
+    manager.add_region(file_region(0x20000, &file)).unwrap();
+
+    let guard = manager.read();
+    let region = guard.find_region(0x10000.into()).unwrap();
+    let faulter = {
+        let manager = manager.clone();
+        thread::spawn(move || {
+            manager
+                .handle_fault(0x20000.into(), AccessFlags::READ)
+                .is_ok()
+        })
+    };
+    assert!(faulter.join().unwrap());
+    assert!(region.get_buf(0x10000.into()).is_ok());
+    drop(guard);
+
+    assert_eq!(manager.clear().regions.len(), 2);
+    assert!(manager.read().is_empty());
+}
+
+#[test]
+fn region_handle_loads_without_the_manager_lock() {
+    let file = TestFile::new(0x10000);
+    let manager = SharedVmaManager::new();
+    manager.add_region(file_region(0x10000, &file)).unwrap();
+    let region = manager.find_region_arc(0x11000.into()).unwrap();
+
+    // Writers are not blocked while the handle is alive
+    drop(manager.write());
+    region.get_buf(0x11000.into()).unwrap();
+    assert!(
+        manager
+            .read()
+            .find_region(0x11000.into())
+            .unwrap()
+            .is_populated(0x11000.into())
+    );
+
+    // A clone of the manager has its own page state
+    let copy = manager.read().clone();
+    region.get_buf(0x10000.into()).unwrap();
+    assert!(
+        !copy
+            .find_region(0x10000.into())
+            .unwrap()
+            .is_populated(0x10000.into())
+    );
+}
+
+#[test]
+fn region_handle_outlives_removal_unseen_by_the_manager() {
+    let file = TestFile::new(0x10000);
+    let manager = SharedVmaManager::new();
+    manager.add_region(file_region(0x10000, &file)).unwrap();
+    let region = manager.find_region_arc(0x10000.into()).unwrap();
+    region.get_buf(0x10000.into()).unwrap();
+
+    let removed = manager.remove_overlapped(range(0x10000, 0x2000)).unwrap();
+    assert_eq!(removed.pages.len(), 1);
+    region.get_buf(0x11000.into()).unwrap();
+    assert!(region.is_populated(0x11000.into()));
+    assert!(manager.read().find_region(0x11000.into()).is_none());
+}
+
+#[test]
+fn region_handle_detaches_when_the_region_changes_in_place() {
+    let file = TestFile::new(0x10000);
+    let mut manager = VmaManager::new();
+    manager.add_region(file_region(0x10000, &file)).unwrap();
+    let region = manager.find_region_arc(0x10000.into()).unwrap();
+    manager.iter_mut().next().unwrap().name = Some("renamed".into());
+
+    region.get_buf(0x10000.into()).unwrap();
+    assert!(
+        !manager
+            .find_region(0x10000.into())
+            .unwrap()
+            .is_populated(0x10000.into())
+    );
+    assert_eq!(region.name, None);
+}
@@ -0,0 +1,96 @@
+mod common;
+
+use axvma::*;
+use common::{TestFile, range};
+use page_table_multiarch::PageSize;
+
+#[test]
+fn smaps_counts_each_kind_of_page_exactly() {
+    let file = TestFile::new(0x10000);
+    let mut manager = VmaManager::new();
+    let mut shared = MmapRegion::new(range(0x10000, 0x4000), file.clone(), 0, PageSize::Size4K);
+    shared.flags = MmapFlags::SHARED;
+    shared.populate_range(&shared.range).unwrap();
+    shared.mark_dirty(0x11000.into());
+    assert!(shared.evict(0x13000.into()));
+    manager.add_region(shared).unwrap();
+
+    let anon = MmapRegion::new_anonymous(range(0x20000, 0x3000), PageSize::Size4K);
+    anon.handle_write_fault(0x20000.into()).unwrap();
+    anon.handle_write_fault(0x21000.into()).unwrap();
+    manager.add_region(anon).unwrap();
+    manager
+        .advise(range(0x21000, 0x1000), Advice::Free)
+        .unwrap();
+
+    // A written page of a private file mapping is both dirty and anonymous
+    let private = MmapRegion::new(range(0x30000, 0x2000), file, 0, PageSize::Size4K);
+    private.handle_write_fault(0x30000.into()).unwrap();
+    manager.add_region(private).unwrap();
+    manager.lock_range(range(0x30000, 0x2000)).unwrap();
+
+    let stats: Vec<_> = manager.smaps().collect();
+    assert_eq!(
+        stats,
+        vec![
+            (
+                range(0x10000, 0x4000),
+                RegionStats {
+                    size: 0x4000,
+                    resident: 0x3000,
+                    dirty: 0x1000,
+                    anonymous: 0,
+                    lazy_free: 0,
+                    locked: 0,
+                }
+            ),
+            (
+                range(0x20000, 0x3000),
+                RegionStats {
+                    size: 0x3000,
+                    resident: 0x2000,
+                    dirty: 0,
+                    anonymous: 0x2000,
+                    lazy_free: 0x1000,
+                    locked: 0,
+                }
+            ),
+            (
+                range(0x30000, 0x2000),
+                RegionStats {
+                    size: 0x2000,
+                    resident: 0x2000,
+                    dirty: 0x1000,
+                    anonymous: 0x1000,
+                    lazy_free: 0,
+                    locked: 0x2000,
+                }
+            ),
+        ]
+    );
+    assert_eq!(
+        manager.find_region(0x10000.into()).unwrap().smaps(),
+        stats[0].1
+    );
+}
+
+#[test]
+fn region_stats_render_like_smaps() {
+    let stats = RegionStats {
+        size: 0x4000,
+        resident: 0x3000,
+        dirty: 0x1000,
+        anonymous: 0,
+        lazy_free: 0,
+        locked: 0x400,
+    };
+    assert_eq!(
+        stats.to_string(),
+        "Size:                 16 kB\n\
+         Rss:                  12 kB\n\
+         Dirty:                 4 kB\n\
+         Anonymous:             0 kB\n\
+         LazyFree:              0 kB\n\
+         Locked:                1 kB\n"
+    );
+}
@@ -0,0 +1,59 @@
+mod common;
+
+use axvma::*;
+use common::{TestFile, range};
+use page_table_multiarch::PageSize;
+
+fn file_region(start: usize, size: usize, file: &TestFile) -> MmapRegion<TestFile> {
+    MmapRegion::new(range(start, size), file.clone(), 0, PageSize::Size4K)
+}
+
+#[test]
+fn snapshot_keeps_resolving_the_old_layout() {
+    let file = TestFile::new(0x10000);
+    let manager = SnapshotVmaManager::new();
+    manager
+        .add_region(file_region(0x10000, 0x4000, &file))
+        .unwrap();
+    manager
+        .add_region(file_region(0x20000, 0x1000, &file))
+        .unwrap();
+    assert_eq!(
+        manager
+            .add_region(file_region(0x13000, 0x1000, &file))
+            .err(),
+        Some(VmaError::Overlap(range(0x13000, 0x1000)))
+    );
+
+    let old = manager.snapshot();
+    let removed = manager.remove_overlapped(range(0x11000, 0x1000)).unwrap();
+    assert_eq!(removed.regions.len(), 1);
+
+    assert!(old.handle_fault(0x11000.into(), AccessFlags::READ).is_ok());
+    assert_eq!(
+        old.find_region(0x11000.into()).unwrap().range,
+        range(0x10000, 0x4000)
+    );
+    assert_eq!(
+        manager
+            .handle_fault(0x11000.into(), AccessFlags::READ)
+            .err(),
+        Some(VmaError::Unmapped(0x11000.into()))
+    );
+    assert_eq!(old.len(), 2);
+    assert_eq!(manager.snapshot().len(), 3);
+
+    // Regions the unmap left alone are shared with the old snapshot
+    manager
+        .handle_fault(0x20000.into(), AccessFlags::READ)
+        .unwrap();
+    assert!(
+        old.find_region(0x20000.into())
+            .unwrap()
+            .is_populated(0x20000.into())
+    );
+
+    assert_eq!(manager.clear().regions.len(), 3);
+    assert!(manager.snapshot().is_empty());
+    assert_eq!(old.len(), 2);
+}
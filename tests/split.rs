@@ -0,0 +1,237 @@
+mod common;
+
+use axvma::*;
+use common::{TestFile, range};
+use memory_addr::VirtAddr;
+use page_table_multiarch::PageSize;
+
+#[test]
+fn split_preserves_every_other_page_of_a_large_region() {
+    // 1 GiB of 4 KiB pages; half of the 262144 pages populated would take
+    // around 5 MiB as a BTreeSet<VirtAddr> but 32 KiB as a bitmap
+    const START: usize = 0x4000_0000;
+    const SIZE: usize = 0x4000_0000;
+    let region = MmapRegion::new(range(START, SIZE), TestFile::new(0x10), 0, PageSize::Size4K);
+    let pages = SIZE / 0x1000;
+    {
+        let mut populated = region.populated.lock();
+        for page in (0..pages).step_by(2) {
+            populated.insert(VirtAddr::from(START + page * 0x1000));
+        }
+        assert_eq!(populated.len(), pages / 2);
+    }
+
+    // Odd page offsets on both sides of the removed range
+    let (before, overlap, after) = region
+        .split_at_range(&range(START + 0x3000 + 77 * 0x1000, 1001 * 0x1000))
+        .unwrap();
+    let segments = [before.unwrap(), overlap.unwrap(), after.unwrap()];
+    let mut total = 0;
+    for segment in &segments {
+        let populated = segment.populated.lock();
+        let mut expected = 0;
+        for page in (segment.range.start.as_usize()..segment.range.end.as_usize()).step_by(0x1000) {
+            let even = ((page - START) / 0x1000).is_multiple_of(2);
+            assert_eq!(populated.contains(page.into()), even, "{page:#x}");
+            expected += even as usize;
+        }
+        assert_eq!(populated.len(), expected);
+        assert!(populated.iter().all(|page| segment.range.contains(page)));
+        total += expected;
+    }
+    assert_eq!(total, pages / 2);
+}
+
+#[test]
+fn page_set_keeps_sparse_pages_in_order() {
+    let mut set = PageSet::new(PageSize::Size4K);
+    assert!(set.insert(0x10_0000.into()));
+    assert!(set.insert(0x1000.into()));
+    assert!(set.insert(0x2_0000_0000usize.into()));
+    assert!(!set.insert(0x1000.into()));
+    assert_eq!(
+        set.iter().collect::<Vec<_>>(),
+        vec![
+            VirtAddr::from(0x1000),
+            VirtAddr::from(0x10_0000),
+            VirtAddr::from(0x2_0000_0000usize),
+        ]
+    );
+    assert!(set.remove(0x10_0000.into()));
+    assert!(!set.remove(0x10_0000.into()));
+    assert_eq!(set.len(), 2);
+}
+
+#[test]
+fn split_inside_a_populated_huge_page_is_rejected() {
+    let file = TestFile::new(0x80_0000);
+    let region = MmapRegion::new(range(0x40_0000, 0x40_0000), file, 0, PageSize::Size2M);
+    region.get_buf(0x40_0000.into()).unwrap();
+
+    for split in [
+        range(0x40_1000, 0x1000),
+        range(0x3f_f000, 0x20_2000),
+        range(0x60_0000, 0x1000),
+    ] {
+        assert_eq!(
+            region.split_at_range(&split).err(),
+            Some(VmaError::Unaligned)
+        );
+    }
+    assert!(region.is_populated(0x40_1000.into()));
+    assert_eq!(
+        region.populated_in(&region.range),
+        vec![(VirtAddr::from(0x40_0000), PageSize::Size2M)]
+    );
+
+    let (before, overlap, after) = region.split_at_range(&range(0x3f_f000, 0x20_1000)).unwrap();
+    assert!(before.is_none());
+    assert_eq!(overlap.unwrap().range, range(0x40_0000, 0x20_0000));
+    assert_eq!(after.unwrap().range, range(0x60_0000, 0x20_0000));
+}
+
+#[test]
+fn demoted_huge_page_splits_into_small_pages() {
+    let file = TestFile::new(0x80_0000);
+    let mut region = MmapRegion::new(range(0x40_0000, 0x40_0000), file, 0, PageSize::Size2M);
+    region.get_buf(0x40_0000.into()).unwrap();
+    assert_eq!(
+        region.demote(PageSize::Size4K).unwrap(),
+        vec![(VirtAddr::from(0x40_0000), PageSize::Size2M)]
+    );
+
+    let (before, overlap, after) = region.split_at_range(&range(0x40_1000, 0x1000)).unwrap();
+    let (before, overlap, after) = (before.unwrap(), overlap.unwrap(), after.unwrap());
+    assert_eq!(before.range, range(0x40_0000, 0x1000));
+    assert_eq!(
+        before.populated_in(&before.range),
+        vec![(VirtAddr::from(0x40_0000), PageSize::Size4K)]
+    );
+    assert!(overlap.is_populated(0x40_1000.into()));
+    assert_eq!(after.range, range(0x40_2000, 0x3f_e000));
+    assert_eq!(after.populated_in(&after.range).len(), 0x1fe);
+    assert!(after.is_populated(0x5f_f000.into()));
+    assert!(!after.is_populated(0x60_0000.into()));
+    assert_eq!(after.file_offset_of(0x60_0000.into()), Ok(0x20_0000));
+}
+
+#[test]
+fn split_partitions_pages_at_and_next_to_both_boundaries() {
+    let region = MmapRegion::new(
+        range(0x10_0000, 0x10_0000),
+        TestFile::new(0x10_0000),
+        0,
+        PageSize::Size4K,
+    );
+    let populated = [
+        0x10_0000, 0x13_f000, 0x14_0000, 0x14_1000, 0x17_f000, 0x18_0000, 0x1f_f000,
+    ];
+    for page in populated {
+        region.get_buf(page.into()).unwrap();
+    }
+    for page in [0x13_f000, 0x14_0000, 0x17_f000, 0x18_0000] {
+        assert!(region.mark_dirty(page.into()));
+    }
+
+    let (before, overlap, after) = region.split_at_range(&range(0x14_0000, 0x4_0000)).unwrap();
+    let (before, overlap, after) = (before.unwrap(), overlap.unwrap(), after.unwrap());
+    let pages = |part: &MmapRegion<TestFile>| -> Vec<usize> {
+        part.populated_iter().map(VirtAddr::as_usize).collect()
+    };
+    let dirty = |part: &MmapRegion<TestFile>| -> Vec<usize> {
+        part.dirty_in(&part.range)
+            .into_iter()
+            .map(VirtAddr::as_usize)
+            .collect()
+    };
+    assert_eq!(pages(&before), vec![0x10_0000, 0x13_f000]);
+    assert_eq!(pages(&overlap), vec![0x14_0000, 0x14_1000, 0x17_f000]);
+    assert_eq!(pages(&after), vec![0x18_0000, 0x1f_f000]);
+    assert_eq!(dirty(&before), vec![0x13_f000]);
+    assert_eq!(dirty(&overlap), vec![0x14_0000, 0x17_f000]);
+    assert_eq!(dirty(&after), vec![0x18_0000]);
+
+    // The original keeps its own pages
+    assert_eq!(pages(&region), populated);
+}
+
+fn pages(addrs: &[usize]) -> Vec<VirtAddr> {
+    addrs.iter().copied().map(VirtAddr::from).collect()
+}
+
+#[test]
+fn split_report_lists_the_pages_of_each_segment() {
+    let region = MmapRegion::new(
+        range(0x10000, 0x6000),
+        TestFile::new(0x10000),
+        0,
+        PageSize::Size4K,
+    );
+    // Populated on both sides of each boundary, except right after the second
+    for vaddr in [0x10000, 0x11000, 0x12000, 0x13000, 0x15000] {
+        region.get_buf(vaddr.into()).unwrap();
+    }
+    let ((before, overlap, after), report) = region
+        .split_at_range_detailed(&range(0x12000, 0x2000))
+        .unwrap();
+    assert_eq!(
+        report,
+        SplitReport {
+            before: Some(SegmentPages {
+                range: range(0x10000, 0x2000),
+                pages: pages(&[0x10000, 0x11000]),
+            }),
+            overlap: Some(SegmentPages {
+                range: range(0x12000, 0x2000),
+                pages: pages(&[0x12000, 0x13000]),
+            }),
+            after: Some(SegmentPages {
+                range: range(0x14000, 0x2000),
+                pages: pages(&[0x15000]),
+            }),
+        }
+    );
+    // The report matches what the segments hold
+    for (segment, listed) in [
+        (before, report.before),
+        (overlap, report.overlap),
+        (after, report.after),
+    ] {
+        let (segment, listed) = (segment.unwrap(), listed.unwrap());
+        assert_eq!(segment.range, listed.range);
+        assert_eq!(segment.populated_iter().collect::<Vec<_>>(), listed.pages);
+    }
+}
+
+#[test]
+fn remove_overlapped_reports_the_splits_it_made() {
+    let file = TestFile::new(0x10000);
+    let mut manager = VmaManager::new();
+    let first = MmapRegion::new(range(0x10000, 0x6000), file.clone(), 0, PageSize::Size4K);
+    for vaddr in [0x10000, 0x11000, 0x12000, 0x13000, 0x15000] {
+        first.get_buf(vaddr.into()).unwrap();
+    }
+    manager.add_region(first).unwrap();
+    let second = MmapRegion::new(range(0x20000, 0x2000), file, 0, PageSize::Size4K);
+    second.get_buf(0x21000.into()).unwrap();
+    manager.add_region(second).unwrap();
+
+    let removed = manager.remove_overlapped(range(0x15000, 0xc000)).unwrap();
+    assert_eq!(removed.splits.len(), 2);
+    let [first, second] = &removed.splits[..] else {
+        unreachable!();
+    };
+    assert_eq!(
+        first.before.as_ref().unwrap().pages,
+        pages(&[0x10000, 0x11000, 0x12000, 0x13000])
+    );
+    assert_eq!(first.overlap.as_ref().unwrap().pages, pages(&[0x15000]));
+    assert!(first.after.is_none());
+    assert!(second.before.is_none());
+    assert_eq!(
+        second.overlap.as_ref().unwrap().range,
+        range(0x20000, 0x1000)
+    );
+    assert!(second.overlap.as_ref().unwrap().pages.is_empty());
+    assert_eq!(second.after.as_ref().unwrap().pages, pages(&[0x21000]));
+}
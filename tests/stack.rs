@@ -0,0 +1,116 @@
+mod common;
+
+use axvma::*;
+use common::{TestFile, range};
+use page_table_multiarch::PageSize;
+
+/// Manager with a region at 0x10000 and a two-page stack at 0x40000
+fn with_stack() -> VmaManager<TestFile> {
+    let mut manager = VmaManager::new();
+    manager
+        .add_region(MmapRegion::new_anonymous(
+            range(0x10000, 0x1000),
+            PageSize::Size4K,
+        ))
+        .unwrap();
+    let mut stack = MmapRegion::new_anonymous(range(0x40000, 0x2000), PageSize::Size4K);
+    stack.flags |= MmapFlags::GROWSDOWN;
+    manager.add_region(stack).unwrap();
+    manager
+}
+
+#[test]
+fn fault_one_page_below_grows_the_stack() {
+    let mut manager = with_stack();
+    manager.set_stack_growth(0x8000, 0x20000);
+    let resolution = manager
+        .handle_fault_or_grow(0x3f010.into(), AccessFlags::WRITE)
+        .unwrap();
+    assert_eq!(resolution.vaddr, 0x3f000.into());
+    assert_eq!(resolution.data, FaultData::Zero);
+    assert_eq!(
+        manager.find_region(0x40000.into()).unwrap().range,
+        range(0x3f000, 0x3000)
+    );
+    assert!(
+        manager
+            .find_region(0x3f000.into())
+            .unwrap()
+            .is_populated(0x3f000.into())
+    );
+}
+
+#[test]
+fn fault_beyond_the_growth_limit_stays_unmapped() {
+    let mut manager = with_stack();
+    manager.set_stack_growth(0x8000, 0x20000);
+    assert_eq!(
+        manager
+            .handle_fault_or_grow(0x37000.into(), AccessFlags::READ)
+            .err(),
+        Some(VmaError::Unmapped(0x37000.into()))
+    );
+    assert_eq!(
+        manager.find_region(0x40000.into()).unwrap().range,
+        range(0x40000, 0x2000)
+    );
+    // Right at the limit still grows
+    assert!(
+        manager
+            .handle_fault_or_grow(0x38000.into(), AccessFlags::READ)
+            .is_ok()
+    );
+
+    // Faults above or far from any stack are not grown into
+    assert_eq!(
+        manager
+            .handle_fault_or_grow(0x50000.into(), AccessFlags::READ)
+            .err(),
+        Some(VmaError::Unmapped(0x50000.into()))
+    );
+    assert_eq!(
+        manager
+            .handle_fault_or_grow(0x11000.into(), AccessFlags::READ)
+            .err(),
+        Some(VmaError::Unmapped(0x11000.into()))
+    );
+}
+
+#[test]
+fn fault_into_the_guard_gap_stays_unmapped() {
+    let mut manager = with_stack();
+    manager.set_stack_growth(0x40000, 0x20000);
+    // 0x30000 is within the gap above the region ending at 0x11000
+    assert_eq!(
+        manager
+            .handle_fault_or_grow(0x30000.into(), AccessFlags::READ)
+            .err(),
+        Some(VmaError::Unmapped(0x30000.into()))
+    );
+    assert_eq!(
+        manager.find_region(0x40000.into()).unwrap().range.start,
+        0x40000.into()
+    );
+    assert!(
+        manager
+            .handle_fault_or_grow(0x31000.into(), AccessFlags::READ)
+            .is_ok()
+    );
+    assert_eq!(
+        manager.find_region(0x31000.into()).unwrap().range,
+        range(0x31000, 0x11000)
+    );
+}
+
+#[test]
+fn grows_down_requires_an_anonymous_region() {
+    let mut manager = VmaManager::new();
+    let mut region = MmapRegion::new(
+        range(0x90000, 0x1000),
+        TestFile::new(0x1000),
+        0,
+        PageSize::Size4K,
+    );
+    region.flags |= MmapFlags::GROWSDOWN;
+    assert_eq!(manager.add_region(region), Err(VmaError::InvalidArgument));
+}
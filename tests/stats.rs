@@ -0,0 +1,91 @@
+mod common;
+
+use axvma::*;
+use common::{TestFile, range};
+use page_table_multiarch::PageSize;
+
+#[test]
+fn stats_add_up_across_a_split() {
+    let file = TestFile::new(0x10000);
+    let mut manager = VmaManager::new();
+    manager
+        .add_region(MmapRegion::new(
+            range(0x10000, 0x5000),
+            file.clone(),
+            0,
+            PageSize::Size4K,
+        ))
+        .unwrap();
+    manager
+        .add_region(MmapRegion::new(
+            range(0x20000, 0x1800),
+            file,
+            0,
+            PageSize::Size4K,
+        ))
+        .unwrap();
+    manager.populate(range(0x10000, 0x12000)).unwrap();
+    manager
+        .find_region(0x12000.into())
+        .unwrap()
+        .mark_dirty(0x12000.into());
+
+    let before = manager.stats();
+    assert_eq!(
+        before,
+        VmaStats {
+            total_mapped: 0x6800,
+            total_resident: 0x6800,
+            total_dirty: 0x1000,
+            region_count: 2,
+            largest_gap: 0xb000,
+        }
+    );
+
+    let removed = manager.remove_overlapped(range(0x11000, 0x2000)).unwrap();
+    let removed_mapped: usize = removed.regions.iter().map(MmapRegion::mapped_bytes).sum();
+    let removed_resident: usize = removed.regions.iter().map(MmapRegion::resident_bytes).sum();
+    let removed_dirty: usize = removed.regions.iter().map(MmapRegion::dirty_bytes).sum();
+    assert_eq!(
+        (removed_mapped, removed_resident, removed_dirty),
+        (0x2000, 0x2000, 0x1000)
+    );
+
+    let after = manager.stats();
+    assert_eq!(after.total_mapped + removed_mapped, before.total_mapped);
+    assert_eq!(
+        after.total_resident + removed_resident,
+        before.total_resident
+    );
+    assert_eq!(after.total_dirty + removed_dirty, before.total_dirty);
+    assert_eq!(after.region_count, 3);
+    assert_eq!(after.largest_gap, 0xb000);
+}
+
+#[test]
+fn splitting_a_region_keeps_its_totals() {
+    let region = MmapRegion::new(
+        range(0x10000, 0x4000),
+        TestFile::new(0x4000),
+        0,
+        PageSize::Size4K,
+    );
+    for page in [0x10000, 0x11000, 0x13000] {
+        region.get_buf(page.into()).unwrap();
+    }
+    let (mapped, resident) = (region.mapped_bytes(), region.resident_bytes());
+    let (before, overlap, after) = region.split_at_range(&range(0x11000, 0x2000)).unwrap();
+    let segments: Vec<_> = [before, overlap, after].into_iter().flatten().collect();
+    assert_eq!(
+        segments.iter().map(MmapRegion::mapped_bytes).sum::<usize>(),
+        mapped
+    );
+    assert_eq!(
+        segments
+            .iter()
+            .map(MmapRegion::resident_bytes)
+            .sum::<usize>(),
+        resident
+    );
+    assert_eq!(VmaManager::<TestFile>::new().stats(), VmaStats::default());
+}
@@ -0,0 +1,99 @@
+#![cfg(feature = "std")]
+
+use axerrno::LinuxError;
+use axvma::*;
+use memory_addr::{VirtAddr, VirtAddrRange};
+use page_table_multiarch::PageSize;
+use std::{
+    fs::{File, OpenOptions},
+    io::{Error, ErrorKind},
+    path::PathBuf,
+    sync::Arc,
+};
+
+fn range(start: usize, size: usize) -> VirtAddrRange {
+    VirtAddrRange::from_start_size(VirtAddr::from(start), size)
+}
+
+/// Temporary file holding `data`, removed when dropped
+struct TempFile(PathBuf);
+
+impl TempFile {
+    fn new(name: &str, data: &[u8]) -> Self {
+        let path = std::env::temp_dir().join(format!("axvma-{}-{name}", std::process::id()));
+        std::fs::write(&path, data).unwrap();
+        Self(path)
+    }
+
+    fn open(&self) -> Arc<File> {
+        Arc::new(
+            OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open(&self.0)
+                .unwrap(),
+        )
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+#[test]
+fn io_errors_map_to_linux_errors() {
+    for (kind, errno) in [
+        (ErrorKind::NotFound, LinuxError::ENOENT),
+        (ErrorKind::PermissionDenied, LinuxError::EACCES),
+        (ErrorKind::AlreadyExists, LinuxError::EEXIST),
+        (ErrorKind::WouldBlock, LinuxError::EAGAIN),
+        (ErrorKind::InvalidInput, LinuxError::EINVAL),
+        (ErrorKind::Interrupted, LinuxError::EINTR),
+        (ErrorKind::UnexpectedEof, LinuxError::EIO),
+    ] {
+        assert_eq!(io_error_to_linux(&Error::from(kind)), errno, "{kind:?}");
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn os_errors_keep_their_errno() {
+    assert_eq!(
+        io_error_to_linux(&Error::from_raw_os_error(13)),
+        LinuxError::EACCES
+    );
+    assert_eq!(
+        io_error_to_linux(&Error::from_raw_os_error(28)),
+        LinuxError::ENOSPC
+    );
+}
+
+#[test]
+fn host_file_pages_match_the_file_bytes() {
+    let data: Vec<u8> = (0..10000u32).map(|i| (i % 253) as u8).collect();
+    let temp = TempFile::new("read", &data);
+    let file = temp.open();
+    assert_eq!(file.len(), Ok(10000));
+
+    let region = MmapRegion::new(range(0x1000, 0x3000), file, 0, PageSize::Size4K);
+    assert_eq!(region.get_buf(0x1000.into()).unwrap()[..], data[..0x1000]);
+    assert_eq!(
+        region.get_buf(0x2000.into()).unwrap()[..],
+        data[0x1000..0x2000]
+    );
+    let last = region.get_buf(0x3000.into()).unwrap();
+    assert_eq!(last[..10000 - 0x2000], data[0x2000..]);
+    assert!(last[10000 - 0x2000..].iter().all(|&byte| byte == 0));
+}
+
+#[test]
+fn host_file_takes_write_back() {
+    let temp = TempFile::new("write", &[0; 0x2000]);
+    let region = MmapRegion::new(range(0x1000, 0x2000), temp.open(), 0, PageSize::Size4K);
+    assert_eq!(region.flush_page(0x2000.into(), &[6; 0x1000]), Ok(0x1000));
+    let contents = std::fs::read(&temp.0).unwrap();
+    assert_eq!(contents[..0x1000], [0; 0x1000]);
+    assert_eq!(contents[0x1000..], [6; 0x1000]);
+}
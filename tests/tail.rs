@@ -0,0 +1,170 @@
+mod common;
+
+use axvma::*;
+use common::{TestFile, pattern, range};
+use page_table_multiarch::PageSize;
+
+#[test]
+fn last_page_of_a_short_region_is_a_whole_page() {
+    let region = MmapRegion::new(
+        range(0x10000, 0x1800),
+        TestFile::new(0x10000),
+        0,
+        PageSize::Size4K,
+    );
+    let page = region.get_buf(0x11000.into()).unwrap();
+    assert_eq!(page.len(), 0x1000);
+    assert_eq!(page[0x7ff], pattern(0x17ff));
+    assert!(page[0x800..].iter().all(|&byte| byte == 0));
+    // Only the part inside the region counts as resident
+    assert_eq!(region.resident_bytes(), 0x800);
+}
+
+#[test]
+fn handle_fault_reports_the_mapping_size_of_the_tail() {
+    let mut manager = VmaManager::new();
+    manager
+        .add_region(MmapRegion::new(
+            range(0x20000, 0x1800),
+            TestFile::new(0x10000),
+            0,
+            PageSize::Size4K,
+        ))
+        .unwrap();
+
+    let resolution = manager
+        .handle_fault(0x21234.into(), AccessFlags::READ)
+        .unwrap();
+    assert_eq!(resolution.vaddr, 0x21000.into());
+    assert_eq!(resolution.size, PageSize::Size4K);
+    let FaultData::Loaded(data) = resolution.data else {
+        panic!("tail page was not loaded from the file");
+    };
+    assert_eq!(data.len(), 0x1000);
+    assert_eq!(data[0], pattern(0x1000));
+    assert!(data[0x800..].iter().all(|&byte| byte == 0));
+
+    let resolution = manager
+        .handle_fault(0x20000.into(), AccessFlags::READ)
+        .unwrap();
+    assert_eq!(resolution.size, PageSize::Size4K);
+    assert!(matches!(&resolution.data, FaultData::Loaded(data) if data.len() == 0x1000));
+}
+
+#[test]
+fn pages_past_the_file_backed_length_fault_as_zero() {
+    let mut manager = VmaManager::new();
+    let region = MmapRegionBuilder::new(range(0x30000, 0x3000))
+        .file(TestFile::new(0x10000), 0)
+        .file_backed_len(0x1100)
+        .build()
+        .unwrap();
+    manager.add_region(region).unwrap();
+
+    let resolution = manager
+        .handle_fault(0x32000.into(), AccessFlags::READ)
+        .unwrap();
+    assert_eq!(resolution.data, FaultData::Zero);
+    assert_eq!(resolution.size, PageSize::Size4K);
+    let region = manager.find_region(0x32000.into()).unwrap();
+    assert!(region.is_populated(0x32000.into()));
+
+    // The boundary page holds file data up to the file-backed length
+    let resolution = manager
+        .handle_fault(0x31000.into(), AccessFlags::READ)
+        .unwrap();
+    let FaultData::Loaded(data) = resolution.data else {
+        panic!("boundary page was not loaded from the file");
+    };
+    assert_eq!(data[0xff], pattern(0x10ff));
+    assert!(data[0x100..].iter().all(|&byte| byte == 0));
+}
+
+#[cfg(feature = "mem-backend")]
+#[test]
+fn borrowing_backends_lend_only_whole_pages() {
+    let data: &'static [u8] = Box::leak(vec![7; 0x2000].into_boxed_slice());
+    let region = MmapRegion::new(
+        range(0x40000, 0x1800),
+        SliceFile::new(data),
+        0,
+        PageSize::Size4K,
+    );
+    assert!(region.get_buf(0x40000.into()).unwrap().is_borrowed());
+    let tail = region.get_buf(0x41000.into()).unwrap();
+    assert!(!tail.is_borrowed());
+    assert_eq!(tail.len(), 0x1000);
+    assert!(tail[0x800..].iter().all(|&byte| byte == 0));
+}
+
+#[test]
+fn get_buf_rejects_addresses_past_the_end_before_aligning() {
+    let region = MmapRegion::new(
+        range(0x10000, 0x1800),
+        TestFile::new(0x10000),
+        0,
+        PageSize::Size4K,
+    );
+    let out_of_range = |vaddr: usize| VmaError::OutOfRange {
+        vaddr: vaddr.into(),
+        range: range(0x10000, 0x1800),
+    };
+    // At the end, inside the last hardware page
+    assert_eq!(
+        region.get_buf(0x11800.into()).err(),
+        Some(out_of_range(0x11800))
+    );
+    // Beyond the end within the same hardware page
+    assert_eq!(
+        region.get_buf(0x11fff.into()).err(),
+        Some(out_of_range(0x11fff))
+    );
+    let mut dst = vec![0; 0x1000];
+    assert_eq!(
+        region.get_buf_into(0x11900.into(), &mut dst),
+        Err(out_of_range(0x11900))
+    );
+    assert!(!region.is_populated(0x11000.into()));
+
+    // The last byte of the region is still inside it
+    let page = region.get_buf(0x117ff.into()).unwrap();
+    assert_eq!(page.len(), 0x1000);
+    assert!(page[0x800..].iter().all(|&byte| byte == 0));
+}
+
+#[test]
+fn end_of_a_page_aligned_region_is_out_of_range() {
+    let region = MmapRegion::new(
+        range(0x10000, 0x2000),
+        TestFile::new(0x10000),
+        0,
+        PageSize::Size4K,
+    );
+    assert!(matches!(
+        region.get_buf(0x12000.into()),
+        Err(VmaError::OutOfRange { .. })
+    ));
+    assert!(region.get_buf(0x11fff.into()).is_ok());
+}
+
+#[test]
+fn faults_past_the_end_of_a_short_region_are_unmapped() {
+    let mut manager = VmaManager::new();
+    manager
+        .add_region(MmapRegion::new(
+            range(0x20000, 0x1800),
+            TestFile::new(0x10000),
+            0,
+            PageSize::Size4K,
+        ))
+        .unwrap();
+    assert_eq!(
+        manager.handle_fault(0x21800.into(), AccessFlags::READ),
+        Err(VmaError::Unmapped(0x21800.into()))
+    );
+    assert!(
+        manager
+            .handle_fault(0x217ff.into(), AccessFlags::READ)
+            .is_ok()
+    );
+}
@@ -0,0 +1,93 @@
+mod common;
+
+use axvma::*;
+use common::{TestFile, range};
+use memory_addr::VirtAddr;
+use page_table_multiarch::PageSize;
+
+fn file_region(start: usize, size: usize, file: &TestFile) -> MmapRegion<TestFile> {
+    MmapRegion::new(range(start, size), file.clone(), 0, PageSize::Size4K)
+}
+
+fn ranges(teardown: &[RegionTeardown<TestFile>]) -> Vec<(usize, usize)> {
+    teardown
+        .iter()
+        .map(|part| (part.range.start.as_usize(), part.range.end.as_usize()))
+        .collect()
+}
+
+#[test]
+fn each_removed_piece_is_torn_down_exactly_once() {
+    let file = TestFile::new(0x100000);
+    let mut manager = VmaManager::new();
+    manager
+        .add_region(file_region(0x10000, 0x10000, &file))
+        .unwrap();
+    manager
+        .handle_fault(0x12000.into(), AccessFlags::READ)
+        .unwrap();
+    manager
+        .protect(range(0x14000, 0x2000), MmapProt::READ)
+        .unwrap();
+    assert_eq!(manager.len(), 3);
+
+    // The retained parts of the split are not torn down
+    let teardown = manager
+        .munmap(0x12000.into(), 0x1000)
+        .unwrap()
+        .into_teardown();
+    assert_eq!(ranges(&teardown), vec![(0x12000, 0x13000)]);
+    assert!(teardown[0].file.is_some());
+    assert_eq!(
+        teardown[0].populated.iter().collect::<Vec<_>>(),
+        vec![VirtAddr::from(0x12000)]
+    );
+
+    let teardown = manager
+        .munmap(0x13000.into(), 0x4000)
+        .unwrap()
+        .into_teardown();
+    assert_eq!(
+        ranges(&teardown),
+        vec![(0x13000, 0x14000), (0x14000, 0x16000), (0x16000, 0x17000)]
+    );
+    assert!(teardown.iter().all(|part| part.populated.is_empty()));
+
+    let teardown = manager.clear().into_teardown();
+    assert_eq!(
+        ranges(&teardown),
+        vec![(0x10000, 0x12000), (0x17000, 0x20000)]
+    );
+    assert!(manager.is_empty());
+}
+
+#[test]
+fn replaced_mappings_are_torn_down() {
+    let file = TestFile::new(0x100000);
+    let mut manager = VmaManager::new();
+    manager
+        .add_region(file_region(0x10000, 0x4000, &file))
+        .unwrap();
+    manager
+        .handle_fault(0x11000.into(), AccessFlags::READ)
+        .unwrap();
+
+    let teardown = manager
+        .add_region_replace(MmapRegion::new_anonymous(
+            range(0x11000, 0x2000),
+            PageSize::Size4K,
+        ))
+        .unwrap()
+        .into_teardown();
+    assert_eq!(ranges(&teardown), vec![(0x11000, 0x13000)]);
+    assert!(teardown[0].file.is_some());
+    assert_eq!(teardown[0].populated.len(), 1);
+
+    // Anonymous mappings have no file to release
+    let teardown = manager
+        .munmap(0x11000.into(), 0x2000)
+        .unwrap()
+        .into_teardown();
+    assert_eq!(ranges(&teardown), vec![(0x11000, 0x13000)]);
+    assert!(teardown[0].file.is_none());
+}
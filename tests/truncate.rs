@@ -0,0 +1,100 @@
+mod common;
+
+use axerrno::LinuxResult;
+use axvma::*;
+use common::{TestFile, range};
+use memory_addr::VirtAddr;
+use page_table_multiarch::PageSize;
+
+/// File without an identity
+#[derive(Clone)]
+struct AnonymousFile(TestFile);
+
+impl VmFile for AnonymousFile {
+    fn read_at(&self, buf: &mut [u8], offset: u64) -> LinuxResult<usize> {
+        self.0.read_at(buf, offset)
+    }
+
+    fn len(&self) -> LinuxResult<u64> {
+        self.0.len()
+    }
+}
+
+fn page(addr: usize) -> (VirtAddr, PageSize) {
+    (VirtAddr::from(addr), PageSize::Size4K)
+}
+
+#[test]
+fn truncation_clips_only_regions_of_that_file() {
+    let file = TestFile::new(0x8000);
+    let other = TestFile::new(0x8000);
+    let id = file.file_id().unwrap();
+    assert_ne!(other.file_id(), Some(id));
+    assert_eq!(file.clone().file_id(), Some(id));
+
+    let mut manager = VmaManager::new();
+    for (start, size, file, offset) in [
+        (0x10000, 0x4000, &file, 0),
+        (0x20000, 0x2000, &file, 0x5000),
+        (0x30000, 0x4000, &other, 0),
+    ] {
+        manager
+            .add_region(MmapRegion::new(
+                range(start, size),
+                file.clone(),
+                offset,
+                PageSize::Size4K,
+            ))
+            .unwrap();
+    }
+    for vaddr in [0x10000, 0x13000, 0x20000, 0x21000, 0x33000] {
+        manager
+            .handle_fault(vaddr.into(), AccessFlags::READ)
+            .unwrap();
+    }
+    assert_eq!(
+        manager
+            .regions_backed_by(id)
+            .map(|region| region.range.start.as_usize())
+            .collect::<Vec<_>>(),
+        vec![0x10000, 0x20000]
+    );
+
+    assert_eq!(
+        manager.invalidate_file(id, 0x3800),
+        vec![page(0x20000), page(0x21000)]
+    );
+    let populated = |vaddr: usize| {
+        manager
+            .find_region(vaddr.into())
+            .unwrap()
+            .is_populated(vaddr.into())
+    };
+    assert!(populated(0x10000) && populated(0x13000) && populated(0x33000));
+    assert!(!populated(0x20000));
+
+    // The page holding the new end survives, pages past it do not
+    assert_eq!(manager.invalidate_file(id, 0x1000), vec![page(0x13000)]);
+    assert!(populated(0x10000));
+}
+
+#[test]
+fn files_without_identity_are_never_grouped() {
+    let file = AnonymousFile(TestFile::new(0x4000));
+    assert_eq!(file.file_id(), None);
+    let mut manager = VmaManager::new();
+    manager
+        .add_region(MmapRegion::new(
+            range(0x10000, 0x4000),
+            file,
+            0,
+            PageSize::Size4K,
+        ))
+        .unwrap();
+    manager
+        .handle_fault(0x13000.into(), AccessFlags::READ)
+        .unwrap();
+    let id = TestFile::new(0).file_id().unwrap();
+    assert_eq!(manager.regions_backed_by(id).count(), 0);
+    assert!(manager.invalidate_file(id, 0).is_empty());
+}
@@ -0,0 +1,211 @@
+mod common;
+
+use axerrno::LinuxError;
+use axvma::*;
+use common::{TestFile, range};
+use memory_addr::VirtAddr;
+use page_table_multiarch::PageSize;
+
+fn populated(region: &MmapRegion<TestFile>) -> Vec<usize> {
+    region.populated_iter().map(VirtAddr::as_usize).collect()
+}
+
+#[test]
+fn removal_reports_populated_pages_inside_the_range() {
+    let file = TestFile::new(0x10000);
+    let mut manager = VmaManager::new();
+    let region = MmapRegion::new(range(0x10000, 0x4000), file, 0, PageSize::Size4K);
+    for page in (0x10000..0x14000).step_by(0x1000) {
+        region.get_buf(page.into()).unwrap();
+    }
+    manager.add_region(region).unwrap();
+
+    let removed = manager.remove_overlapped(range(0x11000, 0x2000)).unwrap();
+    assert_eq!(
+        removed.pages,
+        vec![
+            (VirtAddr::from(0x11000), PageSize::Size4K),
+            (VirtAddr::from(0x12000), PageSize::Size4K),
+        ]
+    );
+    let before = manager.find_region(0x10000.into()).unwrap();
+    let after = manager.find_region(0x13000.into()).unwrap();
+    assert_eq!(populated(before), vec![0x10000]);
+    assert_eq!(populated(after), vec![0x13000]);
+}
+
+#[test]
+fn removal_refuses_to_split_a_huge_page() {
+    let file = TestFile::new(0x80_0000);
+    let mut manager = VmaManager::new();
+    let huge = MmapRegion::new(range(0x40_0000, 0x40_0000), file, 0, PageSize::Size2M);
+    huge.get_buf(0x40_0000.into()).unwrap();
+    manager.add_region(huge).unwrap();
+
+    assert_eq!(
+        manager.remove_overlapped(range(0x40_1000, 0x1000)).err(),
+        Some(VmaError::Unaligned)
+    );
+    let huge = manager.find_region(0x40_0000.into()).unwrap();
+    assert_eq!(huge.range, range(0x40_0000, 0x40_0000));
+    assert!(huge.is_populated(0x40_1000.into()));
+
+    let removed = manager
+        .remove_overlapped(range(0x40_0000, 0x20_0000))
+        .unwrap();
+    assert_eq!(
+        removed.pages,
+        vec![(VirtAddr::from(0x40_0000), PageSize::Size2M)]
+    );
+    assert_eq!(
+        manager.find_region(0x60_0000.into()).unwrap().range,
+        range(0x60_0000, 0x20_0000)
+    );
+}
+
+#[test]
+fn overflowing_split_leaves_every_region_in_place() {
+    let file = TestFile::new(0x1000);
+    let mut manager = VmaManager::new();
+    manager
+        .add_region(MmapRegion::new(
+            range(0x8000, 0x1000),
+            file.clone(),
+            0,
+            PageSize::Size4K,
+        ))
+        .unwrap();
+    manager
+        .add_region(MmapRegion::new(
+            range(0x10000, 0x3000),
+            file,
+            i64::MAX - 0x1000,
+            PageSize::Size4K,
+        ))
+        .unwrap();
+
+    assert_eq!(
+        manager.remove_overlapped(range(0x8000, 0xa000)).err(),
+        Some(VmaError::Overflow)
+    );
+    assert_eq!(manager.len(), 2);
+    assert!(manager.find_region(0x8000.into()).is_some());
+    assert_eq!(
+        manager.find_region(0x12000.into()).unwrap().range,
+        range(0x10000, 0x3000)
+    );
+}
+
+#[test]
+fn munmap_follows_munmap_semantics() {
+    let file = TestFile::new(0x80_0000);
+    let mut manager = VmaManager::new();
+    manager
+        .add_region(MmapRegion::new(
+            range(0x10000, 0x4000),
+            file.clone(),
+            0,
+            PageSize::Size4K,
+        ))
+        .unwrap();
+    manager
+        .add_region(MmapRegion::new(
+            range(0x40_0000, 0x40_0000),
+            file,
+            0,
+            PageSize::Size2M,
+        ))
+        .unwrap();
+
+    assert_eq!(
+        manager.munmap(0x10010.into(), 0x1000).err(),
+        Some(VmaError::Unaligned)
+    );
+    assert_eq!(
+        manager.munmap(0x10000.into(), 0).err(),
+        Some(VmaError::InvalidArgument)
+    );
+    assert_eq!(
+        manager.munmap((usize::MAX & !0xfff).into(), 0x2000).err(),
+        Some(VmaError::InvalidArgument)
+    );
+
+    // The length is rounded up to a whole page
+    let removed = manager.munmap(0x11000.into(), 1).unwrap();
+    assert_eq!(removed.regions.len(), 1);
+    assert_eq!(removed.regions[0].range, range(0x11000, 0x1000));
+
+    // Unmapping a hole succeeds without effect
+    let removed = manager.munmap(0x10_0000.into(), 0x1000).unwrap();
+    assert!(removed.regions.is_empty());
+    assert_eq!(manager.len(), 3);
+
+    // Huge-page regions only split at their own alignment
+    assert_eq!(
+        manager.munmap(0x40_1000.into(), 0x1000).err(),
+        Some(VmaError::Unaligned)
+    );
+    assert_eq!(
+        manager.munmap(0x60_0000.into(), 0x1000).err(),
+        Some(VmaError::Unaligned)
+    );
+    assert_eq!(
+        manager.find_region(0x40_0000.into()).unwrap().range,
+        range(0x40_0000, 0x40_0000)
+    );
+    let removed = manager.munmap(0x60_0000.into(), 0x20_0000).unwrap();
+    assert_eq!(removed.regions[0].range, range(0x60_0000, 0x20_0000));
+    assert_eq!(
+        manager.find_region(0x40_0000.into()).unwrap().range,
+        range(0x40_0000, 0x20_0000)
+    );
+}
+
+#[test]
+fn unmap_spanning_a_pinned_region_changes_nothing() {
+    let file = TestFile::new(0x10000);
+    let mut manager = VmaManager::new();
+    let mut pinned = MmapRegion::new(range(0x10000, 0x2000), file.clone(), 0, PageSize::Size4K);
+    pinned.pinned = true;
+    assert!(pinned.clone().pinned);
+    manager.add_region(pinned).unwrap();
+    manager
+        .add_region(MmapRegion::new(
+            range(0x12000, 0x2000),
+            file,
+            0,
+            PageSize::Size4K,
+        ))
+        .unwrap();
+
+    assert_eq!(
+        manager.munmap(0x11000.into(), 0x2000).err(),
+        Some(VmaError::Pinned)
+    );
+    assert_eq!(
+        LinuxError::from(VmaError::<VirtAddr>::Pinned),
+        LinuxError::EPERM
+    );
+    assert_eq!(manager.len(), 2);
+    assert_eq!(
+        manager.find_region(0x10000.into()).unwrap().range,
+        range(0x10000, 0x2000)
+    );
+    assert_eq!(
+        manager.find_region(0x12000.into()).unwrap().range,
+        range(0x12000, 0x2000)
+    );
+
+    for (new_len, flags) in [(0x1000, RemapFlags::empty()), (0x3000, RemapFlags::MAYMOVE)] {
+        assert_eq!(
+            manager.remap(0x10000.into(), 0x2000, new_len, flags).err(),
+            Some(VmaError::Pinned)
+        );
+    }
+
+    let removed = manager.clear();
+    assert_eq!(removed.regions.len(), 1);
+    assert_eq!(removed.regions[0].range, range(0x12000, 0x2000));
+    assert_eq!(manager.len(), 1);
+    assert!(manager.find_region(0x10000.into()).unwrap().pinned);
+}
@@ -0,0 +1,85 @@
+mod common;
+
+use axvma::*;
+use common::{TestFile, range};
+use memory_addr::{VirtAddr, VirtAddrRange};
+use page_table_multiarch::PageSize;
+
+fn view(
+    range: VirtAddrRange,
+    offset: Option<i64>,
+    prot: MmapProt,
+    populated: &[usize],
+) -> RegionView {
+    RegionView {
+        range,
+        offset,
+        align: PageSize::Size4K,
+        prot,
+        flags: MmapFlags::PRIVATE,
+        populated: populated.iter().copied().map(VirtAddr::from).collect(),
+    }
+}
+
+#[test]
+fn views_compare_whole_layouts() {
+    let mut manager = VmaManager::new();
+    manager
+        .add_region(MmapRegion::new(
+            range(0x10000, 0x3000),
+            TestFile::new(0x4000),
+            0x1000,
+            PageSize::Size4K,
+        ))
+        .unwrap();
+    manager
+        .add_region(MmapRegion::new_anonymous(
+            range(0x20000, 0x1000),
+            PageSize::Size4K,
+        ))
+        .unwrap();
+    manager
+        .handle_fault(0x11000.into(), AccessFlags::READ)
+        .unwrap();
+    manager
+        .protect(range(0x12000, 0x1000), MmapProt::READ)
+        .unwrap();
+
+    assert_eq!(
+        manager.views(),
+        vec![
+            view(
+                range(0x10000, 0x2000),
+                Some(0x1000),
+                MmapProt::all(),
+                &[0x11000]
+            ),
+            view(range(0x12000, 0x1000), Some(0x3000), MmapProt::READ, &[]),
+            view(range(0x20000, 0x1000), None, MmapProt::all(), &[]),
+        ]
+    );
+    let region = manager.find_region(0x10000.into()).unwrap();
+    let before = region.view();
+    assert_eq!(before, manager.views()[0]);
+    region.get_buf(0x10000.into()).unwrap();
+    assert_ne!(region.view(), before);
+}
+
+#[test]
+fn debug_output_does_not_block_on_a_held_lock() {
+    let region = MmapRegion::new(
+        range(0x10000, 0x3000),
+        TestFile::new(0x4000),
+        0x1000,
+        PageSize::Size4K,
+    );
+    region.get_buf(0x11000.into()).unwrap();
+    let debug = format!("{region:#?}");
+    assert!(debug.contains("populated: 1"), "{debug}");
+    assert!(debug.contains("offset: 4096"), "{debug}");
+
+    assert_eq!(format!("{region:?}"), "0x10000-0x13000 0x1000");
+
+    let _held = region.populated.lock();
+    assert!(format!("{region:#?}").contains("populated: <locked>"));
+}
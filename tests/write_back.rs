@@ -0,0 +1,166 @@
+mod common;
+
+use axerrno::{LinuxError, LinuxResult};
+use axvma::*;
+use common::{TestFile, pattern, range};
+use page_table_multiarch::PageSize;
+
+#[derive(Clone)]
+struct ReadOnlyFile;
+
+impl VmFile for ReadOnlyFile {
+    fn read_at(&self, buf: &mut [u8], _offset: u64) -> LinuxResult<usize> {
+        buf.fill(0);
+        Ok(buf.len())
+    }
+
+    fn len(&self) -> LinuxResult<u64> {
+        Ok(0x10000)
+    }
+}
+
+#[test]
+fn flush_page_writes_where_get_buf_reads() {
+    let file = TestFile::new(0x10000);
+    let region = MmapRegion::new(
+        range(0x10000, 0x3000),
+        file.clone(),
+        0x2000,
+        PageSize::Size4K,
+    );
+
+    let page = region.get_buf(0x11000.into()).unwrap();
+    assert_eq!(page[0], pattern(0x3000));
+    assert_eq!(page[0xfff], pattern(0x3fff));
+
+    assert_eq!(region.flush_page(0x11234.into(), &[7; 0x1000]), Ok(0x1000));
+    assert_eq!(file.writes(), vec![(0x3000, vec![7; 0x1000])]);
+    assert_eq!(&file.contents()[0x3000..0x4000], &[7; 0x1000]);
+}
+
+#[test]
+fn flush_page_follows_negative_offsets() {
+    let file = TestFile::new(0x3000);
+    let region = MmapRegion::new(
+        range(0x10000, 0x3000),
+        file.clone(),
+        -0x1000,
+        PageSize::Size4K,
+    );
+
+    let before_file = VmaError::OffsetOutOfFile {
+        offset: -0x1000,
+        file_len: 0x3000,
+    };
+    assert_eq!(region.get_buf(0x10000.into()).err(), Some(before_file));
+    assert_eq!(
+        region.flush_page(0x10000.into(), &[1; 0x1000]),
+        Err(before_file)
+    );
+
+    assert_eq!(region.get_buf(0x11000.into()).unwrap()[1], pattern(1));
+    assert_eq!(region.flush_page(0x11000.into(), &[1; 0x1000]), Ok(0x1000));
+    assert_eq!(file.writes(), vec![(0, vec![1; 0x1000])]);
+}
+
+#[test]
+fn flush_page_stops_at_end_of_file() {
+    let file = TestFile::new(0x1800);
+    let region = MmapRegion::new(range(0x10000, 0x3000), file.clone(), 0, PageSize::Size4K);
+
+    assert_eq!(region.flush_page(0x11000.into(), &[2; 0x1000]), Ok(0x800));
+    assert_eq!(file.writes(), vec![(0x1000, vec![2; 0x800])]);
+    assert_eq!(file.contents().len(), 0x1800);
+
+    assert_eq!(
+        region.flush_page(0x12000.into(), &[2; 0x1000]),
+        Err(VmaError::OffsetOutOfFile {
+            offset: 0x2000,
+            file_len: 0x1800,
+        })
+    );
+    assert_eq!(file.writes().len(), 1);
+}
+
+#[test]
+fn flush_page_writes_at_most_one_page() {
+    let file = TestFile::new(0x10000);
+    let region = MmapRegion::new(range(0x10000, 0x2000), file.clone(), 0, PageSize::Size4K);
+
+    assert_eq!(region.flush_page(0x10010.into(), &[3; 0x2000]), Ok(0x1000));
+    assert_eq!(file.writes(), vec![(0, vec![3; 0x1000])]);
+}
+
+#[test]
+fn read_only_backends_reject_write_back() {
+    let region = MmapRegion::new(range(0x10000, 0x1000), ReadOnlyFile, 0, PageSize::Size4K);
+    assert_eq!(
+        region.flush_page(0x10000.into(), &[0; 0x1000]),
+        Err(VmaError::Backend(LinuxError::EINVAL))
+    );
+}
+
+#[test]
+fn sync_writes_back_dirty_shared_pages() {
+    let file = TestFile::new(0x2800);
+    let mut manager = VmaManager::new();
+    let mut shared = MmapRegion::new(range(0x10000, 0x4000), file.clone(), 0, PageSize::Size4K);
+    shared.flags = MmapFlags::SHARED;
+    manager.add_region(shared).unwrap();
+    manager
+        .add_region(MmapRegion::new(
+            range(0x20000, 0x1000),
+            file.clone(),
+            0,
+            PageSize::Size4K,
+        ))
+        .unwrap();
+    manager.populate(range(0x10000, 0x3000)).unwrap();
+    manager.populate(range(0x20000, 0x1000)).unwrap();
+    let shared = manager.find_region(0x10000.into()).unwrap();
+    let private = manager.find_region(0x20000.into()).unwrap();
+    assert!(shared.mark_dirty(0x10000.into()));
+    assert!(shared.mark_dirty(0x12000.into()));
+    assert!(private.mark_dirty(0x20000.into()));
+
+    let pages: Vec<Vec<u8>> = (0..3).map(|i| vec![0xa0 + i; 0x1000]).collect();
+    let written = manager
+        .sync(range(0, 0x30000), |vaddr| {
+            &pages[(vaddr.as_usize() - 0x10000) / 0x1000 % 3]
+        })
+        .unwrap();
+    assert_eq!(written, 2);
+    // The last page is cut at the end of the file, which does not grow
+    assert_eq!(
+        file.writes(),
+        vec![(0, vec![0xa0; 0x1000]), (0x2000, vec![0xa2; 0x800])]
+    );
+    let contents = file.contents();
+    assert_eq!(contents.len(), 0x2800);
+    assert_eq!(contents[0x1000], pattern(0x1000));
+
+    assert!(!shared.is_dirty(0x10000.into()));
+    assert!(!shared.is_dirty(0x12000.into()));
+    assert!(private.is_dirty(0x20000.into()));
+    assert_eq!(manager.sync(range(0, 0x30000), |_| &pages[0]), Ok(0));
+}
+
+#[test]
+fn sync_skips_pages_past_the_end_of_the_file() {
+    let file = TestFile::new(0x1000);
+    let mut manager = VmaManager::new();
+    let mut shared = MmapRegion::new(range(0x10000, 0x2000), file.clone(), 0, PageSize::Size4K);
+    shared.flags = MmapFlags::SHARED;
+    shared.eof_policy = EofPolicy::ZeroFill;
+    manager.add_region(shared).unwrap();
+    manager.populate(range(0x10000, 0x2000)).unwrap();
+    let region = manager.find_region(0x10000.into()).unwrap();
+    region.mark_dirty(0x11000.into());
+
+    assert_eq!(
+        manager.sync(range(0x10000, 0x2000), |_| &[5; 0x1000]),
+        Ok(0)
+    );
+    assert!(file.writes().is_empty());
+    assert!(!region.is_dirty(0x11000.into()));
+}
@@ -0,0 +1,114 @@
+mod common;
+
+use axvma::*;
+use common::{TestFile, range};
+use page_table_multiarch::PageSize;
+
+fn private_region(file: &TestFile) -> MmapRegion<TestFile> {
+    MmapRegion::new(range(0x10000, 0x4000), file.clone(), 0, PageSize::Size4K)
+}
+
+#[test]
+fn read_then_write_fault_makes_the_page_private() {
+    let file = TestFile::new(0x4000);
+    let contents = file.contents();
+    let region = private_region(&file);
+
+    region.get_buf(0x10000.into()).unwrap();
+    assert!(!region.is_private(0x10000.into()));
+    assert!(region.evict(0x10000.into()));
+    region.get_buf(0x10000.into()).unwrap();
+
+    assert_eq!(
+        region.handle_write_fault(0x10010.into()),
+        Ok(WriteFaultAction::Copy(CowCopy::Data(
+            contents[..0x1000].to_vec()
+        )))
+    );
+    assert!(region.is_private(0x10000.into()));
+    assert!(!region.is_dirty(0x10000.into()));
+    // A private page can no longer be reloaded from the file
+    assert!(!region.evict(0x10000.into()));
+    assert_eq!(
+        region.handle_write_fault(0x10000.into()),
+        Ok(WriteFaultAction::MakeWritable)
+    );
+}
+
+#[test]
+fn write_fault_on_an_unpopulated_page_loads_it_private() {
+    let file = TestFile::new(0x4000);
+    let contents = file.contents();
+    let region = private_region(&file);
+    let Ok(WriteFaultAction::Load(page)) = region.handle_write_fault(0x11000.into()) else {
+        panic!("unpopulated page was not loaded");
+    };
+    assert_eq!(page[..], contents[0x1000..0x2000]);
+    assert!(region.is_populated(0x11000.into()));
+    assert!(region.is_private(0x11000.into()));
+}
+
+#[test]
+fn private_state_follows_split_parts() {
+    let file = TestFile::new(0x4000);
+    let region = private_region(&file);
+    region.handle_write_fault(0x10000.into()).unwrap();
+    region.handle_write_fault(0x11000.into()).unwrap();
+    region.get_buf(0x12000.into()).unwrap();
+
+    let (before, overlap, _) = region.split_at_range(&range(0x11000, 0x3000)).unwrap();
+    assert!(before.unwrap().is_private(0x10000.into()));
+    let overlap = overlap.unwrap();
+    assert!(overlap.is_private(0x11000.into()));
+    assert!(overlap.is_populated(0x12000.into()));
+    assert!(!overlap.is_private(0x12000.into()));
+}
+
+#[test]
+fn forked_private_pages_copy_the_mapped_frame() {
+    let file = TestFile::new(0x4000);
+    let mut manager = VmaManager::new();
+    manager
+        .add_region(MmapRegion::new(
+            range(0x10000, 0x2000),
+            file,
+            0,
+            PageSize::Size4K,
+        ))
+        .unwrap();
+    manager
+        .find_region(0x10000.into())
+        .unwrap()
+        .handle_write_fault(0x10000.into())
+        .unwrap();
+    let child = manager.fork();
+    assert_eq!(
+        child
+            .find_region(0x10000.into())
+            .unwrap()
+            .handle_write_fault(0x10000.into()),
+        Ok(WriteFaultAction::Copy(CowCopy::CopyFrame))
+    );
+}
+
+#[test]
+fn shared_and_read_only_mappings() {
+    let file = TestFile::new(0x4000);
+    let mut shared = private_region(&file);
+    shared.flags = MmapFlags::SHARED;
+    shared.get_buf(0x10000.into()).unwrap();
+    assert_eq!(
+        shared.handle_write_fault(0x10000.into()),
+        Ok(WriteFaultAction::MakeWritable)
+    );
+    assert!(shared.is_dirty(0x10000.into()));
+    assert!(!shared.is_private(0x10000.into()));
+
+    let mut read_only = private_region(&file);
+    read_only.prot = MmapProt::READ;
+    assert_eq!(
+        read_only.handle_write_fault(0x10000.into()),
+        Err(VmaError::AccessDenied)
+    );
+    assert!(!read_only.is_populated(0x10000.into()));
+}
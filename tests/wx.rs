@@ -0,0 +1,96 @@
+mod common;
+
+use axerrno::LinuxError;
+use axvma::*;
+use common::{TestFile, range};
+use memory_addr::VirtAddr;
+use page_table_multiarch::PageSize;
+
+const RW: MmapProt = MmapProt::READ.union(MmapProt::WRITE);
+const RX: MmapProt = MmapProt::READ.union(MmapProt::EXEC);
+
+fn anon(start: usize, size: usize, prot: MmapProt) -> MmapRegion<TestFile> {
+    let mut region = MmapRegion::new_anonymous(range(start, size), PageSize::Size4K);
+    region.prot = prot;
+    region
+}
+
+fn prots(manager: &VmaManager<TestFile>) -> Vec<(usize, MmapProt)> {
+    manager
+        .iter()
+        .map(|region| (region.range.start.as_usize(), region.prot))
+        .collect()
+}
+
+#[test]
+fn deny_rejects_writable_executable_mappings() {
+    let mut manager = VmaManager::new();
+    manager.set_wx_policy(WxPolicy::Deny);
+    let err = manager
+        .add_region(anon(0x10000, 0x2000, MmapProt::all()))
+        .unwrap_err();
+    assert_eq!(err, VmaError::AccessDenied);
+    assert_eq!(LinuxError::from(err), LinuxError::EACCES);
+    assert!(manager.is_empty());
+
+    manager.add_region(anon(0x10000, 0x2000, RW)).unwrap();
+    assert!(manager.would_violate_wx(range(0x10000, 0x1000), MmapProt::all()));
+    assert_eq!(
+        manager.protect(range(0x10000, 0x1000), MmapProt::all()),
+        Err(VmaError::AccessDenied)
+    );
+    // Switching between writable and executable is allowed
+    assert!(!manager.would_violate_wx(range(0x10000, 0x1000), RX));
+    manager.protect(range(0x10000, 0x1000), RX).unwrap();
+    manager.protect(range(0x10000, 0x1000), RW).unwrap();
+}
+
+#[test]
+fn deny_transitions_rejects_flipping_between_write_and_exec() {
+    let mut manager = VmaManager::new();
+    manager.add_region(anon(0x10000, 0x2000, RW)).unwrap();
+    manager.add_region(anon(0x12000, 0x2000, RX)).unwrap();
+    manager.set_wx_policy(WxPolicy::DenyTransitions);
+
+    assert!(manager.would_violate_wx(range(0x13000, 0x1000), RW));
+    assert_eq!(
+        manager.protect(range(0x13000, 0x1000), RW),
+        Err(VmaError::AccessDenied)
+    );
+    assert_eq!(
+        manager.protect(range(0x10000, 0x1000), RX),
+        Err(VmaError::AccessDenied)
+    );
+    // Dropping a permission is no transition
+    assert!(!manager.would_violate_wx(range(0x10000, 0x1000), MmapProt::READ));
+    manager
+        .protect(range(0x10000, 0x1000), MmapProt::READ)
+        .unwrap();
+    assert_eq!(manager.len(), 3);
+}
+
+#[test]
+fn rejected_protect_across_regions_changes_nothing() {
+    let mut manager = VmaManager::new();
+    manager.add_region(anon(0x10000, 0x2000, RW)).unwrap();
+    manager.add_region(anon(0x12000, 0x2000, RX)).unwrap();
+    manager.set_wx_policy(WxPolicy::DenyTransitions);
+    let before = prots(&manager);
+
+    // Fine for the first region, a transition for the second
+    assert_eq!(
+        manager.protect(range(0x11000, 0x2000), RW),
+        Err(VmaError::AccessDenied)
+    );
+    assert_eq!(prots(&manager), before);
+    assert_eq!(manager.len(), 2);
+}
+
+#[test]
+fn heap_stays_within_the_policy() {
+    let mut manager: VmaManager<TestFile> = VmaManager::new();
+    manager.set_wx_policy(WxPolicy::Deny);
+    let mut heap = HeapRegion::new(VirtAddr::from(0x100000), 0x10000);
+    heap.set_brk(&mut manager, 0x102000.into()).unwrap();
+    assert_eq!(manager.find_region(0x100000.into()).unwrap().prot, RW);
+}